@@ -0,0 +1,306 @@
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// A single vm instruction, operands index into the constant pool, the name pool, the function
+/// table or the code of the current function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Constant(usize),  // push a constant
+    Pop,              // throw away the top of the stack
+    Dup(usize),       // push copies of this many values from the top of the stack, in order
+    GetLocal(usize),  // push a local of the current call frame
+    SetLocal(usize),  // pop into a local of the current call frame
+    GetGlobal(usize), // push a global, by name
+    SetGlobal(usize), // pop into a global, by name
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    BitNot,
+    Negate,
+    Not,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    List(usize),                // pop this many values into a list
+    Concat(usize),              // pop this many values and push them printed one after another
+    Map(usize),                 // pop this many keys and values into a map
+    Struct(usize),              // pop the fields of a struct, by name, and push the instance
+    GetField(usize),            // pop an instance, push one of its fields, by name
+    SetField(usize),            // pop a value and an instance, and set one of its fields, by name
+    Len,                        // pop a list, push its length
+    Range(bool), // pop a step, end and start, push the ints of the range, is it inclusive
+    CheckStep,   // error if the step of a range on top of the stack is zero
+    InRange(bool), // pop a step, end and value, push whether the value is still in the range
+    Step(usize), // pop a step and a value, push their sum, or jump if it doesn't fit in an int
+    Index,       // pop an index and a list, push the element
+    SetIndex,    // pop a value, an index and a list, push the list with the element replaced
+    Slice(bool), // pop an end, a start and a list, push the slice, is it inclusive
+    Jump(usize), // jump to an instruction
+    JumpIfFalse(usize), // pop the condition, jump if it was false
+    And(usize),  // jump if the bool on top is false, leaving it there, otherwise pop it
+    Or(usize),   // jump if the bool on top is true, leaving it there, otherwise pop it
+    Coalesce(usize), // jump if the value on top isn't null, leaving it there, otherwise pop it
+    Call(usize, usize), // call a function with this many arguments
+    Invoke(usize, usize), // call a method, by name, on the instance under this many arguments
+    Intrinsic(usize, usize), // call an intrinsic, by name, on the value under this many arguments
+    IntrinsicMut(usize, usize), // the same, but push the changed receiver before what it returns
+    Return,      // pop the return value and leave the current function
+    Print,       // pop a value and print it
+}
+
+/// A compiled white-lang function, the top level of the program is the function `<main>`
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub locals: usize, // arguments included
+    pub code: Vec<Op>,
+    pub defined: bool, // calls can come before the definition
+}
+impl Function {
+    fn new(name: String) -> Self {
+        Function {
+            name,
+            arity: 0,
+            locals: 0,
+            code: vec![],
+            defined: false,
+        }
+    }
+}
+
+/// Compiles the AST into bytecode for the vm, see `Expression::emit` and `Statement::emit`
+#[derive(Debug, Clone)]
+pub struct Bytecode {
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub functions: Vec<Function>,
+    function_ids: HashMap<String, usize>,
+    structs: HashMap<String, Vec<String>>, // the fields of every struct, in the order `Op::Struct` pops them
+    compiling: Vec<(usize, HashMap<String, usize>)>, // the functions we're in and their locals
+    loops: Vec<(Vec<usize>, Vec<usize>)>, // the `break` and `continue` jumps of each loop that still need patching
+    hidden: usize,                        // how many compiler generated variables there are
+}
+impl Bytecode {
+    pub fn new() -> Self {
+        let mut bytecode = Bytecode {
+            constants: vec![],
+            names: vec![],
+            functions: vec![],
+            function_ids: HashMap::new(),
+            structs: HashMap::new(),
+            compiling: vec![],
+            loops: vec![],
+            hidden: 0,
+        };
+        let main = bytecode.function("<main>");
+        bytecode.functions[main].defined = true;
+        bytecode.compiling.push((main, HashMap::new()));
+        bytecode
+    }
+
+    /// Append an instruction to the function being compiled, returns where it went
+    pub fn emit(&mut self, op: Op) -> usize {
+        let code = &mut self.current_mut().code;
+        code.push(op);
+        code.len() - 1
+    }
+
+    /// Where the next instruction will go
+    pub fn here(&self) -> usize {
+        self.functions[self.compiling.last().unwrap().0].code.len()
+    }
+
+    /// Point the jump at `at` to the next instruction
+    pub fn patch(&mut self, at: usize) {
+        let here = self.here();
+        match &mut self.current_mut().code[at] {
+            Op::Jump(target)
+            | Op::JumpIfFalse(target)
+            | Op::And(target)
+            | Op::Or(target)
+            | Op::Coalesce(target)
+            | Op::Step(target) => *target = here,
+            op => panic!("Tried to patch {:?}, which isn't a jump", op),
+        }
+    }
+
+    pub fn constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        let idx = self.constants.len() - 1;
+        self.emit(Op::Constant(idx))
+    }
+
+    pub fn name(&mut self, name: &str) -> usize {
+        match self.names.iter().position(|n| n == name) {
+            Some(idx) => idx,
+            None => {
+                self.names.push(name.to_string());
+                self.names.len() - 1
+            }
+        }
+    }
+
+    /// The function called `name`, or a placeholder for it if we haven't seen its definition yet
+    pub fn function(&mut self, name: &str) -> usize {
+        if let Some(idx) = self.function_ids.get(name) {
+            return *idx;
+        }
+        self.functions.push(Function::new(name.to_string()));
+        self.function_ids
+            .insert(name.to_string(), self.functions.len() - 1);
+        self.functions.len() - 1
+    }
+
+    /// The function called `name` if it has been compiled, methods are looked up by the vm
+    pub fn get_function(&self, name: &str) -> Option<usize> {
+        self.function_ids
+            .get(name)
+            .copied()
+            .filter(|idx| self.functions[*idx].defined)
+    }
+
+    pub fn add_struct(&mut self, name: &str, fields: Vec<String>) {
+        self.structs.insert(name.to_string(), fields);
+    }
+
+    pub fn get_struct_fields(&self, name: &str) -> Vec<String> {
+        self.structs.get(name).cloned().unwrap_or_default()
+    }
+
+    /// A compiler generated variable that can't clash with anything the user writes
+    pub fn hidden_variable(&mut self, hint: &str) -> String {
+        self.hidden += 1;
+        format!("{}.{}", hint, self.hidden)
+    }
+
+    fn in_function(&self) -> bool {
+        self.compiling.len() > 1
+    }
+
+    fn current_mut(&mut self) -> &mut Function {
+        let idx = self.compiling.last().unwrap().0;
+        &mut self.functions[idx]
+    }
+
+    /// `let name = ...`, functions get a new local, everything else is global
+    pub fn declare(&mut self, name: &str) {
+        if !self.in_function() {
+            return;
+        }
+        let (idx, locals) = self.compiling.last_mut().unwrap();
+        if !locals.contains_key(name) {
+            let slot = locals.len();
+            locals.insert(name.to_string(), slot);
+            self.functions[*idx].locals = locals.len();
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<usize> {
+        if !self.in_function() {
+            return None;
+        }
+        self.compiling.last().unwrap().1.get(name).copied()
+    }
+
+    /// Push the value of a variable, locals shadow globals
+    pub fn load(&mut self, name: &str) -> usize {
+        match self.local(name) {
+            Some(slot) => self.emit(Op::GetLocal(slot)),
+            None => {
+                let idx = self.name(name);
+                self.emit(Op::GetGlobal(idx))
+            }
+        }
+    }
+
+    /// Pop the top of the stack into a variable
+    pub fn store(&mut self, name: &str) -> usize {
+        match self.local(name) {
+            Some(slot) => self.emit(Op::SetLocal(slot)),
+            None => {
+                let idx = self.name(name);
+                self.emit(Op::SetGlobal(idx))
+            }
+        }
+    }
+
+    /// Start compiling a function, the arguments are its first locals
+    pub fn begin_function(&mut self, name: &str, args: &[String]) {
+        let idx = self.function(name);
+        self.functions[idx] = Function::new(name.to_string());
+        self.functions[idx].arity = args.len();
+        self.compiling.push((idx, HashMap::new()));
+        for arg in args {
+            self.declare(arg);
+        }
+    }
+
+    /// Finish the current function, falling off the end returns nothing
+    pub fn end_function(&mut self) {
+        self.constant(Value::Unit);
+        self.emit(Op::Return);
+        let (idx, _) = self
+            .compiling
+            .pop()
+            .expect("end_function without begin_function");
+        self.functions[idx].defined = true;
+    }
+
+    pub fn push_loop(&mut self) {
+        self.loops.push((vec![], vec![]));
+    }
+
+    /// Jump out of the innermost loop, the jump is patched once the loop is done
+    pub fn brk(&mut self) {
+        let jump = self.here();
+        match self.loops.last_mut() {
+            Some((breaks, _)) => breaks.push(jump),
+            // validation rejects this, a jump nothing patches would go back to the start
+            None => unreachable!("`break` outside of a loop"),
+        }
+        self.emit(Op::Jump(0));
+    }
+
+    /// Jump to the next iteration of the innermost loop, the jump is patched by `patch_continues`
+    pub fn cont(&mut self) {
+        let jump = self.here();
+        match self.loops.last_mut() {
+            Some((_, continues)) => continues.push(jump),
+            // validation rejects this, a jump nothing patches would go back to the start
+            None => unreachable!("`continue` outside of a loop"),
+        }
+        self.emit(Op::Jump(0));
+    }
+
+    /// Every `continue` in the innermost loop so far jumps to the next instruction
+    pub fn patch_continues(&mut self) {
+        let continues = match self.loops.last_mut() {
+            Some((_, continues)) => std::mem::take(continues),
+            None => return,
+        };
+        for jump in continues {
+            self.patch(jump);
+        }
+    }
+
+    /// Finish the innermost loop, every `break` in it jumps to the next instruction
+    pub fn pop_loop(&mut self) {
+        let (breaks, continues) = self.loops.pop().unwrap_or_default();
+        debug_assert!(continues.is_empty(), "the loop never patched its continues");
+        for jump in breaks {
+            self.patch(jump);
+        }
+    }
+}
@@ -3,5 +3,8 @@ pub type WhiteLangInt = isize;
 pub type WhiteLangString = String;
 pub type WhiteLangBool = bool;
 pub type WhiteLangList<T> = Vec<T>;
+pub type WhiteLangMap<K, V> = Vec<(K, V)>; // kept in insertion order, like a javascript Map
 
+pub const MAX_CALL_DEPTH: usize = 2000; // how many calls deep a program can go, see `StackOverflow`
 pub const WHITE_LANG_FILE_EXTENSION: &str = ".whl";
+pub const DYNAMIC_LINKER: &str = "/lib64/ld-linux-x86-64.so.2";
@@ -1,5 +1,6 @@
+use crate::diagnostic::Diagnostic;
 use crate::javascript::JavaScript;
-use crate::parser::Parser;
+use crate::parser::{Parser, ParserError};
 use crate::program::Program;
 use crate::tokenizer::Tokenizer;
 
@@ -15,7 +16,7 @@ impl CoreObjects {
         let mut tokenizer = Tokenizer::new(src.to_string());
         tokenizer.tokenize();
         let mut parser = Parser::new(&mut tokenizer);
-        parser.parse();
+        let _ = parser.parse(); // tests inspect the errors off of the parser
         let program = Program::from_parser(&mut parser);
         Self {
             parser,
@@ -37,18 +38,24 @@ impl CoreObjects {
         }
     }
 
-    pub(crate) fn set_src(&mut self, src: &str) {
+    pub(crate) fn set_src(&mut self, src: &str) -> Result<(), Vec<Diagnostic>> {
         // init tokenizer
         self.tokenizer.set_source(src.to_string());
         self.tokenizer.tokenize();
+        if self.tokenizer.has_errors() {
+            return Err(self.tokenizer.get_diagnostics());
+        }
         // init parser
         self.parser.set_token_list(self.tokenizer.get_token_list());
-        self.parser.parse();
+        if let Err(errors) = self.parser.parse() {
+            return Err(errors.iter().map(ParserError::to_diagnostic).collect());
+        }
         // init program
         self.program.set_statements_or_expr(
             self.parser.get_statements().get_or_insert(&Vec::new()),
             self.parser.get_expr(),
         );
+        Ok(())
     }
 
     pub fn get_tokenizer_mut(&mut self) -> &mut Tokenizer {
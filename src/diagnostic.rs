@@ -0,0 +1,105 @@
+use crate::tokenizer::Token;
+
+mod test;
+
+/// An error pointing at a span of white-lang source code, rendered in the style of rustc:
+/// ```text
+/// error: mismatched types: expected `string`, found `int`
+///  --> main.whl:1:18
+///   |
+/// 1 | let x : string = 10;
+///   |                  ^^
+///   |
+///   = help: Attempt to set a bad type
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    message: String,      // what went wrong
+    line: usize,          // the line the span starts on, 1 based
+    column: usize,        // the column the span starts on, 0 based
+    length: usize,        // how many characters to underline
+    help: Option<String>, // an optional note on how to fix the problem
+}
+impl Diagnostic {
+    pub fn new(message: String, line: usize, column: usize, length: usize) -> Self {
+        Diagnostic {
+            message,
+            line,
+            column,
+            length,
+            help: None,
+        }
+    }
+
+    /// Point at a single token
+    pub fn from_token(message: String, token: &Token) -> Self {
+        Diagnostic::from_tokens(message, token, token)
+    }
+
+    /// Point at everything from `start` to `end`, spans that cross lines are cut off at the end
+    /// of the first line
+    pub fn from_tokens(message: String, start: &Token, end: &Token) -> Self {
+        let length = if start.get_line() == end.get_line() {
+            (end.get_line_offset() + end.get_length()).saturating_sub(start.get_line_offset())
+        } else {
+            usize::MAX
+        };
+        Diagnostic::new(message, start.get_line(), start.get_line_offset(), length)
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        if !help.is_empty() {
+            self.help = Some(help);
+        }
+        self
+    }
+
+    pub fn get_message(&self) -> String {
+        self.message.clone()
+    }
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    /// Render the diagnostic against the source it was found in
+    pub fn render(&self, file_name: &str, src: &str) -> String {
+        let source_line = src.lines().nth(self.line.saturating_sub(1)).unwrap_or("");
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        // keep tabs in the padding so the carets line up with the source line
+        let padding: String = source_line
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(self.column)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let remaining = source_line.chars().count().saturating_sub(self.column);
+        let carets = "^".repeat(self.length.min(remaining).max(1));
+
+        let mut rendered = format!("error: {}\n", self.message);
+        rendered.push_str(&format!(
+            "{}--> {}:{}:{}\n",
+            gutter,
+            file_name,
+            self.line,
+            self.column + 1
+        ));
+        rendered.push_str(&format!("{} |\n", gutter));
+        rendered.push_str(&format!("{} | {}\n", line_number, source_line));
+        rendered.push_str(&format!("{} | {}{}\n", gutter, padding, carets));
+        if let Some(help) = &self.help {
+            rendered.push_str(&format!("{} |\n", gutter));
+            for (i, line) in help.lines().enumerate() {
+                if i == 0 {
+                    rendered.push_str(&format!("{} = help: {}\n", gutter, line));
+                } else {
+                    rendered.push_str(&format!("{}         {}\n", gutter, line));
+                }
+            }
+        }
+        rendered
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use crate::diagnostic::Diagnostic;
+    use crate::tokenizer::{Token, Tokenizer};
+
+    fn tokens(src: &str) -> Vec<Token> {
+        let mut tokenizer = Tokenizer::new(src.to_string());
+        tokenizer.tokenize();
+        tokenizer.get_token_list().clone()
+    }
+
+    #[test]
+    fn test_renders_a_single_token() {
+        let tokens = tokens("let x = 10;");
+        let rendered = Diagnostic::from_token(String::from("oops"), &tokens[3])
+            .with_help(String::from("Try this instead"))
+            .render("a.whl", "let x = 10;");
+        assert_eq!(
+            rendered,
+            "error: oops\n --> a.whl:1:9\n  |\n1 | let x = 10;\n  |         ^^\n  |\n  = help: Try this instead\n"
+        );
+    }
+
+    #[test]
+    fn test_renders_a_span_of_tokens() {
+        let src = "\nlet y = x + 2;";
+        let tokens = tokens(src);
+        let rendered = Diagnostic::from_tokens(String::from("oops"), &tokens[3], &tokens[5])
+            .render("a.whl", src);
+        assert_eq!(
+            rendered,
+            "error: oops\n --> a.whl:2:9\n  |\n2 | let y = x + 2;\n  |         ^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_spans_across_lines_stop_at_the_end_of_the_first() {
+        let src = "let x = [1,\n2];";
+        let tokens = tokens(src);
+        let diagnostic = Diagnostic::from_tokens(String::from("oops"), &tokens[3], &tokens[7]);
+        assert!(diagnostic
+            .render("a.whl", src)
+            .contains("1 | let x = [1,\n  |         ^^^\n"));
+    }
+
+    #[test]
+    fn test_help_spanning_lines_is_indented() {
+        let rendered = Diagnostic::new(String::from("oops"), 1, 0, 1)
+            .with_help(String::from("first\nsecond"))
+            .render("a.whl", "x");
+        assert!(rendered.ends_with("  = help: first\n          second\n"));
+    }
+
+    #[test]
+    fn test_tabs_keep_the_carets_lined_up() {
+        let rendered = Diagnostic::new(String::from("oops"), 1, 1, 1).render("a.whl", "\tx");
+        assert!(rendered.contains("1 | \tx\n  | \t^\n"));
+    }
+}
@@ -1,19 +1,342 @@
+use crate::parser::expression::functioncallexpression::FunctionCallExpression;
+use crate::parser::expression::listliteralexpression::ListLiteralExpression;
+use crate::parser::expression::mapliteralexpression::MapLiteralExpression;
+use crate::parser::expression::rangeexpression::RangeExpression;
+use crate::parser::parser_traits::Expression;
+use crate::parser::whitetypes::Type;
+use crate::runtime::RuntimeError;
+
+mod test;
+
+/// Copies a list or map the way the runtime does, nested lists and maps are copied too but
+/// struct instances stay shared
+const COPY: &str = "\
+function copy$(value) {
+\tif (Array.isArray(value)) return value.map(copy$);
+\tif (value instanceof Map) return new Map([...value].map(([key, item]) => [key, copy$(item)]));
+\treturn value;
+}
+";
+
+/// Where `index` points in something `len` long, counting negative indices from the end. The end
+/// of a slice may sit one past the last element. Throws the runtime's error when it's out of range
+const POSITION: &str = "\
+function position$(index, len, end = false) {
+\tconst position = index < 0 ? index + len : index;
+\tif (position < 0 || position > len || (position === len && !end)) throw new Error(`index out of range: the len is ${len} but the index is ${index}`);
+\treturn position;
+}
+";
+
+/// `list[index]`, or the value of the key `index` in a map, see `index` in the runtime
+const AT: &str = "\
+function at$(list, index) {
+\tif (!(list instanceof Map)) return list[position$(index, list.length)];
+\tif (!list.has(index)) throw new Error(`the key \\`${index}\\` is not in the map`);
+\treturn list.get(index);
+}
+";
+
+/// `list[start..end]`, or `list[start..=end]`, see `slice` in the runtime
+const SLICE: &str = "\
+function slice$(list, start, end, inclusive) {
+\tconst from = position$(start, list.length, true);
+\tconst to = position$(end, list.length, !inclusive) + (inclusive ? 1 : 0);
+\tif (from > to) throw new Error(`slice start is after its end: \\`${start}${inclusive ? \"..=\" : \"..\"}${end}\\``);
+\treturn list.slice(from, to);
+}
+";
+
+/// `list[index] = value`, a map gets a new key but a list has to have the element already
+const SET: &str = "\
+function set$(list, index, value) {
+\tif (list instanceof Map) list.set(index, value);
+\telse list[position$(index, list.length)] = value;
+}
+";
+
+/// Replaces an element of a list, or the value of a key in a map, with what `update` makes of it
+const UPDATE: &str = "\
+function update$(list, index, update) {
+\tset$(list, index, update(at$(list, index)));
+}
+";
+
+/// `==` the way the runtime does it, lists and maps are equal when their contents are
+const EQUALS: &str = "\
+function equals$(lhs, rhs) {
+\tif (Array.isArray(lhs) && Array.isArray(rhs)) return lhs.length === rhs.length && lhs.every((item, i) => equals$(item, rhs[i]));
+\tif (lhs instanceof Map && rhs instanceof Map) return lhs.size === rhs.size && [...lhs].every(([key, item]) => rhs.has(key) && equals$(item, rhs.get(key)));
+\treturn lhs === rhs;
+}
+";
+
+/// `list.contains(value)`, by the same equality as `==`
+const CONTAINS: &str = "\
+function contains$(list, value) {
+\treturn list.some(item => equals$(item, value));
+}
+";
+
+/// `list.insert(index, value)`, inserting at the length puts it at the end
+const INSERT: &str = "\
+function insert$(list, index, value) {
+\tlist.splice(position$(index, list.length, true), 0, value);
+}
+";
+
+/// `list.remove(index)`, the element that was there
+const REMOVE: &str = "\
+function remove$(list, index) {
+\treturn list.splice(position$(index, list.length), 1)[0];
+}
+";
+
+/// `list.pop()`, javascript's pop gives back undefined for an empty list
+const POP: &str = "\
+function pop$(list) {
+\tif (list.length === 0) throw new Error(\"cannot `pop` from an empty list\");
+\treturn list.pop();
+}
+";
+
+/// `map.remove(key)`, the value that was there. Map.delete() only says whether it was
+const REMOVE_KEY: &str = "\
+function remove_key$(map, key) {
+\tif (!map.has(key)) throw new Error(`the key \\`${key}\\` is not in the map`);
+\tconst value = map.get(key);
+\tmap.delete(key);
+\treturn value;
+}
+";
+
+/// `string.substring(start, end)`, sliced by char like `string[start..end]`
+const SUBSTRING: &str = "\
+function substring$(string, start, end) {
+\treturn slice$([...string], start, end, false).join(\"\");
+}
+";
+
+/// `base ** exponent` on `int`s, javascript would give back a fraction for a negative exponent
+/// and lose bits rather than overflow
+const POWER: &str = "\
+function power$(base, exponent) {
+\tif (exponent < 0) throw new Error(`cannot raise an \\`int\\` to the negative power ${exponent}, use a \\`float\\``);
+\tif (Math.abs(base) <= 1) return Math.pow(base, exponent);
+\t// anything else to the 64th or more is too big, and BigInt would take forever to say so
+\tconst result = exponent < 64 ? BigInt(base) ** BigInt(exponent) : null;
+\tif (result === null || BigInt.asIntN(64, result) !== result) throw new Error(`\\`${base} ** ${exponent}\\` overflows an \\`int\\``);
+\treturn Number(result);
+}
+";
+
+/// `lhs << bits` or `lhs >> bits`, with all 64 bits of an `int`. Javascript only shifts 32 bit
+/// ints, and by the count's low 5 bits
+const SHIFT: &str = "\
+function shift$(lhs, bits, left) {
+\tif (bits < 0 || bits > 63) throw new Error(`cannot shift an \\`int\\` by ${bits} bits`);
+\treturn Number(BigInt.asIntN(64, left ? BigInt(lhs) << BigInt(bits) : BigInt(lhs) >> BigInt(bits)));
+}
+";
+
+/// The text `print` shows for a value, the way the runtime's `Display` does. `seen` holds the
+/// instances being shown, an instance that holds itself is cut short
+const SHOW: &str = "\
+function show$(value, seen = []) {
+\tif (Array.isArray(value)) return `[${value.map(item => show$(item, seen)).join(\", \")}]`;
+\tif (value instanceof Map) return `{${[...value].map(([key, item]) => `${show$(key, seen)}: ${show$(item, seen)}`).join(\", \")}}`;
+\tif (value === null || typeof value !== \"object\") return String(value);
+\tif (seen.includes(value)) return \"...\";
+\tconst fields = Object.keys(value).sort().map(field => `${field}: ${show$(value[field], [...seen, value])}`);
+\treturn fields.length === 0 ? `${value.constructor.name} {}` : `${value.constructor.name} { ${fields.join(\", \")} }`;
+}
+";
+
 #[derive(Debug, Clone)]
 pub struct JavaScript {
     src: String,
     level: String,
-    // javascript runtime?
+    helpers: Vec<&'static str>, // the functions the program needs defined, like `copy$`
 }
 impl JavaScript {
     pub fn new() -> Self {
         Self {
             src: String::new(),
             level: String::new(),
+            helpers: vec![],
         }
     }
 
     pub fn get_src(&self) -> String {
-        self.src.clone()
+        format!("{}{}", self.helpers.concat(), self.src)
+    }
+
+    /// Define `helper` at the top of the program, if it isn't already
+    fn helper(&mut self, helper: &'static str) {
+        if !self.helpers.contains(&helper) {
+            self.helpers.push(helper);
+        }
+    }
+
+    /// Transpile an expression whose value is about to be stored, in a variable, an argument, a
+    /// field or an element. Lists and maps are values in white-lang, so unless it was only just
+    /// built the value gets copied, otherwise javascript would share it between both places
+    pub fn transpile_value(&mut self, expr: &dyn Expression) {
+        let typ = expr.get_white_type().get_non_null_type();
+        // a function's result is already a copy, `return` makes one
+        let fresh = expr.to_any().is::<ListLiteralExpression>()
+            || expr.to_any().is::<MapLiteralExpression>()
+            || expr.to_any().is::<RangeExpression>()
+            || expr.to_any().is::<FunctionCallExpression>();
+        if fresh || !(typ.is_list_type() || typ.is_map_type()) {
+            expr.transpile(self);
+            return;
+        }
+        self.helper(COPY);
+        self.append_no_tabs(String::from("copy$("));
+        expr.transpile(self);
+        self.append_no_tabs(String::from(")"));
+    }
+
+    /// `update$(list, index, $old => ...)`, `update` transpiles what `$old` becomes. The list and
+    /// index are only evaluated once, so `xs[f()] += 1` only calls `f` once
+    pub fn transpile_update(
+        &mut self,
+        list: &dyn Expression,
+        index: &dyn Expression,
+        update: &dyn Fn(&mut JavaScript),
+    ) {
+        self.helper(POSITION);
+        self.helper(AT);
+        self.helper(SET);
+        self.helper(UPDATE);
+        self.append_no_tabs(String::from("update$("));
+        list.transpile(self);
+        self.append_no_tabs(String::from(", "));
+        index.transpile(self);
+        self.append_no_tabs(String::from(", $old => "));
+        update(self);
+        self.append_no_tabs(String::from(")"));
+    }
+
+    /// `at$(list, index)`, `list` transpiles the list, or the map, being indexed
+    pub fn transpile_at(&mut self, list: &dyn Fn(&mut JavaScript), index: &dyn Expression) {
+        self.helper(POSITION);
+        self.helper(AT);
+        self.append_no_tabs(String::from("at$("));
+        list(self);
+        self.append_no_tabs(String::from(", "));
+        index.transpile(self);
+        self.append_no_tabs(String::from(")"));
+    }
+
+    /// `slice$(list, start, end, inclusive)`, `list` transpiles the list being sliced
+    pub(crate) fn transpile_slice(
+        &mut self,
+        list: &dyn Fn(&mut JavaScript),
+        range: &RangeExpression,
+    ) {
+        self.helper(POSITION);
+        self.helper(SLICE);
+        self.append_no_tabs(String::from("slice$("));
+        list(self);
+        self.append_no_tabs(String::from(", "));
+        range.get_start().transpile(self);
+        self.append_no_tabs(String::from(", "));
+        range.get_end().transpile(self);
+        self.append_no_tabs(format!(", {})", range.is_inclusive()));
+    }
+
+    /// `set$(list, index, value)`, the value is copied like any other value being stored
+    pub fn transpile_set(
+        &mut self,
+        list: &dyn Expression,
+        index: &dyn Expression,
+        value: &dyn Expression,
+    ) {
+        self.helper(POSITION);
+        self.helper(SET);
+        self.append_no_tabs(String::from("set$("));
+        list.transpile(self);
+        self.append_no_tabs(String::from(", "));
+        index.transpile(self);
+        self.append_no_tabs(String::from(", "));
+        self.transpile_value(value);
+        self.append_no_tabs(String::from(")"));
+    }
+
+    /// `equals$(lhs, rhs)`, or `!equals$(lhs, rhs)` for `!=`, for operands `===` would only
+    /// compare by reference
+    pub fn transpile_equals(&mut self, lhs: &dyn Expression, rhs: &dyn Expression, equal: bool) {
+        self.helper(EQUALS);
+        self.append_no_tabs(String::from(if equal { "equals$(" } else { "!equals$(" }));
+        lhs.transpile(self);
+        self.append_no_tabs(String::from(", "));
+        rhs.transpile(self);
+        self.append_no_tabs(String::from(")"));
+    }
+
+    /// `function$(receiver, args...)` for the built in method `name`, i.e. `list.contains`, where
+    /// javascript's own method doesn't do what the runtime does
+    pub fn transpile_intrinsic(
+        &mut self,
+        name: &str,
+        receiver: &dyn Expression,
+        args: &[Box<dyn Expression>],
+    ) {
+        let (function, helpers): (&str, &[&'static str]) = match name {
+            "list.contains" => ("contains$", &[EQUALS, CONTAINS]),
+            "list.insert" => ("insert$", &[POSITION, INSERT]),
+            "list.remove" => ("remove$", &[POSITION, REMOVE]),
+            "list.pop" => ("pop$", &[POP]),
+            "map.remove" => ("remove_key$", &[REMOVE_KEY]),
+            "string.substring" => ("substring$", &[POSITION, SLICE, SUBSTRING]),
+            name => panic!("`{}` doesn't need a helper", name),
+        };
+        for helper in helpers {
+            self.helper(helper);
+        }
+        self.append_no_tabs(format!("{}(", function));
+        receiver.transpile(self);
+        for arg in args {
+            self.append_no_tabs(String::from(", "));
+            self.transpile_value(arg.as_ref());
+        }
+        self.append_no_tabs(String::from(")"));
+    }
+
+    /// `power$(base, exponent)`, for `**` on two `int`s
+    pub fn transpile_power(&mut self, base: &dyn Expression, exponent: &dyn Expression) {
+        self.helper(POWER);
+        self.append_no_tabs(String::from("power$("));
+        base.transpile(self);
+        self.append_no_tabs(String::from(", "));
+        exponent.transpile(self);
+        self.append_no_tabs(String::from(")"));
+    }
+
+    /// `shift$(lhs, bits, left)`, for `<<` and `>>`
+    pub fn transpile_shift(&mut self, lhs: &dyn Expression, bits: &dyn Expression, left: bool) {
+        self.helper(SHIFT);
+        self.append_no_tabs(String::from("shift$("));
+        lhs.transpile(self);
+        self.append_no_tabs(String::from(", "));
+        bits.transpile(self);
+        self.append_no_tabs(format!(", {})", left));
+    }
+
+    /// Transpile `expr` as the text `print` shows for it. Javascript formats lists, maps and
+    /// instances its own way, so those go through `show$`
+    pub fn transpile_shown(&mut self, expr: &dyn Expression) {
+        let typ = expr.get_white_type().get_non_null_type();
+        if !(typ.is_list_type() || typ.is_map_type() || matches!(typ, Type::Struct(_))) {
+            expr.transpile(self);
+            return;
+        }
+        self.helper(SHOW);
+        self.append_no_tabs(String::from("show$("));
+        expr.transpile(self);
+        self.append_no_tabs(String::from(")"));
     }
 
     pub fn append(&mut self, value: String) -> &mut JavaScript {
@@ -28,17 +351,17 @@ impl JavaScript {
     }
 
     pub fn newline(&mut self) -> &mut JavaScript {
-        self.src.push_str("\n");
+        self.src.push('\n');
         self
     }
 
     pub fn semicolon(&mut self) -> &mut JavaScript {
-        self.src.push_str(";");
+        self.src.push(';');
         self
     }
 
     pub fn indent(&mut self) -> &mut JavaScript {
-        self.level.push_str("\t");
+        self.level.push('\t');
         self
     }
 
@@ -51,8 +374,41 @@ impl JavaScript {
         self.level = String::new();
         self
     }
-}
 
-mod test {
-    // tests t o  d o
+    /// An expression that throws `error` with the message the runtime gives it, for checks that
+    /// have to happen in the middle of an expression
+    pub fn throw(error: &RuntimeError) -> String {
+        format!(
+            "(() => {{ throw new Error(\"{}\"); }})()",
+            JavaScript::escape(&error.to_string(), '"')
+        )
+    }
+
+    /// The class a struct transpiles to. A class named after the struct could shadow a global the
+    /// generated code needs, like `Math` or `String`, and white-lang names never start with `$`
+    pub fn class_name(name: &str) -> String {
+        format!("${}", name)
+    }
+
+    /// `string` as the inside of a javascript string quoted with `quote`, for a template literal
+    /// that means escaping `$` so `${` doesn't start an interpolation
+    pub fn escape(string: &str, quote: char) -> String {
+        let mut escaped = String::new();
+        for c in string.chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                '$' if quote == '`' => escaped.push_str("\\$"),
+                c if c == quote => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
 }
@@ -0,0 +1,433 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use crate::*;
+
+    fn transpile(src: &str) -> String {
+        let mut core: CoreObjects = CoreObjects::new(src);
+        core.get_program_mut().transpile_to_js()
+    }
+
+    /// Transpile `src` and run it with node
+    fn node(name: &str, src: &str) -> std::process::Output {
+        let path = std::env::temp_dir().join(format!("white_lang_js_{}.js", name));
+        std::fs::write(&path, transpile(src)).unwrap();
+        std::process::Command::new("node")
+            .arg(&path)
+            .output()
+            .expect("node is not installed")
+    }
+
+    /// Transpile `src` and run it with node, gives back what it printed
+    fn run_with_node(name: &str, src: &str) -> String {
+        let output = node(name, src);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[test]
+    fn test_structs_transpile_to_classes() {
+        let src = "
+        struct Counter { count: int } implement Counter {
+            fn increment(by : int) {
+                self.count = self.count + by;
+            }
+        };
+        let c = Counter(count = 1);
+        c.increment(2);";
+        assert_eq!(
+            transpile(src),
+            "class $Counter {\n\
+             \tstatic name = \"Counter\";\n\
+             \tconstructor({count}) {\n\
+             \t\tthis.count = count;\n\
+             \t}\n\
+             \tincrement(by) {\n\
+             \t\tthis.count = this.count + by;\n\
+             \n\
+             \t}\n\
+             }\n\
+             let c = new $Counter({count: 1});\n\
+             c.increment(2);\n"
+        );
+    }
+
+    #[test]
+    fn test_structs_do_not_shadow_javascript_globals() {
+        let src = "struct Math { x: int }; let m = Math(x = 2); print(m.x ** 2);";
+        assert!(transpile(src).ends_with(
+            "class $Math {\n\
+             \tstatic name = \"Math\";\n\
+             \tconstructor({x}) {\n\
+             \t\tthis.x = x;\n\
+             \t}\n\
+             }\n\
+             let m = new $Math({x: 2});\n\
+             console.log(power$(m.x, 2));\n"
+        ));
+    }
+
+    #[test]
+    fn test_else_if_chains_transpile() {
+        let src = "let x = 1; if (x == 0) { print(0); } else if (x == 1) { print(1); } else { print(2); }";
+        assert_eq!(
+            transpile(src),
+            "let x = 1;\n\
+             if (x === 0) {\n\
+             \tconsole.log(0);\n\
+             \n\
+             }\n\
+             else if (x === 1) {\n\
+             \tconsole.log(1);\n\
+             \n\
+             }\n\
+             else {\n\
+             \tconsole.log(2);\n\
+             \n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_ranges_transpile_to_counting_loops() {
+        let src = "for (i in 0..3) { print(i); } for (i in 0..=9 step 3) { print(i); }";
+        assert_eq!(
+            transpile(src),
+            "for (let i = 0, i$end = 3; i < i$end; i++) {\n\
+             \tconsole.log(i);\n\
+             }\n\
+             for (let i = 0, i$end = 9, i$step = 3; i$step > 0 ? i <= i$end : i$step < 0 ? i >= i$end : (() => { throw new Error(\"the step of a range cannot be zero\"); })(); i += i$step) {\n\
+             \tconsole.log(i);\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment_transpiles() {
+        let src = "let x = 7; x += 1; x /= 2; x++; let f = 1.0; f /= 2;";
+        assert_eq!(
+            transpile(src),
+            "let x = 7;\n\
+             x += 1;\n\
+             x = Math.trunc(x / 2);\n\
+             x++;\n\
+             let f = 1;\n\
+             f /= 2;\n"
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment_evaluates_the_target_once() {
+        let src = "let xs = [1]; fn i(): int { return 0; } xs[i()] /= 1 + 1; \
+                   struct P { x: int }; let ps = [P(x = 7)]; ps[i()].x /= 2;";
+        let js = transpile(src);
+        assert!(js.contains("\nupdate$(xs, i(), $old => Math.trunc($old / (1 + 1)));\n"));
+        assert!(js.ends_with("($object => $object.x = Math.trunc($object.x / 2))(at$(ps, i()));\n"));
+    }
+
+    #[test]
+    fn test_incrementing_an_element_transpiles() {
+        let src = "let ys = [1]; fn idx(): int { return 0; } ys[idx()]++; ys[idx()]--;";
+        let js = transpile(src);
+        assert!(js.ends_with(
+            "update$(ys, idx(), $old => $old + 1);\n\
+             update$(ys, idx(), $old => $old - 1);\n"
+        ));
+    }
+
+    #[test]
+    fn test_list_and_map_equality_compares_contents() {
+        let js = transpile("let xs = [1]; print(xs == [1]); print({ \"a\": xs } != { \"a\": [1] }); print(1 == 1);");
+        assert!(js.starts_with("function equals$(lhs, rhs) {\n"));
+        assert!(js.ends_with(
+            "console.log(equals$(xs, [1]));\n\
+             console.log(!equals$(new Map([[\"a\", copy$(xs)]]), new Map([[\"a\", [1]]])));\n\
+             console.log(1 === 1);\n"
+        ));
+    }
+
+    #[test]
+    fn test_bitwise_and_power_operators_transpile() {
+        let src = "print((6 & 3) == 2); print(-2 ** 2); print(~1 << 40);";
+        assert!(transpile(src).ends_with(
+            "console.log((Number(BigInt.asIntN(64, BigInt(6) & BigInt(3)))) === 2);\n\
+             console.log(-power$(2, 2));\n\
+             console.log(shift$(Number(~BigInt(1)), 40, true));\n"
+        ));
+    }
+
+    #[test]
+    fn test_keyword_logical_operators_transpile() {
+        assert_eq!(
+            transpile("print(true and not false or false);"),
+            "console.log(true && !false || false);\n"
+        );
+    }
+
+    #[test]
+    fn test_indexing_transpiles() {
+        let src = "let xs = [1, 2, 3]; print(xs[-1]); print(xs[0..=-2]); xs[-1] += 1;";
+        let js = transpile(src);
+        assert!(js.contains(
+            "function update$(list, index, update) {\n\
+             \tset$(list, index, update(at$(list, index)));\n\
+             }\n"
+        ));
+        assert!(js.ends_with(
+            "let xs = [1,2,3];\n\
+             console.log(at$(xs, -1));\n\
+             console.log(show$(slice$(xs, 0, -2, true)));\n\
+             update$(xs, -1, $old => $old + 1);\n"
+        ));
+    }
+
+    #[test]
+    fn test_list_intrinsics_transpile() {
+        let src = "let xs = [2, 1]; xs.push(3); xs.insert(0, 4); print(xs.remove(1)); xs.sort(); print(xs.len());";
+        assert!(transpile(src).ends_with(
+            "let xs = [2,1];\n\
+             xs.push(3);\n\
+             insert$(xs, 0, 4);\n\
+             console.log(remove$(xs, 1));\n\
+             xs.sort((a, b) => a < b ? -1 : a > b ? 1 : 0);\n\
+             console.log(xs.length);\n"
+        ));
+    }
+
+    #[test]
+    fn test_interpolation_transpiles_to_template_literals() {
+        let src = r#"let x = 1; print("x = {x + 1}, ${x} `{x}`"); print("a\n\"b\"");"#;
+        assert_eq!(
+            transpile(src),
+            "let x = 1;\n\
+             console.log(`x = ${x + 1}, \\$${x} \\`${x}\\``);\n\
+             console.log(\"a\\n\\\"b\\\"\");\n"
+        );
+    }
+
+    #[test]
+    fn test_interpolated_lists_maps_and_structs_are_shown_like_print() {
+        let src = "struct P { x: int }; let xs = [1, 2]; let m = { \"k\": 1 }; let p = P(x = 3); \
+                   print(\"{xs} {m} {p} {xs.len()}\");";
+        let js = transpile(src);
+        assert!(js.starts_with("function show$(value, seen = []) {\n"));
+        assert!(js.ends_with("console.log(`${show$(xs)} ${show$(m)} ${show$(p)} ${xs.length}`);\n"));
+    }
+
+    #[test]
+    fn test_maps_transpile() {
+        let src = "let m = { \"a\": 1 }; m[\"b\"] = 2; print(m[\"a\"]); print(m.keys()); print(m.contains_key(\"a\")); for (k in m) { print(k); }";
+        assert!(transpile(src).ends_with(
+            "let m = new Map([[\"a\", 1]]);\n\
+             set$(m, \"b\", 2);\n\
+             console.log(at$(m, \"a\"));\n\
+             console.log(show$([...m.keys()]));\n\
+             console.log(m.has(\"a\"));\n\
+             for (const k of [...m.keys()]) {\n\
+             \tconsole.log(k);\n\
+             }\n"
+        ));
+    }
+
+    #[test]
+    fn test_coalesce_transpiles() {
+        let src =
+            "let x: int? = null; let b: bool? = null; print(x ?? 1); print(b ?? true || false);";
+        assert_eq!(
+            transpile(src),
+            "let x = null;\n\
+             let b = null;\n\
+             console.log(x ?? 1);\n\
+             console.log(b ?? (true || false));\n"
+        );
+    }
+
+    #[test]
+    fn test_string_intrinsics_transpile() {
+        let src = "let s = \"ab\"; print(s.len()); print(s.upper()); print(s.starts_with(\"a\")); print(s[-1]); print(s.substring(0, 1));";
+        assert!(transpile(src).ends_with(
+            "let s = \"ab\";\n\
+             console.log([...s].length);\n\
+             console.log(s.toUpperCase());\n\
+             console.log(s.startsWith(\"a\"));\n\
+             console.log(at$([...s], -1));\n\
+             console.log(substring$(s, 0, 1));\n"
+        ));
+    }
+
+    #[test]
+    fn test_definitions_are_hoisted() {
+        // classes aren't hoisted in javascript, so structs have to come out before they're used
+        let src = "print(one()); let p = P(a = 1); fn one() { return 1; } struct P { a: int };";
+        assert_eq!(
+            transpile(src),
+            "function one() {\n\
+             \treturn 1;\n\
+             }\n\
+             class $P {\n\
+             \tstatic name = \"P\";\n\
+             \tconstructor({a}) {\n\
+             \t\tthis.a = a;\n\
+             \t}\n\
+             }\n\
+             console.log(one());\n\
+             let p = new $P({a: 1});\n"
+        );
+    }
+    #[test]
+    fn test_stored_lists_and_maps_are_copied() {
+        let src = "let a = [1]; let b = a; let c = [a, [2]]; fn f(xs: list<int>) { return xs; } let d = f(a);";
+        assert_eq!(
+            transpile(src),
+            "function copy$(value) {\n\
+             \tif (Array.isArray(value)) return value.map(copy$);\n\
+             \tif (value instanceof Map) return new Map([...value].map(([key, item]) => [key, copy$(item)]));\n\
+             \treturn value;\n\
+             }\n\
+             function f(xs) {\n\
+             \treturn copy$(xs);\n\
+             }\n\
+             let a = [1];\n\
+             let b = copy$(a);\n\
+             let c = [copy$(a),[2]];\n\
+             let d = f(copy$(a));\n"
+        );
+        // nothing to copy, nothing to define
+        assert_eq!(
+            transpile("let x = 1; let y = x;"),
+            "let x = 1;\nlet y = x;\n"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs node, run it with `cargo test -- --ignored`"]
+    fn test_transpiled_programs_match_the_runtime() {
+        let programs = [
+            (
+                "copies",
+                "let a = [1, 2]; let b = a; b.push(3); \
+                 fn grow(xs: list<int>) { xs.push(9); } grow(a); \
+                 struct Bag { items: list<int> }; let bag = Bag(items = a); bag.items.push(4); \
+                 let grid = [a, a]; grid[0].push(5); \
+                 for (row in grid) { row.push(6); } \
+                 for (x in a) { a.push(x); } \
+                 print(a.len()); print(b.len()); print(bag.items.len()); \
+                 print(grid[0].len()); print(grid[1].len()); \
+                 let m = { \"k\": [1] }; let n = m; n[\"k\"].push(2); m[\"k\"].push(3); \
+                 for (key in m) { m[\"{key}!\"] = []; } \
+                 print(m[\"k\"].len()); print(n[\"k\"].len()); print(m.len());",
+            ),
+            (
+                "compound",
+                "let calls = 0; fn idx(): int { calls++; return 1; } \
+                 let xs = [1, 2, 3]; xs[idx()] += 10; xs[idx()] /= 4; \
+                 let m = { \"k\": 1.5 }; m[\"k\"] *= 2; \
+                 struct P { x: int } implement P { fn halve() { self.x /= 2; } }; \
+                 let ps = [P(x = 19)]; ps[idx() - 1].x /= 2; ps[0].x -= 1; ps[0].halve(); \
+                 print(xs[1]); print(m[\"k\"]); print(ps[0].x); print(calls);",
+            ),
+            (
+                "increments",
+                "let calls = 0; fn idx(): int { calls++; return -1; } \
+                 let ys = [1, 2, 3]; ys[idx()]++; ys[idx()]++; ys[idx() - 1]--; \
+                 let m = { \"a\": 1 }; m[\"a\"]++; \
+                 print(ys[0]); print(ys[1]); print(ys[2]); print(m[\"a\"]); print(calls);",
+            ),
+            (
+                "equality",
+                "let xs = [1, 2]; let ys = xs; ys.push(3); \
+                 print([1] == [1]); print([1] != [1]); print(xs == ys); print(xs == [1, 2]); \
+                 print({ \"a\": [1], \"b\": [] } == { \"b\": [], \"a\": [1] }); \
+                 print({ \"a\": 1 } != { \"a\": 2 }); print([[1.0]] == [[1]]);",
+            ),
+            (
+                "shown",
+                "struct P { x: int, next: P? }; let p = P(x = 3, next = null); p.next = p; \
+                 struct E {}; let xs = [1.5, 2.0]; let m = { \"k\": [1], \"j\": [] }; \
+                 print(\"{xs} {m} {p} {E()} {[[1], [2, 3]]}\"); print(xs); print(m); print(p);",
+            ),
+            (
+                "indexing",
+                "let xs = [1, 2, 3, 4]; print(xs[-1]); print(xs[1..3]); print(xs[1..=-1]); \
+                 print(xs[4..4]); print(xs[2..=1]); print(\"héllo\"[1..=2]); print(\"héllo\"[-4]); \
+                 print(\"héllo\".substring(1, -1)); print(\"héllo\".substring(5, 5)); \
+                 xs[-1] = 9; let m = { \"a\": [1] }; m[\"a\"][0] = 5; m[\"b\"] = []; \
+                 print(xs); print(m);",
+            ),
+            (
+                "contains",
+                "let xs = [[1], [2]]; let ms = [{ \"a\": 1 }]; \
+                 print(xs.contains([2])); print(xs.contains([3])); print(ms.contains({ \"a\": 1 }));",
+            ),
+            (
+                "list_methods",
+                "let xs = [1, 2]; xs.insert(2, 3); xs.insert(-1, 4); xs.insert(0, 5); \
+                 print(xs.remove(-1)); print(xs.remove(0)); print(xs.pop()); print(xs); \
+                 let m = { \"a\": [1], \"b\": [2] }; print(m.remove(\"a\")); print(m);",
+            ),
+            (
+                "bitwise",
+                "print(1 << 40); print(~1 << 40); print(-16 >> 2); print(6 ^ 3); print(1 << 52); \
+                 print(-1 >> 63); print(2 ** 52); print((-2) ** 51); print(-1 ** 1000000000001); print((-1) ** 1000000000001); \
+                 print(1 ** 1000000000000); print(0 ** 0); print(2 ** 0.5); print(2.0 ** -1);",
+            ),
+            (
+                "globals",
+                "struct Math { x: int }; let m = Math(x = 3); print(m.x ** 2);",
+            ),
+        ];
+        for (name, src) in programs {
+            let mut core: CoreObjects = CoreObjects::new(src);
+            core.get_program_mut().execute().unwrap();
+            let interpreted = core.get_program().stdout.clone();
+            let mut core: CoreObjects = CoreObjects::new(src);
+            core.get_program_mut().execute_vm().unwrap();
+            assert_eq!(core.get_program().stdout, interpreted, "{}", name);
+            assert_eq!(run_with_node(name, src), interpreted, "{}", name);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs node, run it with `cargo test -- --ignored`"]
+    fn test_transpiled_index_errors_match_the_runtime() {
+        let programs = [
+            ("past_the_end", "let xs = [1, 2, 3]; print(xs[3]);"),
+            ("before_the_start", "let xs = [1, 2, 3]; xs[-4] = 1;"),
+            ("reversed", "let xs = [1, 2, 3, 4]; print(xs[3..1]);"),
+            ("slice_end", "print(\"abc\"[1..=3]);"),
+            ("missing_key", "let m = { \"a\": 1 }; print(m[\"b\"]);"),
+            ("update", "let ys = [1]; ys[1]++;"),
+            ("insert", "let xs = [1]; xs.insert(3, 2);"),
+            ("remove", "let xs = [1]; print(xs.remove(-2));"),
+            ("negative_exponent", "print(2 ** -1);"),
+            ("power_overflow", "print(2 ** 63);"),
+            ("huge_exponent", "print(3 ** 1000000000000);"),
+            ("shift", "print(1 << 64);"),
+            ("negative_shift", "print(1 >> -1);"),
+            (
+                "remove_key",
+                "let m = { \"a\": 1 }; print(m.remove(\"a\")); m.remove(\"a\");",
+            ),
+            ("pop", "let xs = [1]; xs.pop(); xs.pop();"),
+            ("substring", "print(\"héllo\".substring(2, 6));"),
+            ("reversed_substring", "print(\"héllo\".substring(2, 1));"),
+        ];
+        for (name, src) in programs {
+            let mut core: CoreObjects = CoreObjects::new(src);
+            let error = core.get_program_mut().execute().unwrap_err();
+            let output = node(name, src);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(!output.status.success(), "{}", name);
+            assert!(
+                stderr.contains(&format!("Error: {}\n", error)),
+                "{}: {}",
+                name,
+                stderr
+            );
+        }
+    }
+}
@@ -12,7 +12,4 @@ impl Logger {
     pub fn warn(&self, msg: String) {
         println!("[WARN] {}", msg);
     }
-    pub fn error(&self, msg: String) {
-        panic!("[ERROR] {}", msg);
-    }
 }
@@ -1,11 +1,19 @@
+#![allow(clippy::borrowed_box)] // the AST is built out of boxed trait objects
+
+mod bytecode;
 mod config;
 mod core;
+mod diagnostic;
 mod javascript;
 mod logger;
+mod nasm;
 mod parser;
 mod program;
+mod repl;
 mod runtime;
 mod tokenizer;
+mod value;
+mod vm;
 
 use clap::{App, AppSettings, Arg, ArgMatches, Command};
 use std::cell::Cell;
@@ -26,14 +34,15 @@ thread_local! {
         CoreObjects::new_uninit()
     );
 
-    pub static IS_TESTING: Cell<bool> = Cell::new(false); // legacy trying to move away from this
+    pub static IS_TESTING: Cell<bool> = const { Cell::new(false) }; // legacy trying to move away from this
 
-    pub static DEBUG_INFO_LOGGING_ENABLED: Cell<bool> = Cell::new(false);
+    pub static DEBUG_INFO_LOGGING_ENABLED: Cell<bool> = const { Cell::new(false) };
 
-    pub static RUNTIME_DEBUG_LOGGING_ENABLED: Cell<bool> = Cell::new(false);
+    pub static RUNTIME_DEBUG_LOGGING_ENABLED: Cell<bool> = const { Cell::new(false) };
 }
 
 const LOGGER: Logger = Logger {};
+const STACK_SIZE: usize = 512 * 1024 * 1024;
 
 fn cli_builder() -> ArgMatches {
     App::new("white-lang")
@@ -51,12 +60,17 @@ fn cli_builder() -> ArgMatches {
             Command::new("interpret")
                 .subcommand_required(false)
                 .about("Interpret the source file using rust as a runtime for white-lang")
-                .arg(
+                .args(vec![
                     Arg::new("runtime-info")
                         .short('r')
                         .long("runtime-info")
                         .takes_value(false)
-                        .help("Show runtime debug information on execute")
+                        .help("Show runtime debug information on execute"),
+                    Arg::new("vm")
+                        .long("vm")
+                        .takes_value(false)
+                        .help("Compile the source file to bytecode and run it on the white-lang virtual machine"),
+                    ]
                 ),
             Command::new("transpile")
                 .subcommand_required(false)
@@ -83,6 +97,28 @@ fn cli_builder() -> ArgMatches {
             Command::new("compile")
                 .subcommand_required(false)
                 .about("Compile the source code into x86 assembly")
+                .args(vec![
+                    Arg::new("assemble")
+                        .short('a')
+                        .long("assemble")
+                        .takes_value(false)
+                        .help("Use nasm and ld to turn the generated assembly into an executable\n[NOTE] requires that nasm and ld are installed and in your PATH on your system"),
+                    Arg::new("do-cleanup")
+                        .short('d')
+                        .long("do-cleanup")
+                        .takes_value(false)
+                        .help("Remove the assembly and object files after assembling, only does something if used with -a"),
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("What to name the output files generated by white-lang ex: FILE = myfile, output files will be myfile.asm and myfile"),
+                    ]
+                ),
+            Command::new("repl")
+                .subcommand_required(false)
+                .about("Start an interactive white-lang session, no source file needed"),
         ])
         .arg(
             Arg::new("parse-info")
@@ -108,31 +144,66 @@ fn get_filename_no_extension(path: &String) -> String {
     }
 }
 
+/// Run an external tool (nasm, ld...), bailing out if it fails
+fn run_tool(program: &str, args: &[&str]) {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .unwrap_or_else(|_| panic!("Unable to execute `{} {}`", program, args.join(" ")));
+    if !output.status.success() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        eprintln!("[FATAL] `{} {}` failed", program, args.join(" "));
+        std::process::exit(1);
+    }
+}
+
+/// The tree walking runtime recurses for every white-lang call, so it runs on a thread with room
+/// for `MAX_CALL_DEPTH` of them, the main thread only has room for about a thousand
 fn main() {
+    let main = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("Unable to start the main thread");
+    if main.join().is_err() {
+        std::process::exit(101); // it panicked, and already said why
+    }
+}
+
+fn run() {
     let matches = cli_builder();
 
     let mut should_transpile: bool = false;
     let mut should_compile: bool = false;
     let mut should_interpret: bool = false;
+    let mut should_use_vm: bool = false;
     // argument options
     {
-        if let Some(_) = matches.subcommand_matches("interpret") {
+        if matches.subcommand_matches("repl").is_some() {
+            let stdin = std::io::stdin();
+            let mut repl = repl::Repl::new();
+            if let Err(why) = repl.run(&mut stdin.lock(), &mut std::io::stdout()) {
+                panic!("[FATAL] repl: {}", why);
+            }
+            return;
+        }
+
+        if matches.subcommand_matches("interpret").is_some() {
             LOGGER.info("Interpreting the source file using rust as a runtime".to_string());
             should_interpret = true;
         }
 
-        if let Some(_) = matches.subcommand_matches("compile") {
-            LOGGER.info("Interpreting the source file using rust as a runtime".to_string());
+        if matches.subcommand_matches("compile").is_some() {
+            LOGGER.info("Compiling the source file to x86-64 assembly".to_string());
             should_compile = true;
         }
 
-        if let Some(_) = matches.subcommand_matches("transpile") {
+        if matches.subcommand_matches("transpile").is_some() {
             LOGGER.info("Interpreting the source file using rust as a runtime".to_string());
             should_transpile = true;
         }
 
         if !should_transpile && !should_interpret && !should_compile {
-            LOGGER.warn("Please provide a command to specify what you want to do with the source file\n[HELP] -- interpret, compile or transpile".to_string());
+            LOGGER.warn("Please provide a command to specify what you want to do with the source file\n[HELP] -- interpret, compile, transpile or repl".to_string());
             return;
         }
 
@@ -147,6 +218,7 @@ fn main() {
                 RUNTIME_DEBUG_LOGGING_ENABLED.with(|c| c.set(true));
                 LOGGER.info("runtime-info is enabled".to_string());
             }
+            should_use_vm = subcommand.is_present("vm");
         }
     }
 
@@ -159,7 +231,7 @@ fn main() {
     };
     let path = Path::new(src_path);
     let display = path.display();
-    let mut file = match File::open(&path) {
+    let mut file = match File::open(path) {
         Ok(file) => file,
         Err(why) => panic!("[FATAL] couldn't read {}: {}", display, why),
     };
@@ -173,10 +245,67 @@ fn main() {
     };
     // do stuff with xxx.whl
     CORE_OBJECTS.with(|core| {
-        core.borrow_mut().set_src(source.as_str());
+        if let Err(diagnostics) = core.borrow_mut().set_src(source.as_str()) {
+            for diagnostic in diagnostics.iter() {
+                eprintln!("{}", diagnostic.render(src_path, source.as_str()));
+            }
+            eprintln!(
+                "[FATAL] could not compile {} due to {} previous error(s)",
+                display,
+                diagnostics.len()
+            );
+            std::process::exit(1);
+        }
         // compile the program
         if should_compile {
-            LOGGER.warn(format!("compilation is unimplemented."));
+            let asm = match core.borrow_mut().get_program_mut().compile_to_nasm() {
+                Ok(asm) => asm,
+                Err(errors) => {
+                    for error in errors.iter() {
+                        eprintln!("error: {}", error);
+                    }
+                    eprintln!(
+                        "[FATAL] could not compile {} due to {} previous error(s)",
+                        display,
+                        errors.len()
+                    );
+                    std::process::exit(1);
+                }
+            };
+            let subcommand = matches.subcommand_matches("compile").unwrap();
+            let executable_path = match subcommand.get_one::<String>("output") {
+                Some(output) => output.clone(),
+                None => get_filename_no_extension(src_path),
+            };
+            let asm_file_path = format!("{}.asm", executable_path);
+            let object_file_path = format!("{}.o", executable_path);
+            std::fs::write(&asm_file_path, asm)
+                .unwrap_or_else(|_| panic!("compile: failed to write to {}", asm_file_path));
+            if subcommand.is_present("assemble") {
+                run_tool(
+                    "nasm",
+                    &["-f", "elf64", "-o", &object_file_path, &asm_file_path],
+                );
+                // the generated code calls into libc for printing
+                run_tool(
+                    "ld",
+                    &[
+                        "-o",
+                        &executable_path,
+                        &object_file_path,
+                        "-lc",
+                        "-dynamic-linker",
+                        config::DYNAMIC_LINKER,
+                    ],
+                );
+                LOGGER.info(format!("Assembled {}", executable_path));
+                if subcommand.is_present("do-cleanup") {
+                    for path in [&asm_file_path, &object_file_path] {
+                        std::fs::remove_file(path)
+                            .unwrap_or_else(|_| panic!("Failed to remove {}", path));
+                    }
+                }
+            }
         }
         // transpile the program
         if should_transpile {
@@ -197,36 +326,49 @@ fn main() {
                     should_cleanup = true;
                 }
             }
-            std::fs::write(format!("{}", javascript_file_path), js)
-                .expect(format!("transpile: failed to write to {}", javascript_file_path).as_str());
+            std::fs::write(&javascript_file_path, js).unwrap_or_else(|_| {
+                panic!("transpile: failed to write to {}", javascript_file_path)
+            });
             if should_evaluate {
                 let node_exec = std::process::Command::new("node")
                     .arg(javascript_file_path.clone())
                     .output()
-                    .expect(format!("Unable to execute `node {}`", javascript_file_path).as_str());
+                    .unwrap_or_else(|_| {
+                        panic!("Unable to execute `node {}`", javascript_file_path)
+                    });
 
                 LOGGER.info(format!(
                     "Ran: `node {}\nstdout:\n{}\nstderr:\n{}",
                     javascript_file_path,
-                    String::from_utf8_lossy(&*node_exec.stdout),
-                    String::from_utf8_lossy(&*node_exec.stderr)
+                    String::from_utf8_lossy(&node_exec.stdout),
+                    String::from_utf8_lossy(&node_exec.stderr)
                 ));
 
                 if should_cleanup {
                     std::process::Command::new("rm")
                         .arg(javascript_file_path.clone())
-                        .spawn()
-                        .expect(format!("Failed to execute `rm {}", javascript_file_path).as_str());
+                        .status()
+                        .unwrap_or_else(|_| {
+                            panic!("Failed to execute `rm {}", javascript_file_path)
+                        });
                 }
             }
         }
         // interpret the program
         if should_interpret {
-            core.borrow_mut().get_program_mut().execute();
+            let result = if should_use_vm {
+                core.borrow_mut().get_program_mut().execute_vm()
+            } else {
+                core.borrow_mut().get_program_mut().execute()
+            };
             LOGGER.info(format!(
                 "output:\n{}",
                 core.borrow_mut().get_program_mut().stdout.clone()
             ));
+            if result.is_err() {
+                eprint!("{}", core.borrow_mut().get_program_mut().stderr);
+                std::process::exit(1);
+            }
         }
     })
 }
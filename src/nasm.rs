@@ -0,0 +1,533 @@
+use crate::parser::parser_traits::Expression;
+use crate::parser::whitetypes::Type;
+use std::collections::{BTreeSet, HashMap};
+
+mod test;
+
+/// Every white-lang value fits in a 64 bit register, ints and bools as themselves, floats as their
+/// bits and strings/lists as pointers
+const WORD: usize = 8;
+
+/// Routines the generated code leans on. Anything that calls into libc lines the stack up first
+/// since the generated code pushes and pops temporaries without caring about alignment
+const PRELUDE: &str = "\
+__white_print_int:
+    push rbx
+    mov rbx, rsp
+    and rsp, -16
+    mov rsi, rdi
+    lea rdi, [__white_fmt_int]
+    xor eax, eax
+    call printf wrt ..plt
+    mov rsp, rbx
+    pop rbx
+    ret
+
+; print the shortest number of decimal places that still reads back as the same float
+__white_print_float:
+    push rbx
+    push r12
+    push r13
+    mov rbx, rsp
+    and rsp, -16
+    mov r12, rdi
+    xor r13, r13
+__white_print_float_retry:
+    lea rdi, [__white_float_buf]
+    mov rsi, 512
+    lea rdx, [__white_fmt_float]
+    mov rcx, r13
+    movq xmm0, r12
+    mov eax, 1
+    call snprintf wrt ..plt
+    lea rdi, [__white_float_buf]
+    xor esi, esi
+    call strtod wrt ..plt
+    movq rax, xmm0
+    cmp rax, r12
+    je __white_print_float_done
+    inc r13
+    cmp r13, 340
+    jl __white_print_float_retry
+__white_print_float_done:
+    lea rdi, [__white_float_buf]
+    call __white_print_string
+    mov rsp, rbx
+    pop r13
+    pop r12
+    pop rbx
+    ret
+
+__white_print_bool:
+    lea rax, [__white_str_true]
+    lea rsi, [__white_str_false]
+    test rdi, rdi
+    cmovz rax, rsi
+    mov rdi, rax
+    jmp __white_print_string
+
+__white_print_null:
+    lea rdi, [__white_str_null]
+    jmp __white_print_string
+
+__white_print_newline:
+    lea rdi, [__white_str_newline]
+    jmp __white_print_string
+
+__white_print_string:
+    push rbx
+    mov rbx, rsp
+    and rsp, -16
+    mov rsi, rdi
+    lea rdi, [__white_fmt_string]
+    xor eax, eax
+    call printf wrt ..plt
+    mov rsp, rbx
+    pop rbx
+    ret
+
+; rdi is the list, rsi is the routine that prints a single element
+__white_print_list:
+    push r12
+    push r13
+    push r14
+    mov r12, rdi
+    mov r13, rsi
+    xor r14, r14
+    lea rdi, [__white_str_lbracket]
+    call __white_print_string
+__white_print_list_loop:
+    cmp r14, [r12]
+    jge __white_print_list_done
+    test r14, r14
+    jz __white_print_list_element
+    lea rdi, [__white_str_comma]
+    call __white_print_string
+__white_print_list_element:
+    mov rdi, [r12 + 8 + r14 * 8]
+    call r13
+    inc r14
+    jmp __white_print_list_loop
+__white_print_list_done:
+    lea rdi, [__white_str_rbracket]
+    call __white_print_string
+    pop r14
+    pop r13
+    pop r12
+    ret
+
+__white_alloc:
+    push rbx
+    mov rbx, rsp
+    and rsp, -16
+    call malloc wrt ..plt
+    mov rsp, rbx
+    pop rbx
+    ret
+
+; rax = 1 if the strings in rdi and rsi are the same, null only equals null
+__white_string_eq:
+    mov eax, 1
+    cmp rdi, rsi
+    je __white_string_eq_done
+    xor eax, eax
+    test rdi, rdi
+    jz __white_string_eq_done
+    test rsi, rsi
+    jz __white_string_eq_done
+    push rbx
+    mov rbx, rsp
+    and rsp, -16
+    call strcmp wrt ..plt
+    mov rsp, rbx
+    pop rbx
+    test eax, eax
+    sete al
+    movzx eax, al
+__white_string_eq_done:
+    ret
+
+__white_division_by_zero:
+    lea rsi, [__white_str_division_by_zero]
+    jmp __white_runtime_error
+
+; rcx is the shift count, complains the same way __white_runtime_error does
+__white_invalid_shift:
+    and rsp, -16
+    push rcx
+    push rcx
+    xor edi, edi
+    call fflush wrt ..plt
+    pop rdx
+    pop rdx
+    mov edi, 2
+    lea rsi, [__white_str_invalid_shift]
+    xor eax, eax
+    call dprintf wrt ..plt
+    mov edi, 1
+    call exit wrt ..plt
+
+; rax and rcx are the operands, rdx is the operator, complains the same way __white_runtime_error does
+__white_overflow_add:
+    lea rdx, [__white_str_op_add]
+    jmp __white_overflow
+__white_overflow_sub:
+    lea rdx, [__white_str_op_sub]
+    jmp __white_overflow
+__white_overflow_mul:
+    lea rdx, [__white_str_op_mul]
+    jmp __white_overflow
+__white_overflow_div:
+    lea rdx, [__white_str_op_div]
+    jmp __white_overflow
+__white_overflow_rem:
+    lea rdx, [__white_str_op_rem]
+__white_overflow:
+    and rsp, -16
+    push rax
+    push rcx
+    push rdx
+    push rdx
+    xor edi, edi
+    call fflush wrt ..plt
+    pop rcx
+    pop rcx
+    pop r8
+    pop rdx
+    mov edi, 2
+    lea rsi, [__white_str_overflow]
+    xor eax, eax
+    call dprintf wrt ..plt
+    mov edi, 1
+    call exit wrt ..plt
+
+; rsi is the message, prints whatever is buffered for stdout before complaining on stderr
+__white_runtime_error:
+    and rsp, -16
+    push rsi
+    push rsi
+    xor edi, edi
+    call fflush wrt ..plt
+    pop rsi
+    pop rsi
+    mov edi, 2
+    lea rdx, [__white_str_runtime_error]
+    xchg rsi, rdx
+    xor eax, eax
+    call dprintf wrt ..plt
+    mov edi, 1
+    call exit wrt ..plt
+";
+
+const PRELUDE_DATA: &str = "\
+__white_fmt_int: db \"%ld\", 0
+__white_fmt_float: db \"%.*f\", 0
+__white_fmt_string: db \"%s\", 0
+__white_str_true: db \"true\", 0
+__white_str_false: db \"false\", 0
+__white_str_null: db \"null\", 0
+__white_str_newline: db 10, 0
+__white_str_lbracket: db \"[\", 0
+__white_str_rbracket: db \"]\", 0
+__white_str_comma: db \", \", 0
+__white_str_runtime_error: db \"[RUNTIME ERROR] %s\", 10, 0
+__white_str_division_by_zero: db \"attempt to divide by zero\", 0
+__white_str_invalid_shift: db \"[RUNTIME ERROR] cannot shift an `int` by %ld bits\", 10, 0
+__white_str_overflow: db \"[RUNTIME ERROR] `%ld %s %ld` overflows an `int`\", 10, 0
+__white_str_op_add: db \"+\", 0
+__white_str_op_sub: db \"-\", 0
+__white_str_op_mul: db \"*\", 0
+__white_str_op_div: db \"/\", 0
+__white_str_op_rem: db \"%\", 0
+";
+
+/// A white-lang function that is in the middle of being compiled
+#[derive(Debug, Clone)]
+struct Frame {
+    name: String,
+    code: String,
+    locals: HashMap<String, isize>, // offsets from rbp
+    size: usize,                    // how many bytes of locals we need
+}
+
+/// Builds up an x86-64 nasm program, expressions leave their value in rax
+#[derive(Debug, Clone)]
+pub struct Nasm {
+    text: String,                 // the body of _start
+    functions: String,            // every white-lang function, compiled
+    data: String,                 // string literals
+    globals: BTreeSet<String>,    // variables at the top level live in .bss
+    frames: Vec<Frame>,           // the functions we are compiling right now
+    loops: Vec<(String, String)>, // (continue, break) labels for the loops we are in
+    labels: usize,
+    errors: Vec<String>,
+}
+impl Nasm {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            functions: String::new(),
+            data: String::new(),
+            globals: BTreeSet::new(),
+            frames: vec![],
+            loops: vec![],
+            labels: 0,
+            errors: vec![],
+        }
+    }
+
+    /// The finished program, ready for `nasm -f elf64`
+    pub fn get_src(&self) -> String {
+        let mut src = String::from("; generated by white-lang\ndefault rel\n\nglobal _start\n");
+        for function in [
+            "printf", "snprintf", "strtod", "strcmp", "malloc", "fflush", "dprintf", "exit",
+        ] {
+            src.push_str(format!("extern {}\n", function).as_str());
+        }
+        src.push_str("\nsection .data\n");
+        src.push_str(PRELUDE_DATA);
+        src.push_str(self.data.as_str());
+        src.push_str("\nsection .bss\n__white_float_buf: resb 512\n");
+        for global in self.globals.iter() {
+            src.push_str(format!("{}: resq 1\n", Nasm::global_label(global)).as_str());
+        }
+        src.push_str("\nsection .text\n_start:\n    push rbp\n    mov rbp, rsp\n");
+        src.push_str(self.text.as_str());
+        src.push_str("    and rsp, -16\n    xor edi, edi\n    call exit wrt ..plt\n\n");
+        src.push_str(self.functions.as_str());
+        src.push_str(PRELUDE);
+        src
+    }
+
+    /// Things the backend couldn't handle, if there are any the output shouldn't be assembled
+    pub fn get_errors(&self) -> &Vec<String> {
+        &self.errors
+    }
+
+    pub fn unsupported(&mut self, what: String) -> &mut Nasm {
+        self.errors
+            .push(format!("{} is not supported by the nasm backend", what));
+        self
+    }
+
+    /// Emit a single instruction
+    pub fn instr(&mut self, instruction: String) -> &mut Nasm {
+        let code = match self.frames.last_mut() {
+            Some(frame) => &mut frame.code,
+            None => &mut self.text,
+        };
+        code.push_str("    ");
+        code.push_str(instruction.as_str());
+        code.push('\n');
+        self
+    }
+
+    pub fn label(&mut self, label: &str) -> &mut Nasm {
+        let code = match self.frames.last_mut() {
+            Some(frame) => &mut frame.code,
+            None => &mut self.text,
+        };
+        code.push_str(label);
+        code.push_str(":\n");
+        self
+    }
+
+    /// A fresh label, `hint` just makes the assembly easier to read
+    pub fn new_label(&mut self, hint: &str) -> String {
+        self.labels += 1;
+        format!("__white_{}_{}", hint, self.labels)
+    }
+
+    /// Put a string literal in .data and load its address
+    pub fn string(&mut self, string: &str) -> &mut Nasm {
+        let label = self.new_label("string");
+        let mut bytes: Vec<String> = string.bytes().map(|b| b.to_string()).collect();
+        bytes.push(String::from("0"));
+        self.data
+            .push_str(format!("{}: db {}\n", label, bytes.join(", ")).as_str());
+        self.instr(format!("lea rax, [{}]", label))
+    }
+
+    fn global_label(name: &str) -> String {
+        format!("white_var_{}", name)
+    }
+
+    pub fn function_label(name: &str) -> String {
+        format!("white_fn_{}", name)
+    }
+
+    /// Where a variable lives, locals shadow globals
+    fn location(&mut self, name: &str) -> String {
+        if let Some(frame) = self.frames.last() {
+            if let Some(offset) = frame.locals.get(name) {
+                return format!("qword [rbp {:+}]", offset);
+            }
+        }
+        // functions can see globals that are defined after them
+        self.globals.insert(name.to_string());
+        format!("qword [{}]", Nasm::global_label(name))
+    }
+
+    /// `let name = ...`, makes a new local inside of functions
+    pub fn declare(&mut self, name: &str) -> &mut Nasm {
+        match self.frames.last_mut() {
+            Some(frame) => {
+                if !frame.locals.contains_key(name) {
+                    frame.size += WORD;
+                    frame
+                        .locals
+                        .insert(name.to_string(), -(frame.size as isize));
+                }
+            }
+            None => {
+                self.globals.insert(name.to_string());
+            }
+        }
+        self
+    }
+
+    /// rax <- name
+    pub fn load(&mut self, name: &str) -> &mut Nasm {
+        let location = self.location(name);
+        self.instr(format!("mov rax, {}", location))
+    }
+
+    /// name <- rax
+    pub fn store(&mut self, name: &str) -> &mut Nasm {
+        let location = self.location(name);
+        self.instr(format!("mov {}, rax", location))
+    }
+
+    /// Start compiling a function, arguments are pushed by the caller in order
+    pub fn begin_function(&mut self, name: &str, args: &[String]) -> &mut Nasm {
+        let mut locals = HashMap::new();
+        for (i, arg) in args.iter().enumerate() {
+            // past the saved rbp and the return address
+            locals.insert(
+                arg.clone(),
+                (2 + args.len() - 1 - i) as isize * WORD as isize,
+            );
+        }
+        self.frames.push(Frame {
+            name: name.to_string(),
+            code: String::new(),
+            locals,
+            size: 0,
+        });
+        self
+    }
+
+    /// Finish the current function, the frame is sized now that we've seen every local
+    pub fn end_function(&mut self) -> &mut Nasm {
+        let frame = self
+            .frames
+            .pop()
+            .expect("end_function without begin_function");
+        let label = Nasm::function_label(frame.name.as_str());
+        let size = (frame.size + 15) & !15;
+        self.functions.push_str(
+            format!(
+                "{}:\n    push rbp\n    mov rbp, rsp\n    sub rsp, {}\n{}    xor eax, eax\n{}_return:\n    mov rsp, rbp\n    pop rbp\n    ret\n\n",
+                label, size, frame.code, label
+            )
+            .as_str(),
+        );
+        self
+    }
+
+    /// `return`, the value is already in rax
+    pub fn ret(&mut self) -> &mut Nasm {
+        match self.frames.last() {
+            Some(frame) => {
+                let label = format!("{}_return", Nasm::function_label(frame.name.as_str()));
+                self.instr(format!("jmp {}", label))
+            }
+            None => self.unsupported(String::from("`return` outside of a function")),
+        }
+    }
+
+    pub fn push_loop(&mut self, continue_label: String, break_label: String) -> &mut Nasm {
+        self.loops.push((continue_label, break_label));
+        self
+    }
+
+    pub fn pop_loop(&mut self) -> &mut Nasm {
+        self.loops.pop();
+        self
+    }
+
+    pub fn brk(&mut self) -> &mut Nasm {
+        match self.loops.last() {
+            Some((_, break_label)) => {
+                let label = break_label.clone();
+                self.instr(format!("jmp {}", label))
+            }
+            None => self.unsupported(String::from("`break` outside of a loop")),
+        }
+    }
+
+    pub fn cont(&mut self) -> &mut Nasm {
+        match self.loops.last() {
+            Some((continue_label, _)) => {
+                let label = continue_label.clone();
+                self.instr(format!("jmp {}", label))
+            }
+            None => self.unsupported(String::from("`continue` outside of a loop")),
+        }
+    }
+
+    /// Compile both sides of a binary operator, lhs ends up in rax and rhs in rcx. If either side
+    /// is a float both get moved into xmm0 and xmm1 and this returns true
+    pub fn operands(&mut self, lhs: &Box<dyn Expression>, rhs: &Box<dyn Expression>) -> bool {
+        lhs.compile(self);
+        self.instr(String::from("push rax"));
+        rhs.compile(self);
+        self.instr(String::from("mov rcx, rax"))
+            .instr(String::from("pop rax"));
+        let (lhs_type, rhs_type) = (lhs.get_white_type(), rhs.get_white_type());
+        if lhs_type != Type::Float && rhs_type != Type::Float {
+            return false;
+        }
+        for (typ, register, xmm) in [(lhs_type, "rax", "xmm0"), (rhs_type, "rcx", "xmm1")] {
+            if typ == Type::Float {
+                self.instr(format!("movq {}, {}", xmm, register));
+            } else {
+                self.instr(format!("cvtsi2sd {}, {}", xmm, register));
+            }
+        }
+        true
+    }
+
+    /// Print whatever is in rax, followed by a newline
+    pub fn print(&mut self, typ: Type) -> &mut Nasm {
+        if typ != Type::Void {
+            self.instr(String::from("mov rdi, rax"));
+            match Nasm::printer(&typ) {
+                Some(printer) => {
+                    self.instr(format!("call {}", printer));
+                }
+                None => match Nasm::printer(&typ.get_type_from_list()) {
+                    Some(printer) if typ.is_list_type() => {
+                        self.instr(format!("lea rsi, [{}]", printer))
+                            .instr(String::from("call __white_print_list"));
+                    }
+                    _ => {
+                        self.unsupported(format!("printing a `{}`", typ));
+                    }
+                },
+            }
+        }
+        self.instr(String::from("call __white_print_newline"))
+    }
+
+    fn printer(typ: &Type) -> Option<&'static str> {
+        match typ {
+            Type::Integer => Some("__white_print_int"),
+            Type::Float => Some("__white_print_float"),
+            Type::Boolean => Some("__white_print_bool"),
+            Type::String | Type::Char => Some("__white_print_string"),
+            Type::Null => Some("__white_print_null"),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use crate::*;
+    use std::process::Command;
+
+    fn compile(src: &str) -> Result<String, Vec<String>> {
+        let mut core: CoreObjects = CoreObjects::new(src);
+        core.get_program_mut().compile_to_nasm()
+    }
+
+    fn interpret(src: &str) -> String {
+        let mut core: CoreObjects = CoreObjects::new(src);
+        core.get_program_mut()
+            .execute()
+            .unwrap_or_else(|_| panic!("{}", core.get_program().stderr));
+        core.get_program().stdout.clone()
+    }
+
+    /// Assemble, link and run `src`, needs nasm to do the assembling
+    fn compile_and_run(name: &str, src: &str) -> String {
+        let asm = compile(src).unwrap_or_else(|errors| panic!("{:?}", errors));
+        let path = std::env::temp_dir().join(format!("white_lang_nasm_{}", name));
+        let path = path.to_str().unwrap();
+        std::fs::write(format!("{}.asm", path), asm).unwrap();
+        let nasm = Command::new("nasm")
+            .args([
+                "-f",
+                "elf64",
+                "-o",
+                &format!("{}.o", path),
+                &format!("{}.asm", path),
+            ])
+            .status()
+            .expect("nasm is not installed");
+        assert!(nasm.success());
+        let ld = Command::new("ld")
+            .args(["-o", path, &format!("{}.o", path), "-lc", "-dynamic-linker"])
+            .arg(config::DYNAMIC_LINKER)
+            .status()
+            .unwrap();
+        assert!(ld.success());
+        let output = Command::new(path).output().unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[test]
+    fn test_compile_arithmetic() {
+        let asm = compile("1 + 2 * 3").unwrap();
+        assert!(asm.contains("imul rdx, rcx"));
+        assert!(asm.contains("add rdx, rcx"));
+        assert!(asm.contains("call __white_print_int"));
+        let asm = compile("1.5 - 2").unwrap();
+        assert!(asm.contains("cvtsi2sd xmm1, rcx"));
+        assert!(asm.contains("subsd xmm0, xmm1"));
+        assert!(asm.contains("call __white_print_float"));
+    }
+
+    #[test]
+    fn test_compile_functions_get_their_own_frame() {
+        let asm =
+            compile("fn add(a: int, b: int) : int { let c = a + b; return c; } print(add(1, 2));")
+                .unwrap();
+        assert!(asm.contains("white_fn_add:\n    push rbp\n    mov rbp, rsp\n    sub rsp, 16\n"));
+        assert!(asm.contains("mov rax, qword [rbp +24]")); // a
+        assert!(asm.contains("mov rax, qword [rbp +16]")); // b
+        assert!(asm.contains("mov qword [rbp -8], rax")); // c
+        assert!(asm.contains("jmp white_fn_add_return"));
+        assert!(asm.contains("call white_fn_add\n    add rsp, 16\n"));
+    }
+
+    #[test]
+    fn test_compile_int_arithmetic_checks_for_overflow() {
+        let asm = compile("let x = 1; print(x + 2); print(x - 2); print(x * 2);").unwrap();
+        assert!(asm.contains("add rdx, rcx\n    jo __white_overflow_add\n    mov rax, rdx\n"));
+        assert!(asm.contains("sub rdx, rcx\n    jo __white_overflow_sub\n    mov rax, rdx\n"));
+        assert!(asm.contains("imul rdx, rcx\n    jo __white_overflow_mul\n    mov rax, rdx\n"));
+        let asm = compile("let x = 1; print(x / 2); print(x % 2);").unwrap();
+        assert!(asm.contains("cmp rcx, -1\n    jne __white_divisor_ok_"));
+        assert!(asm.contains("neg rdx\n    jo __white_overflow_div\n"));
+        assert!(asm.contains("neg rdx\n    jo __white_overflow_rem\n"));
+        assert!(!compile("print(1.5 + 2.5);")
+            .unwrap()
+            .contains("jo __white_overflow_add"));
+    }
+
+    #[test]
+    fn test_compile_shifts_check_their_count() {
+        let asm = compile("print(1 << 2); print(8 >> 1);").unwrap();
+        assert!(asm.contains("cmp rcx, 63\n    ja __white_invalid_shift\n    sal rax, cl\n"));
+        assert!(asm.contains("cmp rcx, 63\n    ja __white_invalid_shift\n    sar rax, cl\n"));
+        assert!(!compile("print(6 & 3);")
+            .unwrap()
+            .contains("ja __white_invalid_shift"));
+    }
+
+    #[test]
+    fn test_compile_unsupported() {
+        let errors = compile("struct X { x: int } let x = X(x = 1);").unwrap_err();
+        assert_eq!(errors[0], "struct `X` is not supported by the nasm backend");
+    }
+
+    #[test]
+    #[ignore = "needs nasm and ld, run it with `cargo test -- --ignored`"]
+    fn test_compiled_programs_match_the_runtime() {
+        let programs = [
+            ("expression", "(1 + 2) * 3 - 10 / 4"),
+            ("floats", "print(1.1 - 0.2); print(0.33 + 0.33); print(-2.5 * 2); print(1.0);"),
+            (
+                "comparisons",
+                "print(1 < 2); print(2.5 >= 3.0); print(1 == 1); print(\"a\" != \"b\"); print(not true);",
+            ),
+            (
+                "loops",
+                "let x = 0; while (x < 10) { x = x + 1; if (x == 7) { break; } } print(x); \
+                 for (y in [1, 2, 3]) { print(y * y); } print([true, false]);",
+            ),
+            (
+                "continue",
+                "for (x in [1, 2, 3, 4]) { if (x == 2) { continue; } print(x); } \
+                 let y = 0; while (y < 5) { y = y + 1; if (y < 4) { continue; } print(y); }",
+            ),
+            (
+                "compound assignment",
+                "let x = 7; x += 3; x -= 1; x *= 4; x /= 5; x %= 4; x++; print(x); x--; print(x % 2);",
+            ),
+            (
+                "bitwise",
+                "print(6 & 3); print(6 | 3); print(6 ^ 3); print(~5); print(1 << 4 + 1); print(-16 >> 2);",
+            ),
+            (
+                "short circuit",
+                "let i = 0; print(i != 0 && 10 / i > 1); print(i == 0 or 10 / i > 1); print(true and not false);",
+            ),
+            (
+                "fibonacci",
+                "fn fib(n: int) : int { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); } \
+                 print(fib(20));",
+            ),
+        ];
+        for (name, src) in programs {
+            assert_eq!(compile_and_run(name, src), interpret(src), "{}", name);
+        }
+    }
+}
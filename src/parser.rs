@@ -1,28 +1,41 @@
 pub(crate) mod expression;
 pub(crate) mod parser_traits;
+pub(crate) mod precedence;
 pub(crate) mod statement;
+use crate::diagnostic::Diagnostic;
 use crate::parser::whitetypes::*;
 use crate::tokenizer::TokenType::*;
 use crate::tokenizer::*;
 use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 
-mod symbol_table;
+pub(crate) mod symbol_table;
 mod test;
 pub(crate) mod whitetypes;
 
 use expression::additiveexpression::AdditiveExpression;
+use expression::bitwiseexpression::BitwiseExpression;
 use expression::booleanliteralexpression::BooleanLiteralExpression;
+use expression::coalesceexpression::CoalesceExpression;
 use expression::comparisonexpression::ComparisonExpression;
 use expression::equalityexpression::EqualityExpression;
 use expression::factorexpression::FactorExpression;
+use expression::fieldaccessexpression::FieldAccessExpression;
 use expression::floatliteralexpression::FloatLiteralExpression;
 use expression::functioncallexpression::FunctionCallExpression;
 use expression::identifierexpression::IdentifierExpression;
+use expression::indexexpression::IndexExpression;
 use expression::integerliteralexpression::IntegerLiteralExpression;
+use expression::interpolatedstringexpression::InterpolatedStringExpression;
 use expression::listliteralexpression::ListLiteralExpression;
 use expression::logicalexpression::LogicalExpression;
+use expression::mapliteralexpression::MapLiteralExpression;
+use expression::methodcallexpression::MethodCallExpression;
 use expression::nullliteralexpression::NullLiteralExpression;
 use expression::parenthesizedexpression::ParenthesizedExpression;
+use expression::powerexpression::PowerExpression;
+use expression::rangeexpression::RangeExpression;
 use expression::stringliteralexpression::StringLiteralExpression;
 use expression::syntaxerrorexpression::SyntaxErrorExpression;
 use expression::unaryexpression::UnaryExpression;
@@ -38,11 +51,13 @@ use statement::ifstatement::IfStatement;
 use statement::printstatement::PrintStatement;
 
 use crate::config::WhiteLangFloat;
-use crate::parser::parser_traits::{add_parser_error, Expression, Statement};
+use crate::parser::parser_traits::{expression_id, Expression, Statement};
+use crate::parser::precedence::Precedence;
 use crate::parser::statement::breakstatement::BreakStatement;
+use crate::parser::statement::continuestatement::ContinueStatement;
 use crate::parser::statement::syntaxerrorstatement::SyntaxErrorStatement;
 use crate::parser::ParserErrorType::{
-    EmptyStructVariable, UnexpectedToken, UnknownName, UnterminatedArgList,
+    BadFloat, DuplicateName, IntegerTooLarge, UnexpectedToken, UnterminatedArgList,
 };
 use statement::variablestatement::VariableStatement;
 use symbol_table::SymbolTable;
@@ -69,30 +84,98 @@ pub enum ParserErrorType {
     UnexpectedExpression(Box<dyn Expression>),
     BadType(Type),
     EmptyStructVariable(String),
+    OutsideOfLoop(String), // `break` or `continue` where there is no loop to break out of
+    UnresolvedType(String), // the type of a variable or function that couldn't be inferred
+    IntegerTooLarge(String), // an integer literal that doesn't fit in an `int`
+    BadFloat(String),      // a float literal that isn't a number
+    MissingReturn(String), // a function that can get to its end without returning its value
+    VoidBinding(String),   // a variable given the result of a function that doesn't return one
 }
 impl ParserErrorType {
     fn to_error_msg(&self) -> String {
         use ParserErrorType::*;
         match self {
-            UnexpectedToken(tok) => format!("Unexpected token: {}", tok),
-            UnterminatedArgList(tok) => format!("Unterminated argument list: {}", tok),
-            UnterminatedList(tok) => format!("Unterminated list: {}", tok),
-            BadOperator(op) => format!("Bad operator: {}", op),
-            MismatchedTypes(t1, t2) => format!("Mismatched types: {:?} + {:?}", t1, t2),
-            SymbolDefinitionError => "Symbol definition error: {}".to_string(),
-            DuplicateName(name, typ) => format!(
-                "Duplicate name: {}\n| It was earlier defined as: {0} -> {:?}",
-                name, typ
-            ),
-            BadReturnType => "Bad return type: {}".to_string(),
-            BadVariableType => "Bad variable name: {}".to_string(),
-            UnknownName(name) => format!("Unknown name: {}", name),
-            ArgMismatch => "Argument mismatch: {}".to_string(),
-            IncompatibleTypes(t1, t2) => format!("Incompatible types: {:?} + {:?}", t1, t2),
-            UnexpectedExpression(expr) => format!("Unexpected expression: {:?}", expr),
-            BadType(typ) => format!("Bad type: {:?}", typ),
-            EmptyStructVariable(name) => format!("You didn't populate {} on this struct", name),
+            UnexpectedToken(tok) => match tok.get_type() {
+                Eof => "unexpected end of file".to_string(),
+                _ => format!("unexpected token `{}`", tok.get_string_value()),
+            },
+            UnterminatedArgList(_) => "unterminated argument list".to_string(),
+            UnterminatedList(_) => "unterminated list".to_string(),
+            BadOperator(op) => format!("bad operator `{}`", op),
+            MismatchedTypes(t1, t2) => {
+                format!("mismatched types: expected `{}`, found `{}`", t1, t2)
+            }
+            SymbolDefinitionError => "symbol definition error".to_string(),
+            DuplicateName(name, typ) => {
+                format!("`{}` is already defined as `{}`", name, typ)
+            }
+            BadReturnType => "bad return".to_string(),
+            BadVariableType => "bad variable type".to_string(),
+            UnknownName(name) => format!("cannot find `{}` in this scope", name),
+            ArgMismatch => "wrong number of arguments".to_string(),
+            IncompatibleTypes(t1, t2) => format!("incompatible types `{}` and `{}`", t1, t2),
+            UnexpectedExpression(expr) => format!("unexpected expression `{}`", expr.debug()),
+            BadType(typ) => format!("bad type `{}`", typ),
+            EmptyStructVariable(name) => format!("missing struct field `{}`", name),
+            OutsideOfLoop(keyword) => format!("`{}` outside of a loop", keyword),
+            UnresolvedType(name) => format!("cannot infer the type of `{}`", name),
+            IntegerTooLarge(literal) => format!("integer literal `{}` is too large", literal),
+            BadFloat(literal) => format!("invalid float literal `{}`", literal),
+            MissingReturn(name) => format!("`{}` doesn't return a value on every path", name),
+            VoidBinding(_) => "cannot bind the result of a void function".to_string(),
+        }
+    }
+}
+
+/// A parser error along with the token the parser was looking at when it happened
+#[derive(Clone, Debug)]
+pub struct ParserError {
+    error_type: ParserErrorType,
+    token: Token, // where the error starts
+    end: Token,   // where the error ends, i.e. the end of the expression or statement it is about
+    info: String,
+}
+impl ParserError {
+    pub fn new(error_type: ParserErrorType, token: Token, info: String) -> Self {
+        ParserError {
+            error_type,
+            end: token.clone(),
+            token,
+            info,
+        }
+    }
+    pub fn with_end(mut self, end: Token) -> Self {
+        self.end = end;
+        self
+    }
+    /// Turn the error into something that can be rendered against the source
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::from_tokens(self.error_type.to_error_msg(), &self.token, &self.end)
+            .with_help(self.info.clone())
+    }
+    pub fn get_error_type(&self) -> &ParserErrorType {
+        &self.error_type
+    }
+    pub fn get_token(&self) -> &Token {
+        &self.token
+    }
+    pub fn get_info(&self) -> String {
+        self.info.clone()
+    }
+}
+impl Display for ParserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[PARSE ERROR] line {}, offset {}: {}",
+            self.token.get_line(),
+            self.token.get_line_offset(),
+            self.error_type.to_error_msg()
+        )?;
+        if !self.info.is_empty() {
+            write!(f, "\n| {}", self.info)?;
         }
+        Ok(())
     }
 }
 
@@ -107,7 +190,9 @@ pub struct Parser {
     curr_idx: usize,                         // what token it's on
     curr_fn_def: String,                     // the current function definition
     curr_struct_def: String,                 // the current struct definition
-    errors: Vec<ParserErrorType>,            // and possible errors
+    structs: HashSet<String>,                // every struct the source defines, see `find_structs`
+    spans: HashMap<usize, (Token, Token)>,   // where each expression is, see `mark`
+    errors: Vec<ParserError>,                // and possible errors
 }
 #[allow(dead_code)]
 impl Parser {
@@ -125,6 +210,8 @@ impl Parser {
             curr_idx: 0,
             curr_fn_def: String::new(),
             curr_struct_def: String::new(),
+            structs: HashSet::new(),
+            spans: HashMap::new(),
             errors: vec![],
         }
     }
@@ -139,24 +226,27 @@ impl Parser {
             curr_idx: 0,
             curr_fn_def: "".to_string(),
             curr_struct_def: "".to_string(),
+            structs: HashSet::new(),
+            spans: HashMap::new(),
             errors: vec![],
         }
     }
 
     /// Set the token list, panics if the list is not empty
-    pub fn set_token_list(&mut self, token_list: &Vec<Token>) {
+    pub fn set_token_list(&mut self, token_list: &[Token]) {
         if self.token_list.is_empty() {
-            self.token_list = token_list.clone();
+            self.token_list = token_list.to_vec();
         } else {
             panic!("Do not set token list if the token list is already init!");
         }
     }
 
-    /// Parse tokens into an AST
-    pub fn parse(&mut self) {
+    /// Parse tokens into an AST, collecting every error along the way, then type check it
+    pub fn parse(&mut self) -> Result<(), Vec<ParserError>> {
         if !self.statement_list.is_empty() || !self.expr.get_white_type().eq(&Type::Error) {
-            return;
+            return self.get_result();
         }
+        self.find_structs();
         let expr = self.parse_expression(); // try to parse an expression
                                             // check if the parser got a good expression, and if all tokens are consumed
         if expr
@@ -167,39 +257,197 @@ impl Parser {
         {
             // if we've got more stuff to do, parse statements
             self.curr_idx = 0;
+            self.errors.clear(); // anything we found trying to parse an expression is moot
+            self.spans.clear();
+            let mut spans = vec![];
             while self.has_tokens() {
-                let mut stmt = self.parse_statement();
-                stmt.validate(&mut self.st);
+                let start = self.get_curr_tok();
+                let errors_before = self.errors.len();
+                let stmt = self.parse_statement();
                 self.statement_list.push(stmt);
-                self.check_for_parse_errors();
+                let end = self.token_list[self.curr_idx.saturating_sub(1)].clone();
+                // only check statements that parsed cleanly, otherwise we just get noise
+                spans.push((start, end, self.errors.len() == errors_before));
             }
+            self.check(&spans);
+            // the type errors come after the syntax errors, put them back in source order
+            self.errors.sort_by_key(|error| {
+                let token = error.get_token();
+                (token.get_line(), token.get_line_offset())
+            });
         } else {
             self.expr = expr;
+            self.expr.validate(&mut self.st);
+            let start = self.token_list[0].clone();
+            let end = self.token_list[self.token_list.len().saturating_sub(2)].clone();
+            self.take_validation_errors(&start, &end);
         }
+        self.get_result()
     }
 
-    /// Check for any parse errors, panics if there are any
-    fn check_for_parse_errors(&self) {
-        if !self.errors.is_empty() {
-            panic!(
-                "Parse error occurred at token `{}`, with error type: {:?}",
-                self.get_curr_tok().get_string_value(),
-                self.errors[0].to_error_msg()
-            );
+    /// Type check the statements once they're all parsed, so that functions and structs can be
+    /// used before they're defined. Each statement's errors cover its span, `(start, end, clean)`
+    fn check(&mut self, spans: &[(Token, Token, bool)]) {
+        let clean = |i: usize| spans[i].2;
+        // declare every function and struct up front
+        for (i, stmt) in self.statement_list.iter().enumerate() {
+            if !clean(i) {
+                continue;
+            }
+            if let Some(fds) = stmt.to_any().downcast_ref::<FunctionDefinitionStatement>() {
+                self.st.register_function(fds.name.clone(), fds.clone());
+            } else if let Some(sds) = stmt.to_any().downcast_ref::<StructDefinitionStatement>() {
+                if self.st.has_symbol(sds.name.clone()) {
+                    self.errors.push(
+                        ParserError::new(
+                            DuplicateName(sds.name.clone(), sds.get_type()),
+                            spans[i].0.clone(),
+                            format!("Duplicate name `{}`", sds.name),
+                        )
+                        .with_end(spans[i].1.clone()),
+                    );
+                }
+                self.st.register_struct(sds.name.clone(), sds.clone());
+                for method in sds.methods.values() {
+                    self.st
+                        .register_function(method.name.clone(), method.clone());
+                }
+            }
         }
-        for statement in &self.statement_list {
-            if let Some(_) = statement.to_any().downcast_ref::<SyntaxErrorStatement>() {
-                panic!(
-                    "Parse error occurred at token `{}`",
-                    self.get_curr_tok().get_string_value()
-                );
+        self.infer_return_types(&spans.iter().map(|span| span.2).collect::<Vec<_>>());
+        let mut statements = std::mem::take(&mut self.statement_list);
+        for (i, stmt) in statements.iter_mut().enumerate() {
+            if !clean(i) {
+                continue;
+            }
+            stmt.validate(&mut self.st);
+            let (start, end, _) = &spans[i];
+            self.take_validation_errors(start, end);
+        }
+        self.statement_list = statements;
+    }
+
+    /// Pick up the errors found validating the statement from `start` to `end`. Errors about an
+    /// expression point at it, as long as we know where it is, the rest cover the statement
+    fn take_validation_errors(&mut self, start: &Token, end: &Token) {
+        let position = |token: &Token| (token.get_line(), token.get_line_offset());
+        for (error_type, info, at) in self.st.take_errors() {
+            let (start, end) = at
+                .and_then(|id| self.spans.get(&id))
+                .filter(|(from, to)| {
+                    // a stale span from a dropped expression could land anywhere
+                    position(from) >= position(start) && position(to) <= position(end)
+                })
+                .map(|(from, to)| (from, to))
+                .unwrap_or((start, end));
+            self.errors
+                .push(ParserError::new(error_type, start.clone(), info).with_end(end.clone()));
+        }
+    }
+
+    /// Work out the return types of the declared functions that left theirs off. Each function
+    /// infers its own from its `return`s when it's validated, but calls to functions that haven't
+    /// been validated yet don't have a type, so we validate a copy of the program until every
+    /// return type we can work out is known. Whatever is left is reported by the real validation
+    fn infer_return_types(&mut self, clean: &[bool]) {
+        let mut unresolved = self.st.get_global_names();
+        unresolved.retain(|name| {
+            self.st
+                .get_function(name.clone())
+                .is_some_and(|fds| fds.get_return_type() == Initialized)
+        });
+        while !unresolved.is_empty() {
+            let mut st = self.st.clone();
+            for (stmt, _) in self
+                .statement_list
+                .iter()
+                .zip(clean)
+                .filter(|(_, clean)| **clean)
+            {
+                stmt.clone().validate(&mut st);
+            }
+            let before = unresolved.len();
+            unresolved.retain(|name| {
+                let typ = st.get_function(name.clone()).unwrap().get_return_type();
+                if typ == Initialized || typ == Type::Error {
+                    return true; // an error may just be from a call we couldn't type this round
+                }
+                let mut fds = self.st.get_function(name.clone()).unwrap();
+                fds.set_return_type(typ);
+                self.st.register_function(name.clone(), fds);
+                false
+            });
+            if unresolved.len() == before {
+                break; // nothing new, they're as known as they're going to get
+            }
+        }
+    }
+
+    fn get_result(&self) -> Result<(), Vec<ParserError>> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(self.errors.clone())
+    }
+
+    /// Record an error at the current token
+    fn add_error(&mut self, error: ParserErrorType, info: String) {
+        LOGGER.debug(format!("Error: {:?}\ninfo: {}", error, info), false);
+        let token = self.get_curr_tok();
+        self.push_error(ParserError::new(error, token, info));
+    }
+
+    /// Record an error covering everything from `start` up to the last token consumed
+    fn add_error_from(&mut self, start: Token, error: ParserErrorType, info: String) {
+        LOGGER.debug(format!("Error: {:?}\ninfo: {}", error, info), false);
+        let end = self.token_list[self.curr_idx.saturating_sub(1)].clone();
+        self.push_error(ParserError::new(error, start, info).with_end(end));
+    }
+
+    /// Keep a syntax error unless there's already one where it starts, the first error at a
+    /// token is the one that says what's actually wrong, the rest are fallout from it
+    fn push_error(&mut self, error: ParserError) {
+        let at = |error: &ParserError| (error.token.get_line(), error.token.get_line_offset());
+        if !self.errors.iter().any(|other| at(other) == at(&error)) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Skip tokens until we are just past a `;` or sitting on a `}` so that we can
+    /// resume parsing at the next statement
+    fn synchronize(&mut self) {
+        while self.has_tokens() {
+            if self.curr_idx > 0 {
+                let prev = self.token_list[self.curr_idx - 1].get_type();
+                if prev == SemiColon || prev == RightBrace {
+                    return;
+                }
             }
+            if self.match_token(RightBrace) {
+                return;
+            }
+            self.consume_token();
         }
     }
 
+    /// Parse against an existing symbol table, i.e. one kept alive by the repl
+    pub fn set_symbol_table(&mut self, st: SymbolTable) {
+        self.st = st;
+    }
+
+    /// Hand the symbol table back once parsing is done
+    pub fn take_symbol_table(&mut self) -> SymbolTable {
+        std::mem::replace(&mut self.st, SymbolTable::new())
+    }
+
     /// Retrieve the expression if the parser has it
     pub fn get_expr(&self) -> Option<&Box<dyn Expression>> {
-        if let Some(_) = self.expr.to_any().downcast_ref::<SyntaxErrorExpression>() {
+        if self
+            .expr
+            .to_any()
+            .downcast_ref::<SyntaxErrorExpression>()
+            .is_some()
+        {
             return None;
         }
         Some(&self.expr)
@@ -207,7 +455,12 @@ impl Parser {
 
     /// Get the statement list if the parser has it
     pub fn get_statements(&self) -> Option<&Vec<Box<dyn Statement>>> {
-        if let Some(_) = self.expr.to_any().downcast_ref::<SyntaxErrorExpression>() {
+        if self
+            .expr
+            .to_any()
+            .downcast_ref::<SyntaxErrorExpression>()
+            .is_some()
+        {
             return Some(&self.statement_list);
         }
         None
@@ -218,61 +471,85 @@ impl Parser {
         self.get_curr_tok().get_type().ne(&Eof)
     }
 
-    /// Used by parse elements to report errors during validation
-    pub fn error_panic(&self, error: ParserErrorType) {
-        println!("[PARSE ERROR][FATAL]: {}", error.to_error_msg());
-        panic!(
-            "[PARSE ERROR][FATAL]: {:?} occurred during validation",
-            error
-        );
-    }
-
     /// Whether or not the parser has any errors
     pub fn has_errors(&self) -> bool {
         !self.errors.is_empty()
     }
 
+    /// All the errors the parser has collected so far
+    pub fn get_errors(&self) -> &Vec<ParserError> {
+        &self.errors
+    }
+
     /// Returns the current token
     fn get_curr_tok(&self) -> Token {
         self.token_list[self.curr_idx].clone()
     }
 
-    /// Consume a token unconditionally
+    /// Consume a token unconditionally, we never move past Eof
     fn consume_token(&mut self) {
-        self.curr_idx += 1;
+        if self.curr_idx < self.token_list.len() - 1 {
+            self.curr_idx += 1;
+        }
     }
 
     /// Peek at the next token and see if it matches `typ`
     fn peek_next_token(&self, typ: TokenType) -> bool {
-        self.token_list[self.curr_idx + 1].get_type() == typ
+        match self.token_list.get(self.curr_idx + 1) {
+            Some(tok) => tok.get_type() == typ,
+            None => false,
+        }
     }
 
     /// Check a sequence of tokens and see if they match what we expected
-    fn token_list_like(&mut self, expected: Vec<TokenType>) -> bool {
+    fn token_list_like(&self, expected: Vec<TokenType>) -> bool {
         let len = expected.len();
-        let mut i = 0;
         if self.curr_idx + len > self.token_list.len() - 1 {
             LOGGER.debug(format!("Tried to check token list like {:?} but doing the check will cause an error, returning false.", expected), false);
             return false;
         }
-        for typ in expected.iter() {
-            let mut the_token = self.token_list[self.curr_idx + i].clone();
-            if the_token.string_value == "self".to_string() {
-                the_token.string_value = if self.curr_struct_def != "".to_string() {
-                    self.curr_struct_def.clone()
-                } else {
-                    panic!("Used `self` outside of a struct definition")
-                };
-                self.token_list[self.curr_idx + i] = the_token;
-            }
+        for (i, typ) in expected.iter().enumerate() {
             if !self.token_list[self.curr_idx + i].get_type().eq(typ) {
                 return false;
             }
-            i += 1;
         }
         true
     }
 
+    /// Look past a path like `x`, `x.y`, `self.y.z` or `xs[i].y` and return the type of the token
+    /// after it, `None` if we aren't looking at a path
+    fn token_after_path(&self) -> Option<TokenType> {
+        let mut idx = self.curr_idx;
+        match self.token_list[idx].get_type() {
+            Identifier => {}
+            _Self if self.peek_next_token(Dot) => {} // assigning to `self` itself makes no sense
+            _ => return None,
+        }
+        idx += 1;
+        loop {
+            match self.token_list.get(idx).map(|tok| tok.get_type()) {
+                Some(Dot) if self.token_list.get(idx + 1)?.get_type() == Identifier => idx += 2,
+                Some(LeftBracket) => {
+                    // skip to the matching `]`, indices can have brackets of their own
+                    let mut depth = 0;
+                    loop {
+                        match self.token_list.get(idx)?.get_type() {
+                            LeftBracket => depth += 1,
+                            RightBracket => depth -= 1,
+                            Eof => return None,
+                            _ => {}
+                        }
+                        idx += 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                }
+                typ => return typ,
+            }
+        }
+    }
+
     /// Check if the current token matches `typ`
     fn match_token(&self, typ: TokenType) -> bool {
         self.token_list[self.curr_idx].get_type().eq(&typ)
@@ -284,31 +561,28 @@ impl Parser {
             return false;
         }
         if self.match_token(typ) {
-            if typ == _Self {
-                let mut this_token = self.token_list[self.curr_idx].clone();
-                this_token.string_value = if self.curr_struct_def != "".to_string() {
-                    self.curr_struct_def.clone()
-                } else {
-                    panic!("Used `self` outside of a struct definition")
-                };
-                self.token_list[self.curr_idx] = this_token;
-            }
             self.consume_token();
             return true;
         }
         false
     }
 
-    /// Require that the current token matches `typ`, if it does, consume it and move on, else `panic`
+    /// Require that the current token matches `typ`, if it does, consume it and move on,
+    /// else record an error and leave the token for the parser to synchronize on
     fn require_token(&mut self, typ: TokenType) {
-        use self::ParserErrorType::*;
         if !self.match_token(typ) {
-            LOGGER.warn(format!(
-                "Got unexpected token during parse: {:?}",
-                self.get_curr_tok()
-            ));
-            self.errors.push(UnexpectedToken(self.get_curr_tok()));
-            self.check_for_parse_errors();
+            LOGGER.debug(
+                format!(
+                    "Got unexpected token during parse: {:?}",
+                    self.get_curr_tok()
+                ),
+                false,
+            );
+            self.add_error(
+                UnexpectedToken(self.get_curr_tok()),
+                format!("Expected {}", typ.describe()),
+            );
+            return;
         }
         self.consume_token();
     }
@@ -321,104 +595,153 @@ impl Parser {
         false
     }
 
-    /// Require some type to be at the current token
+    /// Require some type to be at the current token, `T?` is T or null
     fn require_a_type(&mut self) -> Type {
-        let types = vec!["string", "bool", "float", "int", "void"]; // all the primitive types we can assign to so far
-                                                                    // custom struct types
+        let typ = self.require_base_type();
+        match self.match_and_consume(Question) {
+            true => typ.get_nullable_type(), // T?, a T or null
+            false => typ,
+        }
+    }
+
+    /// A type without the `?`
+    fn require_base_type(&mut self) -> Type {
+        let types = ["string", "bool", "float", "int", "void"]; // all the primitive types we can assign to so far
+                                                                // custom struct types
 
         let curr_tok = self.get_curr_tok().get_string_value();
-        if self.st.has_symbol(curr_tok.clone()) {
-            if let Some(obj) = self.st.get_struct(curr_tok.clone()) {
-                self.consume_token();
-                return obj.get_type();
-            }
+        if self.is_struct(&curr_tok) {
+            self.consume_token();
+            return Type::Struct(curr_tok);
         }
-        for i in 0..types.len() - 1 {
+        for typ in types.iter().take(types.len() - 1) {
             // try to match some type, if we get a good one, return it
-            if types[i] == curr_tok {
+            if *typ == curr_tok {
                 self.consume_token();
-                return Type::new(types[i]);
+                return Type::new(typ);
             }
         }
-        let opt_typ = self.try_parse_list_type(); // try and parse a list<type>
-        if opt_typ.is_some() {
-            return opt_typ.unwrap();
+        if let Some(typ) = self.try_parse_list_type() {
+            // try and parse a list<type>
+            return typ;
+        }
+        if let Some(typ) = self.try_parse_map_type() {
+            return typ;
         }
-        self.errors.push(ParserErrorType::BadVariableType); // otherwise we've got some errors
-        self.check_for_parse_errors(); // -> panic
+        self.add_error(
+            ParserErrorType::BadVariableType,
+            format!("`{}` is not a type", curr_tok),
+        ); // otherwise we've got some errors
         Type::Error
     }
 
-    /// Try to parse a list type "list<_type_>"
+    /// Try to parse a list type "list<_type_>", the type can be another list
     fn try_parse_list_type(&mut self) -> Option<Type> {
         if self.match_str_val(String::from("list")) {
             // match list
             self.consume_token();
-            self.match_and_consume(Less); // <
+            self.require_token(Less); // <
             let typ = self.require_a_type().get_list_type(); // make sure we are parsing some type
-            self.match_and_consume(Greater); // >
-                                             //LOGGER.debug(format!("parsed a type: {:?}", typ));
-            if typ != Type::Error {
-                return Some(typ);
+            self.require_closing_angle(); // >
+                                          // if the element type was bad it has already been reported
+            return Some(typ);
+        }
+        None
+    }
+
+    /// Try to parse a map type "map<_key type_, _value type_>"
+    fn try_parse_map_type(&mut self) -> Option<Type> {
+        if self.match_str_val(String::from("map")) {
+            self.consume_token();
+            self.require_token(Less);
+            let key = self.require_a_type();
+            self.require_token(Comma);
+            let value = self.require_a_type();
+            self.require_closing_angle();
+            if key != Type::Error && !key.can_be_key() {
+                self.add_error(
+                    ParserErrorType::BadType(key.clone()),
+                    format!(
+                        "`{}` can't be the key of a map, use int, string, bool or char",
+                        key
+                    ),
+                );
             }
+            return Some(Type::new_map(key, value));
         }
         None
     }
 
+    /// Require the `>` that closes a type, `list<list<int>>` ends in a `>>` that closes two
+    fn require_closing_angle(&mut self) {
+        if self.match_token(GreaterGreater) {
+            // use up the first `>` and leave the other one for the enclosing type
+            self.token_list[self.curr_idx] = self.get_curr_tok().rest(Greater);
+        } else {
+            self.require_token(Greater);
+        }
+    }
+
     // -------------------------------------------------------------------------- //
     /* Statement Parsing - all the statements that White-Lang accepts for now     */
     // -------------------------------------------------------------------------- //
-    /// Parse a statement, if possible
+    /// Parse a statement, if there were any errors skip ahead to the next statement
     fn parse_statement(&mut self) -> Box<dyn Statement> {
+        let errors_before = self.errors.len();
+        let stmt = self.do_parse_statement();
+        if self.errors.len() > errors_before {
+            self.synchronize();
+        }
+        stmt
+    }
+
+    fn do_parse_statement(&mut self) -> Box<dyn Statement> {
         // pretty readable code, I assume you can read it :-)
-        let var_stmt = self.parse_variable_statement();
-        if var_stmt.is_some() {
-            return Box::new(var_stmt.unwrap());
+        if let Some(var_stmt) = self.parse_variable_statement() {
+            return Box::new(var_stmt);
+        }
+        if let Some(fds) = self.parse_function_definition_statement() {
+            return Box::new(fds);
         }
-        let fds = self.parse_function_definition_statement();
-        if fds.is_some() {
-            return Box::new(fds.unwrap());
+        if let Some(fcs) = self.parse_function_call_statement() {
+            return Box::new(fcs);
         }
-        let fcs = self.parse_function_call_statement();
-        if fcs.is_some() {
-            return Box::new(fcs.unwrap());
+        if let Some(ret) = self.parse_return_statement() {
+            return Box::new(ret);
         }
-        let ret = self.parse_return_statement();
-        if ret.is_some() {
-            return Box::new(ret.unwrap());
+        if let Some(for_stmt) = self.parse_for_statement() {
+            return Box::new(for_stmt);
         }
-        let for_stmt = self.parse_for_statement();
-        if for_stmt.is_some() {
-            return Box::new(for_stmt.unwrap());
+        if let Some(assign_stmt) = self.parse_assignment_statement() {
+            return Box::new(assign_stmt);
         }
-        let assign_stmt = self.parse_assignment_statement();
-        if assign_stmt.is_some() {
-            return Box::new(assign_stmt.unwrap());
+        if let Some(print_stmt) = self.parse_print_statement() {
+            return Box::new(print_stmt);
         }
-        let print_stmt = self.parse_print_statement();
-        if print_stmt.is_some() {
-            return Box::new(print_stmt.unwrap());
+        if let Some(if_stmt) = self.parse_if_statement() {
+            return Box::new(if_stmt);
         }
-        let if_stmt = self.parse_if_statement();
-        if if_stmt.is_some() {
-            return Box::new(if_stmt.unwrap());
+        if let Some(while_stmt) = self.parse_while_statement() {
+            return Box::new(while_stmt);
         }
-        let while_stmt = self.parse_while_statement();
-        if while_stmt.is_some() {
-            return Box::new(while_stmt.unwrap());
+        if let Some(break_stmt) = self.parse_break_statement() {
+            return Box::new(break_stmt);
         }
-        let break_stmt = self.parse_break_statement();
-        if break_stmt.is_some() {
-            return Box::new(break_stmt.unwrap());
+        if let Some(continue_stmt) = self.parse_continue_statement() {
+            return Box::new(continue_stmt);
         }
-        let struct_def_stmt = self.parse_struct_definition_statement();
-        if struct_def_stmt.is_some() {
-            return Box::new(struct_def_stmt.unwrap());
+        if let Some(struct_def_stmt) = self.parse_struct_definition_statement() {
+            return Box::new(struct_def_stmt);
         }
-        panic!(
-            "Parse error occurred at token {}",
-            self.get_curr_tok().get_string_value()
+        self.add_error(
+            UnexpectedToken(self.get_curr_tok()),
+            format!(
+                "`{}` does not start a statement",
+                self.get_curr_tok().get_string_value()
+            ),
         );
+        self.consume_token(); // make sure we make progress
+        Box::new(SyntaxErrorStatement::new())
     }
 
     /// Parse a function definition statement
@@ -448,30 +771,43 @@ impl Parser {
             self.consume_token();
             self.require_token(LeftParen);
             while !self.match_and_consume(RightParen) {
-                let mut expr = self.parse_expression();
-                fds.add_arg(expr.clone());
+                if !self.has_tokens() {
+                    self.add_error(
+                        UnterminatedArgList(self.get_curr_tok()),
+                        String::from("Expected `)`"),
+                    );
+                    break;
+                }
+                // an argument is just a name, anything else is reported rather than bound
+                let arg = self.get_curr_tok().get_string_value();
+                self.require_token(Identifier);
                 self.require_token(Colon);
                 let typ = self.require_a_type();
+                let mut expr = IdentifierExpression::new(arg);
                 expr.set_type(typ.clone());
                 LOGGER.debug(format!("[FUNCTION ARGUMENT]{:?}", expr), false);
-                fds.add_arg_type(typ.clone());
-                self.st.register_symbol(expr.debug(), typ.clone());
+                fds.add_arg(Box::new(expr));
+                fds.add_arg_type(typ);
                 if !self.match_and_consume(Comma) {
                     self.require_token(RightParen);
                     break;
                 }
-                if !self.has_tokens() {
-                    self.errors.push(UnterminatedArgList(self.get_curr_tok()));
-                    break;
-                }
             }
             if self.match_and_consume(Colon) {
                 fds.set_return_type(self.require_a_type());
+            } else {
+                fds.infer_return(); // worked out from its returns when it's checked
             }
             self.require_token(LeftBrace);
             self.curr_fn_def = name.clone();
-            self.st.register_function(name.clone(), fds.clone());
             while !self.match_and_consume(RightBrace) {
+                if !self.has_tokens() {
+                    self.add_error(
+                        UnexpectedToken(self.get_curr_tok()),
+                        format!("Function `{}` is missing a closing `}}`", name),
+                    );
+                    break;
+                }
                 let stmt = self.parse_statement();
                 fds.add_statement(stmt);
             }
@@ -497,16 +833,6 @@ impl Parser {
             }
             self.require_token(Equal);
             var_stmt.set_expr(self.parse_expression());
-            let st_says = match self.st.get_symbol_type(var_stmt.get_expr().debug()) {
-                // handles `let x = y;` in functions
-                Some(t) => t,
-                None => Initialized,
-            };
-            if st_says.ne(&Initialized) {
-                var_stmt.set_type(st_says.clone());
-            } else {
-                var_stmt.set_type(var_stmt.get_expr().get_white_type());
-            }
             self.require_token(SemiColon);
             LOGGER.debug(
                 format!("Parsed a variable statement: {:?}", var_stmt),
@@ -540,6 +866,13 @@ impl Parser {
             self.require_token(RightParen);
             self.require_token(LeftBrace);
             while !self.match_and_consume(RightBrace) {
+                if !self.has_tokens() {
+                    self.add_error(
+                        UnexpectedToken(self.get_curr_tok()),
+                        String::from("For loop is missing a closing `}`"),
+                    );
+                    break;
+                }
                 fs.add_statement(self.parse_statement());
             }
             LOGGER.debug(format!("Parsed a for statement: {:?}", fs), false);
@@ -549,12 +882,31 @@ impl Parser {
     }
 
     fn parse_assignment_statement(&mut self) -> Option<AssignmentStatement> {
-        // x = expr;
-        if self.match_token(Identifier) && self.peek_next_token(Equal) {
+        // x = expr; || x.field = expr; || x += expr; || x++;
+        let operator = self.token_after_path()?;
+        if matches!(
+            operator,
+            Equal
+                | PlusEqual
+                | MinusEqual
+                | StarEqual
+                | SlashEqual
+                | PercentEqual
+                | PlusPlus
+                | MinusMinus
+        ) {
             let mut assign_stmt = AssignmentStatement::new();
             assign_stmt.set_variable(self.parse_expression());
-            self.require_token(Equal);
-            assign_stmt.set_expr(self.parse_expression());
+            let operator_str = self.get_curr_tok().get_string_value();
+            self.consume_token();
+            match operator {
+                Equal => assign_stmt.set_expr(self.parse_expression()),
+                PlusPlus | MinusMinus => assign_stmt.set_compound(operator_str, None),
+                _ => {
+                    let value = self.parse_expression();
+                    assign_stmt.set_compound(operator_str, Some(value));
+                }
+            }
             self.require_token(SemiColon);
             LOGGER.debug(
                 format!("Parsed an assignment statement: {:?}", assign_stmt),
@@ -581,7 +933,7 @@ impl Parser {
     }
 
     fn parse_if_statement(&mut self) -> Option<IfStatement> {
-        // if ( <expr> ) { stmts } [else { stmts }]
+        // if ( <expr> ) { stmts } [else if ( <expr> ) { stmts }]* [else { stmts }]
         if self.match_token(If) {
             let mut if_stmt = IfStatement::new();
             self.require_token(If);
@@ -593,16 +945,27 @@ impl Parser {
             while !self.match_and_consume(RightBrace) && self.has_tokens() {
                 if_stmt.add_true_statement(self.parse_statement());
                 if !self.has_tokens() {
-                    self.errors.push(UnexpectedToken(self.get_curr_tok()));
+                    self.add_error(
+                        UnexpectedToken(self.get_curr_tok()),
+                        String::from("Expected `}`"),
+                    );
                     break;
                 }
             }
             if self.match_and_consume(Else) {
+                if let Some(else_if) = self.parse_if_statement() {
+                    if_stmt.set_else_if(else_if);
+                    LOGGER.debug(format!("Parsed an if statement: {:?}", if_stmt), false);
+                    return Some(if_stmt);
+                }
                 self.require_token(LeftBrace);
                 while !self.match_and_consume(RightBrace) && self.has_tokens() {
                     if_stmt.add_false_statement(self.parse_statement());
                     if !self.has_tokens() {
-                        self.errors.push(UnexpectedToken(self.get_curr_tok()));
+                        self.add_error(
+                            UnexpectedToken(self.get_curr_tok()),
+                            String::from("Expected `}`"),
+                        );
                         break;
                     }
                 }
@@ -614,10 +977,8 @@ impl Parser {
     }
 
     fn parse_function_call_statement(&mut self) -> Option<FunctionCallStatement> {
-        // x(args); || x.foo(args);
-        if self.match_token(Identifier) && self.peek_next_token(LeftParen) {
-            self.do_parse_function_call()
-        } else if self.token_list_like(vec![Identifier, Dot, Identifier, LeftParen]) {
+        // x(args); || x.foo(args); || self.foo(args);
+        if self.token_after_path() == Some(LeftParen) {
             self.do_parse_function_call()
         } else {
             None
@@ -625,7 +986,7 @@ impl Parser {
     }
 
     fn do_parse_function_call(&mut self) -> Option<FunctionCallStatement> {
-        let name = self.token_list[self.curr_idx.clone()].get_string_value();
+        let name = self.token_list[self.curr_idx].get_string_value();
         let expr = self.parse_expression(); // retrieve the function call expression
         self.require_token(SemiColon);
         let fcs = FunctionCallStatement::new(expr, name.clone());
@@ -633,7 +994,7 @@ impl Parser {
             format!("Parsed a function call statement: {:?}", fcs),
             false,
         );
-        return Some(fcs);
+        Some(fcs)
     }
 
     fn parse_while_statement(&mut self) -> Option<WhileStatement> {
@@ -648,7 +1009,10 @@ impl Parser {
             while !self.match_and_consume(RightBrace) && self.has_tokens() {
                 while_statement.add_body_statement(self.parse_statement());
                 if !self.has_tokens() {
-                    self.errors.push(UnexpectedToken(self.get_curr_tok()));
+                    self.add_error(
+                        UnexpectedToken(self.get_curr_tok()),
+                        String::from("Expected `}`"),
+                    );
                     break;
                 }
             }
@@ -670,6 +1034,14 @@ impl Parser {
         None
     }
 
+    fn parse_continue_statement(&mut self) -> Option<ContinueStatement> {
+        if self.match_and_consume(Continue) {
+            self.require_token(SemiColon);
+            return Some(ContinueStatement::new());
+        }
+        None
+    }
+
     fn parse_struct_definition_statement(&mut self) -> Option<StructDefinitionStatement> {
         if self.match_and_consume(Struct) {
             let name = self.get_curr_tok().get_string_value();
@@ -678,44 +1050,39 @@ impl Parser {
             self.consume_token();
             self.require_token(LeftBrace);
             while !self.match_and_consume(RightBrace) {
+                if !self.match_token(Identifier) {
+                    self.add_error(
+                        UnexpectedToken(self.get_curr_tok()),
+                        format!("Expected a field name while parsing struct `{}`", name),
+                    );
+                    break;
+                }
                 let expr = self.parse_identifier_expression();
                 self.require_token(Colon);
                 let typ = self.require_a_type();
                 sds.add_field(expr.debug(), typ);
                 self.match_and_consume(Comma);
-                if !self.has_tokens() {
-                    LOGGER.warn(format!(
-                        "Unexpected token: {:?} while parsing struct definition.",
-                        self.get_curr_tok()
-                    ));
-                    self.errors.push(UnexpectedToken(self.get_curr_tok()));
-                }
             }
             if self.match_and_consume(Implement) {
                 if self.match_str_val(name.clone()) {
                     self.consume_token();
                     self.require_token(LeftBrace);
                     while !self.match_and_consume(RightBrace) {
-                        let method_opt = self.parse_function_definition_statement();
-                        if method_opt.is_some() {
-                            let method = method_opt.unwrap();
+                        if let Some(method) = self.parse_function_definition_statement() {
                             sds.add_method(method.name.clone(), method.clone());
                         } else {
-                            self.errors.push(UnexpectedToken(self.get_curr_tok()));
-                            self.check_for_parse_errors();
-                        }
-                        if !self.has_tokens() {
-                            LOGGER.warn(format!(
-                                "Unexpected token: {:?} while parsing struct definition.",
-                                self.get_curr_tok()
-                            ));
-                            self.errors.push(UnexpectedToken(self.get_curr_tok()));
-                            self.check_for_parse_errors();
+                            self.add_error(
+                                UnexpectedToken(self.get_curr_tok()),
+                                format!("Expected a method while implementing `{}`", name),
+                            );
+                            break;
                         }
                     }
                 } else {
-                    self.errors.push(UnexpectedToken(self.get_curr_tok()));
-                    self.check_for_parse_errors();
+                    self.add_error(
+                        UnexpectedToken(self.get_curr_tok()),
+                        format!("Expected `implement {}`", name),
+                    );
                 }
             }
             self.require_token(SemiColon);
@@ -734,194 +1101,203 @@ impl Parser {
     ///
     /// https://en.wikipedia.org/wiki/Recursive_descent_parser#:~:text=In%20computer%20science%2C%20a%20recursive,the%20nonterminals%20of%20the%20grammar.
     fn parse_expression(&mut self) -> Box<dyn Expression> {
-        let expr = self.parse_additive_expression();
+        let expr = self.parse_range_expression();
         //if !IS_TESTING.with(|t| t.get()) {    // not sure if commenting this out is correct, we want to ensure that
         //    expr.validate(&self.st);          // all expressions/statements are validated, but having it here causes
         //}                                     // for statement variables to break.
         expr
     }
 
-    // <expr> + <expr>
-    fn parse_additive_expression(&mut self) -> Box<dyn Expression> {
-        let mut expr = self.parse_factor_expression();
-        while self.match_token(Plus) || self.match_token(Minus) {
-            let operator = self.get_curr_tok().get_string_value(); // get the operator value
+    // <expr>..<expr>, <expr>..=<expr>, either can be followed by `step <expr>`
+    fn parse_range_expression(&mut self) -> Box<dyn Expression> {
+        let first = self.get_curr_tok();
+        let start = self.parse_binary_expression(Precedence::Lowest);
+        let inclusive = self.match_token(TokenType::DotDotEqual);
+        if !inclusive && !self.match_token(TokenType::DotDot) {
+            return start;
+        }
+        self.consume_token();
+        let end = self.parse_binary_expression(Precedence::Lowest);
+        let mut range = RangeExpression::new(start, end, inclusive);
+        // `step` isn't a keyword, so it can still be used as a name everywhere else
+        if self.match_token(TokenType::Identifier) && self.match_str_val(String::from("step")) {
             self.consume_token();
-            let rhs = self.parse_factor_expression(); // get the right hand side
-            let additive_expr = AdditiveExpression::new(expr, operator.clone(), rhs);
-            LOGGER.debug(
-                format!("Parsed an additive expression: {:?}", additive_expr),
-                false,
-            );
-            expr = Box::new(additive_expr);
+            range.set_step(self.parse_binary_expression(Precedence::Lowest));
         }
-        expr
+        self.mark(first, Box::new(range))
     }
 
-    // <expr> * <expr>
-    fn parse_factor_expression(&mut self) -> Box<dyn Expression> {
-        // similar to additive, but deeper in the grammar so we evaluate factor expressions
-        // before we evaluate additive expressions
-        let mut expr = self.parse_logical_expression();
-        while self.match_token(Star) || self.match_token(Slash) {
-            let operator = self.get_curr_tok().get_string_value();
-            self.consume_token();
-            let rhs = self.parse_logical_expression();
-            let factor_expr = FactorExpression::new(expr, operator.clone(), rhs);
-            LOGGER.debug(
-                format!("Parsed a factor expression: {:?}", factor_expr),
-                false,
-            );
-            expr = Box::new(factor_expr);
+    /// Remember where `expr` is in the source, from `start` up to the last token consumed, so
+    /// errors found validating it can point at it
+    fn mark(&mut self, start: Token, expr: Box<dyn Expression>) -> Box<dyn Expression> {
+        if let Some(id) = expression_id(expr.as_ref()) {
+            let end = self.token_list[self.curr_idx.saturating_sub(1)].clone();
+            self.spans.insert(id, (start, end));
         }
         expr
     }
 
-    fn parse_logical_expression(&mut self) -> Box<dyn Expression> {
-        let mut expr = self.parse_comparison_expression();
-        while self.match_token(Land) || self.match_token(Lor) {
+    // <expr> <op> <expr>, precedence climbing over the table in `Precedence`, only operators
+    // that bind at least as tightly as `min` are parsed
+    fn parse_binary_expression(&mut self, min: Precedence) -> Box<dyn Expression> {
+        let start = self.get_curr_tok();
+        let mut expr = self.parse_unary_expression();
+        while let Some(precedence) = Precedence::of(self.get_curr_tok().get_type()) {
+            if precedence < min {
+                break;
+            }
             let operator = self.get_curr_tok().get_string_value();
             self.consume_token();
-            let rhs = self.parse_comparison_expression();
-            let mut logical_expr = LogicalExpression::new(expr, rhs);
-            logical_expr.set_operator(operator);
-            LOGGER.debug(
-                format!("Parsed a logical expression: {:?}", logical_expr),
-                false,
-            );
-            expr = Box::new(logical_expr);
+            let rhs = self.parse_binary_expression(precedence.rhs());
+            expr = self.binary_expression(expr, operator, precedence, rhs);
+            expr = self.mark(start.clone(), expr);
         }
         expr
     }
 
-    // <expr> (> | >= | < | <=) <expr>
-    fn parse_comparison_expression(&mut self) -> Box<dyn Expression> {
-        let expr = self.parse_equality_expression(); // try to parse a lower level expression first
-        if self.match_token(Greater)                // If we match either >
-            || self.match_token(GreaterEqual)       // >=,
-            || self.match_token(Less)               // <,
-            || self.match_token(LessEqual)
-        // or <=
-        {
-            let operator = self.get_curr_tok().get_string_value(); // retrieve op sign
-            self.consume_token(); // consume op
-            let rhs = self.parse_function_call_expression(); // get the right hand side expression
-            let comparison_expr = ComparisonExpression::new(expr, operator.clone(), rhs); // create the expression
-            LOGGER.debug(
-                format!("Parsed a comparison expression: {:?}", comparison_expr),
-                false,
-            );
-            return Box::new(comparison_expr); // return a box wrapper of the expression
-        }
-        expr // if we didn't parse a comparison expression, return whatever we parsed earlier
+    // build the node for a binary operator
+    fn binary_expression(
+        &self,
+        lhs: Box<dyn Expression>,
+        operator: String,
+        precedence: Precedence,
+        rhs: Box<dyn Expression>,
+    ) -> Box<dyn Expression> {
+        let expr: Box<dyn Expression> = match precedence {
+            Precedence::Coalesce => Box::new(CoalesceExpression::new(lhs, rhs)),
+            Precedence::Or | Precedence::And => {
+                let mut logical_expr = LogicalExpression::new(lhs, rhs);
+                logical_expr.set_operator(operator);
+                Box::new(logical_expr)
+            }
+            Precedence::Equality => Box::new(EqualityExpression::new(lhs, operator, rhs)),
+            Precedence::Comparison => Box::new(ComparisonExpression::new(lhs, operator, rhs)),
+            Precedence::BitOr | Precedence::BitXor | Precedence::BitAnd | Precedence::Shift => {
+                Box::new(BitwiseExpression::new(lhs, operator, rhs))
+            }
+            Precedence::Additive => Box::new(AdditiveExpression::new(lhs, operator, rhs)),
+            Precedence::Factor => Box::new(FactorExpression::new(lhs, operator, rhs)),
+            _ => Box::new(PowerExpression::new(lhs, rhs)),
+        };
+        LOGGER.debug(
+            format!("Parsed a {}: {:?}", expr.get_expr_type(), expr),
+            false,
+        );
+        expr
     }
 
-    // <expr> (== | !=) <expr>
-    fn parse_equality_expression(&mut self) -> Box<dyn Expression> {
-        let expr = self.parse_struct_expression(); // first try to parse a lower level expr
-        if self.match_token(EqualEqual) || self.match_token(BangEqual) {
-            // if we match either != or ==
-            let operator = self.get_curr_tok().get_string_value(); // get the op
+    // (- | not | ~) <expr>, binds looser than `**` and tighter than everything else
+    fn parse_unary_expression(&mut self) -> Box<dyn Expression> {
+        if self.match_token(Not) || self.match_token(Minus) || self.match_token(Lnot) {
+            let start = self.get_curr_tok();
+            let operator = self.get_curr_tok().get_string_value(); // get the op sign
             self.consume_token(); // consume the token
-            let rhs = self.parse_expression(); // parse some other expression
-            let equality_expr = EqualityExpression::new(expr, operator.clone(), rhs);
+            let expr = self.parse_binary_expression(Precedence::Unary);
+            let unary_expr = UnaryExpression::new(operator, expr); // create the new expr
             LOGGER.debug(
-                format!("Parsed an equality expression: {:?}", equality_expr),
+                format!("Parsed a unary expression: {:?}", unary_expr),
                 false,
             );
-            return Box::new(equality_expr); // return a box wrapper to the expr
+            return self.mark(start, Box::new(unary_expr)); // return a box wrapper
+        }
+        self.parse_postfix_expression()
+    }
+
+    // obj.field || obj.method(args) || list[index], these chain, i.e. self.points[0].x
+    fn parse_postfix_expression(&mut self) -> Box<dyn Expression> {
+        let start = self.get_curr_tok();
+        let mut expr = self.parse_struct_expression();
+        loop {
+            expr = self.mark(start.clone(), expr);
+            if self.match_and_consume(LeftBracket) {
+                let index = self.parse_expression();
+                self.require_token(RightBracket);
+                let index_expr = IndexExpression::new(expr, index);
+                LOGGER.debug(
+                    format!("Parsed an index expression: {:?}", index_expr),
+                    false,
+                );
+                expr = Box::new(index_expr);
+                continue;
+            }
+            if !self.match_and_consume(Dot) {
+                break;
+            }
+            if !self.match_token(Identifier) {
+                self.add_error(
+                    UnexpectedToken(self.get_curr_tok()),
+                    format!("Expected a field name after `{}.`", expr.debug()),
+                );
+                break;
+            }
+            let name = self.get_curr_tok().get_string_value();
+            self.consume_token();
+            if self.match_and_consume(LeftParen) {
+                let mut method_call = MethodCallExpression::new(expr, name);
+                for arg in self.parse_args() {
+                    method_call.add_arg(arg);
+                }
+                LOGGER.debug(
+                    format!("Parsed a method call expression: {:?}", method_call),
+                    false,
+                );
+                expr = Box::new(method_call);
+            } else {
+                expr = Box::new(FieldAccessExpression::new(expr, name));
+            }
         }
         expr
     }
 
     fn parse_struct_expression(&mut self) -> Box<dyn Expression> {
         let str_val = self.get_curr_tok().get_string_value();
-        if self.st.has_symbol(str_val.clone()) {
-            if let Some(obj) = self.st.get_struct(str_val.clone()) {
-                // now we're in business
-                let mut struct_expr = StructExpression::new(str_val.clone(), obj.get_type());
-                self.consume_token(); // consume the init token
-                self.require_token(LeftParen);
-                // fields
-                let fields = obj.fields.clone();
-                let fields_size = fields.keys().len();
-                let mut handled_fields = vec![];
-                while self.has_tokens() {
-                    let field_name = self.get_curr_tok().get_string_value();
-                    if fields.contains_key(&field_name) {
-                        self.consume_token();
-                        self.require_token(Equal);
-                        let expr = self.parse_expression();
-                        struct_expr.add_field(field_name.clone(), expr);
-                        handled_fields.push(field_name);
-                        if !self.match_and_consume(Comma) {
-                            if fields_size != handled_fields.len() {
-                                for field in handled_fields.iter() {
-                                    if !fields.contains_key(field) {
-                                        add_parser_error(
-                                            EmptyStructVariable(field.clone()),
-                                            "Empty variable".to_string(),
-                                        );
-                                    }
-                                }
-                                add_parser_error(
-                                    UnexpectedToken(self.get_curr_tok()),
-                                    "idk".to_string(),
-                                );
-                            }
-                            self.require_token(RightParen);
-                            break;
-                        }
-                    } else if self.match_and_consume(RightParen) {
-                        break;
-                    } else if !self.has_tokens() {
-                        add_parser_error(
-                            UnexpectedToken(self.get_curr_tok()),
-                            format!("You probably didn't close the paren on your struct :)"),
-                        );
-                    } else {
-                        add_parser_error(
-                            UnknownName(field_name.clone()),
-                            format!("No such field: {}", field_name),
-                        );
-                    }
+        if self.is_struct(&str_val) && self.peek_next_token(LeftParen) {
+            // now we're in business, the fields get checked against the struct during validation
+            let mut struct_expr = StructExpression::new(str_val.clone(), Type::Struct(str_val));
+            self.consume_token(); // consume the init token
+            self.require_token(LeftParen);
+            while self.has_tokens() {
+                if self.match_and_consume(RightParen) {
+                    break;
                 }
-                return Box::new(struct_expr);
+                let field_name = self.get_curr_tok().get_string_value();
+                self.require_token(Identifier);
+                self.require_token(Equal);
+                struct_expr.add_field(field_name, self.parse_expression());
+                if !self.match_and_consume(Comma) {
+                    self.require_token(RightParen);
+                    break;
+                }
+            }
+            if !self.has_tokens() {
+                self.add_error(
+                    UnexpectedToken(self.get_curr_tok()),
+                    String::from("You probably didn't close the paren on your struct :)"),
+                );
             }
+            return Box::new(struct_expr);
         }
         self.parse_function_call_expression()
     }
 
+    /// Whether `name` is a struct, either one defined anywhere in the source or one the
+    /// symbol table already knows about (from an earlier line in the REPL)
+    fn is_struct(&self, name: &str) -> bool {
+        self.structs.contains(name) || self.st.get_struct(name.to_string()).is_some()
+    }
+
+    /// Note the names of every struct before parsing so types and constructors can refer to
+    /// structs that are defined further down
+    fn find_structs(&mut self) {
+        for pair in self.token_list.windows(2) {
+            if pair[0].get_type() == Struct && pair[1].get_type() == Identifier {
+                self.structs.insert(pair[1].get_string_value());
+            }
+        }
+    }
+
     fn parse_function_call_expression(&mut self) -> Box<dyn Expression> {
-        if self.token_list_like(vec![Identifier, Dot, Identifier, LeftParen]) {
-            let namespace = self.get_curr_tok().get_string_value();
-            self.consume_token(); // namespace
-            self.consume_token(); // dot
-            let subname = self.get_curr_tok().get_string_value();
-            self.consume_token(); // subname
-            let mut expr = FunctionCallExpression::new(format!("{}.{}", namespace, subname));
-            self.require_token(LeftParen);
-            expr = self.decorate_function_call(expr);
-            LOGGER.debug(
-                format!("Parsed a function call expression: {:?}", expr),
-                false,
-            );
-            return Box::new(expr); // return whatever we have parsed
-        } else if self.token_list_like(vec![_Self, Dot, Identifier, LeftParen]) {
-            let namespace = self.get_curr_tok().get_string_value();
-            self.consume_token(); // namespace
-            self.consume_token(); // dot
-            let subname = self.get_curr_tok().get_string_value();
-            self.consume_token(); // subname
-            let mut expr = FunctionCallExpression::new(format!("{}.{}", namespace, subname));
-            self.require_token(LeftParen);
-            expr = self.decorate_function_call(expr);
-            LOGGER.debug(
-                format!("Parsed a function call expression: {:?}", expr),
-                false,
-            );
-            return Box::new(expr); // return whatever we have parsed
-        } else if self.match_token(Identifier) && self.peek_next_token(LeftParen) {
+        if self.match_token(Identifier) && self.peek_next_token(LeftParen) {
             // function_name(
             let mut expr = FunctionCallExpression::new(self.get_curr_tok().get_string_value());
             self.require_token(Identifier); // consume the name and paren
@@ -936,43 +1312,37 @@ impl Parser {
         self.parse_list_literal_expression() // otherwise parse a list literal
     }
 
-    fn decorate_function_call(
-        &mut self,
-        mut expr: FunctionCallExpression,
-    ) -> FunctionCallExpression {
+    /// Parse the arguments of a call, up to and including the closing `)`
+    fn parse_args(&mut self) -> Vec<Box<dyn Expression>> {
+        let mut args = vec![];
         loop {
             if self.match_and_consume(RightParen) {
                 break;
             }
-            let arg = self.parse_expression(); // parse some expression
-            expr.add_arg(arg); // add the argument to the argument vector
-                               // parse commas until the end of the arg list
+            args.push(self.parse_expression()); // parse some expression
+                                                // parse commas until the end of the arg list
             if !self.match_and_consume(Comma) {
                 self.require_token(RightParen);
                 break;
             }
             if !self.has_tokens() {
-                self.errors.push(UnterminatedArgList(self.get_curr_tok()));
+                self.add_error(
+                    UnterminatedArgList(self.get_curr_tok()),
+                    String::from("Expected `)`"),
+                );
                 break;
             }
         }
-        let st_says = match self.st.get_function(expr.get_name()) {
-            Some(fds) => {
-                LOGGER.debug(format!("Got a function definition from the symbol table, it's return type is: {:?}", fds.get_return_type()), false);
-                fds.get_return_type()
-            }
-            None => {
-                LOGGER.debug(
-                    format!(
-                        "Failed to retrieve function definition {} from the symbol table",
-                        expr.get_name()
-                    ),
-                    false,
-                );
-                Initialized
-            }
-        };
-        expr.set_type(st_says);
+        args
+    }
+
+    fn decorate_function_call(
+        &mut self,
+        mut expr: FunctionCallExpression,
+    ) -> FunctionCallExpression {
+        for arg in self.parse_args() {
+            expr.add_arg(arg); // add the argument to the argument vector
+        }
         expr
     }
 
@@ -983,18 +1353,41 @@ impl Parser {
             while !self.match_and_consume(RightBracket) {
                 // while the list hasn't been terminated
                 lle.add_expr(self.parse_expression()); // add some new expression to the list
-                self.match_and_consume(Comma); // consume a comma if there is any
-                if !self.has_tokens() {
-                    // check to see if we have an unterminated list
-                    self.errors
-                        .push(ParserErrorType::UnterminatedList(self.get_curr_tok())); // if we do add an error
+                if !self.match_and_consume(Comma) && !self.match_token(RightBracket) {
+                    // anything other than `,` or `]` means the list is unterminated
+                    self.add_error(
+                        ParserErrorType::UnterminatedList(self.get_curr_tok()),
+                        String::from("Expected `,` or `]`"),
+                    );
                     break;
                 }
             }
             LOGGER.debug(format!("Parsed a list literal: {:?}", lle), false);
-            lle.validate(&mut self.st);
             return Box::new(lle); // return a box wrapper of the lle
         }
+        self.parse_map_literal_expression()
+    }
+
+    // { key: value, ... }
+    fn parse_map_literal_expression(&mut self) -> Box<dyn Expression> {
+        if self.match_and_consume(LeftBrace) {
+            let mut mle = MapLiteralExpression::new();
+            while !self.match_and_consume(RightBrace) {
+                let key = self.parse_expression();
+                self.require_token(Colon);
+                mle.add_entry(key, self.parse_expression());
+                if !self.match_and_consume(Comma) && !self.match_token(RightBrace) {
+                    // anything other than `,` or `}` means the map is unterminated
+                    self.add_error(
+                        UnexpectedToken(self.get_curr_tok()),
+                        String::from("Expected `,` or `}`"),
+                    );
+                    break;
+                }
+            }
+            LOGGER.debug(format!("Parsed a map literal: {:?}", mle), false);
+            return Box::new(mle);
+        }
         self.parse_parenthesized_expression()
     }
 
@@ -1011,40 +1404,27 @@ impl Parser {
             );
             return Box::new(pe);
         }
-        self.parse_unary_expression()
-    }
-
-    fn parse_unary_expression(&mut self) -> Box<dyn Expression> {
-        if self.match_token(Not) || self.match_token(Minus) {
-            // match either not or -
-            let operator = self.get_curr_tok().get_string_value(); // get the op sign
-            self.consume_token(); // consume the token
-            let expr = self.parse_float_literal_expression(); // parse some other expression
-            let unary_expr = UnaryExpression::new(operator, expr); // create the new expr
-            LOGGER.debug(
-                format!("Parsed a unary expression: {:?}", unary_expr),
-                false,
-            );
-            return Box::new(unary_expr); // return a box wrapper
-        }
         self.parse_float_literal_expression()
     }
 
     fn parse_float_literal_expression(&mut self) -> Box<dyn Expression> {
-        return if self.match_token(Float) {
+        if self.match_token(Float) {
             // parse float
-            let expr = FloatLiteralExpression::new(
-                self.token_list[self.curr_idx]
-                    .get_string_value()
-                    .parse::<WhiteLangFloat>()
-                    .unwrap(),
-            );
+            let literal = self.get_curr_tok().get_string_value();
+            let value = match literal.parse::<WhiteLangFloat>() {
+                Ok(value) => value,
+                Err(_) => {
+                    self.add_error(BadFloat(literal), String::from("Expected a number"));
+                    0.0
+                }
+            };
+            let expr = FloatLiteralExpression::new(value);
             LOGGER.debug(format!("Parsed a float literal: {:?}", expr), false);
             self.consume_token();
             Box::new(expr)
         } else {
             self.parse_string_literal_expression()
-        };
+        }
     }
 
     fn parse_string_literal_expression(&mut self) -> Box<dyn Expression> {
@@ -1054,26 +1434,55 @@ impl Parser {
         //     self.token_list[self.curr_idx].get_type()
         // );
         // println!("will match: {}", self.match_token(Str));
-        return if self.match_token(Str) {
+        if self.match_token(Str) {
             // parse string
             let expr = StringLiteralExpression::new(self.get_curr_tok().get_string_value());
             self.consume_token();
             LOGGER.debug(format!("Parsed a string literal: {:?}", expr), false);
             Box::new(expr)
+        } else if self.match_token(StrStart) {
+            // "a {x} b {y} c" comes to us as StrStart("a ") x StrMiddle(" b ") y StrEnd(" c")
+            let mut expr =
+                InterpolatedStringExpression::new(self.get_curr_tok().get_string_value());
+            self.consume_token();
+            loop {
+                let part = self.parse_expression();
+                let after = self.get_curr_tok();
+                if !self.match_and_consume(StrMiddle) && !self.match_and_consume(StrEnd) {
+                    self.add_error(
+                        UnexpectedToken(after),
+                        String::from("Expected `}` to close the interpolation"),
+                    );
+                    expr.add_part(part, String::new());
+                    break;
+                }
+                expr.add_part(part, after.get_string_value());
+                if after.get_type() == StrEnd {
+                    break;
+                }
+            }
+            LOGGER.debug(format!("Parsed an interpolated string: {:?}", expr), false);
+            Box::new(expr)
         } else {
             self.parse_integer_literal_expression()
-        };
+        }
     }
 
     fn parse_integer_literal_expression(&mut self) -> Box<dyn Expression> {
         if self.match_token(Int) {
             // parse integers
-            let expr = IntegerLiteralExpression::new(
-                self.token_list[self.curr_idx]
-                    .get_string_value()
-                    .parse::<isize>()
-                    .unwrap(),
-            );
+            let literal = self.get_curr_tok().get_string_value();
+            let value = match literal.parse::<isize>() {
+                Ok(value) => value,
+                Err(_) => {
+                    self.add_error(
+                        IntegerTooLarge(literal),
+                        format!("The largest `int` is {}", isize::MAX),
+                    );
+                    0
+                }
+            };
+            let expr = IntegerLiteralExpression::new(value);
             self.consume_token();
             LOGGER.debug(format!("Parsed an integer literal: {:?}", expr), false);
             return Box::new(expr);
@@ -1082,28 +1491,24 @@ impl Parser {
     }
 
     fn parse_identifier_expression(&mut self) -> Box<dyn Expression> {
-        if self.match_token(Identifier) && self.peek_next_token(Dot) {
-            let name = self.get_curr_tok().get_string_value();
-            self.consume_token(); // name
-            self.consume_token(); // dot
-            let subname = if self.match_token(Identifier) {
-                let tmp = self.get_curr_tok().get_string_value();
-                self.consume_token(); // name
-                tmp
-            } else {
-                add_parser_error(UnexpectedToken(self.get_curr_tok()), "".to_string());
-                "".to_string() // unreachable
-            };
-            let expression = IdentifierExpression::new(format!("{}.{}", name, subname));
-            return Box::new(expression);
-        } else if self.match_token(Identifier) {
+        if self.match_token(Identifier) {
             let name = self.get_curr_tok().get_string_value();
             self.consume_token();
             let expr = IdentifierExpression::new(name);
             LOGGER.debug(format!("Parsed an identifier: {:?}", expr), false);
             return Box::new(expr);
         }
-        return self.parse_boolean_literal_expression();
+        if self.match_token(_Self) {
+            if self.curr_struct_def.is_empty() {
+                self.add_error(
+                    UnexpectedToken(self.get_curr_tok()),
+                    String::from("Used `self` outside of a struct definition"),
+                );
+            }
+            self.consume_token();
+            return Box::new(IdentifierExpression::new(String::from("self")));
+        }
+        self.parse_boolean_literal_expression()
     }
 
     fn parse_boolean_literal_expression(&mut self) -> Box<dyn Expression> {
@@ -1136,8 +1541,12 @@ impl Parser {
                 self.get_curr_tok()
             ));
         } else {
-            LOGGER.debug(format!("Couldn't parse an expression: this is likely because you've got a set of statements, like a normal human being, at the beginning of your file, who'da thunk"), true)
+            LOGGER.debug("Couldn't parse an expression: this is likely because you've got a set of statements, like a normal human being, at the beginning of your file, who'da thunk".to_string(), true)
         }
+        self.add_error(
+            UnexpectedToken(self.get_curr_tok()),
+            String::from("Expected an expression"),
+        );
         Box::new(SyntaxErrorExpression::new())
     }
 }
@@ -1,17 +1,26 @@
 // Expression modules
 pub(crate) mod additiveexpression;
+pub(crate) mod bitwiseexpression;
 pub(crate) mod booleanliteralexpression;
+pub(crate) mod coalesceexpression;
 pub(crate) mod comparisonexpression;
 pub(crate) mod equalityexpression;
 pub(crate) mod factorexpression;
+pub(crate) mod fieldaccessexpression;
 pub(crate) mod floatliteralexpression;
 pub(crate) mod functioncallexpression;
 pub(crate) mod identifierexpression;
+pub(crate) mod indexexpression;
 pub(crate) mod integerliteralexpression;
+pub(crate) mod interpolatedstringexpression;
 pub(crate) mod listliteralexpression;
 pub(crate) mod logicalexpression;
+pub(crate) mod mapliteralexpression;
+pub(crate) mod methodcallexpression;
 pub(crate) mod nullliteralexpression;
 pub(crate) mod parenthesizedexpression;
+pub(crate) mod powerexpression;
+pub(crate) mod rangeexpression;
 pub(crate) mod stringliteralexpression;
 pub(crate) mod structexpression;
 pub(crate) mod syntaxerrorexpression;
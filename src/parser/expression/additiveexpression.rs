@@ -1,11 +1,14 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
 
-use crate::config::{WhiteLangFloat, WhiteLangInt};
-use crate::parser::parser_traits::{add_parser_error, try_print_output, Expression, ToAny};
+use crate::config::WhiteLangFloat;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 #[derive(Clone, Debug)]
 pub(crate) struct AdditiveExpression {
@@ -26,66 +29,63 @@ impl ToAny for AdditiveExpression {
 }
 
 impl Expression for AdditiveExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        // Lots of syntax here, but basically what we are doing is getting whatever
-        // the runtime knows, and doing + or - on it based on the operator
-        // currently WhiteLangFloat and WhiteLangInt are hard coded to f64 and isize
-        // respectively but that will be system dependent in the future, I just
-        // abstracted it to make my life easier in the future
-        let lhs_eval = self.lhs.evaluate(runtime);
-        let rhs_eval = self.rhs.evaluate(runtime);
-        // debug info
-        if self.is_add {
-            crate::LOGGER.debug(
-                format!(
-                    "[ADD_EXPR]: {} + {}",
-                    try_print_output(&lhs_eval),
-                    try_print_output(&rhs_eval)
-                ),
-                crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
-            );
-        } else {
-            crate::LOGGER.debug(
-                format!(
-                    "[ADD_EXPR]: {} - {}",
-                    try_print_output(&lhs_eval),
-                    try_print_output(&rhs_eval)
-                ),
-                crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
-            );
-        }
-        if let Some(lhs_float) = lhs_eval.downcast_ref::<WhiteLangFloat>() {
-            if let Some(rhs_float) = rhs_eval.downcast_ref::<WhiteLangFloat>() {
-                if self.is_add {
-                    return Box::new(lhs_float + rhs_float);
-                }
-                return Box::new(lhs_float - rhs_float);
-            }
-            if let Some(rhs_int) = rhs_eval.downcast_ref::<WhiteLangInt>() {
-                if self.is_add {
-                    return Box::new(lhs_float - *rhs_int as WhiteLangFloat);
-                }
-                return Box::new(lhs_float - *rhs_int as WhiteLangFloat);
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let lhs_eval = self.lhs.evaluate(runtime)?;
+        let rhs_eval = self.rhs.evaluate(runtime)?;
+        crate::LOGGER.debug(
+            format!("[ADD_EXPR]: {} {} {}", lhs_eval, self.operator, rhs_eval),
+            crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
+        );
+        // ints stay ints, as soon as a float gets involved the result is a float
+        let (lhs, rhs) = match (lhs_eval, rhs_eval) {
+            (Value::Int(lhs), Value::Int(rhs)) => {
+                let result = match self.is_add {
+                    true => lhs.checked_add(rhs),
+                    false => lhs.checked_sub(rhs),
+                };
+                return result.map(Value::Int).ok_or_else(|| {
+                    RuntimeError::Overflow(format!("{} {} {}", lhs, self.operator, rhs))
+                });
             }
-        }
-        if let Some(lhs_int) = lhs_eval.downcast_ref::<WhiteLangInt>() {
-            if let Some(rhs_float) = rhs_eval.downcast_ref::<WhiteLangFloat>() {
-                if self.is_add {
-                    return Box::new(*lhs_int as WhiteLangFloat + rhs_float);
-                }
-                return Box::new(*lhs_int as WhiteLangFloat - rhs_float);
-            }
-            if let Some(rhs_int) = rhs_eval.downcast_ref::<WhiteLangInt>() {
-                if self.is_add {
-                    return Box::new(lhs_int + rhs_int);
-                }
-                return Box::new(lhs_int - rhs_int);
+            (Value::Float(lhs), Value::Float(rhs)) => (lhs, rhs),
+            (Value::Float(lhs), Value::Int(rhs)) => (lhs, rhs as WhiteLangFloat),
+            (Value::Int(lhs), Value::Float(rhs)) => (lhs as WhiteLangFloat, rhs),
+            (lhs, rhs) => {
+                return Err(RuntimeError::TypeMismatch(format!(
+                    "cannot {} `{}` and `{}`",
+                    if self.is_add { "add" } else { "subtract" },
+                    lhs.type_name(),
+                    rhs.type_name()
+                )))
             }
+        };
+        Ok(Value::Float(if self.is_add {
+            lhs + rhs
+        } else {
+            lhs - rhs
+        }))
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        if nasm.operands(&self.lhs, &self.rhs) {
+            let op = if self.is_add { "addsd" } else { "subsd" };
+            nasm.instr(format!("{} xmm0, xmm1", op))
+                .instr(String::from("movq rax, xmm0"));
+        } else {
+            // work on a copy so the operands are still around to complain about
+            let op = if self.is_add { "add" } else { "sub" };
+            nasm.instr(String::from("mov rdx, rax"))
+                .instr(format!("{} rdx, rcx", op))
+                .instr(format!("jo __white_overflow_{}", op))
+                .instr(String::from("mov rax, rdx"));
         }
-        unreachable!()
     }
 
-    fn compile(&self) {}
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.lhs.emit(bytecode);
+        self.rhs.emit(bytecode);
+        bytecode.emit(if self.is_add { Op::Add } else { Op::Subtract });
+    }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         self.lhs.transpile(javascript);
@@ -99,26 +99,32 @@ impl Expression for AdditiveExpression {
         self.lhs.validate(st);
         self.rhs.validate(st);
         if self.lhs.get_white_type() != Type::Integer && self.lhs.get_white_type() != Type::Float {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::IncompatibleTypes(
                     self.lhs.get_white_type(),
                     self.rhs.get_white_type(),
                 ),
                 format!(
-                    "You cannot add/subtract two non number types. lhs: {:?} rhs: {:?}",
-                    self.lhs, self.rhs
+                    "You cannot add/subtract two non number types. lhs: `{}` rhs: `{}`",
+                    self.lhs.get_white_type(),
+                    self.rhs.get_white_type()
                 ),
             );
         }
         if self.rhs.get_white_type() != Type::Integer && self.rhs.get_white_type() != Type::Float {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::IncompatibleTypes(
                     self.lhs.get_white_type(),
                     self.rhs.get_white_type(),
                 ),
                 format!(
-                    "You cannot add/subtract two non number types. lhs: {:?} rhs: {:?}",
-                    self.lhs, self.rhs
+                    "You cannot add/subtract two non number types. lhs: `{}` rhs: `{}`",
+                    self.lhs.get_white_type(),
+                    self.rhs.get_white_type()
                 ),
             );
         }
@@ -0,0 +1,145 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use std::any::Any;
+
+// <expr> & <expr>, |, ^, << and >>, only ints have bits to play with
+#[derive(Clone, Debug)]
+pub(crate) struct BitwiseExpression {
+    lhs: Box<dyn Expression>,
+    operator: String,
+    rhs: Box<dyn Expression>,
+}
+
+impl ToAny for BitwiseExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for BitwiseExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let lhs = self.lhs.evaluate(runtime)?;
+        let rhs = self.rhs.evaluate(runtime)?;
+        bitwise(self.operator.as_str(), lhs, rhs)
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.operands(&self.lhs, &self.rhs);
+        if self.operator == "<<" || self.operator == ">>" {
+            // sal and sar only look at the bottom 6 bits of cl, compared unsigned a negative
+            // count is out of range too
+            nasm.instr(String::from("cmp rcx, 63"))
+                .instr(String::from("ja __white_invalid_shift"));
+        }
+        nasm.instr(String::from(match self.operator.as_str() {
+            "&" => "and rax, rcx",
+            "|" => "or rax, rcx",
+            "^" => "xor rax, rcx",
+            "<<" => "sal rax, cl",
+            _ => "sar rax, cl",
+        }));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.lhs.emit(bytecode);
+        self.rhs.emit(bytecode);
+        bytecode.emit(match self.operator.as_str() {
+            "&" => Op::BitAnd,
+            "|" => Op::BitOr,
+            "^" => Op::BitXor,
+            "<<" => Op::ShiftLeft,
+            _ => Op::ShiftRight,
+        });
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        if self.operator == "<<" || self.operator == ">>" {
+            let left = self.operator == "<<";
+            javascript.transpile_shift(self.lhs.as_ref(), self.rhs.as_ref(), left);
+            return;
+        }
+        // javascript does its bitwise operations on numbers as 32 bit ints, so `1 << 40` would
+        // come out wrong, BigInts have all the bits and asIntN wraps them to an `int`
+        javascript.append_no_tabs(String::from("Number(BigInt.asIntN(64, BigInt("));
+        self.lhs.transpile(javascript);
+        javascript.append_no_tabs(format!(") {} BigInt(", self.operator));
+        self.rhs.transpile(javascript);
+        javascript.append_no_tabs(String::from(")))"));
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.lhs.validate(st);
+        self.rhs.validate(st);
+        let (lhs, rhs) = (self.lhs.get_white_type(), self.rhs.get_white_type());
+        if lhs != Type::Integer || rhs != Type::Integer {
+            add_parser_error_at(
+                st,
+                self,
+                ParserErrorType::IncompatibleTypes(lhs.clone(), rhs.clone()),
+                format!(
+                    "You can only use `{}` on ints. lhs: `{}` rhs: `{}`",
+                    self.operator, lhs, rhs
+                ),
+            );
+        }
+    }
+
+    fn debug(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.lhs.debug(),
+            self.operator,
+            self.rhs.debug()
+        )
+    }
+
+    fn get_white_type(&self) -> Type {
+        Type::Integer
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("BitwiseExpression")
+    }
+}
+impl BitwiseExpression {
+    pub(crate) fn new(
+        lhs: Box<dyn Expression>,
+        operator: String,
+        rhs: Box<dyn Expression>,
+    ) -> BitwiseExpression {
+        BitwiseExpression { lhs, operator, rhs }
+    }
+}
+
+/// Apply a bitwise operator to two values, shared with the vm
+pub(crate) fn bitwise(operator: &str, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => (lhs, rhs),
+        (lhs, rhs) => {
+            return Err(RuntimeError::TypeMismatch(format!(
+                "cannot apply `{}` to `{}` and `{}`",
+                operator,
+                lhs.type_name(),
+                rhs.type_name()
+            )))
+        }
+    };
+    Ok(Value::Int(match operator {
+        "&" => lhs & rhs,
+        "|" => lhs | rhs,
+        "^" => lhs ^ rhs,
+        _ if !(0..64).contains(&rhs) => return Err(RuntimeError::InvalidShift(rhs)),
+        "<<" => lhs << rhs,
+        _ => lhs >> rhs, // keeps the sign, like javascript's >>
+    }))
+}
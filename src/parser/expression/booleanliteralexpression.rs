@@ -1,8 +1,11 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 use super::stringliteralexpression::StringLiteralExpression;
@@ -23,12 +26,16 @@ impl ToAny for BooleanLiteralExpression {
 }
 
 impl Expression for BooleanLiteralExpression {
-    fn evaluate(&self, _runtime: &mut Runtime) -> Box<dyn Any> {
-        Box::new(self.boolean)
+    fn evaluate(&self, _runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        Ok(Value::Bool(self.boolean))
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.instr(format!("mov rax, {}", self.boolean as u8));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.constant(Value::Bool(self.boolean));
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
@@ -38,7 +45,7 @@ impl Expression for BooleanLiteralExpression {
     fn validate(&mut self, _st: &mut SymbolTable) {}
 
     fn debug(&self) -> String {
-        String::from(self.boolean.to_string())
+        self.boolean.to_string()
     }
 
     fn get_white_type(&self) -> Type {
@@ -54,6 +61,11 @@ impl BooleanLiteralExpression {
         BooleanLiteralExpression { boolean }
     }
 
+    pub(crate) fn get_value(&self) -> bool {
+        self.boolean
+    }
+
+    #[allow(dead_code)]
     pub(crate) fn to_string_literal(&self) -> StringLiteralExpression {
         StringLiteralExpression::new(self.boolean.to_string())
     }
@@ -0,0 +1,133 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::expression::listliteralexpression::type_empty_literal;
+use crate::parser::expression::logicalexpression::LogicalExpression;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType::{BadType, MismatchedTypes};
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use std::any::Any;
+
+// x ?? default, the default only runs when x is null
+#[derive(Clone, Debug)]
+pub(crate) struct CoalesceExpression {
+    lhs: Box<dyn Expression>,
+    rhs: Box<dyn Expression>,
+    typ: Type,
+}
+
+impl ToAny for CoalesceExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for CoalesceExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        match self.lhs.evaluate(runtime)? {
+            Value::Null => self.rhs.evaluate(runtime),
+            lhs => Ok(lhs),
+        }
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("`{}`", self.debug()));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.lhs.emit(bytecode);
+        let done = bytecode.emit(Op::Coalesce(0));
+        self.rhs.emit(bytecode);
+        bytecode.patch(done);
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        // javascript won't mix `??` with `&&` or `||` unless one of them is in parentheses
+        for (i, side) in [&self.lhs, &self.rhs].into_iter().enumerate() {
+            if i == 1 {
+                javascript.append_no_tabs(String::from(" ?? "));
+            }
+            let logical = side.to_any().is::<LogicalExpression>();
+            if logical {
+                javascript.append_no_tabs(String::from("("));
+            }
+            side.transpile(javascript);
+            if logical {
+                javascript.append_no_tabs(String::from(")"));
+            }
+        }
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.lhs.validate(st);
+        self.rhs.validate(st);
+        let lhs = self.lhs.get_white_type();
+        self.typ = Type::Error;
+        if lhs == Type::Error || self.rhs.get_white_type() == Type::Error {
+            return; // whatever went wrong has already been reported
+        }
+        if !lhs.is_nullable() {
+            add_parser_error_at(
+                st,
+                self.lhs.as_ref(),
+                BadType(lhs.clone()),
+                format!(
+                    "`{}` is `{}`, it can never be null so `??` has nothing to do",
+                    self.lhs.debug(),
+                    lhs
+                ),
+            );
+            return;
+        }
+        // the default stands in for the lhs, so it has to fit where the lhs does
+        let inner = lhs.get_non_null_type();
+        type_empty_literal(self.rhs.as_mut(), &inner);
+        let rhs = self.rhs.get_white_type();
+        if lhs == Type::Null {
+            self.typ = rhs;
+        } else if inner.is_assignable_to(rhs.clone()) {
+            self.typ = inner;
+        } else if lhs.is_assignable_to(rhs.clone()) {
+            self.typ = lhs; // `x ?? y` is still nullable when `y` is
+        } else {
+            add_parser_error_at(
+                st,
+                self.rhs.as_ref(),
+                MismatchedTypes(inner, rhs.clone()),
+                format!(
+                    "`{}` is `{}`, it can't stand in for `{}`",
+                    self.rhs.debug(),
+                    rhs,
+                    self.lhs.debug()
+                ),
+            );
+        }
+    }
+
+    fn debug(&self) -> String {
+        format!("{} ?? {}", self.lhs.debug(), self.rhs.debug())
+    }
+
+    fn get_white_type(&self) -> Type {
+        self.typ.clone()
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("CoalesceExpression")
+    }
+}
+impl CoalesceExpression {
+    pub fn new(lhs: Box<dyn Expression>, rhs: Box<dyn Expression>) -> CoalesceExpression {
+        CoalesceExpression {
+            lhs,
+            rhs,
+            typ: Type::Initialized,
+        }
+    }
+}
@@ -1,10 +1,13 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::config::*;
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{add_parser_error, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use crate::LOGGER;
 use std::any::Any;
 
@@ -28,62 +31,74 @@ impl ToAny for ComparisonExpression {
 }
 
 impl Expression for ComparisonExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        let lhs_eval = self.lhs.evaluate(runtime);
-        let rhs_eval = self.rhs.evaluate(runtime);
-        if let Some(lhs_f64) = lhs_eval.downcast_ref::<WhiteLangFloat>() {
-            if let Some(rhs_f64) = rhs_eval.downcast_ref::<WhiteLangFloat>() {
-                return if self.is_greater && self.is_equal {
-                    Box::new(lhs_f64 >= rhs_f64)
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let lhs_eval = self.lhs.evaluate(runtime)?;
+        let rhs_eval = self.rhs.evaluate(runtime)?;
+        let (lhs, rhs) = match (lhs_eval, rhs_eval) {
+            (Value::Int(lhs), Value::Int(rhs)) => {
+                return Ok(Value::Bool(if self.is_greater && self.is_equal {
+                    lhs >= rhs
                 } else if self.is_less && self.is_equal {
-                    Box::new(lhs_f64 <= rhs_f64)
+                    lhs <= rhs
                 } else if self.is_greater {
-                    Box::new(lhs_f64 > rhs_f64)
+                    lhs > rhs
                 } else {
-                    Box::new(lhs_f64 < rhs_f64)
-                };
+                    lhs < rhs
+                }))
             }
-            if let Some(rhs_isize) = rhs_eval.downcast_ref::<WhiteLangInt>() {
-                return if self.is_greater && self.is_equal {
-                    Box::new(*lhs_f64 >= *rhs_isize as WhiteLangFloat)
-                } else if self.is_less && self.is_equal {
-                    Box::new(*lhs_f64 <= *rhs_isize as WhiteLangFloat)
-                } else if self.is_greater {
-                    Box::new(*lhs_f64 > *rhs_isize as WhiteLangFloat)
-                } else {
-                    Box::new(*lhs_f64 < *rhs_isize as WhiteLangFloat)
-                };
-            }
-        }
-        if let Some(lhs_isize) = lhs_eval.downcast_ref::<isize>() {
-            if let Some(rhs_f64) = rhs_eval.downcast_ref::<f64>() {
-                return if self.is_greater && self.is_equal {
-                    Box::new(*lhs_isize as f64 >= *rhs_f64)
-                } else if self.is_less && self.is_equal {
-                    Box::new(*lhs_isize as f64 <= *rhs_f64)
-                } else if self.is_greater {
-                    Box::new(*lhs_isize as f64 > *rhs_f64)
-                } else {
-                    Box::new((*lhs_isize as f64) < *rhs_f64)
-                };
-            }
-            if let Some(rhs_isize) = rhs_eval.downcast_ref::<isize>() {
-                return if self.is_greater && self.is_equal {
-                    Box::new(*lhs_isize >= *rhs_isize)
-                } else if self.is_less && self.is_equal {
-                    Box::new(*lhs_isize <= *rhs_isize)
-                } else if self.is_greater {
-                    Box::new(*lhs_isize > *rhs_isize)
-                } else {
-                    Box::new(*lhs_isize < *rhs_isize)
-                };
+            (Value::Float(lhs), Value::Float(rhs)) => (lhs, rhs),
+            (Value::Float(lhs), Value::Int(rhs)) => (lhs, rhs as WhiteLangFloat),
+            (Value::Int(lhs), Value::Float(rhs)) => (lhs as WhiteLangFloat, rhs),
+            (lhs, rhs) => {
+                return Err(RuntimeError::TypeMismatch(format!(
+                    "cannot compare `{}` with `{}`",
+                    lhs.type_name(),
+                    rhs.type_name()
+                )))
             }
+        };
+        Ok(Value::Bool(if self.is_greater && self.is_equal {
+            lhs >= rhs
+        } else if self.is_less && self.is_equal {
+            lhs <= rhs
+        } else if self.is_greater {
+            lhs > rhs
+        } else {
+            lhs < rhs
+        }))
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        let is_float = nasm.operands(&self.lhs, &self.rhs);
+        // floats compare like unsigned ints
+        let set = match (self.is_greater, self.is_equal, is_float) {
+            (true, true, true) => "setae",
+            (true, false, true) => "seta",
+            (false, true, true) => "setbe",
+            (false, false, true) => "setb",
+            (true, true, false) => "setge",
+            (true, false, false) => "setg",
+            (false, true, false) => "setle",
+            (false, false, false) => "setl",
+        };
+        if is_float {
+            nasm.instr(String::from("ucomisd xmm0, xmm1"));
+        } else {
+            nasm.instr(String::from("cmp rax, rcx"));
         }
-        unreachable!()
+        nasm.instr(format!("{} al", set))
+            .instr(String::from("movzx eax, al"));
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.lhs.emit(bytecode);
+        self.rhs.emit(bytecode);
+        bytecode.emit(match (self.is_greater, self.is_equal) {
+            (true, true) => Op::GreaterEqual,
+            (true, false) => Op::Greater,
+            (false, true) => Op::LessEqual,
+            (false, false) => Op::Less,
+        });
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
@@ -96,13 +111,15 @@ impl Expression for ComparisonExpression {
         self.lhs.validate(st);
         self.rhs.validate(st);
         if self.lhs.get_white_type() != self.rhs.get_white_type() {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::MismatchedTypes(
                     self.lhs.get_white_type(),
                     self.rhs.get_white_type(),
                 ),
                 format!(
-                    "Types must be comparable: lhs: {:?} rhs {:?}",
+                    "Types must be comparable: lhs: `{}` rhs: `{}`",
                     self.lhs.get_white_type(),
                     self.rhs.get_white_type()
                 ),
@@ -122,7 +139,7 @@ impl Expression for ComparisonExpression {
         let mut builder = String::new();
         builder = builder + &*self.lhs.debug() + " ";
         builder = builder + &*self.operator + " ";
-        builder = builder + &*self.rhs.debug();
+        builder += &*self.rhs.debug();
         builder
     }
 
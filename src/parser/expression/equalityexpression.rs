@@ -1,10 +1,13 @@
-use crate::config::{WhiteLangBool, WhiteLangFloat, WhiteLangInt, WhiteLangString};
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{try_print_output, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::expression::identifierexpression::IdentifierExpression;
+use crate::parser::expression::nullliteralexpression::NullLiteralExpression;
+use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::parser::whitetypes::Type::Null;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -24,99 +27,77 @@ impl ToAny for EqualityExpression {
 }
 
 impl Expression for EqualityExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        let lhs_eval = self.lhs.evaluate(runtime);
-        let rhs_eval = self.rhs.evaluate(runtime);
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let lhs_eval = self.lhs.evaluate(runtime)?;
+        let rhs_eval = self.rhs.evaluate(runtime)?;
         let is_equal = self.operator.contains("==");
-        if is_equal {
-            crate::LOGGER.debug(
-                format!(
-                    "[EQ_EXPR]: {} == {}",
-                    try_print_output(&lhs_eval),
-                    try_print_output(&rhs_eval)
-                ),
-                crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
-            );
-        } else {
-            crate::LOGGER.debug(
-                format!(
-                    "[EQ_EXPR]: {} != {}",
-                    try_print_output(&lhs_eval),
-                    try_print_output(&rhs_eval)
-                ),
-                crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
-            );
-        }
-        // handle null == null
-        if self.lhs.get_white_type() == self.rhs.get_white_type()
-            && self.lhs.get_white_type() == Null
-        {
-            return Box::new(true);
-        }
-        // handle some type == null or null == some type
-        if (self.lhs.get_white_type() != self.rhs.get_white_type())
-            && (self.rhs.get_white_type() == Null || self.lhs.get_white_type() == Null)
-        {
-            if is_equal {
-                return Box::new(false);
-            }
-            return Box::new(true);
-        }
+        crate::LOGGER.debug(
+            format!("[EQ_EXPR]: {} {} {}", lhs_eval, self.operator, rhs_eval),
+            crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
+        );
+        // values that can't be compared are neither equal nor not equal
+        Ok(Value::Bool(match lhs_eval.equals(&rhs_eval) {
+            Some(equal) => equal == is_equal,
+            None => false,
+        }))
+    }
 
-        // lhs : int, rhs : int | float
-        if let Some(lhs_int) = lhs_eval.downcast_ref::<WhiteLangInt>() {
-            if let Some(rhs_int) = rhs_eval.downcast_ref::<WhiteLangInt>() {
-                if is_equal {
-                    return Box::new(lhs_int == rhs_int);
-                }
-                return Box::new(lhs_int != rhs_int);
-            } else if let Some(rhs_float) = rhs_eval.downcast_ref::<WhiteLangFloat>() {
-                if is_equal {
-                    return Box::new(*lhs_int as WhiteLangFloat == *rhs_float);
-                }
-                return Box::new(*lhs_int as WhiteLangFloat != *rhs_float);
-            }
-        }
-        // lhs : string, rhs : string
-        if let Some(lhs_str) = lhs_eval.downcast_ref::<WhiteLangString>() {
-            if let Some(rhs_str) = rhs_eval.downcast_ref::<WhiteLangString>() {
-                if is_equal {
-                    return Box::new(lhs_str.eq(rhs_str));
-                }
-                return Box::new(lhs_str.ne(rhs_str));
-            }
-        }
-        // lhs : bool, rhs : bool
-        if let Some(lhs_bool) = lhs_eval.downcast_ref::<WhiteLangBool>() {
-            if let Some(rhs_bool) = rhs_eval.downcast_ref::<WhiteLangBool>() {
-                if is_equal {
-                    return Box::new(lhs_bool == rhs_bool);
-                }
-                return Box::new(lhs_bool != rhs_bool);
+    fn compile(&self, nasm: &mut Nasm) {
+        let is_equal = self.operator.contains("==");
+        if self.lhs.get_white_type() == Type::String && self.rhs.get_white_type() == Type::String {
+            self.lhs.compile(nasm);
+            nasm.instr(String::from("push rax"));
+            self.rhs.compile(nasm);
+            nasm.instr(String::from("mov rsi, rax"))
+                .instr(String::from("pop rdi"))
+                .instr(String::from("call __white_string_eq"));
+            if !is_equal {
+                nasm.instr(String::from("xor eax, 1"));
             }
-        }
-        // lhs : float, rhs: float | int
-        if let Some(lhs_float) = lhs_eval.downcast_ref::<WhiteLangFloat>() {
-            if let Some(rhs_float) = rhs_eval.downcast_ref::<WhiteLangFloat>() {
-                if is_equal {
-                    return Box::new(lhs_float == rhs_float);
-                }
-                return Box::new(lhs_float != rhs_float);
-            } else if let Some(rhs_int) = rhs_eval.downcast_ref::<WhiteLangInt>() {
-                if is_equal {
-                    return Box::new(*lhs_float == *rhs_int as WhiteLangFloat);
-                }
-                return Box::new(*lhs_float != *rhs_int as WhiteLangFloat);
+        } else if nasm.operands(&self.lhs, &self.rhs) {
+            // NaN is unordered, it isn't equal to anything
+            nasm.instr(String::from("ucomisd xmm0, xmm1"));
+            if is_equal {
+                nasm.instr(String::from("sete al"))
+                    .instr(String::from("setnp cl"))
+                    .instr(String::from("and al, cl"));
+            } else {
+                nasm.instr(String::from("setne al"))
+                    .instr(String::from("setp cl"))
+                    .instr(String::from("or al, cl"));
             }
+            nasm.instr(String::from("movzx eax, al"));
+        } else {
+            let set = if is_equal { "sete" } else { "setne" };
+            nasm.instr(String::from("cmp rax, rcx"))
+                .instr(format!("{} al", set))
+                .instr(String::from("movzx eax, al"));
         }
-        Box::new(false)
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.lhs.emit(bytecode);
+        self.rhs.emit(bytecode);
+        bytecode.emit(if self.operator.contains("==") {
+            Op::Equal
+        } else {
+            Op::NotEqual
+        });
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
+        let compares_contents = |expr: &Box<dyn Expression>| {
+            let typ = expr.get_white_type().get_non_null_type();
+            typ.is_list_type() || typ.is_map_type()
+        };
+        if compares_contents(&self.lhs) || compares_contents(&self.rhs) {
+            javascript.transpile_equals(
+                self.lhs.as_ref(),
+                self.rhs.as_ref(),
+                self.operator.contains("=="),
+            );
+            return;
+        }
         self.lhs.transpile(javascript);
         // because js sucks -> do === to avoid unexpected coercion
         javascript.append_no_tabs(format!(" {}= ", self.operator));
@@ -132,7 +113,7 @@ impl Expression for EqualityExpression {
         let mut builder = String::new();
         builder = builder + &*self.lhs.debug() + " ";
         builder = builder + &*self.operator + " ";
-        builder = builder + &*self.rhs.debug();
+        builder += &*self.rhs.debug();
         builder
     }
 
@@ -143,6 +124,23 @@ impl Expression for EqualityExpression {
     fn get_expr_type(&self) -> String {
         String::from("EqualityExpression")
     }
+
+    fn non_null_when(&self, when: bool) -> Vec<String> {
+        // `x != null` being true or `x == null` being false, either way round
+        if when != (self.operator == "!=") {
+            return vec![];
+        }
+        let is_null = |expr: &Box<dyn Expression>| expr.to_any().is::<NullLiteralExpression>();
+        let variable = match (is_null(&self.lhs), is_null(&self.rhs)) {
+            (false, true) => &self.lhs,
+            (true, false) => &self.rhs,
+            _ => return vec![],
+        };
+        match variable.to_any().is::<IdentifierExpression>() {
+            true => vec![variable.debug()],
+            false => vec![],
+        }
+    }
 }
 #[allow(dead_code)]
 impl EqualityExpression {
@@ -1,10 +1,13 @@
-use crate::config::{WhiteLangFloat, WhiteLangInt};
+use crate::bytecode::{Bytecode, Op};
+use crate::config::WhiteLangFloat;
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{add_parser_error, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -12,7 +15,6 @@ pub(crate) struct FactorExpression {
     lhs: Box<dyn Expression>,
     operator: String,
     rhs: Box<dyn Expression>,
-    is_mult: bool,
 }
 
 impl ToAny for FactorExpression {
@@ -25,48 +27,99 @@ impl ToAny for FactorExpression {
 }
 
 impl Expression for FactorExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        let lhs_eval = self.lhs.evaluate(runtime);
-        let rhs_eval = self.rhs.evaluate(runtime);
-        if let Some(lhs_f64) = lhs_eval.downcast_ref::<WhiteLangFloat>() {
-            if let Some(rhs_f64) = rhs_eval.downcast_ref::<WhiteLangFloat>() {
-                if self.is_mult {
-                    return Box::new(lhs_f64 * rhs_f64);
-                }
-                return Box::new(lhs_f64 / rhs_f64);
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let lhs_eval = self.lhs.evaluate(runtime)?;
+        let rhs_eval = self.rhs.evaluate(runtime)?;
+        let (lhs, rhs) = match (lhs_eval, rhs_eval) {
+            (Value::Int(lhs), Value::Int(rhs)) => {
+                let result = match self.operator.as_str() {
+                    "*" => lhs.checked_mul(rhs),
+                    _ if rhs == 0 => return Err(RuntimeError::DivisionByZero),
+                    "/" => lhs.checked_div(rhs),
+                    _ => lhs.checked_rem(rhs),
+                };
+                return result.map(Value::Int).ok_or_else(|| {
+                    RuntimeError::Overflow(format!("{} {} {}", lhs, self.operator, rhs))
+                });
             }
-            if let Some(rhs_isize) = rhs_eval.downcast_ref::<WhiteLangInt>() {
-                if self.is_mult {
-                    return Box::new(lhs_f64 * *rhs_isize as WhiteLangFloat);
-                }
-                return Box::new(lhs_f64 / *rhs_isize as WhiteLangFloat);
+            (Value::Float(lhs), Value::Float(rhs)) => (lhs, rhs),
+            (Value::Float(lhs), Value::Int(rhs)) => (lhs, rhs as WhiteLangFloat),
+            (Value::Int(lhs), Value::Float(rhs)) => (lhs as WhiteLangFloat, rhs),
+            (lhs, rhs) => {
+                return Err(RuntimeError::TypeMismatch(format!(
+                    "cannot {} `{}` and `{}`",
+                    match self.operator.as_str() {
+                        "*" => "multiply",
+                        "/" => "divide",
+                        _ => "take the remainder of",
+                    },
+                    lhs.type_name(),
+                    rhs.type_name()
+                )))
             }
-        }
-        if let Some(lhs_isize) = lhs_eval.downcast_ref::<WhiteLangInt>() {
-            if let Some(rhs_f64) = rhs_eval.downcast_ref::<WhiteLangFloat>() {
-                if self.is_mult {
-                    return Box::new(*lhs_isize as WhiteLangFloat * rhs_f64);
-                }
-                return Box::new(*lhs_isize as WhiteLangFloat / rhs_f64);
-            }
-            if let Some(rhs_isize) = rhs_eval.downcast_ref::<WhiteLangInt>() {
-                if self.is_mult {
-                    return Box::new(lhs_isize * rhs_isize);
-                }
-                return Box::new(lhs_isize / rhs_isize);
+        };
+        Ok(Value::Float(match self.operator.as_str() {
+            "*" => lhs * rhs,
+            "/" => lhs / rhs,
+            _ => lhs % rhs,
+        }))
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        if nasm.operands(&self.lhs, &self.rhs) {
+            match self.operator.as_str() {
+                "*" => nasm.instr(String::from("mulsd xmm0, xmm1")),
+                "/" => nasm.instr(String::from("divsd xmm0, xmm1")),
+                _ => nasm.unsupported(String::from("the remainder of floats")),
+            };
+            nasm.instr(String::from("movq rax, xmm0"));
+        } else if self.operator == "*" {
+            nasm.instr(String::from("mov rdx, rax"))
+                .instr(String::from("imul rdx, rcx"))
+                .instr(String::from("jo __white_overflow_mul"))
+                .instr(String::from("mov rax, rdx"));
+        } else {
+            // idiv traps on the smallest int divided by -1, so negate it first to find out
+            let divisor_ok = nasm.new_label("divisor_ok");
+            let overflow = if self.operator == "%" { "rem" } else { "div" };
+            nasm.instr(String::from("test rcx, rcx"))
+                .instr(String::from("jz __white_division_by_zero"))
+                .instr(String::from("cmp rcx, -1"))
+                .instr(format!("jne {}", divisor_ok))
+                .instr(String::from("mov rdx, rax"))
+                .instr(String::from("neg rdx"))
+                .instr(format!("jo __white_overflow_{}", overflow))
+                .label(&divisor_ok)
+                .instr(String::from("cqo"))
+                .instr(String::from("idiv rcx"));
+            if self.operator == "%" {
+                nasm.instr(String::from("mov rax, rdx")); // idiv leaves the remainder in rdx
             }
         }
-        unreachable!()
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.lhs.emit(bytecode);
+        self.rhs.emit(bytecode);
+        bytecode.emit(match self.operator.as_str() {
+            "*" => Op::Multiply,
+            "/" => Op::Divide,
+            _ => Op::Modulo,
+        });
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
+        // javascript only has floats, int division has to throw away the fraction itself
+        let truncate = self.operator == "/" && self.get_white_type() == Type::Integer;
+        if truncate {
+            javascript.append_no_tabs(String::from("Math.trunc("));
+        }
         self.lhs.transpile(javascript);
         javascript.append_no_tabs(format!(" {} ", self.operator));
         self.rhs.transpile(javascript);
+        if truncate {
+            javascript.append_no_tabs(String::from(")"));
+        }
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
@@ -75,28 +128,34 @@ impl Expression for FactorExpression {
         if self.lhs.get_white_type().ne(&Type::Float)
             && self.lhs.get_white_type().ne(&Type::Integer)
         {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::IncompatibleTypes(
                     self.lhs.get_white_type(),
                     self.rhs.get_white_type(),
                 ),
                 format!(
-                    "You cannot multiply/divide two non number types. lhs: {:?} rhs: {:?}",
-                    self.lhs, self.rhs
+                    "You cannot multiply/divide/take the remainder of two non number types. lhs: `{}` rhs: `{}`",
+                    self.lhs.get_white_type(),
+                    self.rhs.get_white_type()
                 ),
             );
         }
         if self.rhs.get_white_type().ne(&Type::Float)
             && self.rhs.get_white_type().ne(&Type::Integer)
         {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::IncompatibleTypes(
                     self.lhs.get_white_type(),
                     self.rhs.get_white_type(),
                 ),
                 format!(
-                    "You cannot multiply/divide two non number types. lhs: {:?} rhs: {:?}",
-                    self.lhs, self.rhs
+                    "You cannot multiply/divide/take the remainder of two non number types. lhs: `{}` rhs: `{}`",
+                    self.lhs.get_white_type(),
+                    self.rhs.get_white_type()
                 ),
             );
         }
@@ -106,12 +165,12 @@ impl Expression for FactorExpression {
         let mut builder = String::new();
         builder = builder + &*self.lhs.debug() + " ";
         builder = builder + &*self.operator + " ";
-        builder = builder + &*self.rhs.debug();
+        builder += &*self.rhs.debug();
         builder
     }
 
     fn get_white_type(&self) -> Type {
-        if self.lhs.get_white_type() == Type::Float || self.lhs.get_white_type() == Type::Float {
+        if self.lhs.get_white_type() == Type::Float || self.rhs.get_white_type() == Type::Float {
             return Type::Float;
         }
         Type::Integer
@@ -128,18 +187,13 @@ impl FactorExpression {
         operator: String,
         rhs: Box<dyn Expression>,
     ) -> FactorExpression {
-        FactorExpression {
-            lhs,
-            operator: operator.clone(),
-            rhs,
-            is_mult: operator.contains("*"),
-        }
+        FactorExpression { lhs, operator, rhs }
     }
 
-    fn get_lhs(&self) -> &Box<dyn Expression> {
+    pub(crate) fn get_lhs(&self) -> &Box<dyn Expression> {
         &self.lhs
     }
-    fn get_rhs(&self) -> &Box<dyn Expression> {
+    pub(crate) fn get_rhs(&self) -> &Box<dyn Expression> {
         &self.rhs
     }
 }
@@ -0,0 +1,208 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::expression::identifierexpression::IdentifierExpression;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::statement::assignmentstatement::{Current, EmitCurrent};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType::{BadType, UnknownName};
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use std::any::Any;
+
+// obj.field, obj can be any expression that gives back a struct
+#[derive(Clone, Debug)]
+pub(crate) struct FieldAccessExpression {
+    object: Box<dyn Expression>,
+    field: String,
+    typ: Type,
+}
+
+impl ToAny for FieldAccessExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for FieldAccessExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let object = self.object.evaluate(runtime)?;
+        self.get(&object)
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("struct field `{}`", self.debug()));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.object.emit(bytecode);
+        let idx = bytecode.name(self.field.as_str());
+        bytecode.emit(Op::GetField(idx));
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        self.object.transpile(javascript);
+        javascript.append_no_tabs(format!(".{}", self.field));
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.object.validate(st);
+        self.typ = Type::Error;
+        let struct_id = match self.object.get_white_type() {
+            Type::Struct(s) => s,
+            Type::Error => return, // whatever went wrong has already been reported
+            typ @ Type::Nullable(_) => {
+                add_parser_error_at(
+                    st,
+                    self.object.as_ref(),
+                    BadType(typ.clone()),
+                    might_be_null(self.object.as_ref(), &typ),
+                );
+                return;
+            }
+            typ => {
+                add_parser_error_at(
+                    st,
+                    self.object.as_ref(),
+                    BadType(typ.clone()),
+                    format!(
+                        "`{}` is `{}`, it doesn't have fields",
+                        self.object.debug(),
+                        typ
+                    ),
+                );
+                return;
+            }
+        };
+        match st
+            .get_struct(struct_id.clone())
+            .and_then(|sds| sds.fields.get(&self.field).cloned())
+        {
+            Some(typ) => self.typ = typ,
+            None => add_parser_error_at(
+                st,
+                self,
+                UnknownName(self.field.clone()),
+                format!("No such field `{}` on struct `{}`", self.field, struct_id),
+            ),
+        }
+    }
+
+    fn debug(&self) -> String {
+        format!("{}.{}", self.object.debug(), self.field)
+    }
+
+    fn get_white_type(&self) -> Type {
+        self.typ.clone()
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("FieldAccessExpression")
+    }
+
+    fn set_type(&mut self, typ: Type) {
+        self.typ = typ;
+    }
+}
+impl FieldAccessExpression {
+    pub fn new(object: Box<dyn Expression>, field: String) -> FieldAccessExpression {
+        FieldAccessExpression {
+            object,
+            field,
+            typ: Type::Initialized,
+        }
+    }
+
+    pub fn get_object(&self) -> &Box<dyn Expression> {
+        &self.object
+    }
+
+    pub fn get_field(&self) -> &str {
+        self.field.as_str()
+    }
+
+    /// `obj.field = value`, only the instance `obj` evaluates to sees the change. `obj` is
+    /// evaluated before `value`, the same as the vm
+    pub fn assign(
+        &self,
+        value: &mut dyn FnMut(&mut Runtime, Current) -> Result<Value, RuntimeError>,
+        runtime: &mut Runtime,
+    ) -> Result<(), RuntimeError> {
+        let object = self.object.evaluate(runtime)?;
+        let value = value(runtime, &|_| self.get(&object))?;
+        match object {
+            Value::Struct(instance) => {
+                let mut instance = instance.borrow_mut();
+                if !instance.fields.contains_key(&self.field) {
+                    return Err(RuntimeError::UnknownField(
+                        instance.name.clone(),
+                        self.field.clone(),
+                    ));
+                }
+                instance.fields.insert(self.field.clone(), value);
+                Ok(())
+            }
+            object => Err(self.not_a_struct(&object)),
+        }
+    }
+
+    pub fn emit_assign(
+        &self,
+        value: &mut dyn FnMut(&mut Bytecode, EmitCurrent),
+        bytecode: &mut Bytecode,
+    ) {
+        self.object.emit(bytecode);
+        let idx = bytecode.name(self.field.as_str());
+        value(bytecode, &|bytecode| {
+            bytecode.emit(Op::Dup(1));
+            bytecode.emit(Op::GetField(idx));
+        });
+        bytecode.emit(Op::SetField(idx));
+    }
+
+    /// The field of `object`, the instance this expression's object evaluated to
+    fn get(&self, object: &Value) -> Result<Value, RuntimeError> {
+        match object {
+            Value::Struct(instance) => {
+                let instance = instance.borrow();
+                match instance.fields.get(&self.field) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(RuntimeError::UnknownField(
+                        instance.name.clone(),
+                        self.field.clone(),
+                    )),
+                }
+            }
+            value => Err(self.not_a_struct(value)),
+        }
+    }
+
+    fn not_a_struct(&self, value: &Value) -> RuntimeError {
+        RuntimeError::TypeMismatch(format!(
+            "cannot access the field `{}` on `{}`",
+            self.field,
+            value.type_name()
+        ))
+    }
+}
+
+/// The help for using a `T?` as if it can't be null. Only variables are narrowed by a null check,
+/// so anything else has to be put in one first
+pub(crate) fn might_be_null(object: &dyn Expression, typ: &Type) -> String {
+    let name = object.debug();
+    if object.to_any().is::<IdentifierExpression>() {
+        return format!(
+            "`{}` is `{}`, it might be null, check `{} != null` first",
+            name, typ, name
+        );
+    }
+    format!(
+        "`{}` is `{}`, it might be null, put it in a variable and check that: \
+         `let value = {}; if (value != null) {{ ... }}`",
+        name, typ, name
+    )
+}
@@ -1,9 +1,12 @@
+use crate::bytecode::Bytecode;
 use crate::config::WhiteLangFloat;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 use super::integerliteralexpression::IntegerLiteralExpression;
@@ -24,12 +27,21 @@ impl ToAny for FloatLiteralExpression {
 }
 
 impl Expression for FloatLiteralExpression {
-    fn evaluate(&self, _runtime: &mut Runtime) -> Box<dyn Any> {
-        Box::new(self.value)
+    fn evaluate(&self, _runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        Ok(Value::Float(self.value))
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        // floats travel around as their bits
+        nasm.instr(format!(
+            "mov rax, 0x{:016x} ; {}",
+            self.value.to_bits(),
+            self.value
+        ));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.constant(Value::Float(self.value));
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
@@ -39,7 +51,7 @@ impl Expression for FloatLiteralExpression {
     fn validate(&mut self, _st: &mut SymbolTable) {}
 
     fn debug(&self) -> String {
-        String::from(self.value.to_string())
+        self.value.to_string()
     }
 
     fn get_white_type(&self) -> Type {
@@ -1,12 +1,12 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{
-    add_parser_error, any_into_bool_literal, any_into_f64_literal, any_into_int_literal,
-    Expression, ToAny,
-};
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType::{ArgMismatch, IncompatibleTypes, UnknownName};
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -26,44 +26,52 @@ impl ToAny for FunctionCallExpression {
 }
 
 impl Expression for FunctionCallExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        let mut fds = runtime.get_function(self.name.clone());
-        let mut evaluated_args: Vec<Box<dyn Expression>> = vec![];
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let mut fds = runtime.get_function(self.name.clone())?;
+        let mut evaluated_args: Vec<Value> = vec![];
         for expr in &self.args {
-            let eval = expr.evaluate(runtime);
-            let mut tmp = expr.clone();
-            if let Some(integer) = any_into_int_literal(&eval) {
-                tmp = Box::new(integer);
-            }
-            if let Some(float) = any_into_f64_literal(&eval) {
-                tmp = Box::new(float);
-            }
-            if let Some(boolean) = any_into_bool_literal(&eval) {
-                tmp = Box::new(boolean);
-            }
-            evaluated_args.push(tmp);
+            evaluated_args.push(expr.evaluate(runtime)?);
         }
-        assert_eq!(self.args.len(), evaluated_args.len()); // sanity check, should always pass...
         crate::LOGGER.debug(
             format!(
                 "[FUNCTION CALL] invoking {}...\n| args: {:?}",
-                self.name, self.args
+                self.name, evaluated_args
             ),
             crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
         );
-        let value = fds.invoke(runtime, evaluated_args);
-        runtime.pop_scope();
-        value
+        runtime.push_call(self.name.clone())?;
+        let value = fds.invoke(runtime, evaluated_args)?; // failed calls stay on the call stack for the trace
+        runtime.pop_call();
+        Ok(value)
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        if self.name.contains('.') {
+            nasm.unsupported(format!("calling the method `{}`", self.name));
+            return;
+        }
+        for arg in self.args.iter() {
+            arg.compile(nasm);
+            nasm.instr(String::from("push rax"));
+        }
+        nasm.instr(format!("call {}", Nasm::function_label(self.name.as_str())));
+        if !self.args.is_empty() {
+            nasm.instr(format!("add rsp, {}", 8 * self.args.len()));
+        }
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        for arg in self.args.iter() {
+            arg.emit(bytecode);
+        }
+        let function = bytecode.function(self.name.as_str());
+        bytecode.emit(Op::Call(function, self.args.len()));
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         javascript.append_no_tabs(format!("{}(", self.name));
         for (i, arg) in self.args.iter().enumerate() {
-            arg.transpile(javascript);
+            javascript.transpile_value(arg.as_ref());
             if i != self.args.len() - 1 {
                 javascript.append_no_tabs(String::from(","));
             }
@@ -72,54 +80,61 @@ impl Expression for FunctionCallExpression {
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
-        let fds_opt = st.get_function(self.name.clone());
-        if fds_opt.is_none() {
-            add_parser_error(
-                UnknownName(self.name.clone()),
+        let fds = match st.get_function(self.name.clone()) {
+            Some(fds) => fds,
+            None => {
+                add_parser_error_at(
+                    st,
+                    self,
+                    UnknownName(self.name.clone()),
+                    format!(
+                        "You cannot call: [{}], it has not been defined",
+                        self.name.clone()
+                    ),
+                );
+                self.typ = Type::Null; // TODO: default typing (maybe Object)
+                return;
+            }
+        };
+        self.typ = fds.get_return_type();
+        // the parameters are only in scope inside the function, so check against the declared types
+        let arg_types = &fds.arg_types;
+        if self.args.len() != arg_types.len() {
+            add_parser_error_at(
+                st,
+                self,
+                ArgMismatch,
                 format!(
-                    "You cannot call: [{}], it has not been defined",
-                    self.name.clone()
+                    "Expected {} args, found {}",
+                    arg_types.len(),
+                    self.args.len()
                 ),
             );
-            self.typ = Type::Null; // TODO: default typing (maybe Object)
-        } else {
-            let mut fds = fds_opt.unwrap();
-            self.typ = fds.get_return_type();
-            let args = fds.get_args();
-            if self.args.len() != args.len() {
-                add_parser_error(
-                    ArgMismatch,
-                    format!("Expected {} args, found {}", self.args.len(), args.len()),
+            return;
+        }
+        for (arg, arg_type) in self.args.iter_mut().zip(arg_types.iter()) {
+            arg.validate(st);
+            let param_type = arg.get_white_type();
+            if !arg_type.is_assignable_to(param_type.clone()) {
+                crate::LOGGER.info(format!("symbol table state:\n{:?}", st));
+                add_parser_error_at(
+                    st,
+                    arg.as_ref(),
+                    IncompatibleTypes(param_type.clone(), arg_type.clone()),
+                    format!("You cannot assign `{}` to `{}`", param_type, arg_type),
                 );
-            } else {
-                for i in 0..args.len() {
-                    let arg = &mut args[i];
-                    arg.validate(st);
-                    self.args[i].validate(st);
-                    let param_type = self.args[i].get_white_type();
-                    if !param_type.is_assignable_to(arg.get_white_type()) {
-                        crate::LOGGER.info(format!("symbol table state:\n{:?}", st));
-                        add_parser_error(
-                            IncompatibleTypes(param_type.clone(), arg.get_white_type()),
-                            format!(
-                                "You cannot assign {:?} to {:?}",
-                                param_type,
-                                arg.get_white_type()
-                            ),
-                        );
-                    }
-                }
             }
         }
+        st.widen_globals();
     }
 
     fn debug(&self) -> String {
         let mut builder: String = String::new();
-        builder.push_str(&*self.name);
+        builder.push_str(&self.name);
         builder.push_str(": ");
         for arg in &self.args {
             builder.push_str(arg.debug().as_str());
-            builder.push_str(" ")
+            builder.push(' ')
         }
         builder
     }
@@ -144,15 +159,8 @@ impl FunctionCallExpression {
     pub fn add_arg(&mut self, arg: Box<dyn Expression>) {
         self.args.push(arg);
     }
-    pub fn get_args(&self) -> &Vec<Box<dyn Expression>> {
-        &self.args
-    }
     #[allow(dead_code)]
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
-
-    pub fn set_type(&mut self, typ: Type) {
-        self.typ = typ;
-    }
 }
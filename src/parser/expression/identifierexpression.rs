@@ -1,10 +1,12 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{add_parser_error, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType::UnknownName;
-use crate::runtime::Runtime;
-use crate::LOGGER;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -23,36 +25,41 @@ impl ToAny for IdentifierExpression {
 }
 
 impl Expression for IdentifierExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        let debug_has_key: bool = runtime.has_symbol(self.name.clone());
-        if debug_has_key {
-            println!("Runtime has symbol: {}", self.name);
-        }
-        if let Some(eval) = runtime.get_value(self.name.clone()) {
-            return eval;
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        runtime.get_value(self.name.clone())
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        if self.name.contains('.') {
+            nasm.unsupported(format!("struct field `{}`", self.name));
+            return;
         }
-        panic!("Undefined variable `{}`", self.name);
+        nasm.load(self.name.as_str());
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.load(self.name.as_str());
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
-        javascript.append_no_tabs(self.name.clone());
+        if self.name == "self" {
+            javascript.append_no_tabs(String::from("this")); // `self.x` is `this.x` in a javascript class
+        } else {
+            javascript.append_no_tabs(self.name.clone());
+        }
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
-        let opt_typ = st.get_symbol_type(self.name.clone());
-        if opt_typ.is_some() {
-            self.typ = opt_typ.unwrap();
+        if let Some(typ) = st.get_symbol_type(self.name.clone()) {
+            self.typ = typ;
         } else {
             self.typ = Type::Error;
-            LOGGER.warn(format!("Couldn't get the type for identifier {:?}", self));
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 UnknownName(self.name.clone()),
                 format!(
-                    "You cannot use {} as you have not defined it.\n|Try: let {} = ...",
+                    "You cannot use `{}` as you have not defined it.\nTry: let {} = ...",
                     self.name, self.name
                 ),
             );
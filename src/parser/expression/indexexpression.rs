@@ -0,0 +1,358 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::config::WhiteLangInt;
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::expression::rangeexpression::RangeExpression;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::statement::assignmentstatement::{assign, emit_assign, Current, EmitCurrent};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType::{BadType, MismatchedTypes};
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::{find_key, insert, Value};
+use std::any::Any;
+use std::ops::Range;
+
+// list[index] or list[start..end], negative indices count back from the end so xs[-1] is the
+// last element
+#[derive(Clone, Debug)]
+pub(crate) struct IndexExpression {
+    list: Box<dyn Expression>,
+    index: Box<dyn Expression>,
+    typ: Type,
+}
+
+impl ToAny for IndexExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for IndexExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let list = self.list.evaluate(runtime)?;
+        match self.range() {
+            Some(range) => {
+                let start = range.get_start().evaluate(runtime)?;
+                let end = range.get_end().evaluate(runtime)?;
+                slice(list, start, end, range.is_inclusive())
+            }
+            None => index(list, self.index.evaluate(runtime)?),
+        }
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("indexing `{}`", self.debug()));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.list.emit(bytecode);
+        match self.range() {
+            Some(range) => {
+                range.get_start().emit(bytecode);
+                range.get_end().emit(bytecode);
+                bytecode.emit(Op::Slice(range.is_inclusive()));
+            }
+            None => {
+                self.index.emit(bytecode);
+                bytecode.emit(Op::Index);
+            }
+        }
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        // the helpers check bounds like the runtime does, .at() and .slice() wouldn't complain
+        let string = self.list.get_white_type() == Type::String;
+        let list = |javascript: &mut JavaScript| {
+            if string {
+                // spreading a string splits it into chars rather than utf-16 code units
+                javascript.append_no_tabs(String::from("[..."));
+                self.list.transpile(javascript);
+                javascript.append_no_tabs(String::from("]"));
+            } else {
+                self.list.transpile(javascript);
+            }
+        };
+        match self.range() {
+            Some(range) => {
+                javascript.transpile_slice(&list, range);
+                if string {
+                    javascript.append_no_tabs(String::from(".join(\"\")"));
+                }
+            }
+            None => javascript.transpile_at(&list, self.index.as_ref()),
+        }
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.list.validate(st);
+        self.index.validate(st);
+        self.typ = Type::Error;
+        let list = self.list.get_white_type();
+        if list == Type::Error {
+            return; // whatever went wrong has already been reported
+        }
+        if let Some((key, value)) = list.get_types_from_map() {
+            return self.validate_key(key, value, st);
+        }
+        if !list.is_list_type() && list != Type::String {
+            add_parser_error_at(
+                st,
+                self.list.as_ref(),
+                BadType(list.clone()),
+                format!(
+                    "`{}` is `{}`, only lists, strings and maps can be indexed",
+                    self.list.debug(),
+                    list
+                ),
+            );
+            return;
+        }
+        if let Some(range) = self.range() {
+            if range.has_step() {
+                add_parser_error_at(
+                    st,
+                    self,
+                    BadType(list.clone()),
+                    format!("The slice `{}` cannot have a step", self.debug()),
+                );
+            }
+            self.typ = list;
+            return;
+        }
+        let index = self.index.get_white_type();
+        if index != Type::Integer && index != Type::Error {
+            add_parser_error_at(
+                st,
+                self.index.as_ref(),
+                BadType(index.clone()),
+                format!(
+                    "Indices are `int`s, `{}` is `{}`",
+                    self.index.debug(),
+                    index
+                ),
+            );
+        }
+        // a char of a string is still a string
+        self.typ = list.get_type_from_list();
+    }
+
+    fn debug(&self) -> String {
+        format!("{}[{}]", self.list.debug(), self.index.debug())
+    }
+
+    fn get_white_type(&self) -> Type {
+        self.typ.clone()
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("IndexExpression")
+    }
+
+    fn set_type(&mut self, typ: Type) {
+        self.typ = typ;
+    }
+}
+impl IndexExpression {
+    pub fn new(list: Box<dyn Expression>, index: Box<dyn Expression>) -> IndexExpression {
+        IndexExpression {
+            list,
+            index,
+            typ: Type::Initialized,
+        }
+    }
+
+    pub fn get_list(&self) -> &Box<dyn Expression> {
+        &self.list
+    }
+
+    pub fn get_index(&self) -> &Box<dyn Expression> {
+        &self.index
+    }
+
+    /// `map[key]`, the key has to be a `key` and we get a `value` back
+    fn validate_key(&mut self, key: Type, value: Type, st: &mut SymbolTable) {
+        let index = self.index.get_white_type();
+        if self.range().is_some() {
+            add_parser_error_at(
+                st,
+                self,
+                BadType(self.list.get_white_type()),
+                format!("Maps can't be sliced, `{}` is a map", self.list.debug()),
+            );
+        } else if index != Type::Error && !key.is_assignable_to(index.clone()) {
+            add_parser_error_at(
+                st,
+                self.index.as_ref(),
+                MismatchedTypes(key, index),
+                format!(
+                    "`{}` is not a key of `{}`",
+                    self.index.debug(),
+                    self.list.debug()
+                ),
+            );
+        }
+        self.typ = value;
+    }
+
+    /// The range being sliced out, None for a plain index
+    pub fn range(&self) -> Option<&RangeExpression> {
+        self.index.to_any().downcast_ref::<RangeExpression>()
+    }
+
+    /// `xs[i] = value`, lists are values so the changed list is assigned back to `xs`, which
+    /// can be anything `assign` knows how to assign to
+    pub fn assign(
+        &self,
+        value: &mut dyn FnMut(&mut Runtime, Current) -> Result<Value, RuntimeError>,
+        runtime: &mut Runtime,
+    ) -> Result<(), RuntimeError> {
+        assign(
+            self.list.as_ref(),
+            &mut |runtime, list| {
+                let list = list(runtime)?;
+                let at = self.index.evaluate(runtime)?;
+                let value = value(runtime, &|_| index(list.clone(), at.clone()))?;
+                set_index(list, at, value)
+            },
+            runtime,
+        )
+    }
+
+    pub fn emit_assign(
+        &self,
+        value: &mut dyn FnMut(&mut Bytecode, EmitCurrent),
+        bytecode: &mut Bytecode,
+    ) {
+        emit_assign(
+            self.list.as_ref(),
+            &mut |bytecode, list| {
+                list(bytecode);
+                self.index.emit(bytecode);
+                value(bytecode, &|bytecode| {
+                    bytecode.emit(Op::Dup(2));
+                    bytecode.emit(Op::Index);
+                });
+                bytecode.emit(Op::SetIndex);
+            },
+            bytecode,
+        )
+    }
+
+    /// `xs[i] = value` as `set$(xs, i, value)`, which counts negative indices from the end
+    pub fn transpile_assign(&self, value: &dyn Expression, javascript: &mut JavaScript) {
+        javascript.transpile_set(self.list.as_ref(), self.index.as_ref(), value);
+    }
+}
+
+/// Where `index` points in a list of `len` elements, counting negative indices from the end
+pub(crate) fn position(index: WhiteLangInt, len: usize) -> Option<usize> {
+    let position = if index < 0 {
+        index + len as WhiteLangInt
+    } else {
+        index
+    };
+    usize::try_from(position)
+        .ok()
+        .filter(|position| *position < len)
+}
+
+fn not_indexable(list: &Value, index: &Value) -> RuntimeError {
+    RuntimeError::TypeMismatch(format!(
+        "cannot index `{}` with `{}`",
+        list.type_name(),
+        index.type_name()
+    ))
+}
+
+/// `list[index]`, shared with the vm. Strings are indexed by char, so `"héllo"[1]` is `"é"`, and
+/// maps are indexed by key
+pub(crate) fn index(list: Value, index: Value) -> Result<Value, RuntimeError> {
+    match (list, index) {
+        (Value::Map(mut map), key) => match find_key(&map, &key) {
+            Some(at) => Ok(map.swap_remove(at).1),
+            None => Err(RuntimeError::MissingKey(key.to_string())),
+        },
+        (Value::List(mut list), Value::Int(index)) => match position(index, list.len()) {
+            Some(position) => Ok(list.swap_remove(position)),
+            None => Err(RuntimeError::IndexOutOfRange(index, list.len())),
+        },
+        (Value::String(string), Value::Int(index)) => {
+            let len = string.chars().count();
+            match position(index, len).and_then(|position| string.chars().nth(position)) {
+                Some(c) => Ok(Value::String(c.to_string())),
+                None => Err(RuntimeError::IndexOutOfRange(index, len)),
+            }
+        }
+        (list, index) => Err(not_indexable(&list, &index)),
+    }
+}
+
+/// `list` with `list[index]` replaced by `value`, shared with the vm
+pub(crate) fn set_index(list: Value, index: Value, value: Value) -> Result<Value, RuntimeError> {
+    match (list, index) {
+        (Value::Map(mut map), key) => {
+            insert(&mut map, key, value);
+            Ok(Value::Map(map))
+        }
+        (Value::List(mut list), Value::Int(index)) => match position(index, list.len()) {
+            Some(position) => {
+                list[position] = value;
+                Ok(Value::List(list))
+            }
+            None => Err(RuntimeError::IndexOutOfRange(index, list.len())),
+        },
+        (list, index) => Err(not_indexable(&list, &index)),
+    }
+}
+
+/// `list[start..end]`, or `list[start..=end]`, shared with the vm. Strings slice by char
+pub(crate) fn slice(
+    list: Value,
+    start: Value,
+    end: Value,
+    inclusive: bool,
+) -> Result<Value, RuntimeError> {
+    match (list, start, end) {
+        (Value::List(mut list), Value::Int(start), Value::Int(end)) => {
+            let range = bounds(start, end, inclusive, list.len())?;
+            Ok(Value::List(list.drain(range).collect()))
+        }
+        (Value::String(string), Value::Int(start), Value::Int(end)) => {
+            let range = bounds(start, end, inclusive, string.chars().count())?;
+            Ok(Value::String(
+                string.chars().skip(range.start).take(range.len()).collect(),
+            ))
+        }
+        (list, Value::Int(_), index) | (list, index, _) => Err(not_indexable(&list, &index)),
+    }
+}
+
+/// The positions `start..end` covers in something `len` long, negative indices count from the
+/// end and the end of a slice may sit one past the last element
+fn bounds(
+    start: WhiteLangInt,
+    end: WhiteLangInt,
+    inclusive: bool,
+    len: usize,
+) -> Result<Range<usize>, RuntimeError> {
+    let bound = |index: WhiteLangInt| match index {
+        _ if index == len as WhiteLangInt => Some(len),
+        index => position(index, len),
+    };
+    let from = bound(start).ok_or(RuntimeError::IndexOutOfRange(start, len))?;
+    let to = match bound(end) {
+        Some(to) if inclusive && to < len => to + 1,
+        Some(to) if !inclusive => to,
+        _ => return Err(RuntimeError::IndexOutOfRange(end, len)),
+    };
+    if from > to {
+        let dots = if inclusive { "..=" } else { ".." };
+        let range = format!("{}{}{}", start, dots, end);
+        return Err(RuntimeError::ReversedSlice(range));
+    }
+    Ok(from..to)
+}
@@ -1,9 +1,12 @@
+use crate::bytecode::Bytecode;
 use crate::config::*;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 use super::floatliteralexpression::FloatLiteralExpression;
@@ -24,12 +27,16 @@ impl ToAny for IntegerLiteralExpression {
 }
 
 impl Expression for IntegerLiteralExpression {
-    fn evaluate(&self, _runtime: &mut Runtime) -> Box<dyn Any> {
-        Box::new(self.value)
+    fn evaluate(&self, _runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(self.value))
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.instr(format!("mov rax, {}", self.value));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.constant(Value::Int(self.value));
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
@@ -39,7 +46,7 @@ impl Expression for IntegerLiteralExpression {
     fn validate(&mut self, _st: &mut SymbolTable) {}
 
     fn debug(&self) -> String {
-        String::from(self.value.to_string())
+        self.value.to_string()
     }
 
     fn get_white_type(&self) -> Type {
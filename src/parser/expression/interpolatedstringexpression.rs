@@ -0,0 +1,106 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType::BadType;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use std::any::Any;
+
+// "x = {x}", the values are formatted the same way print formats them. There is always one more
+// string than there are expressions, the strings go around the expressions
+#[derive(Clone, Debug)]
+pub(crate) struct InterpolatedStringExpression {
+    strings: Vec<String>,
+    exprs: Vec<Box<dyn Expression>>,
+}
+
+impl ToAny for InterpolatedStringExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for InterpolatedStringExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let mut string = self.strings[0].clone();
+        for (expr, after) in self.exprs.iter().zip(self.strings.iter().skip(1)) {
+            string.push_str(&expr.evaluate(runtime)?.to_string());
+            string.push_str(after);
+        }
+        Ok(Value::String(string))
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("interpolating `{}`", self.debug()));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.constant(Value::String(self.strings[0].clone()));
+        for (expr, after) in self.exprs.iter().zip(self.strings.iter().skip(1)) {
+            expr.emit(bytecode);
+            bytecode.constant(Value::String(after.clone()));
+        }
+        bytecode.emit(Op::Concat(self.strings.len() + self.exprs.len()));
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        javascript.append_no_tabs(format!("`{}", JavaScript::escape(&self.strings[0], '`')));
+        for (expr, after) in self.exprs.iter().zip(self.strings.iter().skip(1)) {
+            javascript.append_no_tabs(String::from("${"));
+            javascript.transpile_shown(expr.as_ref());
+            javascript.append_no_tabs(format!("}}{}", JavaScript::escape(after, '`')));
+        }
+        javascript.append_no_tabs(String::from("`"));
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        for expr in &mut self.exprs {
+            expr.validate(st);
+            let typ = expr.get_white_type();
+            if typ == Type::Void {
+                add_parser_error_at(
+                    st,
+                    expr.as_ref(),
+                    BadType(typ),
+                    format!("`{}` has no value to put in the string", expr.debug()),
+                );
+            }
+        }
+    }
+
+    fn debug(&self) -> String {
+        let mut builder = self.strings[0].clone();
+        for (expr, after) in self.exprs.iter().zip(self.strings.iter().skip(1)) {
+            builder += &format!("{{{}}}{}", expr.debug(), after);
+        }
+        builder
+    }
+
+    fn get_white_type(&self) -> Type {
+        Type::String
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("InterpolatedStringExpression")
+    }
+}
+impl InterpolatedStringExpression {
+    pub fn new(start: String) -> InterpolatedStringExpression {
+        InterpolatedStringExpression {
+            strings: vec![start],
+            exprs: vec![],
+        }
+    }
+
+    /// An expression and the string that follows it
+    pub fn add_part(&mut self, expr: Box<dyn Expression>, after: String) {
+        self.exprs.push(expr);
+        self.strings.push(after);
+    }
+}
@@ -1,9 +1,13 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{add_parser_error, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::expression::mapliteralexpression::MapLiteralExpression;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType::MismatchedTypes;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use crate::LOGGER;
 use std::any::Any;
 
@@ -23,22 +27,40 @@ impl ToAny for ListLiteralExpression {
 }
 
 impl Expression for ListLiteralExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        let mut evals: Vec<Box<dyn Any>> = vec![];
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let mut evals: Vec<Value> = vec![];
         for expr in &self.exprs {
-            evals.push(expr.evaluate(runtime));
+            evals.push(expr.evaluate(runtime)?);
         }
-        Box::new(evals)
+        Ok(Value::List(evals))
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        // lists are a length followed by the elements
+        for expr in self.exprs.iter() {
+            expr.compile(nasm);
+            nasm.instr(String::from("push rax"));
+        }
+        nasm.instr(format!("mov rdi, {}", 8 * (self.exprs.len() + 1)))
+            .instr(String::from("call __white_alloc"))
+            .instr(format!("mov qword [rax], {}", self.exprs.len()));
+        for i in (0..self.exprs.len()).rev() {
+            nasm.instr(String::from("pop rcx"))
+                .instr(format!("mov [rax + {}], rcx", 8 * (i + 1)));
+        }
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        for expr in self.exprs.iter() {
+            expr.emit(bytecode);
+        }
+        bytecode.emit(Op::List(self.exprs.len()));
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         javascript.append_no_tabs(String::from("["));
         for (i, expr) in self.exprs.iter().enumerate() {
-            expr.transpile(javascript);
+            javascript.transpile_value(expr.as_ref());
             if i < self.exprs.len() - 1 {
                 javascript.append_no_tabs(String::from(","));
             }
@@ -47,16 +69,22 @@ impl Expression for ListLiteralExpression {
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
-        if self.exprs.is_empty() {
-            return;
-        }
-        self.typ = self.exprs[0].get_white_type().get_list_type();
         for expr in &mut self.exprs {
             expr.validate(st);
-            if expr.get_white_type() != self.typ.get_type_from_list() {
-                add_parser_error(
-                    MismatchedTypes(expr.get_white_type(), self.typ.get_type_from_list()),
-                    format!("All items in the list must be of the same type."),
+        }
+        // `[]` has no type of its own, so in `[[], [1]]` the type comes from the other elements,
+        // the same goes for `{}`
+        let Some(item) = Type::common(self.exprs.iter().map(|expr| expr.get_white_type())) else {
+            return;
+        };
+        self.set_type(item.get_list_type());
+        for expr in &self.exprs {
+            if !item.is_assignable_to(expr.get_white_type()) {
+                add_parser_error_at(
+                    st,
+                    expr.as_ref(),
+                    MismatchedTypes(expr.get_white_type(), item.clone()),
+                    "All items in the list must be of the same type.".to_string(),
                 );
             }
         }
@@ -86,8 +114,13 @@ impl Expression for ListLiteralExpression {
         String::from("ListLiteralExpression")
     }
 
-    fn set_type(&mut self, _typ: Type) {
-        self.typ = _typ;
+    fn set_type(&mut self, typ: Type) {
+        // any `[]`s or `{}`s inside take their type from ours
+        let item = typ.get_type_from_list();
+        for expr in &mut self.exprs {
+            type_empty_literal(expr.as_mut(), &item);
+        }
+        self.typ = typ;
     }
 }
 impl ListLiteralExpression {
@@ -102,3 +135,23 @@ impl ListLiteralExpression {
         self.exprs.push(expr);
     }
 }
+
+/// `[]` and `{}` have no elements to tell us their type, so they take the type of wherever they
+/// are going, e.g. `let xs: list<int> = [];`. The same goes for `[null]` and `{"a": null}`, all
+/// their elements say is that they're nullable
+pub(crate) fn type_empty_literal(expr: &mut dyn Expression, typ: &Type) {
+    let untyped = match expr.get_white_type() {
+        Type::Initialized => true,
+        Type::List(item) | Type::Map(_, item) => {
+            *item == Type::Null && typ.is_assignable_to(expr.get_white_type())
+        }
+        _ => false,
+    };
+    if (typ.is_list_type() || typ.is_map_type())
+        && untyped
+        && (expr.to_any().is::<ListLiteralExpression>()
+            || expr.to_any().is::<MapLiteralExpression>())
+    {
+        expr.set_type(typ.clone());
+    }
+}
@@ -1,10 +1,13 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{add_parser_error, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
 use crate::parser::ParserErrorType::MismatchedTypes;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -24,20 +27,39 @@ impl ToAny for LogicalExpression {
 }
 
 impl Expression for LogicalExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        let lhs_eval = self.lhs.evaluate(runtime);
-        let rhs_eval = self.rhs.evaluate(runtime);
-        let lhs_to_bool = *lhs_eval.downcast_ref::<bool>().unwrap();
-        let rhs_to_bool = *rhs_eval.downcast_ref::<bool>().unwrap();
-        if self.is_and() {
-            return Box::new(lhs_to_bool && rhs_to_bool);
-        } else {
-            return Box::new(lhs_to_bool || rhs_to_bool);
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        // the rhs only runs when the lhs doesn't already decide the answer
+        let lhs = match self.lhs.evaluate(runtime)? {
+            Value::Bool(lhs) => lhs,
+            lhs => return Err(self.not_a_bool(&lhs)),
+        };
+        if lhs != self.is_and() {
+            return Ok(Value::Bool(lhs));
         }
+        match self.rhs.evaluate(runtime)? {
+            Value::Bool(rhs) => Ok(Value::Bool(rhs)),
+            rhs => Err(self.not_a_bool(&rhs)),
+        }
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        let done = nasm.new_label(if self.is_and() { "and" } else { "or" });
+        self.lhs.compile(nasm);
+        // false && ... is false and true || ... is true, either way rax already has the answer
+        nasm.instr(String::from("test rax, rax")).instr(format!(
+            "{} {}",
+            if self.is_and() { "jz" } else { "jnz" },
+            done
+        ));
+        self.rhs.compile(nasm);
+        nasm.label(&done);
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.lhs.emit(bytecode);
+        let done = bytecode.emit(if self.is_and() { Op::And(0) } else { Op::Or(0) });
+        self.rhs.emit(bytecode);
+        bytecode.patch(done);
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
@@ -46,22 +68,48 @@ impl Expression for LogicalExpression {
         self.rhs.transpile(javascript);
     }
 
-    fn validate(&mut self, _st: &mut SymbolTable) {
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.lhs.validate(st);
+        // the rhs only runs once the lhs is true for `&&` or false for `||`
+        st.push_scope();
+        for name in self.lhs.non_null_when(self.is_and()) {
+            st.narrow(name);
+        }
+        self.rhs.validate(st);
+        st.pop_scope();
         if self.operator.ne("&&") && self.operator.ne("||") {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::BadOperator(self.operator.clone()),
                 format!("Operator: {} is not valid here", self.operator),
             );
         }
-        if self.lhs.get_white_type() != self.rhs.get_white_type()
-            && self.lhs.get_white_type() != Type::Boolean
-        {
-            add_parser_error(MismatchedTypes(self.lhs.get_white_type(), self.rhs.get_white_type()), format!("You cannot and/or two expressions that do not evaluate to booleans. lhs: {:?} rhs: {:?}", self.lhs, self.rhs));
+        for side in [&self.lhs, &self.rhs] {
+            let typ = side.get_white_type();
+            if typ != Type::Boolean && typ != Type::Error {
+                add_parser_error_at(
+                    st,
+                    self,
+                    MismatchedTypes(Type::Boolean, typ.clone()),
+                    format!(
+                        "You can only use `{}` on bools, `{}` is `{}`",
+                        self.operator,
+                        side.debug(),
+                        typ
+                    ),
+                );
+            }
         }
     }
 
     fn debug(&self) -> String {
-        String::from("lol debug :)")
+        format!(
+            "{} {} {}",
+            self.lhs.debug(),
+            self.operator,
+            self.rhs.debug()
+        )
     }
 
     fn get_white_type(&self) -> Type {
@@ -71,6 +119,16 @@ impl Expression for LogicalExpression {
     fn get_expr_type(&self) -> String {
         String::from("LogicalExpression")
     }
+
+    fn non_null_when(&self, when: bool) -> Vec<String> {
+        // `a && b` being true means both are, `a || b` being false means neither is
+        if when != self.is_and() {
+            return vec![];
+        }
+        let mut names = self.lhs.non_null_when(when);
+        names.extend(self.rhs.non_null_when(when));
+        names
+    }
 }
 impl LogicalExpression {
     pub fn new(lhs: Box<dyn Expression>, rhs: Box<dyn Expression>) -> Self {
@@ -81,13 +139,24 @@ impl LogicalExpression {
         }
     }
 
+    /// `and` and `or` are the same as `&&` and `||`
     pub fn set_operator(&mut self, operator: String) {
-        if operator.eq("&&") || operator.eq("||") {
-            self.operator = operator;
+        match operator.as_str() {
+            "&&" | "and" => self.operator = String::from("&&"),
+            "||" | "or" => self.operator = String::from("||"),
+            _ => {}
         }
     }
 
     fn is_and(&self) -> bool {
         self.operator.contains("&&")
     }
+
+    fn not_a_bool(&self, value: &Value) -> RuntimeError {
+        RuntimeError::TypeMismatch(format!(
+            "cannot apply `{}` to `{}`",
+            self.operator,
+            value.type_name()
+        ))
+    }
 }
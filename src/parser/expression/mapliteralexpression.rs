@@ -0,0 +1,142 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::expression::listliteralexpression::type_empty_literal;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType::{BadType, MismatchedTypes};
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::{insert, Value};
+use std::any::Any;
+
+// { "a": 1, "b": 2 }, if a key shows up twice the last value wins
+#[derive(Clone, Debug)]
+pub(crate) struct MapLiteralExpression {
+    entries: Vec<(Box<dyn Expression>, Box<dyn Expression>)>,
+    typ: Type,
+}
+
+impl ToAny for MapLiteralExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for MapLiteralExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let mut map = vec![];
+        for (key, value) in &self.entries {
+            let key = key.evaluate(runtime)?;
+            insert(&mut map, key, value.evaluate(runtime)?);
+        }
+        Ok(Value::Map(map))
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("the map `{}`", self.debug()));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        for (key, value) in self.entries.iter() {
+            key.emit(bytecode);
+            value.emit(bytecode);
+        }
+        bytecode.emit(Op::Map(self.entries.len()));
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        javascript.append_no_tabs(String::from("new Map(["));
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            javascript.append_no_tabs(String::from("["));
+            key.transpile(javascript);
+            javascript.append_no_tabs(String::from(", "));
+            javascript.transpile_value(value.as_ref());
+            javascript.append_no_tabs(String::from("]"));
+            if i < self.entries.len() - 1 {
+                javascript.append_no_tabs(String::from(", "));
+            }
+        }
+        javascript.append_no_tabs(String::from("])"));
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        for (key, value) in &mut self.entries {
+            key.validate(st);
+            value.validate(st);
+        }
+        // `{}` has no type of its own, like `[]`, a value can be `[]` or `{}` too
+        let key = self.entries.first().map(|(key, _)| key.get_white_type());
+        let value = Type::common(self.entries.iter().map(|(_, value)| value.get_white_type()));
+        let (Some(key), Some(value)) = (key, value) else {
+            return;
+        };
+        if !key.can_be_key() && key != Type::Error {
+            add_parser_error_at(
+                st,
+                self.entries[0].0.as_ref(),
+                BadType(key.clone()),
+                format!(
+                    "`{}` can't be the key of a map, use int, string, bool or char",
+                    key
+                ),
+            );
+        }
+        self.set_type(Type::new_map(key.clone(), value.clone()));
+        for (k, v) in &self.entries {
+            for (expr, expected) in [(k, &key), (v, &value)] {
+                if !expected.is_assignable_to(expr.get_white_type()) {
+                    add_parser_error_at(
+                        st,
+                        expr.as_ref(),
+                        MismatchedTypes(expected.clone(), expr.get_white_type()),
+                        "All keys in the map must be of the same type, and all values too."
+                            .to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn debug(&self) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key.debug(), value.debug()))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    fn get_white_type(&self) -> Type {
+        self.typ.clone()
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("MapLiteralExpression")
+    }
+
+    fn set_type(&mut self, typ: Type) {
+        // any `[]`s or `{}`s among the values take their type from ours
+        if let Some((_, value_type)) = typ.get_types_from_map() {
+            for (_, value) in &mut self.entries {
+                type_empty_literal(value.as_mut(), &value_type);
+            }
+        }
+        self.typ = typ;
+    }
+}
+impl MapLiteralExpression {
+    pub fn new() -> MapLiteralExpression {
+        MapLiteralExpression {
+            entries: vec![],
+            typ: Type::Initialized,
+        }
+    }
+
+    pub fn add_entry(&mut self, key: Box<dyn Expression>, value: Box<dyn Expression>) {
+        self.entries.push((key, value));
+    }
+}
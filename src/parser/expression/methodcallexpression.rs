@@ -0,0 +1,412 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::expression::fieldaccessexpression::might_be_null;
+use crate::parser::expression::stringliteralexpression::StringLiteralExpression;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::statement::assignmentstatement::{
+    assign, emit_assign, is_assignable, Current, EmitCurrent,
+};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType::{ArgMismatch, BadType, IncompatibleTypes, UnknownName};
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use std::any::Any;
+
+// obj.method(args), the method runs with `self` bound to whatever obj evaluates to
+#[derive(Clone, Debug)]
+pub(crate) struct MethodCallExpression {
+    object: Box<dyn Expression>,
+    name: String,
+    args: Vec<Box<dyn Expression>>,
+    typ: Type,
+    intrinsic: Option<bool>, // for built in methods like `xs.push(v)`, whether it changes `xs`
+}
+
+impl ToAny for MethodCallExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for MethodCallExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        if let Some(mutates) = self.intrinsic {
+            return self.call_intrinsic(mutates, runtime);
+        }
+        let receiver = self.object.evaluate(runtime)?;
+        let method = match &receiver {
+            Value::Struct(instance) => format!("{}.{}", instance.borrow().name, self.name),
+            value => {
+                return Err(RuntimeError::TypeMismatch(format!(
+                    "cannot call the method `{}` on `{}`",
+                    self.name,
+                    value.type_name()
+                )))
+            }
+        };
+        let mut fds = runtime.get_function(method.clone())?;
+        let mut evaluated_args: Vec<Value> = vec![];
+        for expr in &self.args {
+            evaluated_args.push(expr.evaluate(runtime)?);
+        }
+        runtime.push_call(method)?;
+        let value = fds.invoke_method(runtime, receiver, evaluated_args)?;
+        runtime.pop_call();
+        Ok(value)
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("calling the method `{}`", self.name));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        if let Some(mutates) = self.intrinsic {
+            return self.emit_intrinsic(mutates, bytecode);
+        }
+        self.object.emit(bytecode);
+        for arg in self.args.iter() {
+            arg.emit(bytecode);
+        }
+        let idx = bytecode.name(self.name.as_str());
+        bytecode.emit(Op::Invoke(idx, self.args.len()));
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        if self.intrinsic.is_some() {
+            return match self.object.get_white_type() {
+                Type::String => self.transpile_string_intrinsic(javascript),
+                Type::Map(..) => self.transpile_map_intrinsic(javascript),
+                _ => self.transpile_list_intrinsic(javascript),
+            };
+        }
+        self.object.transpile(javascript);
+        javascript.append_no_tabs(format!(".{}(", self.name));
+        self.transpile_args(javascript);
+        javascript.append_no_tabs(String::from(")"));
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.object.validate(st);
+        self.typ = Type::Error;
+        let receiver = self.object.get_white_type();
+        if receiver.is_list_type() || receiver.is_map_type() || receiver == Type::String {
+            return self.validate_intrinsic(receiver, st);
+        }
+        let struct_id = match receiver {
+            Type::Struct(s) => s,
+            Type::Error => return, // whatever went wrong has already been reported
+            typ @ Type::Nullable(_) => {
+                add_parser_error_at(
+                    st,
+                    self.object.as_ref(),
+                    BadType(typ.clone()),
+                    might_be_null(self.object.as_ref(), &typ),
+                );
+                return;
+            }
+            typ => {
+                add_parser_error_at(
+                    st,
+                    self.object.as_ref(),
+                    BadType(typ.clone()),
+                    format!(
+                        "`{}` is `{}`, it doesn't have methods",
+                        self.object.debug(),
+                        typ
+                    ),
+                );
+                return;
+            }
+        };
+        let method = format!("{}.{}", struct_id, self.name);
+        let fds = match st.get_function(method.clone()) {
+            Some(fds) => fds,
+            None => {
+                add_parser_error_at(
+                    st,
+                    self,
+                    UnknownName(method),
+                    format!("No such method `{}` on struct `{}`", self.name, struct_id),
+                );
+                return;
+            }
+        };
+        self.typ = fds.get_return_type();
+        self.validate_args(&fds.arg_types, st);
+        st.widen_globals();
+    }
+
+    fn debug(&self) -> String {
+        let mut builder = format!("{}.{}: ", self.object.debug(), self.name);
+        for arg in &self.args {
+            builder.push_str(arg.debug().as_str());
+            builder.push(' ')
+        }
+        builder
+    }
+
+    fn get_white_type(&self) -> Type {
+        self.typ.clone()
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("MethodCallExpression")
+    }
+}
+impl MethodCallExpression {
+    pub fn new(object: Box<dyn Expression>, name: String) -> MethodCallExpression {
+        MethodCallExpression {
+            object,
+            name,
+            args: vec![],
+            typ: Type::Initialized,
+            intrinsic: None,
+        }
+    }
+
+    pub fn add_arg(&mut self, arg: Box<dyn Expression>) {
+        self.args.push(arg);
+    }
+
+    fn validate_args(&mut self, arg_types: &[Type], st: &mut SymbolTable) {
+        if self.args.len() != arg_types.len() {
+            add_parser_error_at(
+                st,
+                self,
+                ArgMismatch,
+                format!(
+                    "Expected {} args, found {}",
+                    arg_types.len(),
+                    self.args.len()
+                ),
+            );
+            return;
+        }
+        for (arg, arg_type) in self.args.iter_mut().zip(arg_types.iter()) {
+            arg.validate(st);
+            let param_type = arg.get_white_type();
+            if !arg_type.is_assignable_to(param_type.clone()) {
+                add_parser_error_at(
+                    st,
+                    arg.as_ref(),
+                    IncompatibleTypes(param_type.clone(), arg_type.clone()),
+                    format!("You cannot assign `{}` to `{}`", param_type, arg_type),
+                );
+            }
+        }
+    }
+
+    fn validate_intrinsic(&mut self, receiver: Type, st: &mut SymbolTable) {
+        let (arg_types, return_type, mutates) = match st.get_intrinsic(&receiver, &self.name) {
+            Some(intrinsic) => intrinsic,
+            None => {
+                add_parser_error_at(
+                    st,
+                    self,
+                    UnknownName(self.name.clone()),
+                    format!("No such method `{}` on `{}`", self.name, receiver),
+                );
+                return;
+            }
+        };
+        let item = receiver.get_type_from_list();
+        if self.name == "sort"
+            && !matches!(
+                item,
+                Type::Integer | Type::Float | Type::String | Type::Boolean | Type::Char
+            )
+        {
+            add_parser_error_at(
+                st,
+                self,
+                BadType(item.clone()),
+                format!(
+                    "You can only sort numbers, strings and bools, not `{}`",
+                    item
+                ),
+            );
+        }
+        self.typ = return_type;
+        self.intrinsic = Some(mutates);
+        self.validate_args(&arg_types, st);
+    }
+
+    /// Built in methods that change their receiver, like `xs.push(v)`, assign the changed
+    /// receiver back to wherever it came from, unless it came from nowhere, like `[1].push(2)`
+    fn call_intrinsic(&self, mutates: bool, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let mut result = Value::Unit;
+        let mut call = |runtime: &mut Runtime, receiver: Current| {
+            let mut receiver = receiver(runtime)?;
+            let mut args = vec![];
+            for arg in &self.args {
+                args.push(arg.evaluate(runtime)?);
+            }
+            let name = format!("{}.{}", receiver.type_name(), self.name);
+            result = runtime.handle_intrinsic(name, &mut receiver, args)?;
+            Ok(receiver)
+        };
+        if mutates && is_assignable(self.object.as_ref()) {
+            assign(self.object.as_ref(), &mut call, runtime)?;
+        } else {
+            call(runtime, &|runtime| self.object.evaluate(runtime))?;
+        }
+        Ok(result)
+    }
+
+    fn emit_intrinsic(&self, mutates: bool, bytecode: &mut Bytecode) {
+        let idx = bytecode.name(self.name.as_str());
+        let argc = self.args.len();
+        let call = |bytecode: &mut Bytecode, op: Op, receiver: EmitCurrent| {
+            receiver(bytecode);
+            for arg in self.args.iter() {
+                arg.emit(bytecode);
+            }
+            bytecode.emit(op);
+        };
+        if !mutates || !is_assignable(self.object.as_ref()) {
+            call(bytecode, Op::Intrinsic(idx, argc), &|bytecode| {
+                self.object.emit(bytecode)
+            });
+            return;
+        }
+        // what the method returns waits in a hidden variable while the receiver is stored back
+        let result = bytecode.hidden_variable("intrinsic.result");
+        bytecode.declare(&result);
+        emit_assign(
+            self.object.as_ref(),
+            &mut |bytecode, receiver| {
+                call(bytecode, Op::IntrinsicMut(idx, argc), receiver);
+                bytecode.store(&result);
+            },
+            bytecode,
+        );
+        bytecode.load(&result);
+    }
+
+    /// The javascript array method that does the same thing as the built in method
+    fn transpile_list_intrinsic(&self, javascript: &mut JavaScript) {
+        if matches!(self.name.as_str(), "contains" | "insert" | "remove" | "pop") {
+            // includes compares lists and maps by reference, and the rest don't check the index
+            // or that there's anything to pop
+            let name = format!("list.{}", self.name);
+            javascript.transpile_intrinsic(&name, self.object.as_ref(), &self.args);
+            return;
+        }
+        self.object.transpile(javascript);
+        let (method, extra) = match self.name.as_str() {
+            "len" => {
+                javascript.append_no_tabs(String::from(".length"));
+                return;
+            }
+            // javascript sorts everything as strings unless told otherwise
+            "sort" => ("sort", "(a, b) => a < b ? -1 : a > b ? 1 : 0"),
+            name => (name, ""),
+        };
+        javascript.append_no_tabs(format!(".{}({}", method, extra));
+        self.transpile_args(javascript);
+        javascript.append_no_tabs(String::from(")"));
+    }
+
+    /// The javascript Map method that does the same thing as the built in method
+    fn transpile_map_intrinsic(&self, javascript: &mut JavaScript) {
+        match self.name.as_str() {
+            "keys" | "values" => {
+                // Map.keys() and Map.values() are iterators, spreading them gets us arrays
+                javascript.append_no_tabs(String::from("[..."));
+                self.object.transpile(javascript);
+                javascript.append_no_tabs(format!(".{}()]", self.name));
+            }
+            "len" => {
+                self.object.transpile(javascript);
+                javascript.append_no_tabs(String::from(".size"));
+            }
+            "contains_key" => {
+                self.object.transpile(javascript);
+                javascript.append_no_tabs(String::from(".has("));
+                self.transpile_args(javascript);
+                javascript.append_no_tabs(String::from(")"));
+            }
+            _ => javascript.transpile_intrinsic("map.remove", self.object.as_ref(), &self.args),
+        }
+    }
+
+    /// The javascript string method that does the same thing as the built in method. Javascript
+    /// indexes strings by utf-16 code unit, spreading one into an array gets us its chars
+    fn transpile_string_intrinsic(&self, javascript: &mut JavaScript) {
+        let spread = |javascript: &mut JavaScript| {
+            javascript.append_no_tabs(String::from("[..."));
+            self.object.transpile(javascript);
+            javascript.append_no_tabs(String::from("]"));
+        };
+        match self.name.as_str() {
+            "len" => {
+                spread(javascript);
+                javascript.append_no_tabs(String::from(".length"));
+            }
+            "chars" => spread(javascript),
+            "substring" => {
+                // slice clamps what's out of range where the runtime raises an error
+                javascript.transpile_intrinsic(
+                    "string.substring",
+                    self.object.as_ref(),
+                    &self.args,
+                );
+            }
+            "find" => {
+                javascript.append_no_tabs(String::from(
+                    "((s, part) => { const i = s.indexOf(part); return i < 0 ? i : [...s.slice(0, i)].length; })(",
+                ));
+                self.object.transpile(javascript);
+                javascript.append_no_tabs(String::from(", "));
+                self.transpile_args(javascript);
+                javascript.append_no_tabs(String::from(")"));
+            }
+            "split" if !self.splits_on_text() => {
+                javascript.append_no_tabs(String::from(
+                    "((s, separator) => separator === \"\" ? [...s] : s.split(separator))(",
+                ));
+                self.object.transpile(javascript);
+                javascript.append_no_tabs(String::from(", "));
+                self.transpile_args(javascript);
+                javascript.append_no_tabs(String::from(")"));
+            }
+            name => {
+                let method = match name {
+                    "upper" => "toUpperCase",
+                    "lower" => "toLowerCase",
+                    "contains" => "includes",
+                    "starts_with" => "startsWith",
+                    "ends_with" => "endsWith",
+                    "replace" => "replaceAll",
+                    name => name, // trim and split are the same
+                };
+                self.object.transpile(javascript);
+                javascript.append_no_tabs(format!(".{}(", method));
+                self.transpile_args(javascript);
+                javascript.append_no_tabs(String::from(")"));
+            }
+        }
+    }
+
+    /// `s.split(",")` can use javascript's split as is, splitting on `""` would split surrogates
+    fn splits_on_text(&self) -> bool {
+        self.args[0]
+            .to_any()
+            .downcast_ref::<StringLiteralExpression>()
+            .is_some_and(|separator| !separator.debug().is_empty())
+    }
+
+    fn transpile_args(&self, javascript: &mut JavaScript) {
+        for (i, arg) in self.args.iter().enumerate() {
+            javascript.transpile_value(arg.as_ref());
+            if i != self.args.len() - 1 {
+                javascript.append_no_tabs(String::from(","));
+            }
+        }
+    }
+}
@@ -1,8 +1,11 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -18,12 +21,16 @@ impl ToAny for NullLiteralExpression {
 }
 
 impl Expression for NullLiteralExpression {
-    fn evaluate(&self, _runtime: &mut Runtime) -> Box<dyn Any> {
-        Box::new("null")
+    fn evaluate(&self, _runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        Ok(Value::Null)
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.instr(String::from("xor eax, eax"));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.constant(Value::Null);
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
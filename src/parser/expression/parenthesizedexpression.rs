@@ -1,8 +1,11 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -20,12 +23,16 @@ impl ToAny for ParenthesizedExpression {
 }
 
 impl Expression for ParenthesizedExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
         self.expr.evaluate(runtime)
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        self.expr.compile(nasm);
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.expr.emit(bytecode);
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
@@ -49,6 +56,10 @@ impl Expression for ParenthesizedExpression {
     fn get_expr_type(&self) -> String {
         String::from("ParenthesizedExpression")
     }
+
+    fn non_null_when(&self, when: bool) -> Vec<String> {
+        self.expr.non_null_when(when)
+    }
 }
 impl ParenthesizedExpression {
     pub fn new(expr: Box<dyn Expression>) -> ParenthesizedExpression {
@@ -0,0 +1,131 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::config::WhiteLangFloat;
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use std::any::Any;
+
+// <expr> ** <expr>, right associative so 2 ** 3 ** 2 is 2 ** 9
+#[derive(Clone, Debug)]
+pub(crate) struct PowerExpression {
+    base: Box<dyn Expression>,
+    exponent: Box<dyn Expression>,
+}
+
+impl ToAny for PowerExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for PowerExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let base = self.base.evaluate(runtime)?;
+        let exponent = self.exponent.evaluate(runtime)?;
+        power(base, exponent)
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("the power `{}`", self.debug()));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.base.emit(bytecode);
+        self.exponent.emit(bytecode);
+        bytecode.emit(Op::Power);
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        if self.get_white_type() == Type::Integer {
+            javascript.transpile_power(self.base.as_ref(), self.exponent.as_ref());
+            return;
+        }
+        // javascript doesn't allow `-2 ** 2`, Math.pow doesn't care what the base looks like
+        javascript.append_no_tabs(String::from("Math.pow("));
+        self.base.transpile(javascript);
+        javascript.append_no_tabs(String::from(", "));
+        self.exponent.transpile(javascript);
+        javascript.append_no_tabs(String::from(")"));
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.base.validate(st);
+        self.exponent.validate(st);
+        let (base, exponent) = (self.base.get_white_type(), self.exponent.get_white_type());
+        let is_number = |typ: &Type| *typ == Type::Integer || *typ == Type::Float;
+        if !is_number(&base) || !is_number(&exponent) {
+            add_parser_error_at(
+                st,
+                self,
+                ParserErrorType::IncompatibleTypes(base.clone(), exponent.clone()),
+                format!(
+                    "You cannot raise non number types to a power. base: `{}` exponent: `{}`",
+                    base, exponent
+                ),
+            );
+        }
+    }
+
+    fn debug(&self) -> String {
+        format!("{} ** {}", self.base.debug(), self.exponent.debug())
+    }
+
+    fn get_white_type(&self) -> Type {
+        if self.base.get_white_type() == Type::Float
+            || self.exponent.get_white_type() == Type::Float
+        {
+            return Type::Float;
+        }
+        Type::Integer
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("PowerExpression")
+    }
+}
+impl PowerExpression {
+    pub(crate) fn new(base: Box<dyn Expression>, exponent: Box<dyn Expression>) -> PowerExpression {
+        PowerExpression { base, exponent }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn get_exponent(&self) -> &Box<dyn Expression> {
+        &self.exponent
+    }
+}
+
+/// Raise `base` to `exponent`, ints stay ints, shared with the vm
+pub(crate) fn power(base: Value, exponent: Value) -> Result<Value, RuntimeError> {
+    let (base, exponent) = match (base, exponent) {
+        (Value::Int(base), Value::Int(exponent)) => {
+            if exponent < 0 {
+                return Err(RuntimeError::NegativeExponent(exponent));
+            }
+            // only 0, 1 and -1 survive an exponent this big, keep its parity for -1
+            let small = u32::try_from(exponent).unwrap_or(u32::MAX - (exponent % 2 == 0) as u32);
+            return match base.checked_pow(small) {
+                Some(result) => Ok(Value::Int(result)),
+                None => Err(RuntimeError::Overflow(format!("{} ** {}", base, exponent))),
+            };
+        }
+        (Value::Float(base), Value::Float(exponent)) => (base, exponent),
+        (Value::Float(base), Value::Int(exponent)) => (base, exponent as WhiteLangFloat),
+        (Value::Int(base), Value::Float(exponent)) => (base as WhiteLangFloat, exponent),
+        (base, exponent) => {
+            return Err(RuntimeError::TypeMismatch(format!(
+                "cannot raise `{}` to the power of `{}`",
+                base.type_name(),
+                exponent.type_name()
+            )))
+        }
+    };
+    Ok(Value::Float(base.powf(exponent)))
+}
@@ -0,0 +1,277 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::config::WhiteLangInt;
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType::BadType;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use std::any::Any;
+
+// start..end, start..=end, optionally followed by `step n`. for loops walk a range without
+// building the list, anywhere else it evaluates to a list<int>
+#[derive(Clone, Debug)]
+pub(crate) struct RangeExpression {
+    start: Box<dyn Expression>,
+    end: Box<dyn Expression>,
+    step: Option<Box<dyn Expression>>,
+    inclusive: bool,
+    typ: Type,
+}
+
+impl ToAny for RangeExpression {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Expression for RangeExpression {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        Ok(Value::List(self.iter(runtime)?.map(Value::Int).collect()))
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("the range `{}`", self.debug()));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.emit_bounds(bytecode);
+        bytecode.emit(Op::Range(self.inclusive));
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        javascript.append_no_tabs(format!(
+            "((start, end, step) => {{ const range = []; for (let i = start; step > 0 ? i {} end : step < 0 ? i {} end : {}; i += step) range.push(i); return range; }})(",
+            self.less(),
+            self.greater(),
+            JavaScript::throw(&RuntimeError::ZeroStep)
+        ));
+        self.start.transpile(javascript);
+        javascript.append_no_tabs(String::from(", "));
+        self.end.transpile(javascript);
+        javascript.append_no_tabs(String::from(", "));
+        self.transpile_step(javascript);
+        javascript.append_no_tabs(String::from(")"));
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.typ = Type::Integer.get_list_type();
+        let mut bounds = vec![&mut self.start, &mut self.end];
+        if let Some(step) = self.step.as_mut() {
+            bounds.push(step);
+        }
+        for bound in bounds {
+            bound.validate(st);
+            let typ = bound.get_white_type();
+            if typ != Type::Integer && typ != Type::Error {
+                add_parser_error_at(
+                    st,
+                    bound.as_ref(),
+                    BadType(typ.clone()),
+                    format!(
+                        "Ranges are made of `int`s, `{}` is `{}`",
+                        bound.debug(),
+                        typ
+                    ),
+                );
+            }
+        }
+    }
+
+    fn debug(&self) -> String {
+        let mut builder = format!(
+            "{}{}{}",
+            self.start.debug(),
+            if self.inclusive { "..=" } else { ".." },
+            self.end.debug()
+        );
+        if let Some(step) = &self.step {
+            builder.push_str(format!(" step {}", step.debug()).as_str());
+        }
+        builder
+    }
+
+    fn get_white_type(&self) -> Type {
+        self.typ.clone()
+    }
+
+    fn get_expr_type(&self) -> String {
+        String::from("RangeExpression")
+    }
+
+    fn set_type(&mut self, typ: Type) {
+        self.typ = typ;
+    }
+}
+impl RangeExpression {
+    pub fn new(
+        start: Box<dyn Expression>,
+        end: Box<dyn Expression>,
+        inclusive: bool,
+    ) -> RangeExpression {
+        RangeExpression {
+            start,
+            end,
+            step: None,
+            inclusive,
+            typ: Type::Initialized,
+        }
+    }
+
+    pub fn set_step(&mut self, step: Box<dyn Expression>) {
+        self.step = Some(step);
+    }
+
+    pub fn is_inclusive(&self) -> bool {
+        self.inclusive
+    }
+
+    pub fn get_start(&self) -> &Box<dyn Expression> {
+        &self.start
+    }
+
+    pub fn get_end(&self) -> &Box<dyn Expression> {
+        &self.end
+    }
+
+    pub fn has_step(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Evaluate the bounds and step, once, and walk the range without allocating anything
+    pub fn iter(&self, runtime: &mut Runtime) -> Result<RangeIter, RuntimeError> {
+        let start = Self::int(self.start.evaluate(runtime)?)?;
+        let end = Self::int(self.end.evaluate(runtime)?)?;
+        let step = match &self.step {
+            Some(step) => Self::int(step.evaluate(runtime)?)?,
+            None => 1,
+        };
+        if step == 0 {
+            return Err(RuntimeError::ZeroStep);
+        }
+        Ok(RangeIter::new(start, end, step, self.inclusive))
+    }
+
+    /// Push the start, end and step, the step checked to not be zero
+    pub fn emit_bounds(&self, bytecode: &mut Bytecode) {
+        self.start.emit(bytecode);
+        self.end.emit(bytecode);
+        match &self.step {
+            Some(step) => step.emit(bytecode),
+            None => {
+                bytecode.constant(Value::Int(1));
+            }
+        }
+        bytecode.emit(Op::CheckStep);
+    }
+
+    /// `for (let x = start, ...; ...; ...) {`, the end and step are only evaluated once
+    pub fn transpile_for(&self, variable: &str, javascript: &mut JavaScript) {
+        javascript.append(format!("for (let {} = ", variable));
+        self.start.transpile(javascript);
+        javascript.append_no_tabs(format!(", {}$end = ", variable));
+        self.end.transpile(javascript);
+        match &self.step {
+            None => javascript.append_no_tabs(format!(
+                "; {} {} {}$end; {}++) {{\n",
+                variable,
+                self.less(),
+                variable,
+                variable
+            )),
+            Some(step) => {
+                javascript.append_no_tabs(format!(", {}$step = ", variable));
+                step.transpile(javascript);
+                javascript.append_no_tabs(format!(
+                    "; {v}$step > 0 ? {v} {} {v}$end : {v}$step < 0 ? {v} {} {v}$end : {}; {v} += {v}$step) {{\n",
+                    self.less(),
+                    self.greater(),
+                    JavaScript::throw(&RuntimeError::ZeroStep),
+                    v = variable
+                ))
+            }
+        };
+    }
+
+    fn transpile_step(&self, javascript: &mut JavaScript) {
+        match &self.step {
+            Some(step) => step.transpile(javascript),
+            None => {
+                javascript.append_no_tabs(String::from("1"));
+            }
+        }
+    }
+
+    fn less(&self) -> &'static str {
+        if self.inclusive {
+            "<="
+        } else {
+            "<"
+        }
+    }
+
+    fn greater(&self) -> &'static str {
+        if self.inclusive {
+            ">="
+        } else {
+            ">"
+        }
+    }
+
+    fn int(value: Value) -> Result<WhiteLangInt, RuntimeError> {
+        match value {
+            Value::Int(int) => Ok(int),
+            value => Err(RuntimeError::TypeMismatch(format!(
+                "cannot make a range out of `{}`",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+/// The ints of a range, counting towards the end by the step
+pub(crate) struct RangeIter {
+    next: Option<WhiteLangInt>, // None once we've gone past the end
+    end: WhiteLangInt,
+    step: WhiteLangInt,
+    inclusive: bool,
+}
+impl RangeIter {
+    pub fn new(
+        start: WhiteLangInt,
+        end: WhiteLangInt,
+        step: WhiteLangInt,
+        inclusive: bool,
+    ) -> Self {
+        RangeIter {
+            next: Some(start),
+            end,
+            step,
+            inclusive,
+        }
+    }
+
+    /// Whether `value` hasn't gone past the end yet
+    pub fn in_range(&self, value: WhiteLangInt) -> bool {
+        match (self.step > 0, self.inclusive) {
+            (true, true) => value <= self.end,
+            (true, false) => value < self.end,
+            (false, true) => value >= self.end,
+            (false, false) => value > self.end,
+        }
+    }
+}
+impl Iterator for RangeIter {
+    type Item = WhiteLangInt;
+
+    fn next(&mut self) -> Option<WhiteLangInt> {
+        let current = self.next.filter(|current| self.in_range(*current))?;
+        self.next = current.checked_add(self.step); // stepping past the largest int ends the range
+        Some(current)
+    }
+}
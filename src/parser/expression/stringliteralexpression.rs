@@ -1,9 +1,12 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::expression::listliteralexpression::ListLiteralExpression;
 use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -21,16 +24,23 @@ impl ToAny for StringLiteralExpression {
 }
 
 impl Expression for StringLiteralExpression {
-    fn evaluate(&self, _runtime: &mut Runtime) -> Box<dyn Any> {
-        Box::new(self.string_value.clone())
+    fn evaluate(&self, _runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        Ok(Value::String(self.string_value.clone()))
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.string(self.string_value.as_str());
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.constant(Value::String(self.string_value.clone()));
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
-        javascript.append_no_tabs(format!("\"{}\"", self.string_value));
+        javascript.append_no_tabs(format!(
+            "\"{}\"",
+            JavaScript::escape(&self.string_value, '"')
+        ));
     }
 
     fn validate(&mut self, _st: &mut SymbolTable) {}
@@ -52,15 +62,17 @@ impl StringLiteralExpression {
         StringLiteralExpression { string_value }
     }
 
+    #[allow(dead_code)]
     pub(crate) fn to_list_literal(&self) -> ListLiteralExpression /*list<char>*/ {
         let mut list = ListLiteralExpression::new();
         for c in self.string_value.chars() {
             list.add_expr(Box::new(StringLiteralExpression::new(c.to_string())));
         }
-        list.set_type(Type::ListString);
+        list.set_type(Type::String.get_list_type());
         list
     }
 
+    #[allow(dead_code)]
     pub(crate) fn concatenate(&self, other: &StringLiteralExpression) -> StringLiteralExpression {
         let mut this = self.string_value.clone();
         let that = other.debug();
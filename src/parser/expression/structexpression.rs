@@ -1,11 +1,14 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{add_parser_error, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::whitetypes::Type::Struct;
 use crate::parser::ParserErrorType;
 use crate::parser::ParserErrorType::UnknownName;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -29,7 +32,6 @@ x.some_fn(args)
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub struct StructExpression {
-    name: String,
     typ: Type,
     fields: HashMap<String, Box<dyn Expression>>,
 }
@@ -44,98 +46,125 @@ impl ToAny for StructExpression {
 
 #[allow(dead_code, unused_variables)]
 impl Expression for StructExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        // register fields
-        for (name, expr) in self.fields.iter() {
-            crate::LOGGER.debug(
-                format!("[RUNTIME] Registering {} with {:?}", name, expr),
-                false,
-            );
-            runtime.set_value(format!("{}.{}", self.name, name), expr.clone());
-        }
-        // register functions
-        let obj = runtime.get_struct(match self.typ.clone() {
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let struct_id = match self.typ.clone() {
             Struct(s) => s,
-            _ => panic!("Something bad happened"),
-        });
-        for (name, fds) in obj.methods.iter() {
-            let copy = name
-                .split(".")
-                .collect::<Vec<&str>>()
-                .last()
-                .unwrap()
-                .to_string();
-            crate::LOGGER.debug(
-                format!("[RUNTIME] Registering {} with {:?}", name, fds),
-                false,
-            );
-            runtime.add_function(format!("{}", name), fds.clone());
-            runtime.add_function(format!("{}.{}", self.name, copy), fds.clone());
+            typ => {
+                return Err(RuntimeError::TypeMismatch(format!(
+                    "`{}` is not a struct type",
+                    typ
+                )))
+            }
+        };
+        runtime.get_struct(struct_id.clone())?; // make sure the definition has been run
+        let mut fields = HashMap::new();
+        for (name, expr) in self.fields.iter() {
+            fields.insert(name.clone(), expr.evaluate(runtime)?);
         }
-        Box::new(self.clone())
+        Ok(Value::new_struct(struct_id, fields))
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("struct `{}`", self.typ));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        let struct_id = match self.typ.clone() {
+            Struct(s) => s,
+            typ => panic!("`{}` is not a struct type", typ),
+        };
+        // `Op::Struct` pops the fields in the order the definition gave the bytecode
+        for name in bytecode.get_struct_fields(struct_id.as_str()) {
+            self.fields[&name].emit(bytecode);
+        }
+        let idx = bytecode.name(struct_id.as_str());
+        bytecode.emit(Op::Struct(idx));
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
-        todo!()
+        let mut fields = self.fields.iter().collect::<Vec<_>>();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        javascript.append_no_tabs(format!(
+            "new {}({{",
+            JavaScript::class_name(&self.typ.to_string())
+        ));
+        for (i, (name, expr)) in fields.into_iter().enumerate() {
+            if i > 0 {
+                javascript.append_no_tabs(String::from(", "));
+            }
+            javascript.append_no_tabs(format!("{}: ", name));
+            javascript.transpile_value(expr.as_ref());
+        }
+        javascript.append_no_tabs(String::from("})"));
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
         let mut struct_id = String::new();
-        if self.name == String::from("default") {
-            panic!("make sure you are setting the name of StructExpression somewhere")
-        }
         match self.typ.clone() {
             Struct(s) => struct_id = s,
-            _ => add_parser_error(
+            _ => add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::BadType(self.typ.clone()),
-                format!("Expected a struct, got {:?}", self.typ),
+                format!("Expected a struct, got `{}`", self.typ),
             ),
         }
         let strct = match st.get_struct(struct_id.clone()) {
             Some(s) => s,
             None => {
-                add_parser_error(
+                add_parser_error_at(
+                    st,
+                    self,
                     UnknownName(struct_id.clone()),
                     format!(
                         "Unable to retrieve struct type: {} from the symbol_table",
                         struct_id
                     ),
                 );
-                unreachable!()
+                return;
             }
         };
-        for (name, expr) in self.fields.iter() {
+        for (name, expr) in self.fields.iter_mut() {
+            expr.validate(st);
             let expected_typ = match strct.get_field_type(name.clone()) {
                 Some(t) => t,
                 None => {
-                    add_parser_error(
+                    add_parser_error_at(
+                        st,
+                        expr.as_ref(),
                         UnknownName(name.clone()),
                         format!("No such field `{}` on struct `{}`", name, struct_id),
                     );
-                    Type::Error // this will never be reached, but gotta appease the compiler
+                    continue;
                 }
             };
             if !expected_typ.is_assignable_to(expr.get_white_type()) {
-                add_parser_error(
+                add_parser_error_at(
+                    st,
+                    expr.as_ref(),
                     ParserErrorType::IncompatibleTypes(expected_typ.clone(), expr.get_white_type()),
                     format!(
-                        "{:?} is not assignable to {:?}",
+                        "`{}` is not assignable to `{}`",
                         expr.get_white_type(),
                         expected_typ
                     ),
                 )
             }
         }
-        for (name, fds) in strct.methods.iter() {
-            let method_name_pure = name.split(".").last().unwrap().to_string();
-            st.register_function(format!("{}.{}", self.name, method_name_pure), fds.clone());
-        }
-        for (name, typ) in strct.fields.iter() {
-            st.register_symbol(format!("{}.{}", self.name, name.to_string()), typ.clone());
+        let mut missing = strct
+            .fields
+            .keys()
+            .filter(|field| !self.fields.contains_key(*field))
+            .cloned()
+            .collect::<Vec<String>>();
+        missing.sort();
+        for field in missing {
+            add_parser_error_at(
+                st,
+                self,
+                ParserErrorType::EmptyStructVariable(field.clone()),
+                format!("Try: {}({} = ...)", struct_id, field),
+            );
         }
     }
 
@@ -150,17 +179,12 @@ impl Expression for StructExpression {
     fn get_expr_type(&self) -> String {
         todo!()
     }
-
-    fn set_name(&mut self, name: String) {
-        self.name = name;
-    }
 }
 
 #[allow(dead_code)]
 impl StructExpression {
     pub fn new(_: String, typ: Type) -> StructExpression {
         Self {
-            name: String::from("default"),
             typ,
             fields: HashMap::new(),
         }
@@ -169,8 +193,4 @@ impl StructExpression {
     pub fn add_field(&mut self, field_name: String, expression: Box<dyn Expression>) {
         self.fields.insert(field_name, expression.clone());
     }
-
-    pub fn set_name(&mut self, name: String) {
-        self.name = name;
-    }
 }
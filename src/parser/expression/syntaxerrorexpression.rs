@@ -1,8 +1,11 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -18,12 +21,16 @@ impl ToAny for SyntaxErrorExpression {
 }
 
 impl Expression for SyntaxErrorExpression {
-    fn evaluate(&self, _runtime: &mut Runtime) -> Box<dyn Any> {
-        panic!("Evaluating SyntaxErrorExpression... panic");
+    fn evaluate(&self, _runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        Err(RuntimeError::SyntaxError)
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, _nasm: &mut Nasm) {
+        panic!("Compiled a syntax error")
+    }
+
+    fn emit(&self, _bytecode: &mut Bytecode) {
+        panic!("Emitted a syntax error")
     }
 
     fn transpile(&self, _: &mut JavaScript) {
@@ -1,10 +1,12 @@
-use crate::config::{WhiteLangFloat, WhiteLangInt};
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::parser_traits::{add_parser_error, Expression, ToAny};
+use crate::nasm::Nasm;
+use crate::parser::parser_traits::{add_parser_error_at, Expression, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -24,59 +26,107 @@ impl ToAny for UnaryExpression {
 }
 
 impl Expression for UnaryExpression {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any> {
-        println!("[UNARY EXPR] eval: {:?}", self);
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError> {
+        let eval = self.expr.evaluate(runtime)?;
+        match eval {
+            Value::Bool(bool) if self.is_not => Ok(Value::Bool(!bool)),
+            _ if self.is_not => Err(RuntimeError::TypeMismatch(format!(
+                "cannot apply `!` to `{}`",
+                eval.type_name()
+            ))),
+            Value::Int(int) if self.is_complement() => Ok(Value::Int(!int)),
+            _ if self.is_complement() => Err(RuntimeError::TypeMismatch(format!(
+                "cannot apply `~` to `{}`",
+                eval.type_name()
+            ))),
+            Value::Int(int) => int
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::Overflow(format!("-({})", int))),
+            Value::Float(float) => Ok(Value::Float(-float)),
+            _ => Err(RuntimeError::TypeMismatch(format!(
+                "cannot negate `{}`",
+                eval.type_name()
+            ))),
+        }
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        self.expr.compile(nasm);
         if self.is_not {
-            let eval = self.expr.evaluate(runtime);
-            if let Some(eval_bool) = eval.downcast_ref::<bool>() {
-                return Box::new(!eval_bool);
-            }
+            nasm.instr(String::from("xor rax, 1"));
+        } else if self.is_complement() {
+            nasm.instr(String::from("not rax"));
+        } else if self.expr.get_white_type() == Type::Float {
+            nasm.instr(String::from("btc rax, 63")); // flip the sign bit
         } else {
-            let eval = self.expr.evaluate(runtime);
-            if let Some(eval_isize) = eval.downcast_ref::<WhiteLangInt>() {
-                return Box::new(-1 * eval_isize);
-            }
-            if let Some(eval_f64) = eval.downcast_ref::<WhiteLangFloat>() {
-                return Box::new(-1 as WhiteLangFloat * eval_f64);
-            }
+            nasm.instr(String::from("neg rax"));
         }
-        unimplemented!()
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.expr.emit(bytecode);
+        bytecode.emit(if self.is_not {
+            Op::Not
+        } else if self.is_complement() {
+            Op::BitNot
+        } else {
+            Op::Negate
+        });
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
+        if self.is_complement() {
+            // `~` on a number only keeps 32 bits, like the bitwise operators
+            javascript.append_no_tabs(String::from("Number(~BigInt("));
+            self.expr.transpile(javascript);
+            javascript.append_no_tabs(String::from("))"));
+            return;
+        }
         if self.is_not {
             javascript.append_no_tabs(String::from("!"));
-            self.expr.transpile(javascript);
         } else {
             javascript.append_no_tabs(String::from("-"));
-            self.expr.transpile(javascript);
         }
+        self.expr.transpile(javascript);
     }
 
-    fn validate(&mut self, _st: &mut SymbolTable) {
+    fn validate(&mut self, st: &mut SymbolTable) {
+        self.expr.validate(st);
         if self.operator == "not"
             && (self.expr.get_white_type() == Type::Integer
                 || self.expr.get_white_type() == Type::Float)
         {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
+                ParserErrorType::BadOperator(self.operator.clone()),
+                "You cannot use `not` on numerical types.".to_string(),
+            );
+        }
+        if self.is_complement() && self.expr.get_white_type() != Type::Integer {
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::BadOperator(self.operator.clone()),
-                format!("You cannot use `not` on numerical types."),
+                "You can only use `~` on ints.".to_string(),
             );
         }
         if self.operator == "-" && self.expr.get_white_type() == Type::Boolean {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self,
                 ParserErrorType::BadOperator(self.operator.clone()),
-                format!("You cannot use `-` on boolean types."),
+                "You cannot use `-` on boolean types.".to_string(),
             );
         }
     }
 
     fn debug(&self) -> String {
         let mut builder = self.operator.clone();
+        if self.is_not {
+            builder += " "; // `not x`, not `notx`
+        }
         builder += &*self.expr.debug();
         builder
     }
@@ -86,7 +136,14 @@ impl Expression for UnaryExpression {
     }
 
     fn get_expr_type(&self) -> String {
-        todo!()
+        String::from("UnaryExpression")
+    }
+
+    fn non_null_when(&self, when: bool) -> Vec<String> {
+        match self.is_not {
+            true => self.expr.non_null_when(!when),
+            false => vec![],
+        }
     }
 }
 impl UnaryExpression {
@@ -97,4 +154,8 @@ impl UnaryExpression {
             is_not: operator.contains("not"),
         }
     }
+
+    fn is_complement(&self) -> bool {
+        self.operator == "~"
+    }
 }
@@ -1,18 +1,28 @@
-use crate::config::{WhiteLangBool, WhiteLangFloat, WhiteLangInt, WhiteLangList, WhiteLangString};
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::expression::additiveexpression::AdditiveExpression;
+use crate::parser::expression::bitwiseexpression::BitwiseExpression;
 use crate::parser::expression::booleanliteralexpression::BooleanLiteralExpression;
+use crate::parser::expression::coalesceexpression::CoalesceExpression;
 use crate::parser::expression::comparisonexpression::ComparisonExpression;
 use crate::parser::expression::equalityexpression::EqualityExpression;
 use crate::parser::expression::factorexpression::FactorExpression;
+use crate::parser::expression::fieldaccessexpression::FieldAccessExpression;
 use crate::parser::expression::floatliteralexpression::FloatLiteralExpression;
 use crate::parser::expression::functioncallexpression::FunctionCallExpression;
 use crate::parser::expression::identifierexpression::IdentifierExpression;
+use crate::parser::expression::indexexpression::IndexExpression;
 use crate::parser::expression::integerliteralexpression::IntegerLiteralExpression;
+use crate::parser::expression::interpolatedstringexpression::InterpolatedStringExpression;
 use crate::parser::expression::listliteralexpression::ListLiteralExpression;
 use crate::parser::expression::logicalexpression::LogicalExpression;
+use crate::parser::expression::mapliteralexpression::MapLiteralExpression;
+use crate::parser::expression::methodcallexpression::MethodCallExpression;
 use crate::parser::expression::nullliteralexpression::NullLiteralExpression;
 use crate::parser::expression::parenthesizedexpression::ParenthesizedExpression;
+use crate::parser::expression::powerexpression::PowerExpression;
+use crate::parser::expression::rangeexpression::RangeExpression;
 use crate::parser::expression::stringliteralexpression::StringLiteralExpression;
 use crate::parser::expression::structexpression::StructExpression;
 use crate::parser::expression::syntaxerrorexpression::SyntaxErrorExpression;
@@ -20,6 +30,7 @@ use crate::parser::expression::unaryexpression::UnaryExpression;
 
 use crate::parser::statement::assignmentstatement::AssignmentStatement;
 use crate::parser::statement::breakstatement::BreakStatement;
+use crate::parser::statement::continuestatement::ContinueStatement;
 use crate::parser::statement::forstatement::ForStatement;
 use crate::parser::statement::functioncallstatement::FunctionCallStatement;
 use crate::parser::statement::functiondefinitionstatement::FunctionDefinitionStatement;
@@ -31,7 +42,8 @@ use crate::parser::statement::syntaxerrorstatement::SyntaxErrorStatement;
 use crate::parser::statement::variablestatement::VariableStatement;
 use crate::parser::statement::whilestatement::WhileStatement;
 
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use crate::LOGGER;
 
 use crate::parser::symbol_table::SymbolTable;
@@ -50,70 +62,39 @@ pub fn default_expr() -> Box<dyn Expression> {
     Box::new(SyntaxErrorExpression::new())
 }
 
-pub fn add_parser_error(error: ParserErrorType, info: String) {
-    LOGGER.error(format!("Error: {:?}\ninfo: {}", error, info));
-}
-pub fn any_into_int_literal(any: &Box<dyn Any>) -> Option<IntegerLiteralExpression> {
-    if let Some(integer) = any.downcast_ref::<WhiteLangInt>() {
-        return Some(IntegerLiteralExpression::new(*integer));
-    }
-    None
-}
-
-pub fn any_into_f64_literal(any: &Box<dyn Any>) -> Option<FloatLiteralExpression> {
-    if let Some(float) = any.downcast_ref::<WhiteLangFloat>() {
-        return Some(FloatLiteralExpression::new(*float));
-    }
-    None
+/// Used by parse elements to report errors during validation, the parser picks them
+/// up off of the symbol table once the statement has been validated
+pub fn add_parser_error(st: &mut SymbolTable, error: ParserErrorType, info: String) {
+    LOGGER.debug(format!("Error: {:?}\ninfo: {}", error, info), false);
+    st.add_error(error, info, None);
 }
 
-pub fn any_into_bool_literal(any: &Box<dyn Any>) -> Option<BooleanLiteralExpression> {
-    if let Some(bool) = any.downcast_ref::<WhiteLangBool>() {
-        return Some(BooleanLiteralExpression::new(*bool));
-    }
-    None
+/// Like `add_parser_error`, but the error points at the expression `at` instead of the whole
+/// statement it's in
+pub fn add_parser_error_at(
+    st: &mut SymbolTable,
+    at: &dyn Expression,
+    error: ParserErrorType,
+    info: String,
+) {
+    LOGGER.debug(format!("Error: {:?}\ninfo: {}", error, info), false);
+    st.add_error(error, info, expression_id(at));
 }
 
-pub fn any_into_string_literal(any: &Box<dyn Any>) -> Option<StringLiteralExpression> {
-    if let Some(string) = any.downcast_ref::<WhiteLangString>() {
-        return Some(StringLiteralExpression::new(string.to_string()));
+/// Identifies an expression by where it lives, the parser uses it to remember where in the
+/// source each expression came from. Expressions without any fields all live in the same
+/// place so they can't be told apart
+pub fn expression_id(expr: &dyn Expression) -> Option<usize> {
+    if std::mem::size_of_val(expr) == 0 {
+        return None;
     }
-    None
+    Some((expr as *const dyn Expression).cast::<()>() as usize)
 }
-
-pub fn try_print_output(evaluated: &Box<dyn Any>) -> String {
-    let mut output = String::new();
-    if let Some(eval_f64) = evaluated.downcast_ref::<WhiteLangFloat>() {
-        let push = eval_f64.to_string();
-        output.push_str(push.as_str());
-    } else if let Some(eval_isize) = evaluated.downcast_ref::<WhiteLangInt>() {
-        let push = eval_isize.to_string();
-        output.push_str(push.as_str());
-    } else if let Some(eval_bool) = evaluated.downcast_ref::<WhiteLangBool>() {
-        let push = eval_bool.to_string();
-        output.push_str(push.as_str());
-    } else if let Some(eval_str) = evaluated.downcast_ref::<&'static str>() {
-        let push = eval_str.to_string();
-        output.push_str(push.as_str());
-    } else if let Some(eval_string) = evaluated.downcast_ref::<WhiteLangString>() {
-        output.push_str(eval_string.as_str());
-    } else if let Some(eval_list) = evaluated.downcast_ref::<WhiteLangList<Box<dyn Any>>>() {
-        output.push_str("[");
-        for (i, thing) in eval_list.iter().enumerate() {
-            output.push_str(try_print_output(thing).as_str());
-            if i < eval_list.len() - 1 {
-                output.push_str(", ");
-            }
-        }
-        output.push_str("]");
-    }
-    output
-}
-
 #[allow(dead_code)]
 pub trait Expression: ToAny + Debug {
-    fn evaluate(&self, runtime: &mut Runtime) -> Box<dyn Any>; // evaluate the expression
-    fn compile(&self); // compile the expression to nasm
+    fn evaluate(&self, runtime: &mut Runtime) -> Result<Value, RuntimeError>; // evaluate the expression
+    fn compile(&self, nasm: &mut Nasm); // compile the expression to nasm, leaving its value in rax
+    fn emit(&self, bytecode: &mut Bytecode); // compile the expression to bytecode, leaving its value on the vm's stack
     fn transpile(&self, javascript: &mut JavaScript); // transpile the expression to javascript
     fn validate(&mut self, st: &mut SymbolTable); // validate the expression via the symbol table
     fn debug(&self) -> String; // for retrieving information about the expression
@@ -125,6 +106,11 @@ pub trait Expression: ToAny + Debug {
     fn set_type(&mut self, _typ: Type) {
         panic!("set_type is not defined on {:?}", self)
     }
+    // the variables that can't be null when this evaluates to `when`, i.e. `x` for `x != null`
+    // and true, the symbol table narrows them from `T?` to `T` wherever that's known
+    fn non_null_when(&self, _when: bool) -> Vec<String> {
+        vec![]
+    }
 }
 
 // using to any to downcast the dyn Expression to the concrete class
@@ -135,6 +121,8 @@ impl Clone for Box<dyn Expression> {
             return Box::new(expr.clone());
         } else if let Some(expr) = self.to_any().downcast_ref::<BooleanLiteralExpression>() {
             return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<CoalesceExpression>() {
+            return Box::new(expr.clone());
         } else if let Some(expr) = self.to_any().downcast_ref::<ComparisonExpression>() {
             return Box::new(expr.clone());
         } else if let Some(expr) = self.to_any().downcast_ref::<EqualityExpression>() {
@@ -165,6 +153,22 @@ impl Clone for Box<dyn Expression> {
             return Box::new(expr.clone());
         } else if let Some(expr) = self.to_any().downcast_ref::<StructExpression>() {
             return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<FieldAccessExpression>() {
+            return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<MethodCallExpression>() {
+            return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<RangeExpression>() {
+            return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<BitwiseExpression>() {
+            return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<PowerExpression>() {
+            return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<IndexExpression>() {
+            return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<InterpolatedStringExpression>() {
+            return Box::new(expr.clone());
+        } else if let Some(expr) = self.to_any().downcast_ref::<MapLiteralExpression>() {
+            return Box::new(expr.clone());
         }
         panic!("Didn't cover expressions exhaustively")
     }
@@ -172,8 +176,9 @@ impl Clone for Box<dyn Expression> {
 
 #[allow(dead_code)]
 pub trait Statement: ToAny + Debug {
-    fn execute(&mut self, runtime: &mut Runtime); // execute the statement
-    fn compile(&self); // compile the statement to nasm
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError>; // execute the statement
+    fn compile(&self, nasm: &mut Nasm); // compile the statement to nasm
+    fn emit(&self, bytecode: &mut Bytecode); // compile the statement to bytecode for the vm
     fn transpile(&self, javascript: &mut JavaScript); // transpile the statement to Javascript
     fn validate(&mut self, st: &mut SymbolTable); // validate the statement via the symbol table
     fn get_expr(&self) -> &Box<dyn Expression>; // retrieve the expression if the statement has one
@@ -204,6 +209,8 @@ impl Clone for Box<dyn Statement> {
             return Box::new(stmt.clone());
         } else if let Some(stmt) = self.to_any().downcast_ref::<BreakStatement>() {
             return Box::new(stmt.clone());
+        } else if let Some(stmt) = self.to_any().downcast_ref::<ContinueStatement>() {
+            return Box::new(stmt.clone());
         } else if let Some(stmt) = self.to_any().downcast_ref::<StructDefinitionStatement>() {
             return Box::new(stmt.clone());
         }
@@ -0,0 +1,82 @@
+use crate::tokenizer::TokenType;
+
+/// How tightly the operators of white-lang bind, loosest first
+///
+/// | precedence   | operators              | associativity |
+/// |--------------|------------------------|---------------|
+/// | `Coalesce`   | `??`                   | right         |
+/// | `Or`         | `\|\|` `or`            | left          |
+/// | `And`        | `&&` `and`             | left          |
+/// | `Equality`   | `==` `!=`              | left          |
+/// | `Comparison` | `<` `<=` `>` `>=`      | left          |
+/// | `BitOr`      | `\|`                   | left          |
+/// | `BitXor`     | `^`                    | left          |
+/// | `BitAnd`     | `&`                    | left          |
+/// | `Shift`      | `<<` `>>`              | left          |
+/// | `Additive`   | `+` `-`                | left          |
+/// | `Factor`     | `*` `/` `%`            | left          |
+/// | `Unary`      | `-` `not` `~` (prefix) |               |
+/// | `Power`      | `**`                   | right         |
+///
+/// Ranges (`a..b`) sit below all of these, and `.field`, `.method()` bind tighter than
+/// anything, so `-2 ** 2` is `-(2 ** 2)` and `-p.x` is `-(p.x)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Precedence {
+    Lowest,
+    Coalesce,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Additive,
+    Factor,
+    Unary,
+    Power,
+}
+impl Precedence {
+    /// The precedence of a binary operator, None if the token isn't one
+    pub fn of(typ: TokenType) -> Option<Precedence> {
+        use TokenType::*;
+        Some(match typ {
+            QuestionQuestion => Precedence::Coalesce,
+            Lor | Or => Precedence::Or,
+            Land | And => Precedence::And,
+            EqualEqual | BangEqual => Precedence::Equality,
+            Less | LessEqual | Greater | GreaterEqual => Precedence::Comparison,
+            Bor => Precedence::BitOr,
+            Lxor => Precedence::BitXor,
+            Band => Precedence::BitAnd,
+            LessLess | GreaterGreater => Precedence::Shift,
+            Plus | Minus => Precedence::Additive,
+            Star | Slash | Percent => Precedence::Factor,
+            StarStar => Precedence::Power,
+            _ => return None,
+        })
+    }
+
+    /// The least an operator's right hand side has to bind, left associative operators
+    /// need one level tighter than themselves so `a - b - c` is `(a - b) - c`
+    pub fn rhs(self) -> Precedence {
+        use Precedence::*;
+        match self {
+            Lowest => Coalesce,
+            Coalesce => Coalesce, // right associative, a ?? b ?? c is a ?? (b ?? c)
+            Or => And,
+            And => Equality,
+            Equality => Comparison,
+            Comparison => BitOr,
+            BitOr => BitXor,
+            BitXor => BitAnd,
+            BitAnd => Shift,
+            Shift => Additive,
+            Additive => Factor,
+            Factor => Unary,
+            Unary => Power,
+            Power => Power, // right associative, 2 ** 3 ** 2 is 2 ** (3 ** 2)
+        }
+    }
+}
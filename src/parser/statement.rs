@@ -1,6 +1,7 @@
 // statements
 pub(crate) mod assignmentstatement;
 pub(crate) mod breakstatement;
+pub(crate) mod continuestatement;
 pub(crate) mod forstatement;
 pub(crate) mod functioncallstatement;
 pub(crate) mod functiondefinitionstatement;
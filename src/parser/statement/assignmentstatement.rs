@@ -1,17 +1,29 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::parser::expression::additiveexpression::AdditiveExpression;
+use crate::parser::expression::factorexpression::FactorExpression;
+use crate::parser::expression::fieldaccessexpression::FieldAccessExpression;
 use crate::parser::expression::identifierexpression::IdentifierExpression;
+use crate::parser::expression::indexexpression::IndexExpression;
+use crate::parser::expression::integerliteralexpression::IntegerLiteralExpression;
+use crate::parser::expression::listliteralexpression::type_empty_literal;
 use crate::parser::expression::syntaxerrorexpression::SyntaxErrorExpression;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
+use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
+use crate::vm::arithmetic;
 
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
 pub(crate) struct AssignmentStatement {
     variable: Box<dyn Expression>,
-    expr: Box<dyn Expression>,
+    expr: Box<dyn Expression>, // the right hand side, `x += 2` assigns `x + 2` and `x++` is `x += 1`
+    operator: String,          // =, +=, -=, *=, /=, %=, ++ or --
 }
 
 impl ToAny for AssignmentStatement {
@@ -24,45 +36,139 @@ impl ToAny for AssignmentStatement {
 }
 
 impl Statement for AssignmentStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        let ident = self
-            .variable
-            .to_any()
-            .downcast_ref::<IdentifierExpression>()
-            .expect("Unable to fetch identifier from the runtime when expecting to be able to");
-        if let Some(boolean) = any_into_bool_literal(&self.expr.evaluate(runtime)) {
-            runtime.set_value(ident.debug(), Box::new(boolean.clone()));
-        } else if let Some(integer) = any_into_int_literal(&self.expr.evaluate(runtime)) {
-            runtime.set_value(ident.debug(), Box::new(integer.clone()));
-        } else if let Some(float) = any_into_f64_literal(&self.expr.evaluate(runtime)) {
-            runtime.set_value(ident.debug(), Box::new(float.clone()));
-        } else {
-            runtime.set_value(ident.debug(), self.expr.clone());
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        assign(
+            self.variable.as_ref(),
+            &mut |runtime, current| match self.operation() {
+                Some(op) => {
+                    let lhs = current(runtime)?;
+                    let rhs = self.expr.evaluate(runtime)?;
+                    arithmetic(op, lhs, rhs)
+                }
+                None => self.expr.evaluate(runtime),
+            },
+            runtime,
+        )
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        let name = self.variable.debug();
+        if self.field().is_some() {
+            nasm.unsupported(format!("assigning to the struct field `{}`", name));
+            return;
         }
+        if self.index().is_some() {
+            nasm.unsupported(format!("assigning to the element `{}`", name));
+            return;
+        }
+        self.value().compile(nasm);
+        nasm.store(name.as_str());
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        emit_assign(
+            self.variable.as_ref(),
+            &mut |bytecode, current| match self.operation() {
+                Some(op) => {
+                    current(bytecode);
+                    self.expr.emit(bytecode);
+                    bytecode.emit(op);
+                }
+                None => self.expr.emit(bytecode),
+            },
+            bytecode,
+        );
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         javascript.append("".to_string());
+        if let Some(index) = self.index() {
+            match self.operation() {
+                Some(_) => javascript.transpile_update(
+                    index.get_list().as_ref(),
+                    index.get_index().as_ref(),
+                    &|javascript| {
+                        self.transpile_operation(
+                            &|javascript| {
+                                javascript.append_no_tabs(String::from("$old"));
+                            },
+                            javascript,
+                        )
+                    },
+                ),
+                None => index.transpile_assign(self.expr.as_ref(), javascript),
+            }
+            javascript.append_no_tabs(String::from(";")).newline();
+            return;
+        }
+        // `/=` on an int has to truncate, and javascript has no operator for that
+        let truncate = self.operator == "/=" && self.variable.get_white_type() == Type::Integer;
+        if let Some(field) = self
+            .field()
+            .filter(|field| truncate && !field.get_object().to_any().is::<IdentifierExpression>())
+        {
+            // `obj` might do something, like call a function, so it can only be evaluated once
+            javascript.append_no_tabs(String::from("($object => $object"));
+            javascript.append_no_tabs(format!(".{} = ", field.get_field()));
+            self.transpile_operation(
+                &|javascript| {
+                    javascript.append_no_tabs(format!("$object.{}", field.get_field()));
+                },
+                javascript,
+            );
+            javascript.append_no_tabs(String::from(")("));
+            field.get_object().transpile(javascript);
+            javascript.append_no_tabs(String::from(");")).newline();
+            return;
+        }
         self.variable.transpile(javascript);
-        javascript.append_no_tabs(format!(" = "));
-        self.expr.transpile(javascript);
+        if self.operator == "=" {
+            javascript.append_no_tabs(" = ".to_string());
+            javascript.transpile_value(self.expr.as_ref());
+        } else if truncate {
+            javascript.append_no_tabs(" = ".to_string());
+            self.transpile_operation(
+                &|javascript| self.variable.transpile(javascript),
+                javascript,
+            );
+        } else if self.operator == "++" || self.operator == "--" {
+            javascript.append_no_tabs(self.operator.clone());
+        } else {
+            javascript.append_no_tabs(format!(" {} ", self.operator));
+            self.expr.transpile(javascript);
+        }
         javascript.append_no_tabs(String::from(";")).newline();
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
         self.variable.validate(st);
         self.expr.validate(st);
-        if self
-            .variable
-            .to_any()
-            .downcast_ref::<IdentifierExpression>()
-            .is_none()
-        {
-            add_parser_error(
+        if let Some(index) = self.index() {
+            if index.get_list().get_white_type() == Type::String {
+                add_parser_error_at(
+                    st,
+                    self.variable.as_ref(),
+                    ParserErrorType::BadType(Type::String),
+                    format!(
+                        "Cannot assign to `{}`, strings can't be changed",
+                        self.variable.debug()
+                    ),
+                );
+            } else if !is_assignable(index.get_list().as_ref()) {
+                add_parser_error_at(
+                    st,
+                    self.variable.as_ref(),
+                    ParserErrorType::UnexpectedExpression(self.variable.clone()),
+                    format!(
+                        "Cannot assign to `{}`, only the elements of lists and maps held by variables and fields can be assigned to",
+                        self.variable.debug()
+                    ),
+                );
+            }
+        } else if !is_assignable(self.variable.as_ref()) {
+            add_parser_error_at(
+                st,
+                self.variable.as_ref(),
                 ParserErrorType::UnexpectedExpression(self.variable.clone()),
                 format!(
                     "Variable: [{}] is not defined in this scope",
@@ -70,23 +176,34 @@ impl Statement for AssignmentStatement {
                 ),
             );
         }
-        if !self
-            .expr
-            .get_white_type()
-            .is_assignable_to(self.variable.get_white_type())
-        {
-            add_parser_error(
-                ParserErrorType::IncompatibleTypes(
-                    self.expr.clone().get_white_type(),
-                    self.variable.clone().get_white_type(),
-                ),
-                format!(
-                    "You cannot assign {:?} to {:?}",
-                    self.expr.get_white_type(),
-                    self.variable.get_white_type()
-                ),
+        if self.operator != "=" {
+            return self.validate_compound(st);
+        }
+        // a narrowed `T?` variable can still be given anything a `T?` can hold
+        let name = self.variable.debug();
+        let variable = match self.variable.to_any().is::<IdentifierExpression>() {
+            true => st
+                .get_declared_type(name.clone())
+                .unwrap_or(self.variable.get_white_type()),
+            false => self.variable.get_white_type(),
+        };
+        type_empty_literal(self.expr.as_mut(), &variable);
+        let value = self.expr.get_white_type();
+        if !variable.is_assignable_to(value.clone()) {
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
+                ParserErrorType::IncompatibleTypes(value.clone(), variable.clone()),
+                format!("You cannot assign `{}` to `{}`", value, variable),
             );
         }
+        if variable.is_nullable() && self.variable.to_any().is::<IdentifierExpression>() {
+            // `x = null` means x might be null from here on, `x = 1` means it can't be
+            match value.is_nullable() {
+                true => st.widen(&name),
+                false => st.narrow(name),
+            }
+        }
     }
 
     fn get_expr(&self) -> &Box<dyn Expression> {
@@ -103,6 +220,7 @@ impl AssignmentStatement {
         AssignmentStatement {
             variable: Box::new(SyntaxErrorExpression::new()),
             expr: Box::new(SyntaxErrorExpression::new()),
+            operator: String::from("="),
         }
     }
 
@@ -113,4 +231,177 @@ impl AssignmentStatement {
     pub fn set_expr(&mut self, expr: Box<dyn Expression>) {
         self.expr = expr;
     }
+
+    /// `x += value;` assigns `x + value`, `x++;` assigns `x + 1`
+    pub fn set_compound(&mut self, operator: String, value: Option<Box<dyn Expression>>) {
+        self.expr = value.unwrap_or_else(|| Box::new(IntegerLiteralExpression::new(1)));
+        self.operator = operator;
+    }
+
+    /// The arithmetic a compound assignment does, None for `=`
+    fn operation(&self) -> Option<Op> {
+        match &self.operator[..1] {
+            "+" => Some(Op::Add),
+            "-" => Some(Op::Subtract),
+            "*" => Some(Op::Multiply),
+            "/" => Some(Op::Divide),
+            "%" => Some(Op::Modulo),
+            _ => None,
+        }
+    }
+
+    /// What gets assigned, `x + 2` for `x += 2`. It reads the target again, so it's only for
+    /// targets that are variables, and only once the statement is validated
+    fn value(&self) -> Box<dyn Expression> {
+        if self.operator == "=" {
+            return self.expr.clone();
+        }
+        let op = self.operator[..1].to_string();
+        match op.as_str() {
+            "+" | "-" => Box::new(AdditiveExpression::new(
+                self.variable.clone(),
+                op,
+                self.expr.clone(),
+            )),
+            _ => Box::new(FactorExpression::new(
+                self.variable.clone(),
+                op,
+                self.expr.clone(),
+            )),
+        }
+    }
+
+    /// `current op value`, where `current` transpiles what reads the target
+    fn transpile_operation(&self, current: &dyn Fn(&mut JavaScript), javascript: &mut JavaScript) {
+        let op = &self.operator[..1];
+        let truncate = op == "/" && self.variable.get_white_type() == Type::Integer;
+        // the right hand side was written on its own, so `x /= 1 + 2` divides by 3
+        let parenthesize = matches!(
+            self.expr.get_expr_type().as_str(),
+            "AdditiveExpression" | "FactorExpression" | "CoalesceExpression"
+        );
+        if truncate {
+            javascript.append_no_tabs(String::from("Math.trunc("));
+        }
+        current(javascript);
+        javascript.append_no_tabs(format!(" {} ", op));
+        if parenthesize {
+            javascript.append_no_tabs(String::from("("));
+        }
+        self.expr.transpile(javascript);
+        if parenthesize {
+            javascript.append_no_tabs(String::from(")"));
+        }
+        if truncate {
+            javascript.append_no_tabs(String::from(")"));
+        }
+    }
+
+    /// `x += value;` and friends only work on numbers, and an `int` can't be given a `float`
+    fn validate_compound(&mut self, st: &mut SymbolTable) {
+        let variable = self.variable.get_white_type();
+        let value = self.expr.get_white_type();
+        for (expr, typ) in [(&self.variable, &variable), (&self.expr, &value)] {
+            if *typ != Type::Integer && *typ != Type::Float && *typ != Type::Error {
+                let info = match self.operator.as_str() {
+                    "++" | "--" => format!(
+                        "`{}` needs a number, `{}` is `{}`",
+                        self.operator,
+                        expr.debug(),
+                        typ
+                    ),
+                    _ => format!(
+                        "`{}` needs a number on both sides, `{}` is `{}`",
+                        self.operator,
+                        expr.debug(),
+                        typ
+                    ),
+                };
+                add_parser_error_at(
+                    st,
+                    expr.as_ref(),
+                    ParserErrorType::BadType(typ.clone()),
+                    info,
+                );
+                return; // one error is enough
+            }
+        }
+        if variable == Type::Integer && value == Type::Float {
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
+                ParserErrorType::IncompatibleTypes(value, variable),
+                String::from("You cannot assign `float` to `int`"),
+            );
+        }
+    }
+
+    /// The field being assigned to, for `obj.field = value;`
+    fn field(&self) -> Option<&FieldAccessExpression> {
+        self.variable
+            .to_any()
+            .downcast_ref::<FieldAccessExpression>()
+    }
+
+    /// The element being assigned to, for `xs[i] = value;`
+    fn index(&self) -> Option<&IndexExpression> {
+        self.variable.to_any().downcast_ref::<IndexExpression>()
+    }
+}
+
+/// Whether `target` is a variable, a field or an element of a list that is one of those
+pub(crate) fn is_assignable(target: &dyn Expression) -> bool {
+    let any = target.to_any();
+    any.is::<IdentifierExpression>()
+        || any.is::<FieldAccessExpression>()
+        || any
+            .downcast_ref::<IndexExpression>()
+            .is_some_and(|index| is_assignable(index.get_list().as_ref()))
+}
+
+/// Reads the current value of whatever is being assigned to, see `assign`
+pub(crate) type Current<'a> = &'a dyn Fn(&mut Runtime) -> Result<Value, RuntimeError>;
+/// Emits the code that pushes the current value of whatever is being assigned to, see `emit_assign`
+pub(crate) type EmitCurrent<'a> = &'a dyn Fn(&mut Bytecode);
+
+/// Assign what `value` evaluates to to `target`, anything `is_assignable` says is assignable.
+/// The parts of the target, like the index of `xs[i]`, are evaluated once, before `value`, the
+/// same as `emit_assign`. `value` gets a `Current` for targets like `xs[i] += 1`
+pub(crate) fn assign(
+    target: &dyn Expression,
+    value: &mut dyn FnMut(&mut Runtime, Current) -> Result<Value, RuntimeError>,
+    runtime: &mut Runtime,
+) -> Result<(), RuntimeError> {
+    if let Some(field) = target.to_any().downcast_ref::<FieldAccessExpression>() {
+        return field.assign(value, runtime);
+    }
+    if let Some(index) = target.to_any().downcast_ref::<IndexExpression>() {
+        return index.assign(value, runtime);
+    }
+    let ident = target
+        .to_any()
+        .downcast_ref::<IdentifierExpression>()
+        .expect("Unable to fetch identifier from the runtime when expecting to be able to");
+    let eval = value(runtime, &|runtime| ident.evaluate(runtime))?;
+    runtime.set_value(ident.debug(), eval);
+    Ok(())
+}
+
+/// Emit the bytecode for `assign`, `value` emits the code that pushes what gets assigned. If it
+/// uses its `EmitCurrent` it has to emit that before anything else
+pub(crate) fn emit_assign(
+    target: &dyn Expression,
+    value: &mut dyn FnMut(&mut Bytecode, EmitCurrent),
+    bytecode: &mut Bytecode,
+) {
+    if let Some(field) = target.to_any().downcast_ref::<FieldAccessExpression>() {
+        return field.emit_assign(value, bytecode);
+    }
+    if let Some(index) = target.to_any().downcast_ref::<IndexExpression>() {
+        return index.emit_assign(value, bytecode);
+    }
+    value(bytecode, &|bytecode| {
+        target.emit(bytecode);
+    });
+    bytecode.store(target.debug().as_str());
 }
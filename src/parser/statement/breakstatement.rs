@@ -1,8 +1,11 @@
+use crate::bytecode::Bytecode;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
-use crate::runtime::Runtime;
+use crate::parser::ParserErrorType;
+use crate::runtime::{Runtime, RuntimeError};
 
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -18,19 +21,32 @@ impl ToAny for BreakStatement {
 }
 
 impl Statement for BreakStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        runtime.set_break(true)
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        runtime.set_break(true);
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.brk();
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.brk();
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         javascript.append(String::from("break;"));
     }
 
-    fn validate(&mut self, _st: &mut SymbolTable) {}
+    fn validate(&mut self, st: &mut SymbolTable) {
+        if !st.in_loop() {
+            add_parser_error(
+                st,
+                ParserErrorType::OutsideOfLoop(String::from("break")),
+                String::from("`break` can only be used inside of a `for` or `while` loop"),
+            );
+        }
+    }
 
     fn get_expr(&self) -> &Box<dyn Expression> {
         panic!("Break statement does not have an expression");
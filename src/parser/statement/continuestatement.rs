@@ -0,0 +1,64 @@
+use crate::bytecode::Bytecode;
+use crate::parser::parser_traits::*;
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::ParserErrorType;
+use crate::runtime::{Runtime, RuntimeError};
+
+use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
+use std::any::Any;
+
+#[derive(Clone, Debug)]
+pub(crate) struct ContinueStatement {}
+
+impl ToAny for ContinueStatement {
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Statement for ContinueStatement {
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        runtime.set_continue(true);
+        Ok(())
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.cont();
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        bytecode.cont();
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        javascript.append(String::from("continue;")).newline();
+    }
+
+    fn validate(&mut self, st: &mut SymbolTable) {
+        if !st.in_loop() {
+            add_parser_error(
+                st,
+                ParserErrorType::OutsideOfLoop(String::from("continue")),
+                String::from("`continue` can only be used inside of a `for` or `while` loop"),
+            );
+        }
+    }
+
+    fn get_expr(&self) -> &Box<dyn Expression> {
+        panic!("Continue statement does not have an expression");
+    }
+
+    fn get_statement_type(&self) -> String {
+        String::from("ContinueStatement")
+    }
+}
+
+impl ContinueStatement {
+    pub fn new() -> Self {
+        ContinueStatement {}
+    }
+}
@@ -1,13 +1,16 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::expression::identifierexpression::IdentifierExpression;
-use crate::parser::expression::listliteralexpression::ListLiteralExpression;
+use crate::parser::expression::rangeexpression::RangeExpression;
 use crate::parser::expression::syntaxerrorexpression::SyntaxErrorExpression;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
 use crate::parser::ParserErrorType::IncompatibleTypes;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 use uuid::Uuid;
 
@@ -28,47 +31,142 @@ impl ToAny for ForStatement {
 }
 
 impl Statement for ForStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        runtime.push_scope(String::from(Uuid::new_v4().to_string()));
-        let any = self.iterator.evaluate(runtime);
-        let list = any.downcast_ref::<Vec<Box<dyn Any>>>().unwrap();
-        for item in list.iter() {
-            let mut is_broken = false;
-            if let Some(integer) = any_into_int_literal(item) {
-                runtime.set_value(self.variable.debug(), Box::new(integer));
-            } else if let Some(float) = any_into_f64_literal(item) {
-                runtime.set_value(self.variable.debug(), Box::new(float));
-            } else if let Some(boolean) = any_into_bool_literal(item) {
-                runtime.set_value(self.variable.debug(), Box::new(boolean));
-            } else if let Some(string) = any_into_string_literal(item) {
-                runtime.set_value(self.variable.debug(), Box::new(string));
-            } else {
-                panic!("Some type in the list variable not covered");
-            }
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        runtime.push_scope(Uuid::new_v4().to_string());
+        let items: Box<dyn Iterator<Item = Value>> = match self.range() {
+            Some(range) => Box::new(range.iter(runtime)?.map(Value::Int)), // no list needed
+            None => match self.iterator.evaluate(runtime)? {
+                Value::List(list) => Box::new(list.into_iter()),
+                Value::Map(map) => Box::new(map.into_iter().map(|(key, _)| key)), // the keys
+                eval => {
+                    runtime.pop_scope();
+                    return Err(RuntimeError::TypeMismatch(format!(
+                        "cannot iterate over `{}`",
+                        eval.type_name()
+                    )));
+                }
+            },
+        };
+        for item in items {
+            runtime.declare_value(self.variable.debug(), item);
             for statement in self.statements.iter_mut() {
-                statement.execute(runtime);
-                if runtime.get_break() {
-                    runtime.set_break(false);
-                    is_broken = true;
+                statement.execute(runtime)?;
+                if runtime.should_unwind() {
                     break;
                 }
             }
-            if is_broken {
+            runtime.set_continue(false); // `continue` only skips the rest of this iteration
+            if runtime.get_break() {
+                runtime.set_break(false);
+                break;
+            }
+            if runtime.has_return() {
                 break;
             }
         }
         runtime.pop_scope();
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        if self.iterator.get_white_type().is_map_type() {
+            nasm.unsupported(format!("looping over the map `{}`", self.iterator.debug()));
+            return;
+        }
+        let (next, done) = (nasm.new_label("for_next"), nasm.new_label("for_done"));
+        // the list and where we are in it get stashed in hidden variables
+        let list = format!("{}_list", next);
+        let index = format!("{}_index", next);
+        self.iterator.compile(nasm);
+        nasm.declare(&list)
+            .store(&list)
+            .instr(String::from("xor eax, eax"))
+            .declare(&index)
+            .store(&index);
+        let top = nasm.new_label("for");
+        let variable = self.variable.debug();
+        nasm.label(&top)
+            .load(&index)
+            .instr(String::from("mov rcx, rax"))
+            .load(&list)
+            .instr(String::from("cmp rcx, [rax]"))
+            .instr(format!("jge {}", done))
+            .instr(String::from("mov rax, [rax + 8 + rcx * 8]"))
+            .declare(&variable)
+            .store(&variable)
+            .push_loop(next.clone(), done.clone());
+        for statement in self.statements.iter() {
+            statement.compile(nasm);
+        }
+        nasm.pop_loop()
+            .label(&next)
+            .load(&index)
+            .instr(String::from("inc rax"))
+            .store(&index)
+            .instr(format!("jmp {}", top))
+            .label(&done);
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        if let Some(range) = self.range() {
+            return self.emit_range(range, bytecode);
+        }
+        // the list and where we are in it get stashed in hidden variables
+        let list = bytecode.hidden_variable("for.list");
+        let index = bytecode.hidden_variable("for.index");
+        self.iterator.emit(bytecode);
+        if self.iterator.get_white_type().is_map_type() {
+            // loop over the keys
+            let keys = bytecode.name("keys");
+            bytecode.emit(Op::Intrinsic(keys, 0));
+        }
+        bytecode.declare(&list);
+        bytecode.store(&list);
+        bytecode.constant(Value::Int(0));
+        bytecode.declare(&index);
+        bytecode.store(&index);
+        let top = bytecode.here();
+        bytecode.load(&index);
+        bytecode.load(&list);
+        bytecode.emit(Op::Len);
+        bytecode.emit(Op::Less);
+        let done = bytecode.emit(Op::JumpIfFalse(0));
+        let variable = self.variable.debug();
+        bytecode.load(&list);
+        bytecode.load(&index);
+        bytecode.emit(Op::Index);
+        bytecode.declare(&variable);
+        bytecode.store(&variable);
+        bytecode.push_loop();
+        for statement in self.statements.iter() {
+            statement.emit(bytecode);
+        }
+        bytecode.patch_continues(); // `continue` moves on to the next element
+        bytecode.load(&index);
+        bytecode.constant(Value::Int(1));
+        bytecode.emit(Op::Add);
+        bytecode.store(&index);
+        bytecode.emit(Op::Jump(top));
+        bytecode.patch(done);
+        bytecode.pop_loop();
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         let variable = self.variable.debug(); // the name of the variable
-        javascript.append(format!("for (const {} of ", variable));
-        self.iterator.transpile(javascript); // may or may not be a list/variable
-        javascript.append(String::from(") {\n"));
+        if let Some(range) = self.range() {
+            range.transpile_for(&variable, javascript);
+        } else {
+            javascript.append(format!("for (const {} of ", variable));
+            // the loop walks its own copy, like the runtime, so the body can change the original
+            if self.iterator.get_white_type().is_map_type() {
+                javascript.append_no_tabs(String::from("[..."));
+                self.iterator.transpile(javascript);
+                javascript.append_no_tabs(String::from(".keys()]"));
+            } else {
+                javascript.transpile_value(self.iterator.as_ref());
+            }
+            javascript.append(String::from(") {\n"));
+        }
         javascript.indent();
         for statement in self.statements.iter() {
             statement.transpile(javascript);
@@ -77,7 +175,37 @@ impl Statement for ForStatement {
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
+        st.widen_for_loop(|st| self.clone().validate_loop(st));
+        self.validate_loop(st);
+    }
+
+    fn get_expr(&self) -> &Box<dyn Expression> {
+        &self.variable
+    }
+
+    fn get_statement_type(&self) -> String {
+        String::from("ForStatement")
+    }
+}
+
+impl ForStatement {
+    /// The actual validation, run on a copy first to find what the loop widens, see
+    /// `SymbolTable::widen_for_loop`
+    fn validate_loop(&mut self, st: &mut SymbolTable) {
         st.push_scope();
+        if self
+            .variable
+            .to_any()
+            .downcast_ref::<IdentifierExpression>()
+            .is_none()
+        {
+            add_parser_error_at(
+                st,
+                self.variable.as_ref(),
+                ParserErrorType::UnexpectedExpression(self.variable.clone()),
+                "You must use an identifier as your iteration variable.".to_string(),
+            );
+        }
         if let Some(id_expr) = self
             .variable
             .to_any()
@@ -86,9 +214,10 @@ impl Statement for ForStatement {
             let name = id_expr.debug();
             if st.has_symbol(name.clone()) {
                 add_parser_error(
+                    st,
                     ParserErrorType::DuplicateName(
                         name.clone(),
-                        st.get_symbol_type(name.clone()).unwrap(),
+                        st.get_symbol_type(name.clone()).unwrap_or(Type::Error),
                     ),
                     format!(
                         "Duplicate name: [{}] has already been defined.",
@@ -99,31 +228,29 @@ impl Statement for ForStatement {
         }
 
         self.iterator.validate(st);
-        let typ = self.iterator.get_white_type().get_type_from_list();
+        let iterator = self.iterator.get_white_type();
+        let typ = match iterator.get_types_from_map() {
+            Some((key, _)) => key, // looping over a map goes through its keys
+            None => iterator.get_type_from_list(),
+        };
         if typ != Type::Error && typ != Type::Initialized {
             st.register_symbol(self.variable.debug(), typ);
         } else {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self.iterator.as_ref(),
                 IncompatibleTypes(typ, self.variable.clone().get_white_type()),
-                format!("Unexpected typing error."),
+                "Unexpected token, make sure your iterator is a list type.".to_string(),
             );
         }
+        st.enter_loop();
         for stmt in &mut self.statements {
             stmt.validate(st);
         }
+        st.exit_loop();
         st.pop_scope();
     }
 
-    fn get_expr(&self) -> &Box<dyn Expression> {
-        &self.variable
-    }
-
-    fn get_statement_type(&self) -> String {
-        String::from("ForStatement")
-    }
-}
-
-impl ForStatement {
     pub fn new() -> Self {
         ForStatement {
             statements: vec![],
@@ -136,37 +263,48 @@ impl ForStatement {
         self.statements.push(stmt);
     }
     pub fn set_iter_var(&mut self, iter_var: Box<dyn Expression>) {
-        if iter_var
-            .to_any()
-            .downcast_ref::<IdentifierExpression>()
-            .is_none()
-        {
-            add_parser_error(
-                ParserErrorType::UnexpectedExpression(iter_var),
-                format!("You must use an identifier as your iteration variable."),
-            );
-        } else {
-            self.variable = iter_var.clone();
-        }
+        self.variable = iter_var;
     }
     pub fn set_iter(&mut self, iter: Box<dyn Expression>) {
-        if iter
-            .to_any()
-            .downcast_ref::<ListLiteralExpression>()
-            .is_some()
-        {
-            self.iterator = iter.clone();
-        } else if iter
-            .to_any()
-            .downcast_ref::<IdentifierExpression>()
-            .is_some()
-        {
-            self.iterator = iter.clone();
-        } else {
-            add_parser_error(
-                ParserErrorType::UnexpectedExpression(iter),
-                format!("Unexpected token, make sure your iterator is a list type."),
-            );
+        self.iterator = iter;
+    }
+
+    fn range(&self) -> Option<&RangeExpression> {
+        self.iterator.to_any().downcast_ref::<RangeExpression>()
+    }
+
+    // counts through the range in hidden variables instead of building a list
+    fn emit_range(&self, range: &RangeExpression, bytecode: &mut Bytecode) {
+        let current = bytecode.hidden_variable("for.current");
+        let end = bytecode.hidden_variable("for.end");
+        let step = bytecode.hidden_variable("for.step");
+        range.emit_bounds(bytecode);
+        for hidden in [&step, &end, &current] {
+            bytecode.declare(hidden);
+            bytecode.store(hidden);
+        }
+        let top = bytecode.here();
+        for hidden in [&current, &end, &step] {
+            bytecode.load(hidden);
+        }
+        bytecode.emit(Op::InRange(range.is_inclusive()));
+        let done = bytecode.emit(Op::JumpIfFalse(0));
+        let variable = self.variable.debug();
+        bytecode.load(&current);
+        bytecode.declare(&variable);
+        bytecode.store(&variable);
+        bytecode.push_loop();
+        for statement in self.statements.iter() {
+            statement.emit(bytecode);
         }
+        bytecode.patch_continues(); // `continue` moves on to the next int
+        bytecode.load(&current);
+        bytecode.load(&step);
+        let overflow = bytecode.emit(Op::Step(0));
+        bytecode.store(&current);
+        bytecode.emit(Op::Jump(top));
+        bytecode.patch(done);
+        bytecode.patch(overflow);
+        bytecode.pop_loop();
     }
 }
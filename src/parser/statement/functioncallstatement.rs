@@ -1,8 +1,9 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::expression::functioncallexpression::FunctionCallExpression;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
 use std::any::Any;
 
 #[allow(dead_code)]
@@ -10,7 +11,6 @@ use std::any::Any;
 pub struct FunctionCallStatement {
     name: String,
     expr: Box<dyn Expression>,
-    args: Vec<Box<dyn Expression>>,
 }
 
 impl ToAny for FunctionCallStatement {
@@ -23,17 +23,24 @@ impl ToAny for FunctionCallStatement {
 }
 
 impl Statement for FunctionCallStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        self.expr.evaluate(runtime);
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        self.expr.evaluate(runtime)?;
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        self.expr.compile(nasm);
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.expr.emit(bytecode);
+        bytecode.emit(Op::Pop);
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
+        javascript.append(String::new());
         self.expr.transpile(javascript);
-        javascript.semicolon();
+        javascript.semicolon().newline();
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
@@ -50,15 +57,8 @@ impl Statement for FunctionCallStatement {
 }
 
 impl FunctionCallStatement {
+    /// `expr` is a function or method call
     pub(crate) fn new(expr: Box<dyn Expression>, name: String) -> Self {
-        let fce = expr
-            .to_any()
-            .downcast_ref::<FunctionCallExpression>()
-            .unwrap();
-        FunctionCallStatement {
-            name,
-            expr: expr.clone(),
-            args: fce.get_args().clone(),
-        }
+        FunctionCallStatement { name, expr }
     }
 }
@@ -1,11 +1,17 @@
+use crate::bytecode::Bytecode;
+use crate::parser::expression::booleanliteralexpression::BooleanLiteralExpression;
 use crate::parser::parser_traits::*;
+use crate::parser::statement::breakstatement::BreakStatement;
+use crate::parser::statement::ifstatement::IfStatement;
 use crate::parser::statement::returnstatement::ReturnStatement;
+use crate::parser::statement::whilestatement::WhileStatement;
 use crate::parser::symbol_table::SymbolTable;
-use crate::parser::ParserErrorType::MismatchedTypes;
 use crate::parser::*;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use uuid::Uuid;
 
 #[derive(Clone, Debug)]
@@ -16,6 +22,7 @@ pub struct FunctionDefinitionStatement {
     pub(crate) arg_names: Vec<String>,
     pub(crate) arg_types: Vec<Type>,
     pub(crate) statements: Vec<Box<dyn Statement>>,
+    pub(crate) inferred: bool, // the return type was left off, it comes from our `return`s
 }
 
 impl ToAny for FunctionDefinitionStatement {
@@ -36,63 +43,78 @@ impl Default for FunctionDefinitionStatement {
             arg_names: vec![],
             arg_types: vec![],
             statements: vec![],
+            inferred: false,
         }
     }
 }
 
 impl Statement for FunctionDefinitionStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
         runtime.add_function(self.name.clone(), self.clone());
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        if self.name.contains('.') {
+            nasm.unsupported(format!("the method `{}`", self.name));
+            return;
+        }
+        nasm.begin_function(self.name.as_str(), &self.arg_names);
+        for statement in self.statements.iter() {
+            statement.compile(nasm);
+        }
+        nasm.end_function();
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.emit_with_args(&self.arg_names, bytecode);
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
-        javascript.append(format!("function {}(", self.name));
-        for (i, arg) in self.args.iter().enumerate() {
-            arg.transpile(javascript);
-            if i != self.args.len() - 1 {
-                javascript.append(String::from(", "));
-            }
-        }
-        javascript.append(String::from(") {")).newline().indent();
-        for stmt in self.statements.iter() {
-            stmt.transpile(javascript);
-        }
-        javascript
-            .newline()
-            .outdent()
-            .append(String::from("}"))
-            .newline();
+        self.transpile_as(format!("function {}", self.name), javascript);
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
-        let mut i = 0;
-        for arg in &mut self.args {
+        let loops = st.reset_loops();
+        let narrowed = st.reset_narrowed();
+        let returns = st.reset_returns();
+        if self.inferred {
+            // the type checker may have worked it out already, recursive calls need it
+            if let Some(fds) = st.get_function(self.name.clone()) {
+                self.return_type = fds.get_return_type();
+            }
+        }
+        st.register_function(self.name.clone(), self.clone()); // so we can call ourselves
+        st.push_scope();
+        for (i, arg) in self.args.iter_mut().enumerate() {
             st.register_symbol(arg.debug(), self.arg_types[i].clone());
-            i += 1;
             arg.validate(st);
         }
         for statement in &mut self.statements {
-            statement.validate(st);
-            let opt_rs = statement.to_any().downcast_ref::<ReturnStatement>();
-            if opt_rs.is_some() {
-                let rs = opt_rs.unwrap();
-                if rs.get_expr().get_white_type() != self.return_type {
-                    add_parser_error(
-                        MismatchedTypes(rs.get_expr().get_white_type(), self.return_type.clone()),
-                        format!(
-                            "You cannot return {:?} from [{}], it is defined to return: {:?}",
-                            rs.get_expr().get_white_type(),
-                            self.name,
-                            self.return_type
-                        ),
-                    );
-                }
-            }
+            statement.validate(st); // return statements check themselves against our return type
+        }
+        st.pop_scope();
+        let returned = st.restore_returns(returns);
+        if self.inferred {
+            self.infer_return_type(returned, st);
+            st.register_function(self.name.clone(), self.clone());
         }
+        let returns_nothing = matches!(
+            self.return_type,
+            Type::Void | Type::Error | Type::Initialized
+        );
+        if !returns_nothing && !always_returns(&self.statements) {
+            add_parser_error(
+                st,
+                ParserErrorType::MissingReturn(self.name.clone()),
+                format!(
+                    "`{}` returns `{}`, it needs a `return` at the end of every path",
+                    self.name, self.return_type
+                ),
+            );
+        }
+        st.restore_narrowed(narrowed);
+        st.restore_loops(loops);
     }
 
     fn get_expr(&self) -> &Box<dyn Expression> {
@@ -112,6 +134,7 @@ impl FunctionDefinitionStatement {
             arg_types: vec![],
             arg_names: vec![],
             statements: vec![],
+            inferred: false,
         }
     }
 
@@ -121,6 +144,55 @@ impl FunctionDefinitionStatement {
     pub fn set_return_type(&mut self, return_type: Type) {
         self.return_type = return_type;
     }
+    /// Leave the return type to be inferred from the function's `return`s
+    pub fn infer_return(&mut self) {
+        self.return_type = Type::Initialized;
+        self.inferred = true;
+    }
+    pub fn is_inferred(&self) -> bool {
+        self.inferred
+    }
+
+    /// A function without a return type returns whatever its `return`s do, or nothing if it
+    /// has none. Returns of calls we can't type yet, i.e. recursive ones, don't count
+    fn infer_return_type(&mut self, returned: Vec<Type>, st: &mut SymbolTable) {
+        if returned.is_empty() {
+            self.return_type = Type::Void;
+            return;
+        }
+        if returned.contains(&Type::Error) {
+            self.return_type = Type::Error; // whatever went wrong has already been reported
+            return;
+        }
+        let known = returned
+            .into_iter()
+            .filter(|typ| *typ != Type::Initialized)
+            .collect::<Vec<Type>>();
+        let Some(typ) = Type::common(known.clone().into_iter()) else {
+            add_parser_error(
+                st,
+                ParserErrorType::UnresolvedType(self.name.clone()),
+                format!(
+                    "Nothing `{}` returns has a type yet, give it one: fn {}(...): type {{ ... }}",
+                    self.name, self.name
+                ),
+            );
+            return;
+        };
+        for other in known {
+            if !typ.is_assignable_to(other.clone()) {
+                add_parser_error(
+                    st,
+                    ParserErrorType::MismatchedTypes(typ.clone(), other.clone()),
+                    format!(
+                        "`{}` returns both `{}` and `{}`, they have to be the same",
+                        self.name, typ, other
+                    ),
+                );
+            }
+        }
+        self.return_type = typ;
+    }
 
     pub fn add_statement(&mut self, statement: Box<dyn Statement>) {
         self.statements.push(statement);
@@ -139,22 +211,120 @@ impl FunctionDefinitionStatement {
         &self.arg_names
     }
 
+    /// Emit the function as a method, the receiver is passed in as the first local, `self`
+    pub fn emit_method(&self, bytecode: &mut Bytecode) {
+        let mut args = vec![String::from("self")];
+        args.extend(self.arg_names.iter().cloned());
+        self.emit_with_args(&args, bytecode);
+    }
+
+    /// Transpile the function as a method of a javascript class, `Struct.method` becomes `method`
+    pub fn transpile_method(&self, javascript: &mut JavaScript) {
+        let name = self.name.rsplit('.').next().unwrap().to_string();
+        self.transpile_as(name, javascript);
+    }
+
+    fn transpile_as(&self, header: String, javascript: &mut JavaScript) {
+        javascript.append(format!("{}(", header));
+        for (i, arg) in self.args.iter().enumerate() {
+            arg.transpile(javascript);
+            if i != self.args.len() - 1 {
+                javascript.append_no_tabs(String::from(", "));
+            }
+        }
+        javascript
+            .append_no_tabs(String::from(") {"))
+            .newline()
+            .indent();
+        for stmt in self.statements.iter() {
+            stmt.transpile(javascript);
+        }
+        javascript
+            .newline()
+            .outdent()
+            .append(String::from("}"))
+            .newline();
+    }
+
+    fn emit_with_args(&self, args: &[String], bytecode: &mut Bytecode) {
+        bytecode.begin_function(self.name.as_str(), args);
+        for statement in self.statements.iter() {
+            statement.emit(bytecode);
+        }
+        bytecode.end_function();
+    }
+
     pub fn invoke(
         &mut self,
         runtime: &mut Runtime,
-        args: Vec<Box<dyn Expression>>,
-    ) -> Box<dyn Any> {
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        self.call(runtime, None, args)
+    }
+
+    /// Invoke the function as a method, with `self` bound to `receiver`
+    pub fn invoke_method(
+        &mut self,
+        runtime: &mut Runtime,
+        receiver: Value,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        self.call(runtime, Some(receiver), args)
+    }
+
+    fn call(
+        &mut self,
+        runtime: &mut Runtime,
+        receiver: Option<Value>,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
         let id = Uuid::new_v4();
-        runtime.push_scope(id.to_string());
-        for (i, arg) in args.iter().enumerate() {
-            runtime.set_value_in_scope(id.to_string(), self.arg_names[i].clone(), arg.clone());
+        runtime.push_frame(id.to_string());
+        if let Some(receiver) = receiver {
+            runtime.set_value_in_scope(id.to_string(), String::from("self"), receiver);
+        }
+        for (name, arg) in self.arg_names.iter().zip(args) {
+            runtime.set_value_in_scope(id.to_string(), name.clone(), arg);
         }
         for statement in &mut self.statements {
-            statement.execute(runtime);
+            statement.execute(runtime)?;
             if runtime.has_return() {
-                return runtime.get_return();
+                break;
             }
         }
-        Box::new(())
+        runtime.pop_frame();
+        Ok(runtime.get_return())
     }
 }
+
+/// Whether running `statements` always ends in a `return`, rather than falling off the end
+fn always_returns(statements: &[Box<dyn Statement>]) -> bool {
+    statements.iter().any(|statement| {
+        let statement = statement.to_any();
+        if let Some(is) = statement.downcast_ref::<IfStatement>() {
+            // an `else if` chain is just an `if` in the false branch
+            return always_returns(is.get_true_stmts()) && always_returns(is.get_false_stmts());
+        }
+        if let Some(ws) = statement.downcast_ref::<WhileStatement>() {
+            // `while (true)` only ever stops by returning, unless it breaks
+            let forever = ws
+                .get_expr()
+                .to_any()
+                .downcast_ref::<BooleanLiteralExpression>()
+                .is_some_and(|condition| condition.get_value());
+            return forever && !breaks(ws.get_body());
+        }
+        statement.is::<ReturnStatement>()
+    })
+}
+
+/// Whether `statements` can break out of the loop they're in, breaks in nested loops don't count
+fn breaks(statements: &[Box<dyn Statement>]) -> bool {
+    statements.iter().any(|statement| {
+        let statement = statement.to_any();
+        match statement.downcast_ref::<IfStatement>() {
+            Some(is) => breaks(is.get_true_stmts()) || breaks(is.get_false_stmts()),
+            None => statement.is::<BreakStatement>(),
+        }
+    })
+}
@@ -1,10 +1,15 @@
-use crate::config::*;
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::expression::syntaxerrorexpression::SyntaxErrorExpression;
+use crate::parser::statement::breakstatement::BreakStatement;
+use crate::parser::statement::continuestatement::ContinueStatement;
+use crate::parser::statement::returnstatement::ReturnStatement;
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::{parser_traits::*, ParserErrorType};
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -12,6 +17,7 @@ pub struct IfStatement {
     true_stmts: Vec<Box<dyn Statement>>,
     false_stmts: Vec<Box<dyn Statement>>,
     expr: Box<dyn Expression>,
+    else_if: bool, // the false branch is just the next `else if` in the chain
 }
 
 impl ToAny for IfStatement {
@@ -23,63 +29,84 @@ impl ToAny for IfStatement {
     }
 }
 impl Statement for IfStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        let eval = self.expr.evaluate(runtime);
-        let downcast = *eval.downcast_ref::<WhiteLangBool>().unwrap();
-        runtime.push_scope(String::from(uuid::Uuid::new_v4().to_string()));
-        if downcast {
-            for statement in &mut self.true_stmts {
-                statement.execute(runtime);
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        let downcast = match self.expr.evaluate(runtime)? {
+            Value::Bool(downcast) => downcast,
+            eval => {
+                return Err(RuntimeError::TypeMismatch(format!(
+                    "cannot branch on `{}`",
+                    eval.type_name()
+                )))
             }
+        };
+        runtime.push_scope(uuid::Uuid::new_v4().to_string());
+        let statements = if downcast {
+            &mut self.true_stmts
         } else {
-            for statement in &mut self.false_stmts {
-                statement.execute(runtime);
+            &mut self.false_stmts
+        };
+        for statement in statements {
+            statement.execute(runtime)?;
+            if runtime.should_unwind() {
+                break; // let the enclosing loop or function deal with it
             }
         }
-        //runtime.pop_scope(); // TODO: nested if in else causes double pop before return can evaluate the expression
+        runtime.pop_scope();
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        let (otherwise, done) = (nasm.new_label("else"), nasm.new_label("end_if"));
+        self.expr.compile(nasm);
+        nasm.instr(String::from("test rax, rax"))
+            .instr(format!("jz {}", otherwise));
+        for statement in self.true_stmts.iter() {
+            statement.compile(nasm);
+        }
+        nasm.instr(format!("jmp {}", done)).label(&otherwise);
+        for statement in self.false_stmts.iter() {
+            statement.compile(nasm);
+        }
+        nasm.label(&done);
     }
 
-    fn transpile(&self, javascript: &mut JavaScript) {
-        javascript.append(String::from("if ("));
-        self.expr.transpile(javascript);
-        javascript
-            .append_no_tabs(String::from(") {"))
-            .newline()
-            .indent();
-        for stmt in self.true_stmts.iter() {
-            stmt.transpile(javascript);
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.expr.emit(bytecode);
+        let otherwise = bytecode.emit(Op::JumpIfFalse(0));
+        for statement in self.true_stmts.iter() {
+            statement.emit(bytecode);
         }
-        javascript.newline().outdent().append(String::from("}"));
-        if self.false_stmts.len() > 0 {
-            javascript
-                .newline()
-                .append(String::from("else {"))
-                .newline()
-                .indent();
-            for stmt in self.false_stmts.iter() {
-                stmt.transpile(javascript);
-            }
-            javascript.outdent().newline().append(String::from("}"));
+        let done = bytecode.emit(Op::Jump(0));
+        bytecode.patch(otherwise);
+        for statement in self.false_stmts.iter() {
+            statement.emit(bytecode);
         }
+        bytecode.patch(done);
+    }
+
+    fn transpile(&self, javascript: &mut JavaScript) {
+        javascript.append(String::new());
+        self.transpile_chain(javascript);
         javascript.newline();
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
         self.expr.validate(st);
         if self.expr.get_white_type() != Type::Boolean {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
                 ParserErrorType::BadType(self.expr.get_white_type()),
                 format!(
-                    "You cannot branch based on type: {:?}",
+                    "You cannot branch based on type: `{}`",
                     self.expr.get_white_type()
                 ),
             );
         }
         st.push_scope();
+        for name in self.expr.non_null_when(true) {
+            st.narrow(name);
+        }
         if !self.true_stmts.is_empty() {
             for i in 0..self.true_stmts.len() {
                 self.true_stmts[i].validate(st);
@@ -87,12 +114,21 @@ impl Statement for IfStatement {
         }
         st.pop_scope();
         st.push_scope();
+        for name in self.expr.non_null_when(false) {
+            st.narrow(name);
+        }
         if !self.false_stmts.is_empty() {
             for i in 0..self.false_stmts.len() {
                 self.false_stmts[i].validate(st);
             }
         }
         st.pop_scope();
+        // `if (x == null) { return; }`, we only get past it when x isn't null
+        if self.false_stmts.is_empty() && exits(&self.true_stmts) {
+            for name in self.expr.non_null_when(false) {
+                st.narrow(name);
+            }
+        }
     }
 
     fn get_expr(&self) -> &Box<dyn Expression> {
@@ -103,6 +139,15 @@ impl Statement for IfStatement {
         String::from("IfStatement")
     }
 }
+/// Whether a block always leaves before it gets to its end
+fn exits(stmts: &[Box<dyn Statement>]) -> bool {
+    stmts.last().is_some_and(|last| {
+        last.to_any().is::<ReturnStatement>()
+            || last.to_any().is::<BreakStatement>()
+            || last.to_any().is::<ContinueStatement>()
+    })
+}
+
 #[allow(dead_code)]
 impl IfStatement {
     pub fn new() -> Self {
@@ -110,6 +155,7 @@ impl IfStatement {
             true_stmts: vec![],
             false_stmts: vec![],
             expr: Box::new(SyntaxErrorExpression::new()),
+            else_if: false,
         }
     }
     pub(crate) fn set_expr(&mut self, expr: Box<dyn Expression>) {
@@ -121,6 +167,43 @@ impl IfStatement {
     pub(crate) fn add_false_statement(&mut self, stmt: Box<dyn Statement>) {
         self.false_stmts.push(stmt);
     }
+    /// `else if (...) { ... }`, the chained if is the only statement in the false branch
+    /// Transpile the if and any `else if`s chained on to it, without indenting the first line
+    fn transpile_chain(&self, javascript: &mut JavaScript) {
+        javascript.append_no_tabs(String::from("if ("));
+        self.expr.transpile(javascript);
+        javascript
+            .append_no_tabs(String::from(") {"))
+            .newline()
+            .indent();
+        for stmt in self.true_stmts.iter() {
+            stmt.transpile(javascript);
+        }
+        javascript.newline().outdent().append(String::from("}"));
+        if self.else_if {
+            let else_if = self.false_stmts[0]
+                .to_any()
+                .downcast_ref::<IfStatement>()
+                .unwrap();
+            javascript.newline().append(String::from("else "));
+            else_if.transpile_chain(javascript);
+        } else if !self.false_stmts.is_empty() {
+            javascript
+                .newline()
+                .append(String::from("else {"))
+                .newline()
+                .indent();
+            for stmt in self.false_stmts.iter() {
+                stmt.transpile(javascript);
+            }
+            javascript.outdent().newline().append(String::from("}"));
+        }
+    }
+
+    pub(crate) fn set_else_if(&mut self, else_if: IfStatement) {
+        self.false_stmts = vec![Box::new(else_if)];
+        self.else_if = true;
+    }
     pub(crate) fn get_true_stmts(&self) -> &Vec<Box<dyn Statement>> {
         &self.true_stmts
     }
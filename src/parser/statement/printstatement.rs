@@ -1,8 +1,10 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
 use crate::program::Program;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -20,19 +22,26 @@ impl ToAny for PrintStatement {
 }
 
 impl Statement for PrintStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        let eval = self.expr.evaluate(runtime);
-        runtime.push_output(String::from(Program::try_print_output(&eval)));
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        let eval = self.expr.evaluate(runtime)?;
+        runtime.push_output(Program::try_print_output(&eval));
         runtime.push_output(String::from("\n"));
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        self.expr.compile(nasm);
+        nasm.print(self.expr.get_white_type());
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.expr.emit(bytecode);
+        bytecode.emit(Op::Print);
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         javascript.append(String::from("console.log("));
-        self.expr.transpile(javascript);
+        javascript.transpile_shown(self.expr.as_ref());
         javascript.append_no_tabs(String::from(");")).newline();
     }
 
@@ -1,8 +1,10 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::*;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
 
 #[derive(Clone, Debug)]
 pub(crate) struct ReturnStatement {
@@ -21,31 +23,58 @@ impl ToAny for ReturnStatement {
 }
 
 impl Statement for ReturnStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        runtime.set_return(self.expr.clone());
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        let value = self.expr.evaluate(runtime)?;
+        runtime.set_return(value);
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        self.expr.compile(nasm);
+        nasm.ret();
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.expr.emit(bytecode);
+        bytecode.emit(Op::Return);
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         javascript.append(String::from("return "));
-        self.expr.transpile(javascript);
+        javascript.transpile_value(self.expr.as_ref());
         javascript.semicolon();
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
-        println!("{}", self.function);
-        let fds = st.get_function(self.function.clone()).unwrap();
         self.expr.validate(st);
+        let fds = match st.get_function(self.function.clone()) {
+            Some(fds) => fds,
+            None => {
+                add_parser_error(
+                    st,
+                    ParserErrorType::BadReturnType,
+                    "`return` may only be used inside of a function".to_string(),
+                );
+                return;
+            }
+        };
         self.return_type = self.expr.get_white_type();
+        st.add_return(self.return_type.clone());
+        if fds.is_inferred() {
+            return; // the function works its return type out from its returns once they're all in
+        }
         //LOGGER.debug(format!("Got {:?}", fds)); // very noisy log
-        if self.return_type != fds.get_return_type() {
-            add_parser_error(
-                ParserErrorType::MismatchedTypes(self.return_type.clone(), fds.get_return_type()),
+        if self.return_type != fds.get_return_type()
+            && !fds
+                .get_return_type()
+                .is_assignable_to(self.return_type.clone())
+        {
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
+                ParserErrorType::MismatchedTypes(fds.get_return_type(), self.return_type.clone()),
                 format!(
-                    "You cannot return {:?} from [{}], it is defined to return: {:?}",
+                    "You cannot return `{}` from [{}], it is defined to return: `{}`",
                     self.return_type,
                     fds.name,
                     fds.get_return_type()
@@ -1,10 +1,12 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{add_parser_error, Expression, Statement, ToAny};
 use crate::parser::statement::functiondefinitionstatement::FunctionDefinitionStatement;
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -27,36 +29,75 @@ impl ToAny for StructDefinitionStatement {
 }
 #[allow(dead_code, unused_variables)]
 impl Statement for StructDefinitionStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
         runtime.add_struct(self.name.clone(), self.clone());
+        for (name, method) in self.methods.iter() {
+            runtime.add_function(name.clone(), method.clone()); // methods are looked up as `Struct.method`
+        }
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        nasm.unsupported(format!("struct `{}`", self.name));
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        // the layout has to be known before the methods, they can construct their own struct
+        self.declare(bytecode);
+        for method in self.methods.values() {
+            method.emit_method(bytecode);
+        }
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
-        todo!()
+        // struct expressions pass their fields by name, i.e. `new Vec2D({x: 1, y: 2})`
+        let mut fields = self.fields.keys().cloned().collect::<Vec<String>>();
+        fields.sort();
+        javascript
+            .append(format!("class {} {{", JavaScript::class_name(&self.name)))
+            .newline()
+            .indent();
+        // node prints an instance under this name
+        javascript
+            .append(format!("static name = \"{}\";", self.name))
+            .newline();
+        javascript
+            .append(format!("constructor({{{}}}) {{", fields.join(", ")))
+            .newline()
+            .indent();
+        for field in fields.iter() {
+            javascript
+                .append(format!("this.{} = {};", field, field))
+                .newline();
+        }
+        javascript.outdent().append(String::from("}")).newline();
+        let mut methods = self
+            .methods
+            .values()
+            .collect::<Vec<&FunctionDefinitionStatement>>();
+        methods.sort_by(|a, b| a.name.cmp(&b.name));
+        for method in methods {
+            method.transpile_method(javascript);
+        }
+        javascript.outdent().append(String::from("}")).newline();
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
-        if st.has_symbol(self.name.clone()) {
+        // the type checker has already declared us, and caught any other struct with our name
+        if let Some(typ) = st.get_symbol_type(self.name.clone()) {
             add_parser_error(
-                ParserErrorType::DuplicateName(
-                    self.name.clone(),
-                    st.get_symbol_type(self.name.clone()).unwrap(),
-                ),
+                st,
+                ParserErrorType::DuplicateName(self.name.clone(), typ),
                 format!("Duplicate name `{}`", self.name),
             );
         }
-        for (name, typ) in self.fields.iter() {
-            st.register_symbol(format!("{}.{}", self.name, name), typ.clone()); // do this because we don't want to clash y and x.y
-        }
-        for (name, method) in self.methods.iter() {
-            let mut func = method.clone();
-            func.name = format!("{}.{}", self.name, name); // do this because we don't want to clash foo() and x.foo()
-            func.validate(st);
-            st.register_function(func.name.clone(), func);
+        st.register_struct(self.name.clone(), self.clone()); // methods can use `self` to get at fields
+        for method in self.methods.values_mut() {
+            st.push_scope();
+            st.register_symbol(String::from("self"), self.typ.clone());
+            method.validate(st);
+            st.pop_scope();
+            st.register_function(method.name.clone(), method.clone());
         }
         st.register_struct(self.name.clone(), self.clone());
     }
@@ -71,6 +112,14 @@ impl Statement for StructDefinitionStatement {
 }
 #[allow(dead_code)]
 impl StructDefinitionStatement {
+    /// Give the bytecode the field layout of the struct, struct expressions need it to emit
+    /// `Op::Struct`, so this happens before any function body is emitted
+    pub fn declare(&self, bytecode: &mut Bytecode) {
+        let mut fields = self.fields.keys().cloned().collect::<Vec<String>>();
+        fields.sort();
+        bytecode.add_struct(self.name.as_str(), fields);
+    }
+
     pub fn new(name: String) -> StructDefinitionStatement {
         Self {
             name: name.clone(),
@@ -1,7 +1,9 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -17,14 +19,18 @@ impl ToAny for SyntaxErrorStatement {
 }
 
 impl Statement for SyntaxErrorStatement {
-    fn execute(&mut self, _: &mut Runtime) {
-        panic!("Evaluated a syntax error")
+    fn execute(&mut self, _: &mut Runtime) -> Result<(), RuntimeError> {
+        Err(RuntimeError::SyntaxError)
     }
 
-    fn compile(&self) {
+    fn compile(&self, _nasm: &mut Nasm) {
         panic!("Compiled a syntax error")
     }
 
+    fn emit(&self, _bytecode: &mut Bytecode) {
+        panic!("Emitted a syntax error")
+    }
+
     fn transpile(&self, _: &mut JavaScript) {
         panic!("Transpiled a syntax error")
     }
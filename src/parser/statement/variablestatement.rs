@@ -1,11 +1,13 @@
+use crate::bytecode::Bytecode;
 use crate::javascript::JavaScript;
-use crate::parser::expression::functioncallexpression::FunctionCallExpression;
+use crate::nasm::Nasm;
+use crate::parser::expression::listliteralexpression::type_empty_literal;
 use crate::parser::parser_traits::*;
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::whitetypes::Type::Initialized;
 use crate::parser::ParserErrorType::*;
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, RuntimeError};
 use crate::LOGGER;
 use std::any::Any;
 
@@ -26,61 +28,83 @@ impl ToAny for VariableStatement {
 }
 
 impl Statement for VariableStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
-        if self.expr.debug() == String::from("StructExpression") {
-            self.expr.evaluate(runtime); // need to do this to get the functions and fields in the runtime
-        }
-        if let Some(fce) = self.expr.to_any().downcast_ref::<FunctionCallExpression>() {
-            let eval = fce.evaluate(runtime);
-            let mut tmp = self.expr.clone();
-            if let Some(integer) = any_into_int_literal(&eval) {
-                tmp = Box::new(integer);
-            }
-            if let Some(float) = any_into_f64_literal(&eval) {
-                tmp = Box::new(float);
-            }
-            if let Some(boolean) = any_into_bool_literal(&eval) {
-                tmp = Box::new(boolean);
-            }
-            if let Some(string) = any_into_string_literal(&eval) {
-                tmp = Box::new(string);
-            }
-            self.expr = tmp;
-        }
-        runtime.set_value(self.name.clone(), self.expr.clone());
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
+        let eval = self.expr.evaluate(runtime)?;
+        runtime.declare_value(self.name.clone(), eval);
+        Ok(())
+    }
+
+    fn compile(&self, nasm: &mut Nasm) {
+        self.expr.compile(nasm);
+        nasm.declare(self.name.as_str()).store(self.name.as_str());
     }
 
-    fn compile(&self) {
-        todo!()
+    fn emit(&self, bytecode: &mut Bytecode) {
+        self.expr.emit(bytecode);
+        bytecode.declare(self.name.as_str());
+        bytecode.store(self.name.as_str());
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
         javascript.append(format!("let {} = ", self.name));
-        self.expr.transpile(javascript);
+        javascript.transpile_value(self.expr.as_ref());
         javascript.semicolon().newline();
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
-        if self.expr.debug() == "StructExpression" {
-            self.expr.set_name(self.name.clone());
-        }
         self.expr.validate(st);
+        type_empty_literal(self.expr.as_mut(), &self.typ);
+        if self.typ == Initialized {
+            // no annotation, so we're whatever we're given
+            self.typ = self.expr.get_white_type();
+        }
         if st.has_symbol(self.name.clone()) {
             add_parser_error(
+                st,
                 DuplicateName(
                     self.name.clone(),
-                    st.get_symbol_type(self.name.clone()).unwrap(),
+                    st.get_symbol_type(self.name.clone()).unwrap_or(Type::Error),
                 ),
                 format!("Duplicate name: {}", self.name.clone()),
             );
         }
-        if self.typ == Type::Error || self.typ == Initialized {
-            add_parser_error(BadType(self.typ.clone()), format!("Got error type."));
-        }
-        if !self.typ.is_assignable_to(self.expr.get_white_type()) {
-            add_parser_error(
+        if self.typ == Initialized {
+            // i.e. `let xs = [];`, there's nothing to say what goes in it
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
+                UnresolvedType(self.name.clone()),
+                format!("Give it a type: let {}: type = ...", self.name),
+            );
+        } else if self.typ == Type::Null {
+            // `let x = null;`, null fits any nullable type so it doesn't pick one
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
+                UnresolvedType(self.name.clone()),
+                format!("Annotate it as nullable: let {}: type? = null", self.name),
+            );
+            self.typ = Type::Error;
+        } else if self.expr.get_white_type() == Type::Void {
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
+                VoidBinding(self.name.clone()),
+                format!(
+                    "`{}` would have no value, the function doesn't return one",
+                    self.name
+                ),
+            );
+            self.typ = Type::Error;
+        } else if self.typ != Type::Error // an error type has already been reported
+            && self.expr.get_white_type() != Type::Error
+            && !self.typ.is_assignable_to(self.expr.get_white_type())
+        {
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
                 MismatchedTypes(self.typ.clone(), self.expr.get_white_type()),
-                format!("Types are not assignable"),
+                "Attempt to set a bad type".to_string(),
             );
         }
         st.register_symbol(self.name.clone(), self.typ.clone());
@@ -104,20 +128,8 @@ impl VariableStatement {
         }
     }
     pub fn set_type(&mut self, typ: Type) {
-        let old_type = self.typ.clone();
-        LOGGER.debug(
-            format!("Set type of `{}` to {:?}", self.name, self.typ),
-            false,
-        );
+        LOGGER.debug(format!("Set type of `{}` to {:?}", self.name, typ), false);
         self.typ = typ;
-        if old_type != Initialized {
-            if old_type != self.typ.clone() {
-                add_parser_error(
-                    MismatchedTypes(old_type, self.typ.clone()),
-                    format!("Attempt to set a bad type"),
-                );
-            }
-        }
     }
 
     pub fn set_expr(&mut self, expr: Box<dyn Expression>) {
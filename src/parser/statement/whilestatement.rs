@@ -1,11 +1,13 @@
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
+use crate::nasm::Nasm;
 use crate::parser::expression::syntaxerrorexpression::SyntaxErrorExpression;
-use crate::parser::parser_traits::{add_parser_error, Expression, Statement, ToAny};
+use crate::parser::parser_traits::{add_parser_error_at, Expression, Statement, ToAny};
 use crate::parser::symbol_table::SymbolTable;
 use crate::parser::whitetypes::Type;
 use crate::parser::ParserErrorType;
-use crate::runtime::Runtime;
-use crate::LOGGER;
+use crate::runtime::{Runtime, RuntimeError};
+use crate::value::Value;
 use std::any::Any;
 
 #[derive(Clone, Debug)]
@@ -23,38 +25,59 @@ impl ToAny for WhileStatement {
     }
 }
 impl Statement for WhileStatement {
-    fn execute(&mut self, runtime: &mut Runtime) {
+    fn execute(&mut self, runtime: &mut Runtime) -> Result<(), RuntimeError> {
         runtime.push_scope(String::from("while"));
-        let mut iterations: usize = 0;
-        let mut is_broken = false;
-        let mut cond: bool = *self.expr.evaluate(runtime).downcast_ref::<bool>().unwrap();
+        let mut cond: bool = self.evaluate_condition(runtime)?;
         while cond {
             for statement in self.body.iter_mut() {
-                statement.execute(runtime);
-                if runtime.get_break() {
-                    is_broken = true; // interrogate break state
-                    runtime.set_break(false); // set break state back to false
+                statement.execute(runtime)?;
+                if runtime.should_unwind() {
                     break;
                 }
             }
-            if is_broken {
+            runtime.set_continue(false); // `continue` only skips the rest of this iteration
+            if runtime.get_break() {
+                runtime.set_break(false); // set break state back to false
                 break;
             }
-            cond = *self.expr.evaluate(runtime).downcast_ref::<bool>().unwrap();
-            iterations += 1;
-            if iterations > usize::MAX - 1 {
-                LOGGER.error("Infinite loop!".to_string()); // idk about this one but I feel like this should be a feature
+            if runtime.has_return() {
+                break;
             }
+            cond = self.evaluate_condition(runtime)?;
         }
         runtime.pop_scope();
+        Ok(())
     }
 
-    fn compile(&self) {
-        todo!()
+    fn compile(&self, nasm: &mut Nasm) {
+        let (top, done) = (nasm.new_label("while"), nasm.new_label("end_while"));
+        nasm.label(&top);
+        self.expr.compile(nasm);
+        nasm.instr(String::from("test rax, rax"))
+            .instr(format!("jz {}", done))
+            .push_loop(top.clone(), done.clone());
+        for statement in self.body.iter() {
+            statement.compile(nasm);
+        }
+        nasm.pop_loop().instr(format!("jmp {}", top)).label(&done);
+    }
+
+    fn emit(&self, bytecode: &mut Bytecode) {
+        let top = bytecode.here();
+        self.expr.emit(bytecode);
+        let done = bytecode.emit(Op::JumpIfFalse(0));
+        bytecode.push_loop();
+        for statement in self.body.iter() {
+            statement.emit(bytecode);
+        }
+        bytecode.patch_continues(); // `continue` goes back to the condition
+        bytecode.emit(Op::Jump(top));
+        bytecode.patch(done);
+        bytecode.pop_loop();
     }
 
     fn transpile(&self, javascript: &mut JavaScript) {
-        javascript.append(format!("while ("));
+        javascript.append("while (".to_string());
         self.expr.transpile(javascript);
         javascript.append_no_tabs(String::from(") {"));
         javascript.indent().newline();
@@ -65,32 +88,46 @@ impl Statement for WhileStatement {
     }
 
     fn validate(&mut self, st: &mut SymbolTable) {
+        st.widen_for_loop(|st| self.clone().validate_loop(st));
+        self.validate_loop(st);
+    }
+
+    fn get_expr(&self) -> &Box<dyn Expression> {
+        &self.expr
+    }
+
+    fn get_statement_type(&self) -> String {
+        String::from("IfStatement")
+    }
+}
+#[allow(dead_code)]
+impl WhileStatement {
+    /// The actual validation, run on a copy first to find what the loop widens, see
+    /// `SymbolTable::widen_for_loop`
+    fn validate_loop(&mut self, st: &mut SymbolTable) {
         self.expr.validate(st);
         if self.expr.get_white_type() != Type::Boolean {
-            add_parser_error(
+            add_parser_error_at(
+                st,
+                self.expr.as_ref(),
                 ParserErrorType::BadType(self.expr.get_white_type()),
-                format!("Expected a boolean type to loop on."),
+                "Expected a boolean type to loop on.".to_string(),
             );
         }
         st.push_scope();
+        for name in self.expr.non_null_when(true) {
+            st.narrow(name);
+        }
+        st.enter_loop();
         if !self.body.is_empty() {
             for i in 0..self.body.len() {
                 self.body[i].validate(st);
             }
         }
+        st.exit_loop();
         st.pop_scope();
     }
 
-    fn get_expr(&self) -> &Box<dyn Expression> {
-        todo!()
-    }
-
-    fn get_statement_type(&self) -> String {
-        String::from("IfStatement")
-    }
-}
-#[allow(dead_code)]
-impl WhileStatement {
     pub fn new() -> Self {
         WhileStatement {
             body: vec![],
@@ -106,4 +143,13 @@ impl WhileStatement {
     pub(crate) fn get_body(&self) -> &Vec<Box<dyn Statement>> {
         &self.body
     }
+    fn evaluate_condition(&self, runtime: &mut Runtime) -> Result<bool, RuntimeError> {
+        match self.expr.evaluate(runtime)? {
+            Value::Bool(cond) => Ok(cond),
+            eval => Err(RuntimeError::TypeMismatch(format!(
+                "cannot loop while `{}`",
+                eval.type_name()
+            ))),
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use crate::parser::statement::functiondefinitionstatement::FunctionDefinitionStatement;
 use crate::parser::whitetypes::Type;
+use crate::parser::ParserErrorType;
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
@@ -9,13 +10,19 @@ use super::statement::structdefinitionstatement::StructDefinitionStatement;
 pub struct SymbolTable {
     symbol_stack: Vec<HashMap<String, Box<dyn Any>>>,
     __self: String,
+    errors: Vec<(ParserErrorType, String, Option<usize>)>, // errors found during validation, see `add_error`
+    loops: usize,               // how many loops deep we are in the current function
+    narrowed: Vec<Vec<String>>, // the `T?` variables known not to be null, one list per scope
+    widened: Vec<String>,       // every variable widened back to `T?`, see `widen_for_loop`
+    returns: Vec<Type>,         // the types returned so far by the function being validated
+    widening: bool,             // validating a copy of a loop, see `widen_for_loop`
 }
 
 impl Debug for SymbolTable {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut display_str = String::new();
         for stack in self.symbol_stack.iter() {
-            for key in stack.keys(){
+            for key in stack.keys() {
                 if let Some(fds) = self.get_function(key.clone()) {
                     display_str.push_str(format!("-- {} -> {:?}\n", key, fds).as_str());
                 } else if let Some(sds) = self.get_struct(key.clone()) {
@@ -24,27 +31,73 @@ impl Debug for SymbolTable {
                     display_str.push_str(format!("-- {} -> {:?}\n", key, typ).as_str());
                 }
             }
-            display_str.push_str("\n");
+            display_str.push('\n');
         }
         write!(f, "self: `{}`\nstacks:\n{}", self.__self, display_str)
     }
 }
 
+// symbols are stored as `dyn Any`, so clone them by downcasting to whatever we put in
+impl Clone for SymbolTable {
+    fn clone(&self) -> Self {
+        let mut symbol_stack = vec![];
+        for scope in self.symbol_stack.iter() {
+            let mut cloned: HashMap<String, Box<dyn Any>> = HashMap::new();
+            for (name, symbol) in scope.iter() {
+                if let Some(typ) = symbol.downcast_ref::<Type>() {
+                    cloned.insert(name.clone(), Box::new(typ.clone()));
+                } else if let Some(fds) = symbol.downcast_ref::<FunctionDefinitionStatement>() {
+                    cloned.insert(name.clone(), Box::new(fds.clone()));
+                } else if let Some(sds) = symbol.downcast_ref::<StructDefinitionStatement>() {
+                    cloned.insert(name.clone(), Box::new(sds.clone()));
+                } else {
+                    panic!("Didn't cover symbols exhaustively");
+                }
+            }
+            symbol_stack.push(cloned);
+        }
+        SymbolTable {
+            symbol_stack,
+            __self: self.__self.clone(),
+            errors: self.errors.clone(),
+            loops: self.loops,
+            narrowed: self.narrowed.clone(),
+            widened: self.widened.clone(),
+            returns: self.returns.clone(),
+            widening: self.widening,
+        }
+    }
+}
+
 #[allow(dead_code)]
 impl SymbolTable {
     pub fn new() -> SymbolTable {
         SymbolTable {
             symbol_stack: vec![HashMap::<String, Box<dyn Any>>::new()], // <- the global scope
             __self: String::new(),
+            errors: vec![],
+            loops: 0,
+            narrowed: vec![vec![]],
+            widened: vec![],
+            returns: vec![],
+            widening: false,
         }
     }
 
+    /// The names of everything defined in the global scope, sorted
+    pub fn get_global_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.symbol_stack[0].keys().cloned().collect();
+        names.sort();
+        names
+    }
+
     pub fn has_symbol(&self, name: String) -> bool {
         self.get_symbol(name).is_some()
     }
 
     pub fn get_symbol(&self, name: String) -> Option<&Box<dyn Any>> {
-        for next in &self.symbol_stack {
+        for next in self.symbol_stack.iter().rev() {
+            // the innermost scope wins, arguments shadow globals
             match next.get(&name) {
                 Some(s) => {
                     return Some(s);
@@ -61,51 +114,59 @@ impl SymbolTable {
     where
         T: Clone + 'static,
     {
-        let retrieve = self.get_symbol(name);
-        if retrieve.is_some() {
-            if retrieve.unwrap().downcast_ref::<T>().is_some() {
-                return Some(retrieve.unwrap().downcast_ref::<T>().unwrap().clone());
-            }
-        }
-        None
+        self.get_symbol(name)
+            .and_then(|symbol| symbol.downcast_ref::<T>())
+            .cloned()
     }
 
     pub fn register_symbol(&mut self, name: String, typ: Type) {
+        self.widen(&name);
         self.symbol_stack
             .last_mut()
             .unwrap()
             .insert(name, Box::new(typ));
     }
 
+    /// Functions and structs are always global, no matter how deep we are when they're defined
     pub fn register_function(&mut self, name: String, def: FunctionDefinitionStatement) {
         self.symbol_stack
-            .last_mut()
+            .first_mut()
             .unwrap()
             .insert(name, Box::new(def));
     }
 
     pub fn register_struct(&mut self, name: String, def: StructDefinitionStatement) {
         self.symbol_stack
-            .last_mut()
+            .first_mut()
             .unwrap()
             .insert(name, Box::new(def));
     }
 
+    /// The type of the variable `name` where we are, a `T?` that can't be null here is a `T`
     pub fn get_symbol_type(&self, name: String) -> Option<Type> {
-        return match self.get_symbol(name) {
+        let typ = self.get_declared_type(name.clone())?;
+        match self.is_narrowed(&name) {
+            true => Some(typ.get_non_null_type()),
+            false => Some(typ),
+        }
+    }
+
+    /// The type the variable `name` was declared with, no matter what we know about it here
+    pub fn get_declared_type(&self, name: String) -> Option<Type> {
+        match self.get_symbol(name) {
             Some(t) => {
                 if t.downcast_ref::<Type>().is_some() {
-                    return Some(t.downcast_ref::<Type>().unwrap().clone());
+                    Some(t.downcast_ref::<Type>().unwrap().clone())
                 } else {
                     None
                 }
             }
             _ => None,
-        };
+        }
     }
 
     pub fn get_function(&self, name: String) -> Option<FunctionDefinitionStatement> {
-        return match self.get_symbol(name) {
+        match self.get_symbol(name) {
             Some(t) => {
                 if t.downcast_ref::<FunctionDefinitionStatement>().is_some() {
                     Some(
@@ -118,11 +179,11 @@ impl SymbolTable {
                 }
             }
             _ => None,
-        };
+        }
     }
 
     pub fn get_struct(&self, name: String) -> Option<StructDefinitionStatement> {
-        return match self.get_symbol(name) {
+        match self.get_symbol(name) {
             Some(t) => {
                 if t.downcast_ref::<StructDefinitionStatement>().is_some() {
                     Some(
@@ -135,15 +196,135 @@ impl SymbolTable {
                 }
             }
             _ => None,
-        };
+        }
+    }
+
+    /// The built in method `name` on `receiver`, i.e. `xs.push(v)`, as its argument types, its
+    /// return type and whether it changes the receiver. None if there is no such method
+    pub fn get_intrinsic(&self, receiver: &Type, name: &str) -> Option<(Vec<Type>, Type, bool)> {
+        use Type::*;
+        if *receiver == String {
+            return Some(match name {
+                "len" => (vec![], Integer, false),
+                "upper" | "lower" | "trim" => (vec![], String, false),
+                "split" => (vec![String], String.get_list_type(), false),
+                "contains" | "starts_with" | "ends_with" => (vec![String], Boolean, false),
+                "replace" => (vec![String, String], String, false),
+                "find" => (vec![String], Integer, false),
+                "substring" => (vec![Integer, Integer], String, false),
+                "chars" => (vec![], String.get_list_type(), false),
+                _ => return None,
+            });
+        }
+        if let Some((key, value)) = receiver.get_types_from_map() {
+            return Some(match name {
+                "len" => (vec![], Integer, false),
+                "keys" => (vec![], key.get_list_type(), false),
+                "values" => (vec![], value.get_list_type(), false),
+                "contains_key" => (vec![key], Boolean, false),
+                "remove" => (vec![key], value, true),
+                _ => return None,
+            });
+        }
+        if !receiver.is_list_type() {
+            return None;
+        }
+        let item = receiver.get_type_from_list();
+        Some(match name {
+            "len" => (vec![], Integer, false),
+            "push" => (vec![item], Void, true),
+            "pop" => (vec![], item, true),
+            "insert" => (vec![Integer, item], Void, true),
+            "remove" => (vec![Integer], item, true),
+            "contains" => (vec![item], Boolean, false),
+            "reverse" | "sort" => (vec![], Void, true),
+            _ => return None,
+        })
     }
 
     pub fn push_scope(&mut self) {
         self.symbol_stack
             .push(HashMap::<String, Box<dyn Any>>::new());
+        self.narrowed.push(vec![]);
     }
     pub fn pop_scope(&mut self) {
         self.symbol_stack.pop();
+        self.narrowed.pop();
+    }
+
+    /// `name` can't be null until the current scope ends, or until it's widened again
+    pub fn narrow(&mut self, name: String) {
+        self.narrowed.last_mut().unwrap().push(name);
+    }
+    /// `name` might be null again, i.e. it was assigned something that might be null
+    pub fn widen(&mut self, name: &str) {
+        for scope in self.narrowed.iter_mut() {
+            scope.retain(|narrowed| narrowed != name);
+        }
+        self.widened.push(name.to_string());
+    }
+    pub fn is_narrowed(&self, name: &str) -> bool {
+        self.narrowed
+            .iter()
+            .flatten()
+            .any(|narrowed| narrowed == name)
+    }
+    /// The function being called can assign to any global, so no global stays narrowed past a call
+    pub fn widen_globals(&mut self) {
+        let globals: Vec<String> = self
+            .narrowed
+            .iter()
+            .flatten()
+            .filter(|name| {
+                // the innermost scope with the name is the global one, it isn't shadowed
+                self.symbol_stack
+                    .iter()
+                    .rposition(|scope| scope.contains_key(*name))
+                    == Some(0)
+            })
+            .cloned()
+            .collect();
+        for name in globals {
+            self.widen(&name);
+        }
+    }
+    /// Loops run their bodies over and over, so a variable widened anywhere in one can't be
+    /// narrowed anywhere in it. `validate` validates a copy of the loop, until it widens nothing new.
+    /// Loops inside that copy are only validated once, the outermost loop's fixpoint covers them,
+    /// otherwise every level of nesting would double the work
+    pub fn widen_for_loop(&mut self, validate: impl Fn(&mut SymbolTable)) {
+        if self.widening {
+            return;
+        }
+        loop {
+            if self.narrowed.iter().all(|scope| scope.is_empty()) {
+                return; // nothing left to widen
+            }
+            let mut copy = self.clone();
+            copy.widened.clear();
+            copy.widening = true;
+            validate(&mut copy);
+            let widened: Vec<String> = copy
+                .widened
+                .into_iter()
+                .filter(|name| self.is_narrowed(name))
+                .collect();
+            if widened.is_empty() {
+                return;
+            }
+            for name in widened {
+                self.widen(&name);
+            }
+        }
+    }
+    /// Function bodies can run at any time, so nothing outside them stays narrowed inside them,
+    /// returns what to put back once the function is done
+    pub fn reset_narrowed(&mut self) -> Vec<Vec<String>> {
+        let empty = vec![vec![]; self.narrowed.len()];
+        std::mem::replace(&mut self.narrowed, empty)
+    }
+    pub fn restore_narrowed(&mut self, narrowed: Vec<Vec<String>>) {
+        self.narrowed = narrowed;
     }
 
     pub fn set_self(&mut self, name: String) {
@@ -152,4 +333,43 @@ impl SymbolTable {
     pub fn get_self(&self) -> String {
         self.__self.clone()
     }
+
+    pub fn enter_loop(&mut self) {
+        self.loops += 1;
+    }
+    pub fn exit_loop(&mut self) {
+        self.loops -= 1;
+    }
+    pub fn in_loop(&self) -> bool {
+        self.loops > 0
+    }
+    /// Function bodies can't `break` or `continue` the loop they're defined in, returns the depth
+    /// to put back once the function is done
+    pub fn reset_loops(&mut self) -> usize {
+        std::mem::take(&mut self.loops)
+    }
+    pub fn restore_loops(&mut self, loops: usize) {
+        self.loops = loops;
+    }
+
+    pub fn add_return(&mut self, typ: Type) {
+        self.returns.push(typ);
+    }
+    /// Start collecting the returns of a function, returns the ones to put back once it's done
+    pub fn reset_returns(&mut self) -> Vec<Type> {
+        std::mem::take(&mut self.returns)
+    }
+    /// Put back what `reset_returns` gave us, returns the types the function returned
+    pub fn restore_returns(&mut self, returns: Vec<Type>) -> Vec<Type> {
+        std::mem::replace(&mut self.returns, returns)
+    }
+
+    /// `at` is the id of the expression the error is about, if there is one
+    pub fn add_error(&mut self, error: ParserErrorType, info: String, at: Option<usize>) {
+        self.errors.push((error, info, at));
+    }
+    /// Drain the errors reported since the last call
+    pub fn take_errors(&mut self) -> Vec<(ParserErrorType, String, Option<usize>)> {
+        std::mem::take(&mut self.errors)
+    }
 }
@@ -1,24 +1,32 @@
-///
-/// WhiteLang Parser Tests
-///
+//!
+//! WhiteLang Parser Tests
+//!
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod test {
     use crate::parser::expression::additiveexpression::AdditiveExpression;
+    use crate::parser::expression::bitwiseexpression::BitwiseExpression;
     use crate::parser::expression::booleanliteralexpression::BooleanLiteralExpression;
+    use crate::parser::expression::coalesceexpression::CoalesceExpression;
     use crate::parser::expression::comparisonexpression::ComparisonExpression;
     use crate::parser::expression::equalityexpression::EqualityExpression;
     use crate::parser::expression::factorexpression::FactorExpression;
     use crate::parser::expression::floatliteralexpression::FloatLiteralExpression;
     use crate::parser::expression::identifierexpression::IdentifierExpression;
+    use crate::parser::expression::indexexpression::IndexExpression;
     use crate::parser::expression::integerliteralexpression::IntegerLiteralExpression;
+    use crate::parser::expression::interpolatedstringexpression::InterpolatedStringExpression;
     use crate::parser::expression::listliteralexpression::ListLiteralExpression;
     use crate::parser::expression::logicalexpression::LogicalExpression;
+    use crate::parser::expression::mapliteralexpression::MapLiteralExpression;
     use crate::parser::expression::nullliteralexpression::NullLiteralExpression;
     use crate::parser::expression::parenthesizedexpression::ParenthesizedExpression;
+    use crate::parser::expression::powerexpression::PowerExpression;
+    use crate::parser::expression::rangeexpression::RangeExpression;
     use crate::parser::expression::stringliteralexpression::StringLiteralExpression;
     use crate::parser::expression::unaryexpression::UnaryExpression;
-    use crate::parser::parser_traits::Statement;
+    use crate::parser::parser_traits::{Expression, Statement};
     use crate::parser::statement::assignmentstatement::AssignmentStatement;
     use crate::parser::statement::forstatement::ForStatement;
     use crate::parser::statement::functioncallstatement::FunctionCallStatement;
@@ -30,6 +38,7 @@ mod test {
     use crate::parser::statement::whilestatement::WhileStatement;
     use crate::parser::symbol_table::SymbolTable;
     use crate::parser::whitetypes::Type;
+    use crate::parser::ParserErrorType;
     use crate::TokenType::*;
     use crate::Tokenizer;
     use crate::{CoreObjects, Parser};
@@ -56,11 +65,11 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
     /// Assert that a string is not an integer
     fn test_require() {
         let mut parser = init_parser("\"\"".to_string());
         parser.require_token(Int);
+        assert!(parser.has_errors());
     }
 
     #[test]
@@ -78,10 +87,11 @@ mod test {
     /// Test parsing a string literal
     fn test_parse_string_expression() {
         let parser = init_parser("\"Hello World\"".to_string());
-        if let None = parser
+        if parser
             .expr
             .to_any()
             .downcast_ref::<StringLiteralExpression>()
+            .is_none()
         {
             panic!()
         }
@@ -161,11 +171,24 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
     /// test for errors when the function call doesn't have a closed paren
     fn test_fn_unterminated_args() {
         let mut parser = Parser::new(&mut Tokenizer::new("x(".to_string()));
         let _ = parser.parse_function_call_expression();
+        assert!(parser.has_errors());
+    }
+
+    #[test]
+    /// test that function arguments have to be names, and a definition cut short is an error
+    fn test_fn_args_must_be_names() {
+        for src in ["fn f(1: int) {}", "fn", "fn f(", "fn f(x: int"] {
+            let mut parser = Parser::new(&mut Tokenizer::new(src.to_string()));
+            let _ = parser.parse();
+            assert!(parser.has_errors(), "`{}` should be an error", src);
+        }
+        let mut parser = Parser::new(&mut Tokenizer::new("fn f(1: int) {}".to_string()));
+        let _ = parser.parse();
+        assert_eq!(parser.get_errors()[0].get_info(), "Expected a name");
     }
 
     #[test]
@@ -211,6 +234,62 @@ mod test {
         assert!(rhs.is_some());
     }
 
+    #[test]
+    /// the loosest operator ends up at the root of the tree
+    fn test_operator_precedence() {
+        let cases = [
+            ("true || false && false", "LogicalExpression"),
+            ("true && 1 < 2", "LogicalExpression"),
+            ("1 == 1 + 2", "EqualityExpression"),
+            ("1 < 2 == true", "EqualityExpression"),
+            ("1 + 2 < 4", "ComparisonExpression"),
+            ("6 & 3 < 4", "ComparisonExpression"),
+            ("1 | 2 & 3", "BitwiseExpression"),
+            ("1 << 2 + 3", "BitwiseExpression"),
+            ("1 + 2 * 3", "AdditiveExpression"),
+            ("(1 + 2) * 3", "FactorExpression"),
+            ("2 * -3", "FactorExpression"),
+            ("-2 ** 2", "UnaryExpression"),
+            ("-2 * 2", "FactorExpression"),
+            ("2 ** 3 ** 2", "PowerExpression"),
+            ("1..2 + 3", "RangeExpression"),
+        ];
+        for (src, expr_type) in cases {
+            let parser = init_parser(src.to_string());
+            assert_eq!(parser.expr.get_expr_type(), expr_type, "{}", src);
+        }
+    }
+
+    #[test]
+    /// operators of the same precedence group to the left, except for `**`
+    fn test_operator_associativity() {
+        let parser = init_parser("8 / 4 * 2".to_string());
+        let expr = parser.expr.clone();
+        let factor = expr.to_any().downcast_ref::<FactorExpression>().unwrap();
+        assert!(factor
+            .get_lhs()
+            .to_any()
+            .downcast_ref::<FactorExpression>()
+            .is_some());
+        let parser = init_parser("2 ** 3 ** 2".to_string());
+        let expr = parser.expr.clone();
+        let power = expr.to_any().downcast_ref::<PowerExpression>().unwrap();
+        assert!(power
+            .get_exponent()
+            .to_any()
+            .downcast_ref::<PowerExpression>()
+            .is_some());
+    }
+
+    #[test]
+    /// `and` and `or` are spelled-out `&&` and `||`
+    fn test_parse_keyword_logical_operators() {
+        let parser = init_parser("true and false or not true".to_string());
+        let expr = parser.expr.clone();
+        assert!(expr.to_any().downcast_ref::<LogicalExpression>().is_some());
+        assert_eq!(expr.debug(), "true && false || not true");
+    }
+
     #[test]
     /// test parsing a factor expression
     fn test_parse_factor_expression() {
@@ -220,6 +299,26 @@ mod test {
         assert_eq!(expr.debug(), "1 * 1");
     }
 
+    #[test]
+    /// test parsing bitwise and power expressions, and how tightly they bind
+    fn test_parse_bitwise_and_power_expressions() {
+        let parser = init_parser("1 | 2 ^ 3 & 4 << 1 + 1".to_string());
+        let expr = parser.expr.clone();
+        assert!(expr.to_any().downcast_ref::<BitwiseExpression>().is_some());
+        assert_eq!(expr.debug(), "1 | 2 ^ 3 & 4 << 1 + 1");
+        let parser = init_parser("2 * 3 ** 2 ** 2".to_string());
+        let expr = parser.expr.clone();
+        let factor = expr.to_any().downcast_ref::<FactorExpression>().unwrap();
+        assert_eq!(factor.debug(), "2 * 3 ** 2 ** 2");
+        assert_eq!(factor.get_white_type(), Type::Integer);
+        let parser = init_parser("let x = 2 ** 0.5; let y = 1.5 & 1; let z = ~true;".to_string());
+        assert_eq!(parser.get_errors().len(), 2);
+        assert_eq!(
+            parser.st.get_symbol_type(String::from("x")),
+            Some(Type::Float)
+        );
+    }
+
     #[test]
     /// test parsing equality expressions
     fn test_parse_equality_expression() {
@@ -343,10 +442,10 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
     fn test_parse_variable_statement_bad_assignment_type() {
         let parser = init_parser(String::from("let x : string = 10;"));
         println!("{:?}", parser.statement_list);
+        assert!(parser.has_errors());
     }
 
     #[test]
@@ -382,15 +481,121 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
     fn test_parse_function_definition_mismatched_return() {
         let parser = init_parser("fn foo() : string { let x = 10; return x; }".to_string());
-        let stmt = parser.statement_list.first().unwrap();
-        assert!(!parser.has_errors()); // parser will actually have errors and panic for reals
-        let _ = stmt
+        assert!(parser.has_errors());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::MismatchedTypes(_, _)
+        ));
+    }
+
+    #[test]
+    fn test_functions_have_to_return_on_every_path() {
+        for src in [
+            "fn f(x: int): int { if (x > 0) { return x; } }",
+            "fn f(x: int) { if (x > 0) { return x; } }",
+            "fn f(x: int): int { while (true) { if (x > 0) { break; } return x; } }",
+        ] {
+            let parser = init_parser(src.to_string());
+            assert!(
+                matches!(
+                    parser.get_errors()[0].get_error_type(),
+                    ParserErrorType::MissingReturn(name) if name == "f"
+                ),
+                "{}",
+                src
+            );
+        }
+        for src in [
+            "fn f(x: int): int { if (x > 0) { return x; } else if (x < 0) { return 0 - x; } else { return 0; } }",
+            "fn f(x: int): int { while (true) { for (y in [1]) { break; } return x; } }",
+            "fn f(x: int) { if (x > 0) { print(x); } }",
+        ] {
+            assert!(!init_parser(src.to_string()).has_errors(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_binding_a_void_result_is_an_error() {
+        for src in [
+            "fn f() { print(1); }\nlet x = f();",
+            "fn f() {}\nlet x: int = f();",
+        ] {
+            let parser = init_parser(src.to_string());
+            assert_eq!(parser.get_errors().len(), 1, "{}", src);
+            assert!(matches!(
+                parser.get_errors()[0].get_error_type(),
+                ParserErrorType::VoidBinding(name) if name == "x"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_function_return_type_is_inferred() {
+        let parser = init_parser(
+            "fn twice(x: int) { return double(x); }\nfn double(x: int) { return x * 2; }\nfn nothing() { print(1); }"
+                .to_string(),
+        );
+        assert!(!parser.has_errors());
+        let return_type = |name: &str| {
+            parser
+                .st
+                .get_function(name.to_string())
+                .unwrap()
+                .get_return_type()
+        };
+        assert_eq!(return_type("twice"), Type::Integer);
+        assert_eq!(return_type("double"), Type::Integer);
+        assert_eq!(return_type("nothing"), Type::Void);
+    }
+
+    #[test]
+    fn test_unresolved_types_are_errors() {
+        let parser = init_parser("let xs = [];".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::UnresolvedType(_)
+        ));
+        let parser = init_parser("fn forever(n: int) { return forever(n); }".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::UnresolvedType(_)
+        ));
+        let parser =
+            init_parser("fn mixed(b: bool) { if (b) { return 1; } return \"one\"; }".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::MismatchedTypes(Type::Integer, Type::String)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_struct_is_an_error() {
+        let parser = init_parser("struct A { x: int };\nstruct A { y: int };".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::DuplicateName(_, _)
+        ));
+    }
+
+    #[test]
+    /// the parser should report every broken statement, not just the first one
+    fn test_parser_collects_multiple_errors() {
+        let mut parser = init_parser(
+            "let x : string = 10;\nlet y = ;\nlet z = 1;\nprint(undefined);".to_string(),
+        );
+        assert_eq!(parser.get_errors().len(), 3);
+        assert_eq!(parser.get_errors()[0].get_token().get_line(), 1);
+        assert_eq!(parser.get_errors()[1].get_token().get_line(), 2);
+        assert_eq!(parser.get_errors()[2].get_token().get_line(), 4);
+        assert!(parser.parse().is_err());
+        // parsing picked back up after the bad statements
+        assert!(parser.statement_list.iter().any(|stmt| stmt
             .to_any()
-            .downcast_ref::<FunctionDefinitionStatement>()
-            .unwrap();
+            .downcast_ref::<VariableStatement>()
+            .is_some_and(|var| var.get_type() == Type::Integer)));
     }
 
     #[test]
@@ -412,6 +617,364 @@ mod test {
         let _ = stmt.to_any().downcast_ref::<ForStatement>().unwrap();
     }
 
+    #[test]
+    fn test_range_expression_parses() {
+        let parser = init_parser("let step = 2; let r = 0..step + 1 step step;".to_string());
+        assert!(!parser.has_errors());
+        let expr = parser.statement_list[1].get_expr();
+        let range = expr.to_any().downcast_ref::<RangeExpression>().unwrap();
+        assert_eq!(range.debug(), "0..step + 1 step step");
+        assert_eq!(range.get_white_type(), Type::Integer.get_list_type());
+        let parser = init_parser("for (x in 1..=\"10\") { }".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::BadType(Type::String)
+        ));
+    }
+
+    #[test]
+    fn test_index_expression_parses() {
+        let parser =
+            init_parser("let xs = [1, 2, 3]; let x = xs[-1]; let ys = xs[0..2];".to_string());
+        assert!(!parser.has_errors());
+        let expr = parser.statement_list[1].get_expr();
+        let index = expr.to_any().downcast_ref::<IndexExpression>().unwrap();
+        assert!(index.range().is_none());
+        assert_eq!(index.debug(), "xs[-1]");
+        assert_eq!(index.get_white_type(), Type::Integer);
+        let expr = parser.statement_list[2].get_expr();
+        let slice = expr.to_any().downcast_ref::<IndexExpression>().unwrap();
+        assert!(slice.range().is_some());
+        assert_eq!(slice.get_white_type(), Type::Integer.get_list_type());
+        let parser = init_parser("let xs = [1, 2, 3]; xs[1] = 4; xs[0] += 1;".to_string());
+        assert!(!parser.has_errors());
+        assert!(parser.statement_list[1]
+            .to_any()
+            .downcast_ref::<AssignmentStatement>()
+            .is_some());
+        let parser = init_parser("let xs = [1, 2, 3]; let x = xs[\"1\"];".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::BadType(Type::String)
+        ));
+        let parser = init_parser("let x = 1; let y = x[0];".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::BadType(Type::Integer)
+        ));
+    }
+
+    #[test]
+    fn test_nested_list_types() {
+        let list = |typ: Type| typ.get_list_type();
+        assert_eq!(Type::new("list<list<int>>"), list(list(Type::Integer)));
+        let parser = init_parser(
+            "struct Math { x: int }; let grid: list<list<int>> = [[], [1]]; let ms: list<Math> = []; let row = grid[0];"
+                .to_string(),
+        );
+        assert!(!parser.has_errors());
+        assert_eq!(
+            parser.statement_list[1].get_expr().get_white_type(),
+            list(list(Type::Integer))
+        );
+        assert_eq!(
+            parser.statement_list[2].get_expr().get_white_type(),
+            list(Type::Struct(String::from("Math")))
+        );
+        assert_eq!(
+            parser.statement_list[3].get_expr().get_white_type(),
+            list(Type::Integer)
+        );
+        let parser = init_parser("let xs: list<int> = [[1]];".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::MismatchedTypes(..)
+        ));
+        let parser = init_parser("let xs = [[1], [\"a\"]];".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::MismatchedTypes(..)
+        ));
+    }
+
+    #[test]
+    fn test_null_literals_take_the_nullable_type() {
+        let int = || Type::Integer.get_nullable_type();
+        let parser = init_parser(
+            "let xs: list<int?> = [null]; let m: map<string, int?> = {\"a\": null}; let ys = [[null], [1]]; let zs = [1, null];"
+                .to_string(),
+        );
+        assert!(!parser.has_errors());
+        assert_eq!(
+            parser.statement_list[0].get_expr().get_white_type(),
+            int().get_list_type()
+        );
+        assert_eq!(
+            parser.statement_list[1].get_expr().get_white_type(),
+            Type::new_map(Type::String, int())
+        );
+        assert_eq!(
+            parser.statement_list[2].get_expr().get_white_type(),
+            int().get_list_type().get_list_type()
+        );
+        assert_eq!(
+            parser.statement_list[3].get_expr().get_white_type(),
+            int().get_list_type()
+        );
+        for src in [
+            "let xs: list<int> = [null];",
+            "let m: map<int, int?> = {\"a\": null};",
+        ] {
+            let parser = init_parser(src.to_string());
+            assert!(
+                matches!(
+                    parser.get_errors()[0].get_error_type(),
+                    ParserErrorType::MismatchedTypes(..)
+                ),
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_map_types() {
+        let map = |key: Type, value: Type| Type::new_map(key, value);
+        assert_eq!(
+            Type::new("map<string, list<int>>"),
+            map(Type::String, Type::Integer.get_list_type())
+        );
+        let parser = init_parser(
+            "let m: map<string, map<int, bool>> = { \"a\": {} }; let inner = m[\"a\"]; let keys = m.keys();"
+                .to_string(),
+        );
+        assert!(!parser.has_errors());
+        assert!(parser.statement_list[0]
+            .get_expr()
+            .to_any()
+            .downcast_ref::<MapLiteralExpression>()
+            .is_some());
+        assert_eq!(
+            parser.statement_list[1].get_expr().get_white_type(),
+            map(Type::Integer, Type::Boolean)
+        );
+        assert_eq!(
+            parser.statement_list[2].get_expr().get_white_type(),
+            Type::String.get_list_type()
+        );
+        let parser = init_parser("let m = { 1.5: 1 };".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::BadType(Type::Float)
+        ));
+        let parser = init_parser("let m = { \"a\": 1 }; print(m[1]);".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::MismatchedTypes(Type::String, Type::Integer)
+        ));
+    }
+
+    #[test]
+    fn test_nullable_types() {
+        assert_eq!(
+            Type::new("list<int?>?"),
+            Type::Integer
+                .get_nullable_type()
+                .get_list_type()
+                .get_nullable_type()
+        );
+        let parser = init_parser(
+            "let x: int? = null; let y = x ?? 1; let z = [1, null]; if (x != null) { print(x + 1); }"
+                .to_string(),
+        );
+        assert!(!parser.has_errors());
+        assert!(parser.statement_list[1]
+            .get_expr()
+            .to_any()
+            .downcast_ref::<CoalesceExpression>()
+            .is_some());
+        assert_eq!(
+            parser.statement_list[1].get_expr().get_white_type(),
+            Type::Integer
+        );
+        assert_eq!(
+            parser.statement_list[2].get_expr().get_white_type(),
+            Type::Integer.get_nullable_type().get_list_type()
+        );
+    }
+
+    #[test]
+    fn test_null_safety() {
+        let first_error = |src: &str| {
+            init_parser(src.to_string()).get_errors()[0]
+                .get_error_type()
+                .clone()
+        };
+        assert!(matches!(
+            first_error("let x: int = null;"),
+            ParserErrorType::MismatchedTypes(Type::Integer, Type::Null)
+        ));
+        assert!(matches!(
+            first_error("let x: int? = 1; print(x + 1);"),
+            ParserErrorType::IncompatibleTypes(..)
+        ));
+        assert!(matches!(
+            first_error("fn f(x: int?): int { if (x != null) { x = null; return x; } return 0; }"),
+            ParserErrorType::MismatchedTypes(Type::Integer, _)
+        ));
+        assert!(matches!(
+            first_error(
+                "let x: int? = 1; if (x != null) { while (true) { print(x + 1); x = null; } }"
+            ),
+            ParserErrorType::IncompatibleTypes(..)
+        ));
+        // widened in a loop nested in the loop it's used in
+        assert!(matches!(
+            first_error(
+                "let x: int? = 1; if (x != null) { while (true) { print(x + 1); while (true) { x = null; } } }"
+            ),
+            ParserErrorType::IncompatibleTypes(..)
+        ));
+        // nested loops are checked once each, not once per level they're nested in
+        let depth = 32;
+        let src = format!(
+            "let x: int? = 1; if (x != null) {{ {} print(x + 1); {} }}",
+            "while (true) { ".repeat(depth),
+            "} ".repeat(depth)
+        );
+        assert!(!init_parser(src).has_errors());
+        assert!(matches!(
+            first_error("let x = 1; print(x ?? 2);"),
+            ParserErrorType::BadType(Type::Integer)
+        ));
+        // the call might set the global back to null
+        assert!(matches!(
+            first_error(
+                "let g: int? = 1; fn clear() { g = null; } if (g != null) { clear(); print(g + 1); }"
+            ),
+            ParserErrorType::IncompatibleTypes(..)
+        ));
+        let parser = init_parser(
+            "fn clear() {} fn f(x: int?) { if (x != null) { clear(); print(x + 1); } }".to_string(),
+        );
+        assert!(!parser.has_errors());
+        // narrowed by the early return
+        let parser = init_parser(
+            "fn f(x: int?): int { if (x == null) { return 0; } return x; }".to_string(),
+        );
+        assert!(!parser.has_errors());
+        let parser = init_parser("let x = null;".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::UnresolvedType(_)
+        ));
+        assert_eq!(
+            parser.get_errors()[0].get_info(),
+            "Annotate it as nullable: let x: type? = null"
+        );
+    }
+
+    #[test]
+    fn test_null_help_depends_on_what_might_be_null() {
+        let first_info = |src: &str| init_parser(src.to_string()).get_errors()[0].get_info();
+        let node = "struct Node { value: int, next: Node? }; let n: Node? = null;";
+        assert_eq!(
+            first_info(format!("{} print(n.value);", node).as_str()),
+            "`n` is `Node?`, it might be null, check `n != null` first"
+        );
+        // checking a field doesn't narrow it, so suggesting that would be no help
+        assert_eq!(
+            first_info(
+                format!(
+                    "{} if (n != null) {{ if (n.next != null) {{ print(n.next.value); }} }}",
+                    node
+                )
+                .as_str()
+            ),
+            "`n.next` is `Node?`, it might be null, put it in a variable and check that: \
+             `let value = n.next; if (value != null) { ... }`"
+        );
+    }
+
+    #[test]
+    fn test_list_intrinsics_are_typed() {
+        let parser = init_parser("let xs = [1.5]; let x = xs.pop(); let n = xs.len();".to_string());
+        assert!(!parser.has_errors());
+        assert_eq!(
+            parser.statement_list[1].get_expr().get_white_type(),
+            Type::Float
+        );
+        assert_eq!(
+            parser.statement_list[2].get_expr().get_white_type(),
+            Type::Integer
+        );
+        let parser = init_parser("let xs = [1]; xs.push(\"1\");".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::IncompatibleTypes(Type::String, Type::Integer)
+        ));
+        let parser = init_parser("let xs = [1]; xs.explode();".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::UnknownName(_)
+        ));
+        let parser =
+            init_parser("struct P { x: int }; let ps = [P(x = 1)]; ps.sort();".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::BadType(Type::Struct(_))
+        ));
+    }
+
+    #[test]
+    fn test_interpolated_string_parses() {
+        let parser = init_parser("let x = 1; let s = \"x = {x + 1}!\";".to_string());
+        assert!(!parser.has_errors());
+        let expr = parser.statement_list[1].get_expr();
+        assert!(expr
+            .to_any()
+            .downcast_ref::<InterpolatedStringExpression>()
+            .is_some());
+        assert_eq!(expr.get_white_type(), Type::String);
+        let parser = init_parser("let x = 1; print(\"{x\");".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::UnexpectedToken(_)
+        ));
+    }
+
+    #[test]
+    fn test_string_intrinsics_are_typed() {
+        let parser = init_parser(
+            "let s = \"a,b\"; let parts = s.split(\",\"); let c = s[0]; let n = s.find(\"b\");"
+                .to_string(),
+        );
+        assert!(!parser.has_errors());
+        assert_eq!(
+            parser.statement_list[1].get_expr().get_white_type(),
+            Type::String.get_list_type()
+        );
+        assert_eq!(
+            parser.statement_list[2].get_expr().get_white_type(),
+            Type::String
+        );
+        assert_eq!(
+            parser.statement_list[3].get_expr().get_white_type(),
+            Type::Integer
+        );
+        let parser = init_parser("let s = \"abc\"; let t = s.substring(\"1\", 2);".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::IncompatibleTypes(Type::String, Type::Integer)
+        ));
+        let parser = init_parser("let s = \"abc\"; s[0] = \"x\";".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::BadType(Type::String)
+        ));
+    }
+
     #[test]
     fn test_assign_statement_parses() {
         let parser = init_parser("let x : int = 10; x = 5;".to_string());
@@ -426,6 +989,50 @@ mod test {
         assert!(!parser.has_errors());
     }
 
+    #[test]
+    fn test_compound_assignment_parses() {
+        let parser = init_parser("let x = 1; x += 2; x %= 3; x++; x--;".to_string());
+        assert!(!parser.has_errors());
+        for stmt in &parser.statement_list[1..] {
+            assert!(stmt
+                .to_any()
+                .downcast_ref::<AssignmentStatement>()
+                .is_some());
+        }
+        let parser = init_parser("let x = 1; x += 2.5; let s = \"s\"; s++;".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::IncompatibleTypes(Type::Float, Type::Integer)
+        ));
+        assert!(parser.get_errors().len() > 1);
+    }
+
+    #[test]
+    fn test_compound_assignment_needs_numbers() {
+        let parser = init_parser("let s = \"a\"; s += \"b\";".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::BadType(Type::String)
+        ));
+        assert_eq!(
+            parser.get_errors()[0].get_info(),
+            "`+=` needs a number on both sides, `s` is `string`"
+        );
+        let parser = init_parser("let x = 1; let s = \"2\"; x *= s;".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        assert_eq!(
+            parser.get_errors()[0].get_info(),
+            "`*=` needs a number on both sides, `s` is `string`"
+        );
+        let parser = init_parser("let b = true; b--;".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        assert_eq!(
+            parser.get_errors()[0].get_info(),
+            "`--` needs a number, `b` is `bool`"
+        );
+    }
+
     #[test]
     fn test_print_statement_parse() {
         let parser = init_parser("print(1);".to_string());
@@ -448,6 +1055,28 @@ mod test {
         assert!(!parser.has_errors());
     }
 
+    #[test]
+    fn test_if_statement_else_if_parses() {
+        let parser = init_parser(
+            "if (1 < 2) { print(1); } else if (2 < 3) { print(2); } else if (3 < 4) { } else { print(4); }"
+                .to_string(),
+        );
+        assert!(!parser.has_errors());
+        let stmt = parser.statement_list[0]
+            .to_any()
+            .downcast_ref::<IfStatement>()
+            .unwrap();
+        let else_if = stmt.get_false_stmts()[0]
+            .to_any()
+            .downcast_ref::<IfStatement>()
+            .unwrap();
+        let last = else_if.get_false_stmts()[0]
+            .to_any()
+            .downcast_ref::<IfStatement>()
+            .unwrap();
+        assert_eq!(last.get_false_stmts().len(), 1);
+    }
+
     #[test]
     fn test_if_statement_no_else_parses() {
         let parser = init_parser("if (1 < 2) { print(\"Hello World!\\n\"); }".to_string());
@@ -464,6 +1093,36 @@ mod test {
         assert!(!parser.has_errors());
     }
 
+    #[test]
+    fn test_continue_outside_of_a_loop() {
+        let parser = init_parser("for (x in [1]) { continue; } continue;".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::OutsideOfLoop(_)
+        ));
+        let parser = init_parser("while (true) { fn foo() { continue; } }".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::OutsideOfLoop(_)
+        ));
+    }
+
+    #[test]
+    fn test_break_outside_of_a_loop() {
+        let parser = init_parser("while (true) { break; } break;".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::OutsideOfLoop(_)
+        ));
+        let parser = init_parser("for (x in [1]) { fn foo() { break; } }".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::OutsideOfLoop(_)
+        ));
+    }
+
     #[test]
     fn test_function_call_statement_parses() {
         let parser =
@@ -590,4 +1249,75 @@ mod test {
         println!("{:?}", strct_expr);
         assert_eq!("This looks right", "This looks right");
     }
+
+    #[test]
+    fn test_struct_fields_are_checked() {
+        let parser = init_parser(
+            "
+        struct X { x: int };
+        let a = X(x = 1);
+        a.y = 2;
+        "
+            .to_string(),
+        );
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::UnknownName(field) if field == "y"
+        ));
+    }
+
+    #[test]
+    fn test_integer_literal_too_large() {
+        let parser = init_parser("let x = 9223372036854775808;".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::IntegerTooLarge(_)
+        ));
+    }
+
+    #[test]
+    fn test_bad_float_literal() {
+        let parser = init_parser("print(1.2.3);".to_string());
+        assert!(parser.has_errors());
+    }
+
+    #[test]
+    fn test_self_outside_of_a_struct() {
+        let parser = init_parser("let x = self.y;".to_string());
+        assert!(matches!(
+            parser.get_errors()[0].get_error_type(),
+            ParserErrorType::UnexpectedToken(_)
+        ));
+    }
+
+    #[test]
+    /// one error per spot in the source, and expected tokens read the way they're written
+    fn test_parser_errors_are_not_repeated() {
+        let parser = init_parser("foo(;".to_string());
+        assert_eq!(parser.get_errors().len(), 1);
+        let parser = init_parser("let x = (1 + 2;".to_string());
+        assert_eq!(parser.get_errors()[0].get_info(), "Expected `)`");
+    }
+
+    #[test]
+    /// validation errors point at the expression that's wrong, not the whole statement
+    fn test_validation_errors_point_at_the_expression() {
+        let parser = init_parser("fn f(a: int): int { return a; }\nprint(f(true));".to_string());
+        let diagnostic = parser.get_errors()[0].to_diagnostic();
+        assert_eq!((diagnostic.get_line(), diagnostic.get_column()), (2, 8));
+    }
+
+    #[test]
+    /// errors render as a snippet of the source with the span underlined
+    fn test_parser_error_renders_snippet() {
+        let src = "let x = 1;\nlet y : string = x + 2;";
+        let parser = init_parser(src.to_string());
+        let rendered = parser.get_errors()[0]
+            .to_diagnostic()
+            .render("test.whl", src);
+        assert_eq!(
+            rendered,
+            "error: mismatched types: expected `string`, found `int`\n --> test.whl:2:18\n  |\n2 | let y : string = x + 2;\n  |                  ^^^^^\n  |\n  = help: Attempt to set a bad type\n"
+        );
+    }
 }
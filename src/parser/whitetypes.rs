@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter};
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Type {
     Char,
@@ -7,96 +9,180 @@ pub enum Type {
     Boolean,
     Null,
     Object,
-    ListChar,
-    ListString,
-    ListInteger,
-    ListFloat,
-    ListBoolean,
-    ListObject,
+    List(Box<Type>),           // list<T>, for any T including other lists
+    Map(Box<Type>, Box<Type>), // map<K, V>, see `can_be_key` for what K can be
+    Nullable(Box<Type>),       // T?, a T or null
     Struct(String),
-    ListStruct(String),
     Initialized,
     Void,
     Error,
 }
+impl Display for Type {
+    /// Types are displayed the way they are written in white-lang
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use Type::*;
+        match self {
+            Char => write!(f, "char"),
+            String => write!(f, "string"),
+            Integer => write!(f, "int"),
+            Float => write!(f, "float"),
+            Boolean => write!(f, "bool"),
+            Null => write!(f, "null"),
+            Object => write!(f, "object"),
+            List(typ) => write!(f, "list<{}>", typ),
+            Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Nullable(typ) => write!(f, "{}?", typ),
+            Struct(name) => write!(f, "{}", name),
+            Initialized => write!(f, "{{unknown}}"),
+            Void => write!(f, "void"),
+            Error => write!(f, "{{error}}"),
+        }
+    }
+}
 impl Type {
+    /// The built in types, written the way they are in white-lang, structs need the symbol table
     pub fn new(typ: &str) -> Type {
-        let toks: Vec<&str> = typ.split('<').collect();
-        match toks[0] {
+        if let Some(typ) = typ.strip_suffix('?') {
+            return Type::new(typ.trim()).get_nullable_type();
+        }
+        if let Some(item) = typ
+            .strip_prefix("list<")
+            .and_then(|rest| rest.strip_suffix('>'))
+        {
+            return Type::new(item.trim()).get_list_type();
+        }
+        if let Some(types) = typ
+            .strip_prefix("map<")
+            .and_then(|rest| rest.strip_suffix('>'))
+        {
+            // the comma between the key and value types is the one outside any other <>
+            let mut depth = 0;
+            let comma = types.char_indices().find(|(_, c)| {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+                *c == ',' && depth == 0
+            });
+            return match comma {
+                Some((at, _)) => Type::new_map(
+                    Type::new(types[..at].trim()),
+                    Type::new(types[at + 1..].trim()),
+                ),
+                None => Type::Error,
+            };
+        }
+        match typ {
             "char" => Type::Char,
             "string" => Type::String,
             "int" => Type::Integer,
             "float" => Type::Float,
             "bool" => Type::Boolean,
-            "list" => match toks[1] {
-                "char>" => Type::ListChar,
-                "string>" => Type::ListString,
-                "int>" => Type::ListInteger,
-                "float>" => Type::ListFloat,
-                "bool>" => Type::ListBoolean,
-                _ => Type::Error,
-            },
             "void" => Type::Void,
             _ => Type::Error,
         }
     }
+    /// A list of this type
     pub fn get_list_type(&self) -> Type {
         use Type::*;
         match self {
-            Char => ListChar,
-            String => ListString,
-            Integer => ListInteger,
-            Float => ListFloat,
-            Boolean => ListBoolean,
-            Null => ListObject,
-            Object => ListObject,
-            ListChar => ListChar,
-            ListString => ListString,
-            ListInteger => ListInteger,
-            ListFloat => ListFloat,
-            ListBoolean => ListBoolean,
-            ListObject => ListObject,
-            Struct(a) => ListStruct(a.clone()),
-            ListStruct { .. } => self.clone(),
-            Initialized => Error,
-            Void => Error,
-            Error => Error,
+            Initialized | Void | Error => Error,
+            typ => List(Box::new(typ.clone())),
         }
     }
+    /// What this is a list of, anything that isn't a list is given back as is
     pub fn get_type_from_list(&self) -> Type {
-        use Type::*;
         match self {
-            ListChar => Char,
-            ListString => String,
-            ListInteger => Integer,
-            ListFloat => Float,
-            ListBoolean => Boolean,
-            ListObject => Object,
-            ListStruct(s) => Struct(s.to_string()),
+            Type::List(typ) => *typ.clone(),
             _ => self.clone(),
         }
     }
+    /// Whether a value of type `other` can be stored where a `self` is expected, only nullable
+    /// types can hold `null`
     pub fn is_assignable_to(&self, other: Type) -> bool {
-        if other == Type::Void {
-            return false;
-        } else if other == Type::Null {
-            return true;
-        } else if *self == other {
-            return true;
+        match (self, other) {
+            (_, Type::Void) => false,
+            (Type::Nullable(_), Type::Null) => true,
+            (Type::Nullable(slot), Type::Nullable(value)) => slot.is_assignable_to(*value),
+            (Type::Nullable(slot), value) => slot.is_assignable_to(value),
+            (Type::Null, Type::Null) => true, // i.e. the items of `[null, null]`
+            (_, Type::Null) => false,
+            (Type::List(slot), Type::List(value)) => slot.is_assignable_to(*value),
+            (Type::Map(slot_key, slot), Type::Map(key, value)) => {
+                **slot_key == *key && slot.is_assignable_to(*value)
+            }
+            (slot, value) => *slot == value,
         }
-        false
     }
     pub fn is_list_type(&self) -> bool {
+        matches!(self, Type::List(_))
+    }
+    /// A map from `key`s to `value`s
+    pub fn new_map(key: Type, value: Type) -> Type {
+        match (&key, &value) {
+            (Type::Error, _) | (_, Type::Error) => Type::Error,
+            _ => Type::Map(Box::new(key), Box::new(value)),
+        }
+    }
+    /// The key and value types of a map type
+    pub fn get_types_from_map(&self) -> Option<(Type, Type)> {
+        match self {
+            Type::Map(key, value) => Some((*key.clone(), *value.clone())),
+            _ => None,
+        }
+    }
+    pub fn is_map_type(&self) -> bool {
+        matches!(self, Type::Map(..))
+    }
+    /// Only types that compare by value make sense as the keys of a map
+    pub fn can_be_key(&self) -> bool {
+        matches!(
+            self,
+            Type::Integer | Type::String | Type::Boolean | Type::Char
+        )
+    }
+    /// This type or null, `T??` is just `T?`
+    pub fn get_nullable_type(&self) -> Type {
         use Type::*;
         match self {
-            ListChar => true,
-            ListString => true,
-            ListInteger => true,
-            ListBoolean => true,
-            ListFloat => true,
-            ListObject => true,
-            ListStruct(_) => true,
-            _ => false,
+            Nullable(_) | Null => self.clone(),
+            Initialized | Void | Error => Error,
+            typ => Nullable(Box::new(typ.clone())),
+        }
+    }
+    /// What's left of this type once null is ruled out
+    pub fn get_non_null_type(&self) -> Type {
+        match self {
+            Type::Nullable(typ) => *typ.clone(),
+            _ => self.clone(),
+        }
+    }
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, Type::Nullable(_) | Type::Null)
+    }
+    /// The type of a list's elements or a map's values, the one they all fit in, so `null`s
+    /// among them make it nullable. Items that don't fit are left for the caller to report, and
+    /// it's None if none of them have a type yet
+    pub fn common(types: impl Iterator<Item = Type>) -> Option<Type> {
+        let mut types = types.filter(|typ| *typ != Type::Initialized);
+        let first = types.next()?;
+        Some(types.fold(first, |common, typ| common.unify(&typ).unwrap_or(common)))
+    }
+    /// The type both this and `other` fit in, if there is one, e.g. `null` and `int` make `int?`
+    fn unify(&self, other: &Type) -> Option<Type> {
+        use Type::*;
+        match (self, other) {
+            _ if self == other => Some(self.clone()),
+            (Null, typ) | (typ, Null) => Some(typ.get_nullable_type()),
+            (Nullable(typ), other) | (other, Nullable(typ)) => {
+                Some(typ.unify(&other.get_non_null_type())?.get_nullable_type())
+            }
+            (List(item), List(other)) => Some(List(Box::new(item.unify(other)?))),
+            (Map(key, value), Map(other_key, other)) if key == other_key => {
+                Some(Map(key.clone(), Box::new(value.unify(other)?)))
+            }
+            _ => None,
         }
     }
 }
@@ -1,37 +1,41 @@
-use crate::config::WhiteLangList;
-use crate::config::*;
+use crate::bytecode::{Bytecode, Op};
 use crate::javascript::JavaScript;
-use crate::parser::expression::syntaxerrorexpression::SyntaxErrorExpression;
+use crate::nasm::Nasm;
 use crate::parser::parser_traits::{Expression, Statement};
-use crate::parser::ParserErrorType;
-use crate::runtime::Runtime;
+use crate::parser::statement::functiondefinitionstatement::FunctionDefinitionStatement;
+use crate::parser::statement::structdefinitionstatement::StructDefinitionStatement;
+use crate::parser::{ParserError, ParserErrorType};
+use crate::runtime::{report_error, Runtime, RuntimeError};
+use crate::value::Value;
+use crate::vm::Vm;
 use crate::{Parser, Tokenizer};
-use std::any::Any;
 
 #[allow(dead_code)]
 pub struct Program {
     statements: Vec<Box<dyn Statement>>,
-    expr: Box<dyn Expression>,
+    expr: Option<Box<dyn Expression>>, // a bare expression, otherwise the statements are run
     runtime: Runtime,
     javascript: JavaScript,
+    nasm: Nasm,
     pub stdout: String,
     pub stderr: String,
     errors: Vec<ParserErrorType>,
 }
 impl Program {
-    pub fn from_src(src: String) -> Self {
+    pub fn from_src(src: String) -> Result<Self, Vec<ParserError>> {
         let mut tokenizer: Tokenizer = Tokenizer::new(src);
         let mut parser: Parser = Parser::new(&mut tokenizer);
-        parser.parse();
-        Program::from_parser(&mut parser)
+        parser.parse()?;
+        Ok(Program::from_parser(&mut parser))
     }
     pub fn from_parser(parser: &mut Parser) -> Self {
         if let Some(statements) = parser.get_statements() {
             return Program {
-                statements: statements.clone(),
-                expr: Box::new(SyntaxErrorExpression::new()),
+                statements: Program::hoist(statements),
+                expr: None,
                 runtime: Runtime::new(),
                 javascript: JavaScript::new(),
+                nasm: Nasm::new(),
                 stdout: String::new(),
                 stderr: String::new(),
                 errors: vec![],
@@ -40,9 +44,10 @@ impl Program {
         if let Some(expr) = parser.get_expr() {
             return Program {
                 statements: vec![],
-                expr: expr.clone(),
+                expr: Some(expr.clone()),
                 runtime: Runtime::new(),
                 javascript: JavaScript::new(),
+                nasm: Nasm::new(),
                 stdout: String::new(),
                 stderr: String::new(),
                 errors: vec![],
@@ -53,9 +58,10 @@ impl Program {
     pub fn new_uninit() -> Program {
         Program {
             statements: vec![],
-            expr: Box::new(SyntaxErrorExpression::new()),
+            expr: None,
             runtime: Runtime::new(),
             javascript: JavaScript::new(),
+            nasm: Nasm::new(),
             stdout: "".to_string(),
             stderr: "".to_string(),
             errors: vec![],
@@ -64,34 +70,86 @@ impl Program {
 
     pub fn set_statements_or_expr(
         &mut self,
-        statements: &Vec<Box<dyn Statement>>,
+        statements: &[Box<dyn Statement>],
         expr: Option<&Box<dyn Expression>>,
     ) {
-        if statements.is_empty() {
-            self.expr = expr.unwrap().clone();
-        } else if expr.is_none() {
-            self.statements = statements.clone();
-        } else {
-            panic!("Must have statements or an expression!")
+        match expr {
+            // an empty program is just one without any statements
+            None => self.statements = Program::hoist(statements),
+            Some(expr) if statements.is_empty() => self.expr = Some(expr.clone()),
+            Some(_) => panic!("Must have statements or an expression!"),
+        }
+    }
+
+    /// Move function and struct definitions in front of everything else, they can be used
+    /// before they're defined so they have to exist before any other statement runs
+    fn hoist(statements: &[Box<dyn Statement>]) -> Vec<Box<dyn Statement>> {
+        let (mut definitions, rest): (Vec<_>, Vec<_>) =
+            statements.iter().cloned().partition(|statement| {
+                statement.to_any().is::<FunctionDefinitionStatement>()
+                    || statement.to_any().is::<StructDefinitionStatement>()
+            });
+        definitions.extend(rest);
+        definitions
+    }
+
+    /// Run the program, if it fails the error and a white-lang stack trace end up in stderr
+    pub fn execute(&mut self) -> Result<(), RuntimeError> {
+        let result = self.run();
+        if let Err(error) = &result {
+            self.stderr = self.runtime.report(error);
         }
+        result
     }
 
-    pub fn execute(&mut self) {
-        if self.statements.is_empty() {
-            let eval = self.expr.evaluate(&mut self.runtime);
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        if let Some(expr) = &self.expr {
+            let eval = expr.evaluate(&mut self.runtime)?;
             self.stdout += &Program::try_print_output(&eval);
-            self.stdout.push_str("\n");
+            self.stdout.push('\n');
         } else {
             for statement in self.statements.iter_mut() {
-                statement.execute(&mut self.runtime);
-                self.stdout = self.runtime.get_output();
+                let result = statement.execute(&mut self.runtime);
+                self.stdout = self.runtime.get_output(); // keep whatever was printed before an error
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the program on the bytecode vm instead of the tree walking runtime, output ends up in
+    /// stdout and stderr the same way it does for `execute`
+    pub fn execute_vm(&mut self) -> Result<(), RuntimeError> {
+        let mut bytecode = Bytecode::new();
+        // functions are hoisted above structs they can construct, so every layout goes in first
+        for statement in &self.statements {
+            if let Some(definition) = statement
+                .to_any()
+                .downcast_ref::<StructDefinitionStatement>()
+            {
+                definition.declare(&mut bytecode);
             }
         }
+        if let Some(expr) = &self.expr {
+            expr.emit(&mut bytecode);
+            bytecode.emit(Op::Print);
+        } else {
+            for statement in &self.statements {
+                statement.emit(&mut bytecode);
+            }
+        }
+        let mut vm = Vm::new(bytecode);
+        let result = vm.run();
+        self.stdout = vm.take_output();
+        if let Err(error) = &result {
+            self.stderr = report_error(error, &vm.stack_trace());
+        }
+        result
     }
 
     pub fn transpile_to_js(&mut self) -> String {
-        if self.statements.is_empty() {
-            self.expr.transpile(&mut self.javascript);
+        if let Some(expr) = &self.expr {
+            expr.transpile(&mut self.javascript);
         } else {
             for statement in &self.statements {
                 statement.transpile(&mut self.javascript);
@@ -100,32 +158,24 @@ impl Program {
         self.javascript.get_src()
     }
 
-    pub fn try_print_output(evaluated: &Box<dyn Any>) -> String {
-        let mut output = String::new();
-        if let Some(eval_f64) = evaluated.downcast_ref::<WhiteLangFloat>() {
-            let push = eval_f64.to_string();
-            output.push_str(push.as_str());
-        } else if let Some(eval_isize) = evaluated.downcast_ref::<WhiteLangInt>() {
-            let push = eval_isize.to_string();
-            output.push_str(push.as_str());
-        } else if let Some(eval_bool) = evaluated.downcast_ref::<WhiteLangBool>() {
-            let push = eval_bool.to_string();
-            output.push_str(push.as_str());
-        } else if let Some(eval_str) = evaluated.downcast_ref::<&'static str>() {
-            let push = eval_str.to_string();
-            output.push_str(push.as_str());
-        } else if let Some(eval_string) = evaluated.downcast_ref::<WhiteLangString>() {
-            output.push_str(eval_string.as_str());
-        } else if let Some(eval_list) = evaluated.downcast_ref::<WhiteLangList<Box<dyn Any>>>() {
-            output.push_str("[");
-            for (i, thing) in eval_list.iter().enumerate() {
-                output.push_str(Program::try_print_output(thing).as_str());
-                if i < eval_list.len() - 1 {
-                    output.push_str(", ");
-                }
+    /// Compile the program to nasm, bare expressions get printed like they do when interpreted
+    pub fn compile_to_nasm(&mut self) -> Result<String, Vec<String>> {
+        if let Some(expr) = &self.expr {
+            expr.compile(&mut self.nasm);
+            self.nasm.print(expr.get_white_type());
+        } else {
+            for statement in &self.statements {
+                statement.compile(&mut self.nasm);
             }
-            output.push_str("]");
         }
-        output
+        if !self.nasm.get_errors().is_empty() {
+            return Err(self.nasm.get_errors().clone());
+        }
+        Ok(self.nasm.get_src())
+    }
+
+    /// How `print` shows a value, bare expressions get printed the same way
+    pub fn try_print_output(evaluated: &Value) -> String {
+        evaluated.to_string()
     }
 }
@@ -0,0 +1,266 @@
+use crate::parser::parser_traits::Statement;
+use crate::parser::symbol_table::SymbolTable;
+use crate::parser::{Parser, ParserError};
+use crate::program::Program;
+use crate::runtime::Runtime;
+use crate::tokenizer::Tokenizer;
+use std::io::{BufRead, Write};
+
+mod test;
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+const REPL_FILE_NAME: &str = "<repl>";
+
+/// The white-lang repl
+/// Keeps one runtime and symbol table alive so that every line can see what came before it
+pub struct Repl {
+    runtime: Runtime,
+    st: SymbolTable,
+}
+impl Repl {
+    pub fn new() -> Self {
+        Repl {
+            runtime: Runtime::new(),
+            st: SymbolTable::new(),
+        }
+    }
+
+    /// Read lines from `input` until it runs dry or the user types `:quit`
+    pub fn run(
+        &mut self,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+    ) -> std::io::Result<()> {
+        writeln!(output, "white-lang repl, type :help for a list of commands")?;
+        let mut src = String::new();
+        loop {
+            write!(
+                output,
+                "{}",
+                if src.is_empty() {
+                    PROMPT
+                } else {
+                    CONTINUATION_PROMPT
+                }
+            )?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(());
+            }
+            if src.is_empty() && line.trim() == ":quit" {
+                return Ok(());
+            }
+            src.push_str(line.as_str());
+            // keep reading until every block has been closed
+            if !Repl::is_complete(src.as_str()) {
+                continue;
+            }
+            write!(output, "{}", self.eval(src.as_str()))?;
+            src.clear();
+        }
+    }
+
+    /// Evaluate one (possibly multi-line) chunk of input and return whatever should be shown
+    pub fn eval(&mut self, src: &str) -> String {
+        let trimmed = src.trim();
+        if trimmed.is_empty() {
+            return String::new();
+        }
+        if let Some(command) = trimmed.strip_prefix(':') {
+            return self.meta_command(command);
+        }
+        let mut parser = match self.parse(src) {
+            Ok(parser) => parser,
+            Err(rendered) => return rendered,
+        };
+        if let Some(expr) = parser.get_expr() {
+            // bare expressions get printed
+            let expr = expr.clone();
+            return match expr.evaluate(&mut self.runtime) {
+                Ok(eval) => {
+                    self.st = parser.take_symbol_table();
+                    let mut printed = self.runtime.take_output();
+                    let value = Program::try_print_output(&eval);
+                    if !value.is_empty() {
+                        printed.push_str(value.as_str());
+                        printed.push('\n');
+                    }
+                    printed
+                }
+                Err(error) => {
+                    let printed = self.runtime.take_output();
+                    printed + self.runtime.report(&error).as_str()
+                }
+            };
+        }
+        let mut statements: Vec<Box<dyn Statement>> =
+            parser.get_statements().cloned().unwrap_or_default();
+        let mut printed = String::new();
+        for statement in statements.iter_mut() {
+            let result = statement.execute(&mut self.runtime);
+            printed.push_str(self.runtime.take_output().as_str());
+            if let Err(error) = result {
+                // nothing the input defined is kept, so later lines can't see half made names
+                printed.push_str(self.runtime.report(&error).as_str());
+                return printed;
+            }
+        }
+        self.st = parser.take_symbol_table();
+        printed
+    }
+
+    /// Parse `src` against a copy of the repl's symbol table, the parser's symbol table only
+    /// becomes the repl's once the input has run. If there are errors the rendered errors are
+    /// returned
+    fn parse(&mut self, src: &str) -> Result<Parser, String> {
+        let mut tokenizer = Tokenizer::new(src.to_string());
+        tokenizer.tokenize();
+        if tokenizer.has_errors() {
+            return Err(tokenizer
+                .get_diagnostics()
+                .iter()
+                .map(|diagnostic| diagnostic.render(REPL_FILE_NAME, src))
+                .collect());
+        }
+        let mut parser = Parser::new(&mut tokenizer);
+        parser.set_symbol_table(self.st.clone());
+        if let Err(errors) = parser.parse() {
+            return Err(errors
+                .iter()
+                .map(ParserError::to_diagnostic)
+                .map(|diagnostic| diagnostic.render(REPL_FILE_NAME, src))
+                .collect());
+        }
+        Ok(parser)
+    }
+
+    fn meta_command(&mut self, command: &str) -> String {
+        let (name, rest) = match command.split_once(char::is_whitespace) {
+            Some((name, rest)) => (name, rest.trim()),
+            None => (command, ""),
+        };
+        match name {
+            "type" => self.type_of(rest),
+            "env" => self.env(),
+            "reset" => {
+                self.runtime = Runtime::new();
+                self.st = SymbolTable::new();
+                String::from("environment cleared\n")
+            }
+            "help" => String::from(
+                ":type <expr>  show the type of an expression\n\
+                 :env          show everything that has been defined\n\
+                 :reset        forget everything that has been defined\n\
+                 :quit         leave the repl\n",
+            ),
+            _ => format!("unknown command `:{}`, try :help\n", name),
+        }
+    }
+
+    /// `:type <expr>`, figure out the type of an expression without running it
+    fn type_of(&mut self, src: &str) -> String {
+        if src.is_empty() {
+            return String::from("usage: :type <expr>\n");
+        }
+        // the parser has already validated the expression, and asking for a type never defines
+        // anything so its symbol table is thrown away
+        match self.parse(src) {
+            Ok(parser) => match parser.get_expr() {
+                Some(expr) => format!("{}\n", expr.get_white_type()),
+                None => String::from(":type expects an expression\n"),
+            },
+            Err(rendered) => rendered,
+        }
+    }
+
+    /// `:env`, list the globals along with their types and values
+    fn env(&mut self) -> String {
+        let mut listing = String::new();
+        for name in self.st.get_global_names() {
+            if name.contains('.') {
+                continue; // struct fields and methods show up under their struct
+            }
+            if let Some(fds) = self.st.get_function(name.clone()) {
+                let args = fds
+                    .arg_names
+                    .iter()
+                    .zip(fds.arg_types.iter())
+                    .map(|(arg, typ)| format!("{}: {}", arg, typ))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                listing.push_str(
+                    format!("fn {}({}) : {}\n", name, args, fds.get_return_type()).as_str(),
+                );
+            } else if self.st.get_struct(name.clone()).is_some() {
+                listing.push_str(format!("struct {}\n", name).as_str());
+            } else if let Some(typ) = self.st.get_symbol_type(name.clone()) {
+                let value = match self.runtime.get_value(name.clone()) {
+                    Ok(eval) => Program::try_print_output(&eval),
+                    Err(_) => String::from("?"),
+                };
+                listing.push_str(format!("{}: {} = {}\n", name, typ, value).as_str());
+            }
+        }
+        listing
+    }
+
+    /// Whether or not every `(`, `[` and `{` in `src` has been closed, ignoring strings and comments
+    fn is_complete(src: &str) -> bool {
+        let mut depth: isize = 0;
+        let mut chars = src.chars().peekable();
+        let mut prev = ' ';
+        while let Some(c) = chars.next() {
+            let token_start = !prev.is_alphanumeric() && prev != '_';
+            prev = c;
+            match c {
+                'r' if token_start && matches!(chars.peek(), Some('"' | '#')) => {
+                    // r"raw string" or r#"raw string"#, it ends at a quote followed by the #s
+                    let mut hashes = 0;
+                    while chars.next_if_eq(&'#').is_some() {
+                        hashes += 1;
+                    }
+                    if chars.next_if_eq(&'"').is_none() {
+                        continue; // just an `r`
+                    }
+                    while let Some(c) = chars.next() {
+                        if c == '"' && (0..hashes).all(|_| chars.next_if_eq(&'#').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                '"' => {
+                    // skip over the string, minding escaped quotes
+                    while let Some(c) = chars.next() {
+                        if c == '\\' {
+                            chars.next();
+                        } else if c == '"' {
+                            break;
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => {
+                    for c in chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                '/' if chars.next_if_eq(&'*').is_some() => {
+                    // block comments don't nest, the first */ ends them
+                    while let Some(c) = chars.next() {
+                        if c == '*' && chars.next_if_eq(&'/').is_some() {
+                            break;
+                        }
+                    }
+                }
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        depth <= 0
+    }
+}
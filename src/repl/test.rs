@@ -0,0 +1,78 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use crate::repl::Repl;
+
+    #[test]
+    fn test_repl_prints_bare_expressions() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval("1 + 2"), "3\n");
+        assert_eq!(repl.eval("\"hi\""), "hi\n");
+        assert_eq!(repl.eval("[1, 2]"), "[1, 2]\n");
+    }
+
+    #[test]
+    fn test_repl_keeps_state_between_lines() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval("let x = 10;"), "");
+        assert_eq!(repl.eval("x = x + 1;"), "");
+        assert_eq!(repl.eval("x * 2"), "22\n");
+        assert_eq!(
+            repl.eval("fn double(y: int) : int {\n  return y * 2;\n}"),
+            ""
+        );
+        assert_eq!(repl.eval("double(x)"), "22\n");
+        assert_eq!(repl.eval("print(double(2));"), "4\n");
+    }
+
+    #[test]
+    fn test_repl_multi_line_input() {
+        assert!(!Repl::is_complete("fn foo() {"));
+        assert!(!Repl::is_complete("while (true) {\n  print(\"}\");"));
+        assert!(Repl::is_complete("while (true) {\n  print(\"}\");\n}"));
+        assert!(Repl::is_complete("let x = 1; // {"));
+        assert!(Repl::is_complete("let x = 1; /* { */"));
+        assert!(!Repl::is_complete("/* } */ fn foo() {"));
+        assert!(Repl::is_complete("let s = r\"{\";"));
+        assert!(Repl::is_complete("let s = r#\"say \"{\"\"#;"));
+        assert!(!Repl::is_complete("let bar = [r\n"));
+    }
+
+    #[test]
+    fn test_repl_type_command() {
+        let mut repl = Repl::new();
+        repl.eval("let x = 1.5;");
+        assert_eq!(repl.eval(":type x"), "float\n");
+        assert_eq!(repl.eval(":type [1, 2, 3]"), "list<int>\n");
+        assert_eq!(repl.eval(":type x > 1.0"), "bool\n");
+    }
+
+    #[test]
+    fn test_repl_env_and_reset() {
+        let mut repl = Repl::new();
+        repl.eval("let x = 1;");
+        repl.eval("fn foo(a: int) : int { return a; }");
+        assert_eq!(repl.eval(":env"), "fn foo(a: int) : int\nx: int = 1\n");
+        assert_eq!(repl.eval(":reset"), "environment cleared\n");
+        assert_eq!(repl.eval(":env"), "");
+        assert!(repl
+            .eval("x")
+            .starts_with("error: cannot find `x` in this scope"));
+    }
+
+    #[test]
+    fn test_repl_errors_do_not_define_anything() {
+        let mut repl = Repl::new();
+        assert!(repl.eval("let x : string = 10;").starts_with("error:"));
+        assert!(repl.eval("x").starts_with("error:"));
+        assert_eq!(
+            repl.eval("1 / 0"),
+            "[RUNTIME ERROR] attempt to divide by zero\nstack trace:\n    at <main>\n"
+        );
+        assert_eq!(repl.eval("2"), "2\n");
+        // a line that fails while it runs doesn't define anything either
+        assert!(repl.eval("let bad = 1 / 0;").starts_with("[RUNTIME ERROR]"));
+        assert!(repl.eval("bad").starts_with("error: cannot find `bad`"));
+        assert_eq!(repl.eval(":env"), "");
+    }
+}
@@ -1,15 +1,82 @@
-use crate::parser::expression::identifierexpression::IdentifierExpression;
-use crate::parser::expression::stringliteralexpression::StringLiteralExpression;
-use crate::parser::expression::syntaxerrorexpression::SyntaxErrorExpression;
-use crate::parser::parser_traits::Expression;
+use crate::config::{WhiteLangInt, MAX_CALL_DEPTH};
 use crate::parser::statement::functiondefinitionstatement::FunctionDefinitionStatement;
 use crate::parser::statement::structdefinitionstatement::StructDefinitionStatement;
 use crate::parser::whitetypes::Type;
-use std::any::Any;
+use crate::value::Value;
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 
+pub(crate) mod intrinsics;
 mod test;
 
+/// Errors that can happen while a white-lang program is running
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeError {
+    DivisionByZero,                       // integer division or modulo by zero
+    TypeMismatch(String),                 // an operator got values it can't work with
+    UnknownFunction(String),              // calling a function the runtime doesn't know about
+    UnknownVariable(String),              // reading a variable that isn't in any scope
+    UnknownStruct(String),                // instantiating a struct that was never defined
+    UnknownField(String, String),         // the struct, and the field it doesn't have
+    IndexOutOfRange(WhiteLangInt, usize), // the index, and the length of the list
+    ReversedSlice(String),                // the range of a slice that starts after it ends
+    EmptyList(String),                    // the list method that needs an element to work with
+    MissingKey(String),                   // looking up a key that isn't in a map
+    UnknownIntrinsic(String),             // calling an intrinsic that doesn't exist
+    ZeroStep,                             // a range that would never get anywhere
+    NegativeExponent(WhiteLangInt),       // raising an int to a negative power
+    InvalidShift(WhiteLangInt),           // shifting an int by less than 0 or more than 63 bits
+    Overflow(String),                     // int arithmetic whose result doesn't fit in an int
+    StackOverflow,                        // calls nested deeper than `MAX_CALL_DEPTH`
+    SyntaxError,                          // evaluating something the parser couldn't make sense of
+}
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use RuntimeError::*;
+        match self {
+            DivisionByZero => write!(f, "attempt to divide by zero"),
+            TypeMismatch(msg) => write!(f, "type mismatch: {}", msg),
+            UnknownFunction(name) => write!(f, "cannot find function `{}`", name),
+            UnknownVariable(name) => write!(f, "cannot find value `{}`", name),
+            UnknownStruct(name) => write!(f, "cannot find struct `{}`", name),
+            UnknownField(name, field) => write!(f, "no field `{}` on struct `{}`", field, name),
+            IndexOutOfRange(index, len) => write!(
+                f,
+                "index out of range: the len is {} but the index is {}",
+                len, index
+            ),
+            ReversedSlice(range) => write!(f, "slice start is after its end: `{}`", range),
+            EmptyList(method) => write!(f, "cannot `{}` from an empty list", method),
+            MissingKey(key) => write!(f, "the key `{}` is not in the map", key),
+            UnknownIntrinsic(name) => write!(f, "no such intrinsic `{}`", name),
+            ZeroStep => write!(f, "the step of a range cannot be zero"),
+            NegativeExponent(exponent) => write!(
+                f,
+                "cannot raise an `int` to the negative power {}, use a `float`",
+                exponent
+            ),
+            InvalidShift(bits) => write!(f, "cannot shift an `int` by {} bits", bits),
+            Overflow(operation) => write!(f, "`{}` overflows an `int`", operation),
+            StackOverflow => write!(
+                f,
+                "stack overflow: calls nested over {} deep",
+                MAX_CALL_DEPTH
+            ),
+            SyntaxError => write!(f, "tried to run a syntax error"),
+        }
+    }
+}
+
+/// Describe a runtime error along with a white-lang stack trace, innermost call first
+pub fn report_error(error: &RuntimeError, trace: &[String]) -> String {
+    let mut report = format!("[RUNTIME ERROR] {}\nstack trace:\n", error);
+    for frame in trace {
+        report.push_str(format!("    at {}\n", frame).as_str());
+    }
+    report
+}
+
 struct Intrinsic {
     name: Name,
     _return_type: Type, // these fields are mostly for symbolic posturing, just so we know what happens here
@@ -28,72 +95,141 @@ impl Intrinsic {
 type Name = String;
 
 pub struct Runtime {
-    scopes: Vec<HashMap<String, Box<dyn Expression>>>,
+    scopes: Vec<HashMap<String, Value>>,
     ids: Vec<String>,
+    frames: Vec<usize>, // where the scopes of each function call start, a call only sees its own and the globals
     functions: HashMap<Name, FunctionDefinitionStatement>,
     structs: HashMap<Name, StructDefinitionStatement>,
     intrinsics: HashMap<Name, Intrinsic>,
-    ret: Box<dyn Expression>,
+    ret: Option<Value>,
     pub(crate) output: String,
     brk: bool,
+    cont: bool, // skip the rest of the innermost loop's body
     __self: String,
+    call_stack: Vec<String>, // the functions we are currently in, for stack traces
 }
 impl Runtime {
     pub fn new() -> Self {
-        Runtime {
+        let mut runtime = Runtime {
             scopes: vec![HashMap::new()],
             ids: vec![String::from("global")],
+            frames: vec![],
             functions: HashMap::new(),
             structs: HashMap::new(),
             intrinsics: HashMap::new(),
-            ret: Box::new(SyntaxErrorExpression::new()),
+            ret: None,
             output: String::new(),
             brk: false,
+            cont: false,
             __self: String::new(),
-        }
+            call_stack: vec![],
+        };
+        runtime.register_intrinsics();
+        runtime
     }
 
     /// Register some hard-coded intrinsics for basic whitetypes
-    #[allow(unused_variables)]
     pub fn register_intrinsics(&mut self) {
         // strings
-        let string_to_list = Intrinsic::new(
-            "String.to_list".to_string(),
-            Type::ListString,
-            HashMap::from([("s".to_string(), Type::String)]),
-        );
+        let string_methods = [
+            ("len", Type::Integer, vec![]),
+            ("upper", Type::String, vec![]),
+            ("lower", Type::String, vec![]),
+            ("trim", Type::String, vec![]),
+            (
+                "split",
+                Type::String.get_list_type(),
+                vec![("separator", Type::String)],
+            ),
+            ("contains", Type::Boolean, vec![("part", Type::String)]),
+            ("starts_with", Type::Boolean, vec![("part", Type::String)]),
+            ("ends_with", Type::Boolean, vec![("part", Type::String)]),
+            (
+                "replace",
+                Type::String,
+                vec![("from", Type::String), ("to", Type::String)],
+            ),
+            ("find", Type::Integer, vec![("part", Type::String)]),
+            (
+                "substring",
+                Type::String,
+                vec![("start", Type::Integer), ("end", Type::Integer)],
+            ),
+            ("chars", Type::String.get_list_type(), vec![]),
+        ];
+        for (method, return_type, args) in string_methods {
+            let args = args
+                .into_iter()
+                .map(|(arg, typ)| (arg.to_string(), typ))
+                .collect();
+            let intrinsic = Intrinsic::new(format!("string.{}", method), return_type, args);
+            self.intrinsics.insert(intrinsic.name.clone(), intrinsic);
+        }
 
-        self.intrinsics.insert(string_to_list.name.clone(), string_to_list);
+        // lists, `object` stands in for the type of the elements, see `SymbolTable::get_intrinsic`
+        let list_methods = [
+            ("len", Type::Integer, vec![]),
+            ("push", Type::Void, vec!["value"]),
+            ("pop", Type::Object, vec![]),
+            ("insert", Type::Void, vec!["index", "value"]),
+            ("remove", Type::Object, vec!["index"]),
+            ("contains", Type::Boolean, vec!["value"]),
+            ("reverse", Type::Void, vec![]),
+            ("sort", Type::Void, vec![]),
+        ];
+        for (method, return_type, args) in list_methods {
+            let args = args
+                .into_iter()
+                .map(|arg| {
+                    let typ = if arg == "index" {
+                        Type::Integer
+                    } else {
+                        Type::Object
+                    };
+                    (arg.to_string(), typ)
+                })
+                .collect();
+            let intrinsic = Intrinsic::new(format!("list.{}", method), return_type, args);
+            self.intrinsics.insert(intrinsic.name.clone(), intrinsic);
+        }
+
+        // maps, `object` stands in for the types of the keys and values here too
+        let map_methods = [
+            ("len", Type::Integer, vec![]),
+            ("keys", Type::Object.get_list_type(), vec![]),
+            ("values", Type::Object.get_list_type(), vec![]),
+            ("contains_key", Type::Boolean, vec!["key"]),
+            ("remove", Type::Object, vec!["key"]),
+        ];
+        for (method, return_type, args) in map_methods {
+            let args = args
+                .into_iter()
+                .map(|arg| (arg.to_string(), Type::Object))
+                .collect();
+            let intrinsic = Intrinsic::new(format!("map.{}", method), return_type, args);
+            self.intrinsics.insert(intrinsic.name.clone(), intrinsic);
+        }
     }
 
     pub fn has_intrisic(&self, name: Name) -> bool {
         self.intrinsics.contains_key(&name)
     }
 
-    pub fn handle_intrinsic(&mut self, name: Name, args: HashMap<Name, Box<dyn Expression>>) -> Box<dyn Expression> {
-        match name.as_str() {
-            "String.to_list" => {
-                let mut expr = args.get(&"s".to_string()).unwrap().clone();
-                let to_list = if let Some(string) = expr.to_any().downcast_ref::<StringLiteralExpression>() {
-                    string.to_list_literal()
-                } else if let Some(identifier) = expr.to_any_mut().downcast_mut::<IdentifierExpression>() {
-                    if identifier.get_white_type() != Type::String {
-                        panic!("Identifier: {:?} is not a string!", identifier);
-                    }
-                    let eval = identifier.evaluate(self);
-                    let the_string = eval.downcast_ref::<String>().expect("Evaluated an identifier that I thought was a string but turns out it's not. What gives?");
-                    StringLiteralExpression::new(the_string.clone()).to_list_literal()
-                } else {
-                    panic!("Called intrinsic String.to_list without a string argument")
-                };
-                Box::new(to_list)
-            }
-
-            _ => panic!("No such intrinsic: {}", name),
+    /// Call the intrinsic `name` on `receiver`, the ones that change their receiver, like
+    /// `list.push`, change `receiver`
+    pub fn handle_intrinsic(
+        &mut self,
+        name: Name,
+        receiver: &mut Value,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        if !self.has_intrisic(name.clone()) {
+            return Err(RuntimeError::UnknownIntrinsic(name));
         }
+        intrinsics::call(name.as_str(), receiver, args)
     }
 
-    pub fn get_value(&mut self, name: Name) -> Option<Box<dyn Any + '_>> {
+    pub fn get_value(&mut self, name: Name) -> Result<Value, RuntimeError> {
         if self.has_intrisic(name.clone()) {
             // TODO: arg passing?
         }
@@ -102,52 +238,71 @@ impl Runtime {
             format!("[RUNTIME] Searching for value: {}", name),
             log_runtime_debug,
         );
-        for (i, scope) in self.scopes.iter_mut().rev().enumerate() {
+        for i in self.visible_scopes() {
             crate::LOGGER.debug(
                 format!("[RUNTIME] scope: sid.{}", self.ids[i]),
                 log_runtime_debug,
             );
-            for (name, expr) in scope.iter() {
-                crate::LOGGER.debug(
-                    format!("-- {} \t\t=> {}", name, expr.debug()),
-                    log_runtime_debug,
-                );
+            for (name, value) in self.scopes[i].iter() {
+                crate::LOGGER.debug(format!("-- {} \t\t=> {:?}", name, value), log_runtime_debug);
             }
-            if scope.contains_key(&name) {
-                let val = scope.remove(&name).unwrap();
+            if let Some(value) = self.scopes[i].get(&name) {
                 crate::LOGGER.debug(
-                    format!("[RUNTIME] got {} value: {:?}", name, val),
+                    format!("[RUNTIME] got {} value: {:?}", name, value),
                     log_runtime_debug,
                 );
-                scope.insert(name.clone(), val.clone());
-                return Some(val.evaluate(self));
+                return Ok(value.clone());
             }
         }
-        None
+        if self.functions.contains_key(&name) {
+            return Ok(Value::Function(name));
+        }
+        Err(RuntimeError::UnknownVariable(name))
+    }
+
+    /// The scopes the current function call can see, innermost first: its own and then the globals
+    fn visible_scopes(&self) -> Vec<usize> {
+        let base = self.frames.last().copied().unwrap_or(0);
+        let mut visible: Vec<usize> = (base..self.scopes.len()).rev().collect();
+        if base > 0 {
+            visible.push(0);
+        }
+        visible
     }
 
-    pub fn set_value(&mut self, name: String, value: Box<dyn Expression>) {
+    /// Assign to the nearest variable called `name` the current call can see, see `declare_value`
+    pub fn set_value(&mut self, name: String, value: Value) {
         crate::LOGGER.debug(
             format!("[RUNTIME] Setting {} -> {:?}", name, value),
             crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
         );
-        for (i, scope) in self.scopes.iter_mut().enumerate() {
-            if scope.contains_key(&name) {
-                crate::LOGGER.debug(
-                    format!("[RUNTIME] Setting {} in {}", name, self.ids[i]),
-                    crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
-                );
-                scope.insert(name.clone(), value.clone());
-            }
-        }
+        let idx = self
+            .visible_scopes()
+            .into_iter()
+            .find(|i| self.scopes[*i].contains_key(&name))
+            .unwrap_or(self.scopes.len() - 1); // the type checker makes sure it's been declared
         crate::LOGGER.debug(
-            format!("[RUNTIME] Setting {} in {}", name, self.ids.last().unwrap()),
+            format!("[RUNTIME] Setting {} in {}", name, self.ids[idx]),
             crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
         );
-        self.scopes.last_mut().unwrap().insert(name.clone(), value);
+        self.scopes[idx].insert(name, value);
     }
 
-    pub fn set_value_in_scope(&mut self, id: String, name: String, value: Box<dyn Expression>) {
+    /// Declare `name` in the innermost scope, shadowing anything else called `name`
+    pub fn declare_value(&mut self, name: String, value: Value) {
+        crate::LOGGER.debug(
+            format!(
+                "[RUNTIME] Declaring {} in {} -> {:?}",
+                name,
+                self.ids.last().unwrap(),
+                value
+            ),
+            crate::RUNTIME_DEBUG_LOGGING_ENABLED.with(|cell| !cell.get()),
+        );
+        self.scopes.last_mut().unwrap().insert(name, value);
+    }
+
+    pub fn set_value_in_scope(&mut self, id: String, name: String, value: Value) {
         let idx = match self.ids.iter().position(|id_| id_.clone() == id) {
             Some(idx) => idx,
             None => {
@@ -162,24 +317,25 @@ impl Runtime {
         self.functions.insert(name, fds);
     }
 
-    pub fn get_function(&mut self, name: String) -> FunctionDefinitionStatement {
-        if !self.functions.contains_key(&name) {
-            crate::LOGGER.error(format!("function: `{}` not in the functions map", name));
-            unreachable!();
+    pub fn get_function(
+        &mut self,
+        name: String,
+    ) -> Result<FunctionDefinitionStatement, RuntimeError> {
+        match self.functions.get(&name) {
+            Some(fds) => Ok(fds.clone()),
+            None => Err(RuntimeError::UnknownFunction(name)),
         }
-        self.functions.get(&name).unwrap().clone()
     }
 
     pub fn add_struct(&mut self, name: String, sds: StructDefinitionStatement) {
         self.structs.insert(name.clone(), sds.clone());
     }
 
-    pub fn get_struct(&self, name: String) -> StructDefinitionStatement {
-        if !self.structs.contains_key(&name) {
-            crate::LOGGER.error(format!("struct: `{}` not in the structs map", name));
-            unreachable!(); // need this here to get compiler to heck off
+    pub fn get_struct(&self, name: String) -> Result<StructDefinitionStatement, RuntimeError> {
+        match self.structs.get(&name) {
+            Some(sds) => Ok(sds.clone()),
+            None => Err(RuntimeError::UnknownStruct(name)),
         }
-        self.structs.get(&name).unwrap().clone()
     }
 
     pub fn push_scope(&mut self, typ: String) {
@@ -192,21 +348,66 @@ impl Runtime {
         self.ids.pop();
     }
 
-    pub fn set_return(&mut self, ret: Box<dyn Expression>) {
-        self.ret = ret;
+    /// Push the scope of a function call, which can't see the scopes of its caller
+    pub fn push_frame(&mut self, id: String) {
+        self.frames.push(self.scopes.len());
+        self.push_scope(id);
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.pop_scope();
+        self.frames.pop();
     }
 
-    pub fn has_return(&mut self) -> bool {
-        if let Some(_) = self.ret.to_any().downcast_ref::<SyntaxErrorExpression>() {
-            return false;
+    /// Return statements evaluate their value up front, before the function's scope goes away
+    pub fn set_return(&mut self, ret: Value) {
+        self.ret = Some(ret);
+    }
+
+    pub fn has_return(&self) -> bool {
+        self.ret.is_some()
+    }
+
+    /// Take the return value out of the return slot, functions without one return unit
+    pub fn get_return(&mut self) -> Value {
+        self.ret.take().unwrap_or(Value::Unit)
+    }
+
+    /// Whether or not the statements in a block should stop running, i.e. we hit a
+    /// `break`, a `continue` or a `return`
+    pub fn should_unwind(&self) -> bool {
+        self.brk || self.cont || self.has_return()
+    }
+
+    /// Runs out of room past `MAX_CALL_DEPTH` calls, before the native stack does
+    pub fn push_call(&mut self, name: String) -> Result<(), RuntimeError> {
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow);
         }
-        return true;
+        self.call_stack.push(name);
+        Ok(())
+    }
+    pub fn pop_call(&mut self) {
+        self.call_stack.pop();
     }
 
-    pub fn get_return(&mut self) -> Box<dyn Any> {
-        let ret = self.ret.clone(); // get the return value
-        self.ret = Box::new(SyntaxErrorExpression::new()); // clear the return slot
-        ret.evaluate(self)
+    /// Describe a runtime error along with the white-lang stack trace, see `take_stack_trace`
+    pub fn report(&mut self, error: &RuntimeError) -> String {
+        report_error(error, &self.take_stack_trace())
+    }
+
+    /// The white-lang stack trace at the point an error was raised, innermost call first.
+    /// Clears the call stack (and any scopes the failed calls left behind)
+    pub fn take_stack_trace(&mut self) -> Vec<String> {
+        let mut trace: Vec<String> = self.call_stack.drain(..).rev().collect();
+        trace.push(String::from("<main>"));
+        self.scopes.truncate(1);
+        self.ids.truncate(1);
+        self.frames.clear();
+        self.ret = None;
+        self.brk = false;
+        self.cont = false;
+        trace
     }
 
     pub fn push_output(&mut self, str: String) {
@@ -214,7 +415,12 @@ impl Runtime {
     }
 
     pub fn get_output(&self) -> String {
-        return self.output.clone();
+        self.output.clone()
+    }
+
+    /// Take everything printed since the last call
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
     }
 
     pub fn has_symbol(&self, name: String) -> bool {
@@ -232,6 +438,12 @@ impl Runtime {
     pub fn get_break(&self) -> bool {
         self.brk
     }
+    pub fn set_continue(&mut self, cont: bool) {
+        self.cont = cont;
+    }
+    pub fn get_continue(&self) -> bool {
+        self.cont
+    }
 
     pub fn set_self(&mut self, name: String) {
         self.__self = name;
@@ -0,0 +1,164 @@
+use crate::config::{WhiteLangInt, WhiteLangMap};
+use crate::parser::expression::indexexpression::{position, slice};
+use crate::runtime::RuntimeError;
+use crate::value::{find_key, Value};
+use std::cmp::Ordering;
+
+/// Run the intrinsic `name`, i.e. `list.push`, on `receiver`. Intrinsics that change their
+/// receiver change it in place, whoever called them decides where it goes from there
+pub(crate) fn call(
+    name: &str,
+    receiver: &mut Value,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    match receiver {
+        Value::String(string) => match name.strip_prefix("string.") {
+            Some(method) => string_method(method, string, args),
+            None => Err(RuntimeError::UnknownIntrinsic(name.to_string())),
+        },
+        Value::List(list) => match name.strip_prefix("list.") {
+            Some(method) => list_method(method, list, args),
+            None => Err(RuntimeError::UnknownIntrinsic(name.to_string())),
+        },
+        Value::Map(map) => match name.strip_prefix("map.") {
+            Some(method) => map_method(method, map, args),
+            None => Err(RuntimeError::UnknownIntrinsic(name.to_string())),
+        },
+        receiver => Err(RuntimeError::TypeMismatch(format!(
+            "cannot call `{}` on `{}`",
+            name,
+            receiver.type_name()
+        ))),
+    }
+}
+
+fn list_method(
+    method: &str,
+    list: &mut Vec<Value>,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let mut args = args.into_iter();
+    let (first, second) = (args.next(), args.next());
+    match (method, first, second) {
+        ("len", None, None) => Ok(Value::Int(list.len() as WhiteLangInt)),
+        ("push", Some(value), None) => {
+            list.push(value);
+            Ok(Value::Unit)
+        }
+        ("pop", None, None) => list
+            .pop()
+            .ok_or(RuntimeError::EmptyList(String::from("pop"))),
+        ("insert", Some(Value::Int(index)), Some(value)) => {
+            // inserting at the length puts it at the end
+            let at = match index {
+                _ if index == list.len() as WhiteLangInt => list.len(),
+                index => position(index, list.len())
+                    .ok_or(RuntimeError::IndexOutOfRange(index, list.len()))?,
+            };
+            list.insert(at, value);
+            Ok(Value::Unit)
+        }
+        ("remove", Some(Value::Int(index)), None) => match position(index, list.len()) {
+            Some(at) => Ok(list.remove(at)),
+            None => Err(RuntimeError::IndexOutOfRange(index, list.len())),
+        },
+        ("contains", Some(value), None) => Ok(Value::Bool(
+            list.iter().any(|item| item.equals(&value) == Some(true)),
+        )),
+        ("reverse", None, None) => {
+            list.reverse();
+            Ok(Value::Unit)
+        }
+        ("sort", None, None) => {
+            let mut unsortable = None;
+            list.sort_by(|a, b| {
+                compare(a, b).unwrap_or_else(|| {
+                    unsortable = Some(a.type_name());
+                    Ordering::Equal
+                })
+            });
+            match unsortable {
+                Some(typ) => Err(RuntimeError::TypeMismatch(format!(
+                    "cannot sort a list of `{}`",
+                    typ
+                ))),
+                None => Ok(Value::Unit),
+            }
+        }
+        (method, ..) => Err(RuntimeError::UnknownIntrinsic(format!("list.{}", method))),
+    }
+}
+
+/// Keys and values come out in the order the keys went in
+fn map_method(
+    method: &str,
+    map: &mut WhiteLangMap<Value, Value>,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let mut args = args.into_iter();
+    Ok(match (method, args.next()) {
+        ("len", None) => Value::Int(map.len() as WhiteLangInt),
+        ("keys", None) => Value::List(map.iter().map(|(key, _)| key.clone()).collect()),
+        ("values", None) => Value::List(map.iter().map(|(_, value)| value.clone()).collect()),
+        ("contains_key", Some(key)) => Value::Bool(find_key(map, &key).is_some()),
+        ("remove", Some(key)) => match find_key(map, &key) {
+            Some(at) => map.remove(at).1,
+            None => return Err(RuntimeError::MissingKey(key.to_string())),
+        },
+        (method, _) => return Err(RuntimeError::UnknownIntrinsic(format!("map.{}", method))),
+    })
+}
+
+/// Strings are made of chars, not bytes, so every index and length here counts chars
+fn string_method(method: &str, string: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut args = args.into_iter();
+    let (first, second) = (args.next(), args.next());
+    let chars = |string: &str| -> Value {
+        Value::List(
+            string
+                .chars()
+                .map(|c| Value::String(c.to_string()))
+                .collect(),
+        )
+    };
+    Ok(match (method, first, second) {
+        ("len", None, None) => Value::Int(string.chars().count() as WhiteLangInt),
+        ("upper", None, None) => Value::String(string.to_uppercase()),
+        ("lower", None, None) => Value::String(string.to_lowercase()),
+        ("trim", None, None) => Value::String(string.trim().to_string()),
+        ("chars", None, None) => chars(string),
+        // splitting on nothing splits between every char, the same as javascript
+        ("split", Some(Value::String(separator)), None) if separator.is_empty() => chars(string),
+        ("split", Some(Value::String(separator)), None) => Value::List(
+            string
+                .split(separator.as_str())
+                .map(|part| Value::String(part.to_string()))
+                .collect(),
+        ),
+        ("contains", Some(Value::String(part)), None) => Value::Bool(string.contains(&part)),
+        ("starts_with", Some(Value::String(part)), None) => Value::Bool(string.starts_with(&part)),
+        ("ends_with", Some(Value::String(part)), None) => Value::Bool(string.ends_with(&part)),
+        ("replace", Some(Value::String(from)), Some(Value::String(to))) => {
+            Value::String(string.replace(&from, &to))
+        }
+        ("find", Some(Value::String(part)), None) => Value::Int(match string.find(&part) {
+            Some(byte) => string[..byte].chars().count() as WhiteLangInt,
+            None => -1,
+        }),
+        ("substring", Some(start), Some(end)) => {
+            return slice(Value::String(string.to_string()), start, end, false)
+        }
+        (method, ..) => return Err(RuntimeError::UnknownIntrinsic(format!("string.{}", method))),
+    })
+}
+
+/// How two elements of a list order, None if they don't
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => Some(a.total_cmp(b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
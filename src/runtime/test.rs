@@ -1,11 +1,21 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod test {
+    use crate::runtime::RuntimeError;
     use crate::*;
 
+    /// Runs `src` on both the runtime and the vm, they should always agree
     fn test_execute(src: &str, expected: &str) {
         let mut core: CoreObjects = CoreObjects::new(src);
-        core.get_program_mut().execute();
+        core.get_program_mut()
+            .execute()
+            .unwrap_or_else(|_| panic!("{}", core.get_program().stderr));
         assert_eq!(core.get_program_mut().stdout.as_str(), expected);
+        let mut core: CoreObjects = CoreObjects::new(src);
+        core.get_program_mut()
+            .execute_vm()
+            .unwrap_or_else(|_| panic!("vm: {}", core.get_program().stderr));
+        assert_eq!(core.get_program().stdout.as_str(), expected, "vm");
     }
 
     #[test]
@@ -78,6 +88,23 @@ mod test {
         test_execute("1.1 != null", "true\n");
     }
 
+    #[test]
+    fn test_lists_and_maps_are_equal_when_their_contents_are() {
+        test_execute("print([1] == [1]); print([1] != [1]);", "true\nfalse\n");
+        test_execute(
+            "print([1, 2] == [1]); print([[1], [2]] == [[1], [2.0]]);",
+            "false\ntrue\n",
+        );
+        test_execute(
+            "print({ \"a\": 1, \"b\": [2] } == { \"b\": [2], \"a\": 1 }); print({ \"a\": 1 } != { \"a\": 2 });",
+            "true\ntrue\n",
+        );
+        test_execute(
+            "let xs: list<int>? = null; print(xs == null); print(xs == []);",
+            "true\nfalse\n",
+        );
+    }
+
     #[test]
     fn test_basic_assignment() {
         test_execute("let x : int = 0; x = 1; print(x);", "1\n");
@@ -106,6 +133,27 @@ mod test {
         test_execute(src, "1\n");
     }
 
+    #[test]
+    fn test_else_if_chains() {
+        let src = "
+        fn sign(x : int) : string {
+            if (x > 0) {
+                return \"positive\";
+            } else if (x < 0) {
+                return \"negative\";
+            } else {
+                return \"zero\";
+            }
+        }
+        print(sign(5));
+        print(sign(-5));
+        print(sign(0));
+        let y = 3;
+        if (y == 1) { print(1); } else if (y == 2) { print(2); } else if (y == 3) { print(3); }
+        if (y == 1) { print(1); } else if (y == 2) { print(2); }";
+        test_execute(src, "positive\nnegative\nzero\n3\n");
+    }
+
     #[test]
     fn test_if_statements_statically_scoped() {
         let mut src = "\
@@ -144,6 +192,35 @@ mod test {
         test_execute(src, "2\n");
     }
 
+    #[test]
+    fn test_calls_only_see_their_own_variables_and_globals() {
+        let src = "
+        let total = 0;
+        let x = 5;
+        fn add(x: int) { total += x; }
+        fn shadow() { let x = 100; add(x); }
+        add(1); shadow();
+        print(total); print(x);";
+        test_execute(src, "101\n5\n");
+    }
+
+    #[test]
+    fn test_recursion_keeps_each_calls_locals() {
+        let src = "
+        fn b() { let t = 2; }
+        fn a() { let t = 1; b(); print(t); }
+        a();";
+        test_execute(src, "1\n");
+        let src = "
+        fn g(n: int): int { n = n - 1; if (n > 0) { g(n); } return n; }
+        print(g(3));";
+        test_execute(src, "2\n");
+        let src = "
+        fn rec(n: int): int { let local = n; if (n > 0) { rec(n - 1); } return local; }
+        print(rec(3));";
+        test_execute(src, "3\n");
+    }
+
     #[test]
     fn test_logical_expression_evaluates() {
         let mut src = "print(true && false);";
@@ -180,6 +257,12 @@ mod test {
         test_execute(src, "1\n");
     }
 
+    #[test]
+    fn test_empty_programs_do_nothing() {
+        test_execute("", "");
+        test_execute("// just a comment\n", "");
+    }
+
     #[test]
     fn test_basic_program() {
         let src = "\
@@ -275,6 +358,167 @@ mod test {
         test_execute(src, "1\n");
     }
 
+    #[test]
+    fn test_continue_skips_to_the_next_iteration() {
+        let src = "
+        for (x in [1, 2, 3, 4]) {
+            if (x == 2) {
+                continue;
+            }
+            print(x);
+        }
+        let y = 0;
+        while (y < 5) {
+            y = y + 1;
+            if (y < 4) { continue; }
+            print(y);
+        }
+        for (a in [1, 2]) {
+            for (b in [1, 2, 3]) {
+                if (b == 2) { continue; }
+                print(a * 10 + b);
+            }
+        }";
+        test_execute(src, "1\n3\n4\n4\n5\n11\n13\n21\n23\n");
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let src = "
+        struct Counter { count: int } implement Counter {
+            fn bump() {
+                self.count += 10;
+                self.count++;
+            }
+        };
+        let x = 7;
+        x += 3; print(x);
+        x -= 1; print(x);
+        x *= 4; print(x);
+        x /= 5; print(x);
+        x %= 4; print(x);
+        x++; print(x);
+        x--; x--; print(x);
+        let f = 1.5;
+        f *= 2; f++; print(f);
+        let c = Counter(count = 1);
+        c.count += 5; c.count--; c.bump(); print(c.count);";
+        test_execute(src, "10\n9\n36\n7\n3\n4\n2\n4\n16\n");
+    }
+
+    #[test]
+    fn test_compound_assignment_evaluates_the_target_once() {
+        let src = "
+        let calls = 0;
+        fn idx(): int { calls++; return 1; }
+        let xs = [1, 2, 3];
+        xs[idx()] += 10;
+        let grid = [[1, 2], [3, 4]];
+        grid[idx()][idx() - 1] *= 5;
+        struct P { x: int, ys: list<int> };
+        let ps = [P(x = 9, ys = [])];
+        ps[idx() - 1].x /= 2;
+        xs[idx()] /= 1 + 1;
+        grid[idx()].push(7);
+        print(xs); print(grid); print(ps[0].x); print(calls);";
+        test_execute(src, "[1, 6, 3]\n[[1, 2], [15, 4, 7]]\n4\n6\n");
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let cases = [
+            ("1 + 2 * 3", "7"),
+            ("(1 + 2) * 3", "9"),
+            ("10 - 4 - 3", "3"),
+            ("64 / 4 / 2", "8"),
+            ("7 - 2 * 3 % 4", "5"),
+            ("2 ** 3 ** 2", "512"),
+            ("-2 ** 2", "-4"),
+            ("2 * -3", "-6"),
+            ("1 + 2 == 3", "true"),
+            ("1 < 2 == true", "true"),
+            ("2 > 1 + 3", "false"),
+            ("true || false && false", "true"),
+            ("false && false || true", "true"),
+            ("1 << 2 + 1", "8"),
+            ("6 & 3 == 2", "true"),
+            ("not true == false", "true"),
+        ];
+        for (expr, expected) in cases {
+            test_execute(
+                format!("print({});", expr).as_str(),
+                format!("{}\n", expected).as_str(),
+            );
+        }
+    }
+
+    #[test]
+    /// the rhs of `&&` and `||` only runs when the lhs doesn't decide the answer
+    fn test_short_circuit() {
+        let src = "
+        fn loud(b: bool) : bool { print(\"loud\"); return b; }
+        print(false && loud(true));
+        print(true || loud(false));
+        print(true and loud(false));
+        print(false or loud(true));
+        let i = 0;
+        print(i != 0 && 10 / i > 1);
+        print(not (i == 0 or 10 / i > 1));
+        ";
+        test_execute(src, "false\ntrue\nloud\nfalse\nloud\ntrue\nfalse\nfalse\n");
+    }
+
+    #[test]
+    fn test_bitwise_and_power_operators() {
+        let src = "
+        print(6 & 3);
+        print(6 | 3);
+        print(6 ^ 3);
+        print(~5);
+        print(1 << 4 + 1);
+        print(-16 >> 2);
+        print(1 | 2 ^ 3 & 6);
+        print(2 ** 3 ** 2);
+        print(2 * 3 ** 2);
+        print(4.0 ** 0.5);
+        print(-7 % 3);
+        print(1 << 40);
+        print(1 << 63);
+        print((-1) ** 10000000001);
+        print(2 ** 62);";
+        test_execute(
+            src,
+            "2\n7\n5\n-6\n32\n-4\n1\n512\n18\n2\n-1\n1099511627776\n-9223372036854775808\n-1\n4611686018427387904\n",
+        );
+    }
+
+    #[test]
+    fn test_ranges() {
+        let src = "
+        for (x in 0..3) { print(x); }
+        for (x in 1..=3) { print(x); }
+        for (x in 10..0 step -4) { print(x); }
+        for (x in 0..=6 step 3) {
+            if (x == 3) { continue; }
+            print(x);
+        }
+        for (x in 3..0) { print(x); }
+        print(2..5);";
+        test_execute(src, "0\n1\n2\n1\n2\n3\n10\n6\n2\n0\n6\n[2, 3, 4]\n");
+    }
+
+    #[test]
+    fn test_ranges_stop_at_the_largest_int() {
+        let src = "
+        for (i in 9223372036854775806..=9223372036854775807) { print(i); }
+        for (i in 0..9223372036854775807 step 5000000000000000000) { print(i); }
+        for (i in -9223372036854775807..=-9223372036854775807 - 1 step -1) { print(i); }";
+        test_execute(
+            src,
+            "9223372036854775806\n9223372036854775807\n0\n5000000000000000000\n-9223372036854775807\n-9223372036854775808\n",
+        );
+    }
+
     #[test]
     fn test_stucts_work() {
         let src = "\
@@ -291,4 +535,606 @@ mod test {
         print(myVec.display());";
         test_execute(src, "2\n3\n");
     }
+
+    #[test]
+    fn test_struct_instances_have_their_own_fields() {
+        let src = "
+        struct Vec2D { x: int, y: int };
+        let a = Vec2D(x = 1, y = 2);
+        let b = Vec2D(x = 3, y = 4);
+        a.x = 10;
+        print(a.x);
+        print(b.x);
+        let c = a;
+        c.y = 20;
+        print(a.y);";
+        test_execute(src, "10\n3\n20\n");
+    }
+
+    #[test]
+    fn test_printing_a_struct_shows_its_fields() {
+        let src = "
+        struct P { y: string, x: int };
+        struct Empty {};
+        struct Node { value: int, next: Node? };
+        print(P(x = 3, y = \"a\"));
+        print([Empty()]);
+        let n = Node(value = 1, next = null);
+        n.next = Node(value = 2, next = null);
+        print(n);
+        n.next = n;
+        print(n);";
+        test_execute(
+            src,
+            "P { x: 3, y: a }\n[Empty {}]\nNode { next: Node { next: null, value: 2 }, value: 1 }\nNode { next: ..., value: 1 }\n",
+        );
+    }
+
+    #[test]
+    fn test_methods_can_construct_their_own_struct() {
+        let src = "
+        struct V { x: int, y: int } implement V {
+            fn swapped(): V {
+                return V(x = self.y, y = self.x);
+            }
+        };
+        let v = V(x = 1, y = 2).swapped();
+        print(v.x);
+        print(v.y);";
+        test_execute(src, "2\n1\n");
+    }
+
+    #[test]
+    /// lists and maps are values, storing one anywhere stores a copy
+    fn test_lists_and_maps_are_copied_when_stored() {
+        let src = "
+        let a = [1, 2];
+        let b = a;
+        b.push(3);
+        fn grow(xs: list<int>) { xs.push(9); }
+        grow(a);
+        struct Bag { items: list<int> };
+        let bag = Bag(items = a);
+        bag.items.push(4);
+        let grid = [a, a];
+        grid[0].push(5);
+        for (row in grid) { row.push(6); }
+        for (x in a) { a.push(x); }
+        print(a.len());
+        print(b.len());
+        print(bag.items.len());
+        print(grid[0].len());
+        print(grid[1].len());
+        let m = { \"k\": [1] };
+        let n = m;
+        n[\"k\"].push(2);
+        m[\"k\"].push(3);
+        for (key in m) { m[\"{key}!\"] = []; }
+        print(m[\"k\"].len());
+        print(n[\"k\"].len());
+        print(m.len());";
+        test_execute(src, "4\n3\n3\n3\n2\n2\n2\n2\n");
+    }
+
+    #[test]
+    fn test_methods_bind_self_to_the_receiver() {
+        let src = "
+        struct Counter { count: int } implement Counter {
+            fn increment(by : int) {
+                self.count = self.count + by;
+            }
+            fn twice(by : int) : int {
+                self.increment(by);
+                self.increment(by);
+                return self.count;
+            }
+        };
+        let a = Counter(count = 0);
+        let b = Counter(count = 100);
+        a.increment(1);
+        print(a.twice(2));
+        print(b.twice(5));
+        print(a.count);";
+        test_execute(src, "5\n110\n5\n");
+    }
+
+    #[test]
+    fn test_for_statement_nested_lists() {
+        let src = "
+        for (pair in [[1, 2], [3, 4]]) {
+            print(pair);
+        }";
+        test_execute(src, "[1, 2]\n[3, 4]\n");
+    }
+
+    #[test]
+    fn test_variables_hold_values_not_expressions() {
+        let src = "
+        let x = 1;
+        let y = x + 1;
+        x = 10;
+        print(y);";
+        test_execute(src, "2\n");
+    }
+
+    fn test_execute_error(src: &str, expected: RuntimeError, expected_stderr: &str) {
+        let mut core: CoreObjects = CoreObjects::new(src);
+        assert_eq!(core.get_program_mut().execute(), Err(expected.clone()));
+        assert_eq!(core.get_program().stderr.as_str(), expected_stderr);
+        let mut core: CoreObjects = CoreObjects::new(src);
+        assert_eq!(core.get_program_mut().execute_vm(), Err(expected), "vm");
+        assert_eq!(core.get_program().stderr.as_str(), expected_stderr, "vm");
+    }
+
+    #[test]
+    fn test_division_by_zero_is_an_error() {
+        test_execute_error(
+            "1 / 0",
+            RuntimeError::DivisionByZero,
+            "[RUNTIME ERROR] attempt to divide by zero\nstack trace:\n    at <main>\n",
+        );
+    }
+
+    #[test]
+    fn test_int_overflow_is_an_error() {
+        let max = "let max = 9223372036854775807; let min = -max - 1;";
+        for (src, operation) in [
+            ("print(max + 1);", "9223372036854775807 + 1"),
+            ("print(min - 1);", "-9223372036854775808 - 1"),
+            ("print(max * 2);", "9223372036854775807 * 2"),
+            ("print(min / -1);", "-9223372036854775808 / -1"),
+            ("print(min % -1);", "-9223372036854775808 % -1"),
+            ("print(-min);", "-(-9223372036854775808)"),
+            ("let x = max; x++;", "9223372036854775807 + 1"),
+            ("let x = min; x -= 1;", "-9223372036854775808 - 1"),
+        ] {
+            test_execute_error(
+                format!("{} {}", max, src).as_str(),
+                RuntimeError::Overflow(operation.to_string()),
+                format!(
+                    "[RUNTIME ERROR] `{}` overflows an `int`\nstack trace:\n    at <main>\n",
+                    operation
+                )
+                .as_str(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_zero_step_is_an_error() {
+        test_execute_error(
+            "let s = 0; for (x in 0..10 step s) { print(x); }",
+            RuntimeError::ZeroStep,
+            "[RUNTIME ERROR] the step of a range cannot be zero\nstack trace:\n    at <main>\n",
+        );
+    }
+
+    #[test]
+    fn test_list_indexing() {
+        let src = "
+        let xs = [1, 2, 3, 4, 5];
+        print(xs[0] + xs[-1]);
+        print(xs[1..3]);
+        print(xs[1..=-2]);
+        print(xs[-2..5]);
+        print(xs[2..2]);
+        xs[0] = 10;
+        xs[-1] += 5;
+        print(xs);
+        struct P { xs: list<int> };
+        let p = P(xs = [1, 2]);
+        p.xs[-1] = 9;
+        print(p.xs);
+        print([4, 5, 6][1]);
+        ";
+        test_execute(
+            src,
+            "6\n[2, 3]\n[2, 3, 4]\n[4, 5]\n[]\n[10, 2, 3, 4, 10]\n[1, 9]\n5\n",
+        );
+    }
+
+    #[test]
+    fn test_incrementing_an_element_evaluates_the_index_once() {
+        let src = "
+        let calls = 0;
+        fn idx(): int { calls++; return -1; }
+        let ys = [1, 2, 3];
+        ys[idx()]++;
+        ys[idx()]++;
+        ys[idx() - 1]--;
+        let m = { \"a\": 1 };
+        fn key(): string { calls++; return \"a\"; }
+        m[key()]++;
+        print(ys); print(m); print(calls);";
+        test_execute(src, "[1, 1, 5]\n{a: 2}\n4\n");
+    }
+
+    #[test]
+    fn test_index_out_of_range_is_an_error() {
+        let stderr = |index: i32| {
+            format!(
+                "[RUNTIME ERROR] index out of range: the len is 3 but the index is {}\nstack trace:\n    at <main>\n",
+                index
+            )
+        };
+        test_execute_error(
+            "let xs = [1, 2, 3]; print(xs[3]);",
+            RuntimeError::IndexOutOfRange(3, 3),
+            stderr(3).as_str(),
+        );
+        test_execute_error(
+            "let xs = [1, 2, 3]; xs[-4] = 1;",
+            RuntimeError::IndexOutOfRange(-4, 3),
+            stderr(-4).as_str(),
+        );
+    }
+
+    #[test]
+    fn test_reversed_slices_are_an_error() {
+        test_execute_error(
+            "let xs = [1, 2, 3, 4]; print(xs[3..1]);",
+            RuntimeError::ReversedSlice(String::from("3..1")),
+            "[RUNTIME ERROR] slice start is after its end: `3..1`\nstack trace:\n    at <main>\n",
+        );
+        test_execute_error(
+            "print(\"abc\"[-1..=0]);",
+            RuntimeError::ReversedSlice(String::from("-1..=0")),
+            "[RUNTIME ERROR] slice start is after its end: `-1..=0`\nstack trace:\n    at <main>\n",
+        );
+        // an empty slice isn't reversed
+        test_execute(
+            "let xs = [1, 2, 3, 4]; print(xs[2..2]); print(xs[2..=1]);",
+            "[]\n[]\n",
+        );
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let src = "
+        let grid: list<list<int>> = [[1, 2], [3, 4]];
+        grid[0][1] = 9;
+        grid[1].push(5);
+        print(grid);
+        print(grid[1][-1]);
+        let rows: list<list<string>> = [[]];
+        rows[0].push(\"a\");
+        rows.push([\"b\", \"c\"]);
+        print(rows);
+        fn squares(n: int) : list<list<int>> {
+            let out: list<list<int>> = [];
+            for (i in 0..n) { out.push([i, i * i]); }
+            return out;
+        }
+        print(squares(3));
+        ";
+        test_execute(
+            src,
+            "[[1, 9], [3, 4, 5]]\n5\n[[a], [b, c]]\n[[0, 0], [1, 1], [2, 4]]\n",
+        );
+    }
+
+    #[test]
+    fn test_maps() {
+        let src = "
+        let ages: map<string, int> = { \"ann\": 31, \"bob\": 27 };
+        ages[\"cat\"] = 5;
+        ages[\"ann\"] += 1;
+        print(ages[\"ann\"]);
+        print(ages.len());
+        print(ages.keys());
+        print(ages.values());
+        print(ages.contains_key(\"bob\"));
+        print(ages.remove(\"bob\"));
+        print(ages.contains_key(\"bob\"));
+        for (name in ages) { print(name); }
+        let groups: map<int, list<string>> = { 1: [] };
+        groups[1].push(\"x\");
+        groups[2] = [\"y\"];
+        print(groups);
+        print({ true: 1, true: 2 });
+        ";
+        test_execute(
+            src,
+            "32\n3\n[ann, bob, cat]\n[32, 27, 5]\ntrue\n27\nfalse\nann\ncat\n{1: [x], 2: [y]}\n{true: 2}\n",
+        );
+    }
+
+    #[test]
+    fn test_nullable_types() {
+        let src = "
+        fn find(xs: list<int>, target: int): int? {
+            for (i in 0..xs.len()) {
+                if (xs[i] == target) { return i; }
+            }
+            return null;
+        }
+        fn describe(at: int?): string {
+            if (at == null) { return \"missing\"; }
+            return \"at {at * 10}\";
+        }
+        let xs = [4, 5, 6];
+        print(describe(find(xs, 5)));
+        print(describe(find(xs, 7)));
+        print(find(xs, 7) ?? -1);
+        let a: int? = null;
+        print(a ?? a ?? 3);
+        print(a != null && a > 2);
+        print(a == null || a > 2);
+        let names: list<string?> = [\"ann\", null];
+        for (name in names) { print(name ?? \"nobody\"); }
+        let i = 0;
+        let cur: string? = names[0];
+        while (cur != null) { print(cur.upper()); i += 1; cur = names[i]; }
+        ";
+        test_execute(
+            src,
+            "at 10\nmissing\n-1\n3\nfalse\ntrue\nann\nnobody\nANN\n",
+        );
+    }
+
+    #[test]
+    fn test_forward_references_and_inferred_returns() {
+        let src = "
+        print(fib(10));
+        let list = Node(value = 1, next = Node(value = 2, next = null));
+        print(total(list));
+        print(list.first() * 2);
+        print(is_odd(7));
+        fn fib(n: int) {
+            if (n < 2) { return n; }
+            return fib(n - 1) + fib(n - 2);
+        }
+        fn total(n: Node?): int {
+            if (n == null) { return 0; }
+            return n.value + total(n.next);
+        }
+        fn is_even(n: int): bool { if (n == 0) { return true; } return is_odd(n - 1); }
+        fn is_odd(n: int) { if (n == 0) { return false; } return is_even(n - 1); }
+        fn find(xs: list<int>, target: int) {
+            for (i in 0..xs.len()) {
+                if (xs[i] == target) { return i; }
+            }
+            return null;
+        }
+        print(find([4, 5], 5) ?? -1);
+        print(mk().x);
+        fn mk(): V { return V(x = 1); }
+        struct V { x: int };
+        struct Node {
+            value: int,
+            next: Node?
+        } implement Node {
+            fn first() { return self.value; }
+        };
+        ";
+        test_execute(src, "55\n3\n2\ntrue\n1\n1\n");
+    }
+
+    #[test]
+    fn test_missing_key_is_an_error() {
+        test_execute_error(
+            "let m = { \"a\": 1 }; print(m.remove(\"b\"));",
+            RuntimeError::MissingKey(String::from("b")),
+            "[RUNTIME ERROR] the key `b` is not in the map\nstack trace:\n    at <main>\n",
+        );
+    }
+
+    #[test]
+    fn test_list_intrinsics() {
+        let src = "
+        let xs: list<int> = [];
+        for (i in 0..4) { xs.push(i * i); }
+        print(xs.len());
+        print(xs.pop());
+        xs.insert(0, 7);
+        xs.insert(xs.len(), 2);
+        print(xs);
+        print(xs.remove(-1));
+        print(xs.contains(7));
+        xs.sort();
+        print(xs);
+        xs.reverse();
+        print(xs);
+        struct Bag { items: list<string> };
+        let b = Bag(items = [\"b\"]);
+        b.items.push(\"a\");
+        b.items.sort();
+        print(b.items);
+        fn last(ys: list<int>) : int { return ys.pop(); }
+        let zs = [1, 2, 3];
+        print(last(zs));
+        print(zs);
+        ";
+        test_execute(
+            src,
+            "4\n9\n[7, 0, 1, 4, 2]\n2\ntrue\n[0, 1, 4, 7]\n[7, 4, 1, 0]\n[a, b]\n3\n[1, 2, 3]\n",
+        );
+    }
+
+    #[test]
+    fn test_pop_from_an_empty_list_is_an_error() {
+        test_execute_error(
+            "let xs = [1]; xs.pop(); xs.pop();",
+            RuntimeError::EmptyList(String::from("pop")),
+            "[RUNTIME ERROR] cannot `pop` from an empty list\nstack trace:\n    at <main>\n",
+        );
+    }
+
+    #[test]
+    fn test_string_intrinsics() {
+        let src = "
+        let s = \"  Hello, world!  \".trim();
+        print(s.len());
+        print(s.upper());
+        print(s.lower());
+        print(s.split(\", \").len());
+        print(s.contains(\"wo\") && s.starts_with(\"He\") && s.ends_with(\"!\"));
+        print(s.replace(\"l\", \"L\"));
+        print(s.find(\"wo\"));
+        print(s.find(\"zz\"));
+        print(s.substring(7, -1));
+        print(s.chars());
+        print(s[1]);
+        print(s[-1]);
+        print(s[0..5]);
+        let empty = \"\";
+        print(\"abc\".split(empty));
+        ";
+        test_execute(
+            src,
+            "13\nHELLO, WORLD!\nhello, world!\n2\ntrue\nHeLLo, worLd!\n7\n-1\nworld\n\
+             [H, e, l, l, o, ,,  , w, o, r, l, d, !]\ne\n!\nHello\n[a, b, c]\n",
+        );
+    }
+
+    #[test]
+    /// strings count chars, not bytes
+    fn test_string_intrinsics_are_unicode() {
+        use crate::parser::expression::indexexpression::{index, slice};
+        use crate::runtime::intrinsics::call;
+        use crate::value::Value;
+        let string = |s: &str| Value::String(s.to_string());
+        let mut s = string("héllo 🎉 wörld");
+        assert_eq!(call("string.len", &mut s, vec![]), Ok(Value::Int(13)));
+        assert_eq!(
+            call("string.find", &mut s, vec![string("wö")]),
+            Ok(Value::Int(8))
+        );
+        assert_eq!(
+            call(
+                "string.substring",
+                &mut s,
+                vec![Value::Int(6), Value::Int(7)]
+            ),
+            Ok(string("🎉"))
+        );
+        assert_eq!(
+            call("string.upper", &mut s, vec![]),
+            Ok(string("HÉLLO 🎉 WÖRLD"))
+        );
+        assert_eq!(index(s.clone(), Value::Int(-4)), Ok(string("ö")));
+        assert_eq!(
+            slice(s.clone(), Value::Int(0), Value::Int(2), true),
+            Ok(string("hél"))
+        );
+        assert_eq!(
+            index(s, Value::Int(13)),
+            Err(RuntimeError::IndexOutOfRange(13, 13))
+        );
+    }
+
+    #[test]
+    /// the tokenizer keeps multi byte chars in string literals whole
+    fn test_unicode_string_literals_run() {
+        let src = "
+        let s = \"héllo 🎉 wörld\";
+        print(s.len());
+        print(s.find(\"wö\"));
+        print(s.substring(6, 7));
+        print(s.upper());
+        print(s[-4]);
+        print(s[0..=2]);
+        print(\"ü\".chars());
+        ";
+        test_execute(src, "13\n8\n🎉\nHÉLLO 🎉 WÖRLD\nö\nhél\n[ü]\n");
+        test_execute_error(
+            "let s = \"🎉\"; print(s[1]);",
+            RuntimeError::IndexOutOfRange(1, 1),
+            "[RUNTIME ERROR] index out of range: the len is 1 but the index is 1\nstack trace:\n    at <main>\n",
+        );
+    }
+
+    #[test]
+    fn test_string_escapes_and_interpolation() {
+        let src = r#"
+        let x = 3;
+        let xs = [1, 2];
+        print("a\tb\n\"c\" \\ \{x\} \u{1F389}");
+        print(r"{x}\n");
+        print("x = {x}, x * 2 = {x * 2}, {xs} {"{x}{x}".len()}");
+        fn greet(name: string) : string { return "hi {name}!"; }
+        print(greet("bob"));
+        "#;
+        test_execute(
+            src,
+            "a\tb\n\"c\" \\ {x} 🎉\n{x}\\n\nx = 3, x * 2 = 6, [1, 2] 2\nhi bob!\n",
+        );
+    }
+
+    #[test]
+    fn test_remainder_by_zero_is_an_error() {
+        test_execute_error(
+            "let x = 1; x %= 0;",
+            RuntimeError::DivisionByZero,
+            "[RUNTIME ERROR] attempt to divide by zero\nstack trace:\n    at <main>\n",
+        );
+    }
+
+    #[test]
+    fn test_bad_exponents_and_shifts_are_errors() {
+        test_execute_error(
+            "let e = -1; print(2 ** e);",
+            RuntimeError::NegativeExponent(-1),
+            "[RUNTIME ERROR] cannot raise an `int` to the negative power -1, use a `float`\nstack trace:\n    at <main>\n",
+        );
+        test_execute_error(
+            "print(1 << 64);",
+            RuntimeError::InvalidShift(64),
+            "[RUNTIME ERROR] cannot shift an `int` by 64 bits\nstack trace:\n    at <main>\n",
+        );
+        test_execute_error(
+            "let b = 2; print(b ** 63);",
+            RuntimeError::Overflow(String::from("2 ** 63")),
+            "[RUNTIME ERROR] `2 ** 63` overflows an `int`\nstack trace:\n    at <main>\n",
+        );
+    }
+
+    #[test]
+    fn test_recursing_too_deep_is_a_stack_overflow() {
+        // the runtime recurses for every call, it needs more room than a test thread has
+        let test = std::thread::Builder::new().stack_size(512 * 1024 * 1024);
+        let test = test.spawn(|| {
+            let src = "fn f(n: int): int { if (n == 0) { return 0; } return 1 + f(n - 1); }";
+            let depth = crate::config::MAX_CALL_DEPTH as isize;
+            test_execute(
+                &format!("{} print(f({}));", src, depth - 1),
+                &format!("{}\n", depth - 1),
+            );
+            let src = format!("{} print(f({}));", src, depth);
+            let mut core: CoreObjects = CoreObjects::new(&src);
+            assert_eq!(
+                core.get_program_mut().execute(),
+                Err(RuntimeError::StackOverflow)
+            );
+            let mut core: CoreObjects = CoreObjects::new(&src);
+            assert_eq!(
+                core.get_program_mut().execute_vm(),
+                Err(RuntimeError::StackOverflow)
+            );
+            let stderr = core.get_program().stderr.clone();
+            assert!(
+                stderr.starts_with("[RUNTIME ERROR] stack overflow: calls nested over 2000 deep\n")
+            );
+            assert!(stderr.ends_with("    at f\n    at <main>\n"));
+        });
+        test.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_runtime_error_has_stack_trace() {
+        let src = "
+        fn divide(a : int, b : int) : int { return a / b; }
+        fn half_of_nothing(a : int) : int { return divide(a, 0); }
+        print(1);
+        print(half_of_nothing(2));
+        print(3);";
+        test_execute_error(
+            src,
+            RuntimeError::DivisionByZero,
+            "[RUNTIME ERROR] attempt to divide by zero\nstack trace:\n    at divide\n    at half_of_nothing\n    at <main>\n",
+        );
+        let mut core: CoreObjects = CoreObjects::new(src);
+        let _ = core.get_program_mut().execute();
+        assert_eq!(core.get_program().stdout.as_str(), "1\n"); // output up until the error is kept
+    }
 }
@@ -1,3 +1,4 @@
+use crate::diagnostic::Diagnostic;
 use std::char;
 use std::collections::HashMap;
 use std::fmt;
@@ -8,45 +9,60 @@ use std::fmt::Display;
 pub enum TokenType {
     // all token types that White-Lang implements
     // Types
-    Str,   // "string"
-    Int,   // 123
-    Float, // 123.456
+    Str,       // "string"
+    StrStart,  // "string {
+    StrMiddle, // } string {
+    StrEnd,    // } string"
+    Int,       // 123
+    Float,     // 123.456
     // Syntax
-    LeftParen,    // (
-    RightParen,   // )
-    LeftBracket,  // [
-    RightBracket, // ]
-    LeftBrace,    // {
-    RightBrace,   // }
-    Equal,        // =
-    Bang,         // !
-    Plus,         // +
-    PlusPlus,     // ++
-    PlusEqual,    // +=
-    Minus,        // -
-    MinusMinus,   // --
-    MinusEqual,   // -=
-    Star,         // *
-    Slash,        // /
-    Band,         // & (bitwise and)
-    Land,         // &&
-    Bor,          // | (bitwise or)
-    Lor,          // ||
-    Lnot,         // ~
-    Lxor,         // ^
-    SemiColon,    // ;
-    Colon,        // :
-    EqualEqual,   // ==
-    Comma,        // ,
-    Dot,          // .
-    Greater,      // >
-    Less,         // <
-    BangEqual,    // !=
-    GreaterEqual, // >=
-    LessEqual,    // <=
+    LeftParen,        // (
+    RightParen,       // )
+    LeftBracket,      // [
+    RightBracket,     // ]
+    LeftBrace,        // {
+    RightBrace,       // }
+    Equal,            // =
+    Bang,             // !
+    Plus,             // +
+    PlusPlus,         // ++
+    PlusEqual,        // +=
+    Minus,            // -
+    MinusMinus,       // --
+    MinusEqual,       // -=
+    Star,             // *
+    StarStar,         // **
+    StarEqual,        // *=
+    Slash,            // /
+    SlashEqual,       // /=
+    Percent,          // %
+    PercentEqual,     // %=
+    Band,             // & (bitwise and)
+    Land,             // &&
+    Bor,              // | (bitwise or)
+    Lor,              // ||
+    Lnot,             // ~
+    Lxor,             // ^
+    SemiColon,        // ;
+    Colon,            // :
+    EqualEqual,       // ==
+    Comma,            // ,
+    Dot,              // .
+    DotDot,           // ..
+    DotDotEqual,      // ..=
+    Greater,          // >
+    GreaterGreater,   // >>
+    Less,             // <
+    LessLess,         // <<
+    BangEqual,        // !=
+    GreaterEqual,     // >=
+    LessEqual,        // <=
+    Question,         // ?
+    QuestionQuestion, // ??
     // Keywords
     Null,       // null
     And,        // and
+    Or,         // or
     Not,        // not
     If,         // if
     While,      // while
@@ -63,6 +79,7 @@ pub enum TokenType {
     True,       // true
     False,      // false
     Break,      // break
+    Continue,   // continue
     Struct,     // struct
     Implement,  // implement
     _Self,      // self
@@ -79,17 +96,103 @@ enum ErrorType {
     // Errors, will probably add more
     UnterminatedString,
     UnexpectedToken,
+    BadEscape,
 }
 
 impl Display for ErrorType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s: String = match self {
-            ErrorType::UnterminatedString => String::from("Unterminated String"),
-            ErrorType::UnexpectedToken => String::from("Unexpected Token"),
+            ErrorType::UnterminatedString => String::from("unterminated string"),
+            ErrorType::UnexpectedToken => String::from("unexpected character"),
+            ErrorType::BadEscape => String::from("unknown escape sequence"),
         };
         write!(f, "{}", s)
     }
 }
+impl TokenType {
+    /// How the token type reads in an error message, i.e. `)` instead of `RightParen`
+    pub fn describe(&self) -> String {
+        let lexeme = match self {
+            TokenType::Str | TokenType::StrStart | TokenType::StrMiddle | TokenType::StrEnd => {
+                return String::from("a string")
+            }
+            TokenType::Int => return String::from("an integer"),
+            TokenType::Float => return String::from("a float"),
+            TokenType::Identifier => return String::from("a name"),
+            TokenType::Eof => return String::from("the end of the file"),
+            TokenType::Error => return String::from("an error"),
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::Equal => "=",
+            TokenType::Bang => "!",
+            TokenType::Plus => "+",
+            TokenType::PlusPlus => "++",
+            TokenType::PlusEqual => "+=",
+            TokenType::Minus => "-",
+            TokenType::MinusMinus => "--",
+            TokenType::MinusEqual => "-=",
+            TokenType::Star => "*",
+            TokenType::StarStar => "**",
+            TokenType::StarEqual => "*=",
+            TokenType::Slash => "/",
+            TokenType::SlashEqual => "/=",
+            TokenType::Percent => "%",
+            TokenType::PercentEqual => "%=",
+            TokenType::Band => "&",
+            TokenType::Land => "&&",
+            TokenType::Bor => "|",
+            TokenType::Lor => "||",
+            TokenType::Lnot => "~",
+            TokenType::Lxor => "^",
+            TokenType::SemiColon => ";",
+            TokenType::Colon => ":",
+            TokenType::EqualEqual => "==",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::DotDot => "..",
+            TokenType::DotDotEqual => "..=",
+            TokenType::Greater => ">",
+            TokenType::GreaterGreater => ">>",
+            TokenType::Less => "<",
+            TokenType::LessLess => "<<",
+            TokenType::BangEqual => "!=",
+            TokenType::GreaterEqual => ">=",
+            TokenType::LessEqual => "<=",
+            TokenType::Question => "?",
+            TokenType::QuestionQuestion => "??",
+            TokenType::Null => "null",
+            TokenType::And => "and",
+            TokenType::Or => "or",
+            TokenType::Not => "not",
+            TokenType::If => "if",
+            TokenType::While => "while",
+            TokenType::For => "for",
+            TokenType::In => "in",
+            TokenType::Else => "else",
+            TokenType::Return => "return",
+            TokenType::Let => "let",
+            TokenType::Print => "print",
+            TokenType::Function => "fn",
+            TokenType::True => "true",
+            TokenType::False => "false",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Struct => "struct",
+            TokenType::Implement => "implement",
+            TokenType::_Self => "self",
+            TokenType::As => "as",
+            TokenType::Arrow => "->",
+            TokenType::GoTo => "goto",
+            TokenType::Extends => "extends",
+            TokenType::Implements => "implements",
+        };
+        format!("`{}`", lexeme)
+    }
+}
 impl Display for TokenType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}, ", self)
@@ -142,6 +245,28 @@ impl Token {
     pub fn get_string_value(&self) -> String {
         self.string_value.clone()
     }
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+    pub fn get_line_offset(&self) -> usize {
+        self.line_offset
+    }
+    // how many characters of source the token covers
+    pub fn get_length(&self) -> usize {
+        self.end - self.start
+    }
+    // what's left of the token once its first character has been used, as a `typ`, e.g. the
+    // second `>` of the `>>` at the end of `list<list<int>>`
+    pub fn rest(&self, typ: TokenType) -> Token {
+        Token::init(
+            typ,
+            self.string_value.chars().skip(1).collect(),
+            self.start + 1,
+            self.end,
+            self.line,
+            self.line_offset + 1,
+        )
+    }
 }
 
 fn init_keywords() -> HashMap<String, TokenType> {
@@ -153,6 +278,7 @@ fn init_keywords() -> HashMap<String, TokenType> {
     keywords.insert("return".to_string(), TokenType::Return);
     // boolean operators
     keywords.insert("and".to_string(), TokenType::And);
+    keywords.insert("or".to_string(), TokenType::Or);
     keywords.insert("not".to_string(), TokenType::Not);
     keywords.insert("false".to_string(), TokenType::False);
     keywords.insert("true".to_string(), TokenType::True);
@@ -164,6 +290,7 @@ fn init_keywords() -> HashMap<String, TokenType> {
     keywords.insert("while".to_string(), TokenType::While);
     keywords.insert("for".to_string(), TokenType::For);
     keywords.insert("break".to_string(), TokenType::Break);
+    keywords.insert("continue".to_string(), TokenType::Continue);
     keywords.insert("else".to_string(), TokenType::Else);
     keywords.insert("in".to_string(), TokenType::In);
     keywords.insert("goto".to_string(), TokenType::GoTo);
@@ -180,14 +307,19 @@ fn init_keywords() -> HashMap<String, TokenType> {
 pub struct Tokenizer {
     // the tokenizer
     token_list: Vec<Token>,               // maintains a list of tokens
-    errors: Vec<ErrorType>,               // associated errors
+    errors: Vec<(ErrorType, Token)>,      // associated errors
     keywords: HashMap<String, TokenType>, // all the keywords in White-Lang
     src: String,                          // the source code
     char_vec: Vec<char>,                  // the source code, but characters
     curr_char: char,                      // the current char we are reading
-    position: usize,                      // position data in the string
+    position: usize,                      // how many chars into the source we are
+    byte_position: usize,                 // how many bytes into the source we are, for slicing src
     line: usize,                          // what line we are on
     line_offset: usize,                   // what the line offset is on the line
+    token_start: usize,                   // where the token we are scanning started
+    token_line: usize,                    // the line the token we are scanning started on
+    token_offset: usize,                  // the line offset the token we are scanning started at
+    interpolations: Vec<usize>, // how many `{`s deep we are in each interpolation we're inside
 }
 impl std::fmt::Display for Tokenizer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -204,11 +336,16 @@ impl Tokenizer {
             errors: vec![],
             keywords: init_keywords(),
             src,
-            char_vec: char_vec,
+            char_vec,
             curr_char: '\0',
             position: 0,
+            byte_position: 0,
             line: 1,
             line_offset: 0,
+            token_start: 0,
+            token_line: 1,
+            token_offset: 0,
+            interpolations: vec![],
         }
     }
 
@@ -221,13 +358,18 @@ impl Tokenizer {
             char_vec: vec![],
             curr_char: '\0',
             position: 0,
+            byte_position: 0,
             line: 1,
             line_offset: 0,
+            token_start: 0,
+            token_line: 1,
+            token_offset: 0,
+            interpolations: vec![],
         }
     }
 
     pub fn set_source(&mut self, src: String) {
-        if self.src.eq("") {
+        if self.src.is_empty() {
             self.src = src.clone();
             self.char_vec = src.chars().collect()
         } else {
@@ -246,14 +388,14 @@ impl Tokenizer {
     }
     // a quick little function for putting tokens into the list instead of typing out the whole function every time
     fn add_token(&mut self, typ: TokenType, strval: String) {
-        let len = strval.len();
+        let len = strval.chars().count();
         self.token_list.push(Token::init(
             typ,
             strval,
-            self.position,
-            self.position + len,
-            self.line,
-            self.line_offset,
+            self.token_start,
+            self.token_start + len,
+            self.token_line,
+            self.token_offset,
         ));
     }
     // tells you if an identifier is a keyword or not
@@ -262,7 +404,11 @@ impl Tokenizer {
     }
     // tells you if you are done tokenizing src
     fn tokenization_end(&self) -> bool {
-        self.position >= self.src.len()
+        self.position >= self.char_vec.len()
+    }
+    // the source from byte `start` up to where we are now
+    fn lexeme(&self, start: usize) -> String {
+        self.src[start..self.byte_position].to_string()
     }
     // if `a` matches whatever is at src[position], consume it, otherwise move on
     fn match_and_consume(&mut self, a: char) -> bool {
@@ -280,6 +426,14 @@ impl Tokenizer {
         let chr = self.char_vec[self.position];
         self.curr_char = chr;
         self.position += 1;
+        self.byte_position += chr.len_utf8();
+        if chr == '\n' {
+            // updates line and line_offset as needed
+            self.line += 1;
+            self.line_offset = 0;
+        } else {
+            self.line_offset += 1;
+        }
         chr
     }
     // returns the character at src[position] without consuming it
@@ -291,19 +445,19 @@ impl Tokenizer {
     }
     // for cases when you want to look at the character at src[position + 1]
     fn peek_next(&self) -> char {
-        if self.tokenization_end() {
-            return '\0';
-        }
-        self.char_vec[self.position + 1]
+        self.char_vec
+            .get(self.position + 1)
+            .copied()
+            .unwrap_or('\0')
     }
     // the crux of token scanning
     fn scan_token(&mut self) {
-        if self.scan_number() {
-            // scan number, then string if that fails, then identifier, then syntax if all those fail
-            return;
-        } else if self.scan_string() {
-            return;
-        } else if self.scan_identifier() {
+        // remember where the token starts so that errors can point at it
+        self.token_start = self.position;
+        self.token_line = self.line;
+        self.token_offset = self.line_offset;
+        // scan number, then string if that fails, then identifier, then syntax if all those fail
+        if self.scan_number() || self.scan_string() || self.scan_identifier() {
             return;
         }
         self.scan_syntax();
@@ -312,9 +466,23 @@ impl Tokenizer {
     fn scan_syntax(&mut self) {
         if !self.tokenization_end() {
             if self.match_and_consume('{') {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
                 self.add_token(TokenType::LeftBrace, String::from("{"));
             } else if self.match_and_consume('}') {
-                self.add_token(TokenType::RightBrace, String::from("}"));
+                match self.interpolations.last_mut() {
+                    // the end of an interpolation, back to the string
+                    Some(0) => {
+                        self.interpolations.pop();
+                        self.scan_string_contents(TokenType::StrEnd, TokenType::StrMiddle);
+                    }
+                    Some(depth) => {
+                        *depth -= 1;
+                        self.add_token(TokenType::RightBrace, String::from("}"));
+                    }
+                    None => self.add_token(TokenType::RightBrace, String::from("}")),
+                }
             } else if self.match_and_consume('[') {
                 self.add_token(TokenType::LeftBracket, String::from("["));
             } else if self.match_and_consume(']') {
@@ -330,15 +498,27 @@ impl Tokenizer {
             } else if self.match_and_consume(',') {
                 self.add_token(TokenType::Comma, String::from(","));
             } else if self.match_and_consume('.') {
-                self.add_token(TokenType::Dot, String::from("."));
+                if self.match_and_consume('.') {
+                    if self.match_and_consume('=') {
+                        self.add_token(TokenType::DotDotEqual, String::from("..="));
+                    } else {
+                        self.add_token(TokenType::DotDot, String::from(".."));
+                    }
+                } else {
+                    self.add_token(TokenType::Dot, String::from("."));
+                }
             } else if self.match_and_consume('>') {
-                if self.match_and_consume('=') {
+                if self.match_and_consume('>') {
+                    self.add_token(TokenType::GreaterGreater, String::from(">>"))
+                } else if self.match_and_consume('=') {
                     self.add_token(TokenType::GreaterEqual, String::from(">="))
                 } else {
                     self.add_token(TokenType::Greater, String::from(">"));
                 }
             } else if self.match_and_consume('<') {
-                if self.match_and_consume('=') {
+                if self.match_and_consume('<') {
+                    self.add_token(TokenType::LessLess, String::from("<<"))
+                } else if self.match_and_consume('=') {
                     self.add_token(TokenType::LessEqual, String::from("<="))
                 } else {
                     self.add_token(TokenType::Less, String::from("<"));
@@ -354,8 +534,8 @@ impl Tokenizer {
                     }
                 } else if self.match_and_consume('*') {
                     // multiline comment begin
-                    while !(self.peek() == '*' && self.peek_next() == '/')
-                        && !self.tokenization_end()
+                    while !(self.tokenization_end()
+                        || self.peek() == '*' && self.peek_next() == '/')
                     {
                         self.consume_char();
                         if self.tokenization_end() {
@@ -364,6 +544,8 @@ impl Tokenizer {
                     }
                     self.consume_char(); // consume * and / at the end of the comment block
                     self.consume_char();
+                } else if self.match_and_consume('=') {
+                    self.add_token(TokenType::SlashEqual, String::from("/="));
                 } else {
                     self.add_token(TokenType::Slash, String::from("/"));
                 }
@@ -384,13 +566,31 @@ impl Tokenizer {
                     self.add_token(TokenType::Minus, String::from("-"));
                 }
             } else if self.match_and_consume('*') {
-                self.add_token(TokenType::Star, String::from("*"));
+                if self.match_and_consume('*') {
+                    self.add_token(TokenType::StarStar, String::from("**"));
+                } else if self.match_and_consume('=') {
+                    self.add_token(TokenType::StarEqual, String::from("*="));
+                } else {
+                    self.add_token(TokenType::Star, String::from("*"));
+                }
+            } else if self.match_and_consume('%') {
+                if self.match_and_consume('=') {
+                    self.add_token(TokenType::PercentEqual, String::from("%="));
+                } else {
+                    self.add_token(TokenType::Percent, String::from("%"));
+                }
             } else if self.match_and_consume('&') {
                 if self.match_and_consume('&') {
                     self.add_token(TokenType::Land, String::from("&&"));
                 } else {
                     self.add_token(TokenType::Band, String::from("&"));
                 }
+            } else if self.match_and_consume('?') {
+                if self.match_and_consume('?') {
+                    self.add_token(TokenType::QuestionQuestion, String::from("??"));
+                } else {
+                    self.add_token(TokenType::Question, String::from("?"));
+                }
             } else if self.match_and_consume('|') {
                 if self.match_and_consume('|') {
                     self.add_token(TokenType::Lor, String::from("||"));
@@ -416,80 +616,183 @@ impl Tokenizer {
             } else {
                 let error = self.consume_char();
                 self.add_token(TokenType::Error, String::from(error));
+                let tok = self.token_list.last().unwrap().clone();
+                self.errors.push((ErrorType::UnexpectedToken, tok));
             }
         }
     }
-    // String scanning [regex: "[literally anything in unicode]"]
+    // String scanning [regex: "[literally anything in unicode]"], the value of the token has its
+    // escapes decoded. `{expression}` interpolates, which we tokenize as a StrStart up to the `{`,
+    // the expression's tokens, then StrMiddle or StrEnd picking up again after the `}`
     fn scan_string(&mut self) -> bool {
-        if !self.tokenization_end() {
-            if self.match_and_consume('"') {
-                let start = self.position; // set the start
-                while !self.match_and_consume('"') {
-                    // while we haven't terminated the string
-                    if self.tokenization_end() {
-                        // if we got to the end of tokenization, we have an unterminated string
-                        self.errors.push(ErrorType::UnterminatedString); // push the error and exit
-                        return true;
-                    } else if self.match_and_consume('\\') {
-                        // deal with escaped quotes or just backslashes
-                        if self.match_and_consume('"') {}
-                    } else if self.match_and_consume('"') {
-                        // checks for the end of the string
-                        break;
-                    } else if !self.tokenization_end() {
-                        // otherwise consume whatever is there
-                        self.consume_char();
+        if self.scan_raw_string() {
+            return true;
+        }
+        if !self.tokenization_end() && self.match_and_consume('"') {
+            self.scan_string_contents(TokenType::Str, TokenType::StrStart);
+            return true;
+        }
+        false
+    }
+    // scans the rest of a string, `whole` is the token type if we make it to the closing quote
+    // and `part` is the token type if we run into an interpolation first
+    fn scan_string_contents(&mut self, whole: TokenType, part: TokenType) {
+        let mut value = String::new();
+        loop {
+            if self.tokenization_end() {
+                // if we got to the end of tokenization, we have an unterminated string
+                let tok = Token::init(
+                    TokenType::Error,
+                    String::from("\""),
+                    self.token_start,
+                    self.token_start + 1,
+                    self.token_line,
+                    self.token_offset,
+                );
+                self.errors.push((ErrorType::UnterminatedString, tok)); // push the error and exit
+                return;
+            }
+            let (start, line, offset) = (self.position, self.line, self.line_offset);
+            match self.consume_char() {
+                '"' => break,
+                '{' => {
+                    // the tokens of the expression come next, and the `}` that closes it carries
+                    // on with the string
+                    self.interpolations.push(0);
+                    self.add_string_token(part, value);
+                    return;
+                }
+                '\\' => match self.scan_escape() {
+                    Some(c) => value.push(c),
+                    None => {
+                        let escape = self.char_vec[start..self.position].iter().collect();
+                        let tok = Token::init(
+                            TokenType::Error,
+                            escape,
+                            start,
+                            self.position,
+                            line,
+                            offset,
+                        );
+                        self.errors.push((ErrorType::BadEscape, tok));
                     }
+                },
+                c => value.push(c),
+            }
+        }
+        self.add_string_token(whole, value);
+    }
+    // the char an escape sequence stands for, we have already consumed the `\`
+    fn scan_escape(&mut self) -> Option<char> {
+        match self.consume_char() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            c @ ('\\' | '"' | '{' | '}') => Some(c),
+            'u' => {
+                // \u{1F389}
+                if !self.match_and_consume('{') {
+                    return None;
+                }
+                let mut hex = String::new();
+                while self.peek().is_ascii_hexdigit() && hex.len() < 6 {
+                    hex.push(self.consume_char());
+                }
+                if !self.match_and_consume('}') {
+                    return None;
                 }
-                let substr = self.src.as_str()[start..self.position - 1].to_string(); // retrieve the substring, kinda wonky, will break with unicode chars
+                u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+            }
+            _ => None,
+        }
+    }
+    // r"raw strings", where nothing is escaped or interpolated, any #s after the r have to be
+    // matched after the closing quote, so r#"say "hi""# can hold quotes
+    fn scan_raw_string(&mut self) -> bool {
+        let hashes = self.char_vec[self.position..]
+            .iter()
+            .skip(1)
+            .take_while(|c| **c == '#')
+            .count();
+        if self.peek() != 'r' || self.char_vec.get(self.position + 1 + hashes) != Some(&'"') {
+            return false;
+        }
+        for _ in 0..hashes + 2 {
+            self.consume_char(); // r, the #s and the "
+        }
+        let start = self.byte_position;
+        loop {
+            if self.tokenization_end() {
                 let tok = Token::init(
-                    TokenType::Str,
-                    substr,
-                    start,
-                    self.position,
-                    self.line,
-                    self.line_offset,
-                ); // create the token
-                self.token_list.push(tok); // push it
+                    TokenType::Error,
+                    String::from("r\""),
+                    self.token_start,
+                    self.token_start + 2,
+                    self.token_line,
+                    self.token_offset,
+                );
+                self.errors.push((ErrorType::UnterminatedString, tok));
+                return true;
+            }
+            if self.consume_char() == '"'
+                && self.char_vec[self.position..]
+                    .iter()
+                    .take(hashes)
+                    .filter(|c| **c == '#')
+                    .count()
+                    == hashes
+            {
+                let value = self.src[start..self.byte_position - 1].to_string();
+                for _ in 0..hashes {
+                    self.consume_char();
+                }
+                self.add_string_token(TokenType::Str, value);
                 return true;
             }
         }
-        false
+    }
+    // string tokens don't look like their source, so they cover everything we've scanned
+    fn add_string_token(&mut self, typ: TokenType, value: String) {
+        self.token_list.push(Token::init(
+            typ,
+            value,
+            self.token_start,
+            self.position,
+            self.token_line,
+            self.token_offset,
+        ));
     }
     fn scan_number(&mut self) -> bool {
         if !self.tokenization_end() {
             // regex: [0-9]+\.[0-9]
-            let start = self.position;
+            let start = self.byte_position;
             let mut float_flag: bool = false; // handle decimal numbers
-            if self.peek().is_numeric() {
-                // rust has nice built int char functions that tell you if stuff is alphabetic or numeric, in this case
-                while self.peek().is_numeric() {
+            if self.peek().is_ascii_digit() {
+                // is_numeric() would let in digits from other scripts that we can't parse as numbers
+                while self.peek().is_ascii_digit() {
                     // while we are dealing with numbers
                     self.consume_char(); // consume the character
                     if self.tokenization_end() {
                         // check for the end
                         break;
                     }
-                    if self.peek() == '.' {
-                        if self.peek_next().is_numeric() {
-                            float_flag = true;
-                            self.consume_char();
-                        } else {
-                            // TODO: make test_tokenize_bad_float pass
-                            self.src = self.src.replacen(".", "", 1);
-                        }
+                    if !float_flag && self.peek() == '.' && self.peek_next().is_ascii_digit() {
+                        // otherwise the `.` is left for `..` or a method call, a second `.` ends the number
+                        float_flag = true;
+                        self.consume_char();
                     }
                 }
-                let substr: String = self.src.as_mut_str()[start..self.position].to_string(); // retrieve the substring
+                let substr: String = self.lexeme(start); // retrieve the substring
                 if float_flag {
                     // create either int or float token based on whether or not we encountered "."
                     let tok = Token::init(
                         TokenType::Float,
                         substr,
-                        start,
+                        self.token_start,
                         self.position,
-                        self.line,
-                        self.line_offset,
+                        self.token_line,
+                        self.token_offset,
                     );
                     self.token_list.push(tok);
                     return true;
@@ -497,10 +800,10 @@ impl Tokenizer {
                     let tok = Token::init(
                         TokenType::Int,
                         substr,
-                        start,
+                        self.token_start,
                         self.position,
-                        self.line,
-                        self.line_offset,
+                        self.token_line,
+                        self.token_offset,
                     );
                     self.token_list.push(tok);
                     return true;
@@ -515,7 +818,7 @@ impl Tokenizer {
     fn scan_identifier(&mut self) -> bool {
         if self.peek().is_alphabetic() {
             // regex: [a-zA-Z_][a-zA-Z_0-9]*
-            let start = self.position; // set start
+            let start = self.byte_position; // set start
             self.consume_char(); // consume the first char
             while self.peek().is_alphanumeric() || self.peek().eq(&'_') {
                 // while we have anything [a-zA-Z_0-9]
@@ -524,7 +827,7 @@ impl Tokenizer {
                     break;
                 }
             }
-            let substr: String = self.src.as_mut_str()[start..self.position].to_string(); // this breaks down if the compiler encounters a non-ascii char :/
+            let substr: String = self.lexeme(start);
             let substr_clone = substr.clone();
             if self.is_keyword(&substr) {
                 // this is weird rust spaghetti, because the memory thing is pissy otherwise, feels dumb
@@ -535,10 +838,10 @@ impl Tokenizer {
                 let tok = Token::init(
                     typ,
                     substr,
-                    start,
+                    self.token_start,
                     self.position,
-                    self.line,
-                    self.line_offset,
+                    self.token_line,
+                    self.token_offset,
                 ); // init the token
                 self.token_list.push(tok);
             } else {
@@ -546,10 +849,10 @@ impl Tokenizer {
                 let tok = Token::init(
                     TokenType::Identifier,
                     substr_clone,
-                    start,
+                    self.token_start,
                     self.position,
-                    self.line,
-                    self.line_offset,
+                    self.token_line,
+                    self.token_offset,
                 );
                 self.token_list.push(tok);
             }
@@ -560,15 +863,12 @@ impl Tokenizer {
     fn consume_whitespace(&mut self) {
         while !self.tokenization_end() {
             // consume whitespace
-            if self.peek() == '\r' || self.peek() == '\t' || self.peek() == ' ' {
+            if self.peek() == '\r'
+                || self.peek() == '\t'
+                || self.peek() == ' '
+                || self.peek() == '\n'
+            {
                 self.consume_char();
-                self.line_offset += 1; // updates line_offset and line as needed
-                continue;
-            }
-            if self.peek() == '\n' {
-                self.consume_char();
-                self.line += 1;
-                self.line_offset = 0;
                 continue;
             }
             break;
@@ -583,8 +883,37 @@ impl Tokenizer {
             self.consume_whitespace(); // consume that whitespace
             self.scan_token(); // scan them tokens
         }
+        self.token_start = self.position;
+        self.token_line = self.line;
+        self.token_offset = self.line_offset;
         self.add_token(TokenType::Eof, String::new()); // add eof at the end of token_list
     }
+
+    // whether or not we ran into anything we couldn't tokenize
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+    // the errors found during tokenization, ready to be rendered
+    pub fn get_diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .map(|(error, token)| {
+                let diagnostic = Diagnostic::from_token(error.to_string(), token);
+                match error {
+                    ErrorType::UnterminatedString => {
+                        diagnostic.with_help(String::from("add a closing `\"` to the string"))
+                    }
+                    ErrorType::UnexpectedToken => diagnostic.with_help(format!(
+                        "`{}` is not valid white-lang syntax",
+                        token.get_string_value()
+                    )),
+                    ErrorType::BadEscape => diagnostic.with_help(String::from(
+                        "the escapes are \\n \\t \\r \\0 \\\\ \\\" \\{ \\} and \\u{...}",
+                    )),
+                }
+            })
+            .collect()
+    }
 }
 
 // Tests!
@@ -604,6 +933,7 @@ mod test {
         let tokenizer = init_test(String::from(""));
         assert!(!tokenizer.is_keyword(&"not_a_keyword".to_string()));
         assert!(tokenizer.is_keyword(&"for".to_string()));
+        assert!(tokenizer.is_keyword(&"or".to_string()));
     }
     #[test]
     fn test_consume_char() {
@@ -631,6 +961,17 @@ mod test {
         assert_eq!(token.get_string_value(), String::from("1.1"));
     }
 
+    #[test]
+    fn test_tokenize_float_with_two_dots() {
+        let mut tokenizer = init_test(String::from("1.2.3"));
+        tokenizer.tokenize();
+        let token = tokenizer.get_token(0);
+        assert_eq!(token.get_type(), TokenType::Float);
+        assert_eq!(token.get_string_value(), String::from("1.2"));
+        assert_eq!(tokenizer.get_token(1).get_string_value(), String::from("."));
+        assert_eq!(tokenizer.get_token(2).get_string_value(), String::from("3"));
+    }
+
     /*
     #[test]
     fn test_tokenize_bad_float() {
@@ -676,7 +1017,59 @@ mod test {
         let tokenizer = init_test(String::from("\"abc\\\"\"")); // 'abc\"'
         let token = tokenizer.get_token(0);
         assert_eq!(token.get_type(), TokenType::Str);
-        assert_eq!(token.get_string_value(), String::from("abc\\\""));
+        assert_eq!(token.get_string_value(), String::from("abc\""));
+        assert_eq!(token.get_length(), 7);
+        let tokenizer = init_test(String::from(r#""\t\\\n\{\u{1F389}\u{e9}""#));
+        assert_eq!(tokenizer.get_token(0).get_string_value(), "\t\\\n{🎉é");
+        assert!(!tokenizer.has_errors());
+        let tokenizer = init_test(String::from(r#""\q \u{110000} \u{}""#));
+        let errors: Vec<String> = tokenizer
+            .get_diagnostics()
+            .iter()
+            .map(|error| error.get_message())
+            .collect();
+        assert_eq!(errors, vec!["unknown escape sequence"; 3]);
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let tokenizer = init_test(String::from(r##"r"\n{x}" r#"say "hi""# r"##));
+        assert_eq!(tokenizer.get_token(0).get_string_value(), "\\n{x}");
+        assert_eq!(tokenizer.get_token(1).get_string_value(), "say \"hi\"");
+        // an r on its own is just an identifier
+        assert_eq!(tokenizer.get_token(2).get_type(), TokenType::Identifier);
+        assert!(!tokenizer.has_errors());
+    }
+
+    #[test]
+    fn test_interpolation_tokenization() {
+        let tokenizer = init_test(String::from("\"a {x} b {f(\"{y}\")} c\" {}"));
+        use TokenType::*;
+        let tokens: Vec<(TokenType, String)> = tokenizer
+            .get_token_list()
+            .iter()
+            .map(|tok| (tok.get_type(), tok.get_string_value()))
+            .collect();
+        let expected = vec![
+            (StrStart, "a "),
+            (Identifier, "x"),
+            (StrMiddle, " b "),
+            (Identifier, "f"),
+            (LeftParen, "("),
+            (StrStart, ""),
+            (Identifier, "y"),
+            (StrEnd, ""),
+            (RightParen, ")"),
+            (StrEnd, " c"),
+            (LeftBrace, "{"),
+            (RightBrace, "}"),
+            (Eof, ""),
+        ];
+        let expected: Vec<(TokenType, String)> = expected
+            .into_iter()
+            .map(|(typ, value)| (typ, value.to_string()))
+            .collect();
+        assert_eq!(tokens, expected);
     }
 
     #[test]
@@ -695,7 +1088,7 @@ mod test {
     #[test]
     fn test_syntax_tokenization() {
         let mut tokenizer = Tokenizer::new(String::from(
-            "{ } [ ] ( ) , . ; : + ++ += - -- -= = == != ! > >= < <= & && | || ~ ^",
+            "{ } [ ] ( ) , . ; : + ++ += - -- -= = == != ! > >= < <= & && | || ~ ^ * *= / /= % %= ** << >> ? ??",
         ));
         tokenizer.tokenize();
         use TokenType::*;
@@ -731,7 +1124,19 @@ mod test {
             Lor,
             Lnot,
             Lxor,
+            Star,
+            StarEqual,
+            Slash,
+            SlashEqual,
+            Percent,
+            PercentEqual,
+            StarStar,
+            LessLess,
+            GreaterGreater,
+            Question,
+            QuestionQuestion,
         ];
+        assert_eq!(tok_l.len() - 1, type_vec.len());
         for i in 0..tok_l.len() - 1 {
             assert_eq!(tok_l[i].get_type(), type_vec[i]);
         }
@@ -749,6 +1154,21 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_range_tokenization() {
+        let mut tokenizer = Tokenizer::new("0..10 1..=n 1.5".to_string());
+        tokenizer.tokenize();
+        use TokenType::*;
+        let tok_l = tokenizer.token_list.clone();
+        let type_vec: Vec<TokenType> =
+            vec![Int, DotDot, Int, Int, DotDotEqual, Identifier, Float, Eof];
+        assert_eq!(tok_l.len(), type_vec.len());
+        for i in 0..tok_l.len() {
+            assert_eq!(tok_l[i].get_type(), type_vec[i]);
+        }
+        assert_eq!(tok_l[2].get_string_value(), "10");
+    }
+
     #[test]
     fn test_multiline_comment() {
         let mut tokenizer = Tokenizer::new(
@@ -772,11 +1192,78 @@ mod test {
         let mut tokenizer = Tokenizer::new(String::from("struct X () {}"));
         tokenizer.tokenize();
         assert_eq!(tokenizer.token_list.len(), 7);
-        let token_types = vec![
+        let token_types = [
             Struct, Identifier, LeftParen, RightParen, LeftBrace, RightBrace, Eof,
         ];
-        for i in 0..tokenizer.token_list.len() - 1 {
-            assert_eq!(tokenizer.token_list[i].get_type(), token_types[i]);
+        for (token, typ) in tokenizer.token_list.iter().zip(token_types.iter()) {
+            assert_eq!(token.get_type(), *typ);
         }
     }
+
+    #[test]
+    fn test_token_positions() {
+        let tokenizer = init_test(String::from("let x = 1;\n// comment\n  print(x);"));
+        let print = tokenizer
+            .get_token_list()
+            .iter()
+            .find(|tok| tok.get_type() == TokenType::Print)
+            .unwrap();
+        assert_eq!(print.get_line(), 3);
+        assert_eq!(print.get_line_offset(), 2);
+        assert_eq!(print.get_length(), 5);
+        let one = tokenizer.get_token(3);
+        assert_eq!(one.get_line(), 1);
+        assert_eq!(one.get_line_offset(), 8);
+    }
+
+    #[test]
+    fn test_unicode_tokenization() {
+        let tokenizer = init_test(String::from(
+            "// ünïcödé 🎉\nlet café = \"héllo 🎉\"; /* ∑ */ print(café);",
+        ));
+        use TokenType::*;
+        let types: Vec<TokenType> = tokenizer
+            .get_token_list()
+            .iter()
+            .map(|tok| tok.get_type())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                Let, Identifier, Equal, Str, SemiColon, Print, LeftParen, Identifier, RightParen,
+                SemiColon, Eof
+            ]
+        );
+        assert_eq!(tokenizer.get_token(1).get_string_value(), "café");
+        let string = tokenizer.get_token(3);
+        assert_eq!(string.get_string_value(), "héllo 🎉");
+        assert_eq!(string.get_length(), 9); // counting the quotes
+                                            // columns count chars, not bytes
+        assert_eq!(string.get_line_offset(), 11);
+        assert_eq!(tokenizer.get_token(5).get_line_offset(), 30);
+        assert_eq!(tokenizer.get_token(7).get_string_value(), "café");
+    }
+
+    #[test]
+    fn test_unicode_errors_render() {
+        let src = "let s = \"ü\" @ 1;";
+        let tokenizer = init_test(String::from(src));
+        let rendered = tokenizer.get_diagnostics()[0].render("test.whl", src);
+        assert_eq!(
+            rendered,
+            "error: unexpected character\n --> test.whl:1:13\n  |\n1 | let s = \"ü\" @ 1;\n  |             ^\n  |\n  = help: `@` is not valid white-lang syntax\n"
+        );
+    }
+
+    #[test]
+    fn test_tokenizer_errors_render() {
+        let src = "let s = \"abc";
+        let tokenizer = init_test(String::from(src));
+        assert!(tokenizer.has_errors());
+        let rendered = tokenizer.get_diagnostics()[0].render("test.whl", src);
+        assert_eq!(
+            rendered,
+            "error: unterminated string\n --> test.whl:1:9\n  |\n1 | let s = \"abc\n  |         ^\n  |\n  = help: add a closing `\"` to the string\n"
+        );
+    }
 }
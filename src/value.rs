@@ -0,0 +1,170 @@
+use crate::config::{
+    WhiteLangBool, WhiteLangFloat, WhiteLangInt, WhiteLangList, WhiteLangMap, WhiteLangString,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+/// Everything a white-lang expression can evaluate to, shared by the runtime and the vm
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(WhiteLangInt),
+    Float(WhiteLangFloat),
+    Bool(WhiteLangBool),
+    String(WhiteLangString),
+    List(WhiteLangList<Value>),
+    Map(WhiteLangMap<Value, Value>),
+    Struct(Rc<RefCell<Instance>>), // instances are shared, copying one only copies the reference
+    Function(String),              // a reference to the function with this name
+    Null,
+    Unit, // what functions without a return value give back
+}
+impl Value {
+    /// The white-lang name for the value, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Function(_) => "function",
+            Value::Null => "null",
+            Value::Struct(_) | Value::Unit => "object",
+        }
+    }
+
+    /// A fresh instance of the struct `name`
+    pub fn new_struct(name: String, fields: HashMap<String, Value>) -> Value {
+        Value::Struct(Rc::new(RefCell::new(Instance { name, fields })))
+    }
+
+    /// Compare two values for `==` and `!=`, `None` if the two can't be compared at all.
+    /// Ints and floats compare by value, lists and maps by their contents, structs by identity,
+    /// and null is only ever equal to null
+    pub fn equals(&self, other: &Value) -> Option<bool> {
+        match (self, other) {
+            (Value::Null, Value::Null) => Some(true),
+            (Value::Null, _) | (_, Value::Null) => Some(false),
+            (Value::Int(lhs), Value::Int(rhs)) => Some(lhs == rhs),
+            (Value::Int(lhs), Value::Float(rhs)) => Some(*lhs as WhiteLangFloat == *rhs),
+            (Value::Float(lhs), Value::Int(rhs)) => Some(*lhs == *rhs as WhiteLangFloat),
+            (Value::Float(lhs), Value::Float(rhs)) => Some(lhs == rhs),
+            (Value::String(lhs), Value::String(rhs)) => Some(lhs == rhs),
+            (Value::Bool(lhs), Value::Bool(rhs)) => Some(lhs == rhs),
+            (Value::Struct(lhs), Value::Struct(rhs)) => Some(Rc::ptr_eq(lhs, rhs)),
+            (Value::List(lhs), Value::List(rhs)) => Some(
+                lhs.len() == rhs.len()
+                    && lhs
+                        .iter()
+                        .zip(rhs)
+                        .all(|(lhs, rhs)| lhs.equals(rhs) == Some(true)),
+            ),
+            // the order keys were added in doesn't matter
+            (Value::Map(lhs), Value::Map(rhs)) => Some(
+                lhs.len() == rhs.len()
+                    && lhs.iter().all(|(key, value)| match find_key(rhs, key) {
+                        Some(at) => value.equals(&rhs[at].1) == Some(true),
+                        None => false,
+                    }),
+            ),
+            _ => None,
+        }
+    }
+}
+/// A heap allocated struct instance, every instance has its own fields
+#[derive(Clone, PartialEq)]
+pub struct Instance {
+    pub name: String, // the struct this is an instance of
+    pub fields: HashMap<String, Value>,
+}
+impl Debug for Instance {
+    /// Fields are shown the way `print` shows them, a derived `Debug` would never finish on an
+    /// instance that holds itself
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by_key(|(name, _)| *name);
+        f.debug_struct("Instance")
+            .field("name", &self.name)
+            .field(
+                "fields",
+                &fields
+                    .into_iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Display for Value {
+    /// How `print` shows a value, structs show their fields by name as `P { x: 3 }` and unit
+    /// doesn't show anything
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(int) => write!(f, "{}", int),
+            Value::Float(float) => write!(f, "{}", float),
+            Value::Bool(bool) => write!(f, "{}", bool),
+            Value::String(string) => write!(f, "{}", string),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, value) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Function(name) => write!(f, "<fn {}>", name),
+            Value::Null => write!(f, "null"),
+            Value::Struct(instance) => {
+                // an instance can hold itself, so one that's already being printed is cut short
+                let instance = match instance.try_borrow_mut() {
+                    Ok(instance) => instance,
+                    Err(_) => return write!(f, "..."),
+                };
+                let mut fields: Vec<_> = instance.fields.iter().collect();
+                fields.sort_by_key(|(name, _)| *name);
+                if fields.is_empty() {
+                    return write!(f, "{} {{}}", instance.name);
+                }
+                write!(f, "{} {{ ", instance.name)?;
+                for (i, (name, value)) in fields.into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Unit => Ok(()),
+        }
+    }
+}
+
+/// Where `key` is in `map`, keys are compared the same way `==` compares them
+pub fn find_key(map: &WhiteLangMap<Value, Value>, key: &Value) -> Option<usize> {
+    map.iter()
+        .position(|(other, _)| other.equals(key) == Some(true))
+}
+
+/// Set `map[key]` to `value`, a new key goes on the end
+pub fn insert(map: &mut WhiteLangMap<Value, Value>, key: Value, value: Value) {
+    match find_key(map, &key) {
+        Some(at) => map[at].1 = value,
+        None => map.push((key, value)),
+    }
+}
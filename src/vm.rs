@@ -0,0 +1,487 @@
+use crate::bytecode::{Bytecode, Op};
+use crate::config::{WhiteLangFloat, WhiteLangInt, MAX_CALL_DEPTH};
+use crate::parser::expression::bitwiseexpression::bitwise;
+use crate::parser::expression::indexexpression::{index, set_index, slice};
+use crate::parser::expression::powerexpression::power;
+use crate::parser::expression::rangeexpression::RangeIter;
+use crate::runtime::{intrinsics, RuntimeError};
+use crate::value::{insert, Value};
+
+mod test;
+
+struct Frame {
+    function: usize,
+    ip: usize,
+    base: usize, // where the frame's locals start on the stack
+}
+
+/// A stack based virtual machine for white-lang bytecode, an alternative to the tree walking
+/// `Runtime`
+pub struct Vm {
+    bytecode: Bytecode,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    globals: Vec<Option<Value>>,
+    output: String,
+}
+impl Vm {
+    pub fn new(bytecode: Bytecode) -> Self {
+        let globals = vec![None; bytecode.names.len()];
+        let locals = bytecode.functions[0].locals;
+        Vm {
+            bytecode,
+            stack: vec![Value::Unit; locals],
+            frames: vec![Frame {
+                function: 0,
+                ip: 0,
+                base: 0,
+            }],
+            globals,
+            output: String::new(),
+        }
+    }
+
+    /// Run `<main>` to completion, on failure the frames are left as they were for `stack_trace`
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let code = &self.bytecode.functions[frame.function].code;
+            if frame.ip >= code.len() {
+                return Ok(()); // only <main> can fall off the end, functions always return
+            }
+            let op = code[frame.ip];
+            frame.ip += 1;
+            match op {
+                Op::Constant(idx) => self.stack.push(self.bytecode.constants[idx].clone()),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Dup(count) => {
+                    let top = self.stack.len() - count;
+                    self.stack.extend_from_within(top..);
+                }
+                Op::GetLocal(slot) => {
+                    let base = self.frames.last().unwrap().base;
+                    self.stack.push(self.stack[base + slot].clone());
+                }
+                Op::SetLocal(slot) => {
+                    let base = self.frames.last().unwrap().base;
+                    self.stack[base + slot] = self.pop();
+                }
+                Op::GetGlobal(idx) => match &self.globals[idx] {
+                    Some(value) => self.stack.push(value.clone()),
+                    None => {
+                        return Err(RuntimeError::UnknownVariable(
+                            self.bytecode.names[idx].clone(),
+                        ))
+                    }
+                },
+                Op::SetGlobal(idx) => self.globals[idx] = Some(self.pop()),
+                Op::Add | Op::Subtract | Op::Multiply | Op::Divide | Op::Modulo => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack.push(arithmetic(op, lhs, rhs)?);
+                }
+                Op::Power => {
+                    let exponent = self.pop();
+                    let base = self.pop();
+                    self.stack.push(power(base, exponent)?);
+                }
+                Op::BitAnd | Op::BitOr | Op::BitXor | Op::ShiftLeft | Op::ShiftRight => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let operator = match op {
+                        Op::BitAnd => "&",
+                        Op::BitOr => "|",
+                        Op::BitXor => "^",
+                        Op::ShiftLeft => "<<",
+                        _ => ">>",
+                    };
+                    self.stack.push(bitwise(operator, lhs, rhs)?);
+                }
+                Op::BitNot => match self.pop() {
+                    Value::Int(int) => self.stack.push(Value::Int(!int)),
+                    value => {
+                        return Err(RuntimeError::TypeMismatch(format!(
+                            "cannot apply `~` to `{}`",
+                            value.type_name()
+                        )))
+                    }
+                },
+                Op::Greater | Op::GreaterEqual | Op::Less | Op::LessEqual => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack.push(compare(op, lhs, rhs)?);
+                }
+                Op::Equal | Op::NotEqual => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    // values that can't be compared are neither equal nor not equal
+                    let result = match lhs.equals(&rhs) {
+                        Some(equal) => equal == (op == Op::Equal),
+                        None => false,
+                    };
+                    self.stack.push(Value::Bool(result));
+                }
+                Op::Negate => {
+                    let value = match self.pop() {
+                        Value::Int(int) => Value::Int(
+                            int.checked_neg()
+                                .ok_or_else(|| RuntimeError::Overflow(format!("-({})", int)))?,
+                        ),
+                        Value::Float(float) => Value::Float(-float),
+                        value => {
+                            return Err(RuntimeError::TypeMismatch(format!(
+                                "cannot negate `{}`",
+                                value.type_name()
+                            )))
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                Op::Not => match self.pop() {
+                    Value::Bool(bool) => self.stack.push(Value::Bool(!bool)),
+                    value => {
+                        return Err(RuntimeError::TypeMismatch(format!(
+                            "cannot apply `!` to `{}`",
+                            value.type_name()
+                        )))
+                    }
+                },
+                Op::List(len) => {
+                    let list = self.stack.split_off(self.stack.len() - len);
+                    self.stack.push(Value::List(list));
+                }
+                Op::Map(len) => {
+                    let mut map = vec![];
+                    let mut entries = self.stack.split_off(self.stack.len() - 2 * len).into_iter();
+                    while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+                        insert(&mut map, key, value);
+                    }
+                    self.stack.push(Value::Map(map));
+                }
+                Op::Concat(len) => {
+                    let parts = self.stack.split_off(self.stack.len() - len);
+                    let string = parts.iter().map(|part| part.to_string()).collect();
+                    self.stack.push(Value::String(string));
+                }
+                Op::Struct(idx) => {
+                    let name = self.bytecode.names[idx].clone();
+                    let names = self.bytecode.get_struct_fields(name.as_str());
+                    let values = self.stack.split_off(self.stack.len() - names.len());
+                    let fields = names.into_iter().zip(values).collect();
+                    self.stack.push(Value::new_struct(name, fields));
+                }
+                Op::GetField(idx) => {
+                    let field = self.bytecode.names[idx].clone();
+                    let value = match self.pop() {
+                        Value::Struct(instance) => {
+                            let instance = instance.borrow();
+                            match instance.fields.get(&field) {
+                                Some(value) => value.clone(),
+                                None => {
+                                    return Err(RuntimeError::UnknownField(
+                                        instance.name.clone(),
+                                        field,
+                                    ))
+                                }
+                            }
+                        }
+                        value => {
+                            return Err(RuntimeError::TypeMismatch(format!(
+                                "cannot access the field `{}` on `{}`",
+                                field,
+                                value.type_name()
+                            )))
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                Op::SetField(idx) => {
+                    let field = self.bytecode.names[idx].clone();
+                    let value = self.pop();
+                    match self.pop() {
+                        Value::Struct(instance) => {
+                            let mut instance = instance.borrow_mut();
+                            if !instance.fields.contains_key(&field) {
+                                return Err(RuntimeError::UnknownField(
+                                    instance.name.clone(),
+                                    field,
+                                ));
+                            }
+                            instance.fields.insert(field, value);
+                        }
+                        object => {
+                            return Err(RuntimeError::TypeMismatch(format!(
+                                "cannot access the field `{}` on `{}`",
+                                field,
+                                object.type_name()
+                            )))
+                        }
+                    }
+                }
+                Op::Len => match self.pop() {
+                    Value::List(list) => self.stack.push(Value::Int(list.len() as WhiteLangInt)),
+                    value => {
+                        return Err(RuntimeError::TypeMismatch(format!(
+                            "cannot iterate over `{}`",
+                            value.type_name()
+                        )))
+                    }
+                },
+                Op::Range(inclusive) => {
+                    let (start, end, step) = self.pop_range()?;
+                    let range = RangeIter::new(start, end, step, inclusive);
+                    self.stack
+                        .push(Value::List(range.map(Value::Int).collect()));
+                }
+                Op::CheckStep => {
+                    if let Some(Value::Int(0)) = self.stack.last() {
+                        return Err(RuntimeError::ZeroStep);
+                    }
+                }
+                Op::InRange(inclusive) => {
+                    let (value, end, step) = self.pop_range()?;
+                    let range = RangeIter::new(value, end, step, inclusive);
+                    self.stack.push(Value::Bool(range.in_range(value)));
+                }
+                Op::Step(target) => {
+                    let (value, step) = match (self.pop(), self.pop()) {
+                        (Value::Int(step), Value::Int(value)) => (value, step),
+                        (_, value) => {
+                            return Err(RuntimeError::TypeMismatch(format!(
+                                "cannot make a range out of `{}`",
+                                value.type_name()
+                            )))
+                        }
+                    };
+                    // like `RangeIter`, a range ends when the next int wouldn't fit
+                    match value.checked_add(step) {
+                        Some(next) => self.stack.push(Value::Int(next)),
+                        None => self.frames.last_mut().unwrap().ip = target,
+                    }
+                }
+                Op::Index => {
+                    let idx = self.pop();
+                    let list = self.pop();
+                    self.stack.push(index(list, idx)?);
+                }
+                Op::SetIndex => {
+                    let value = self.pop();
+                    let idx = self.pop();
+                    let list = self.pop();
+                    self.stack.push(set_index(list, idx, value)?);
+                }
+                Op::Slice(inclusive) => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let list = self.pop();
+                    self.stack.push(slice(list, start, end, inclusive)?);
+                }
+                Op::Jump(target) => self.frames.last_mut().unwrap().ip = target,
+                Op::JumpIfFalse(target) => match self.pop() {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => self.frames.last_mut().unwrap().ip = target,
+                    value => {
+                        return Err(RuntimeError::TypeMismatch(format!(
+                            "cannot branch on `{}`",
+                            value.type_name()
+                        )))
+                    }
+                },
+                Op::And(target) | Op::Or(target) => match self.stack.last() {
+                    // the lhs decided it, the rhs never runs
+                    Some(Value::Bool(lhs)) if *lhs == matches!(op, Op::Or(_)) => {
+                        self.frames.last_mut().unwrap().ip = target
+                    }
+                    Some(Value::Bool(_)) => {
+                        self.pop();
+                    }
+                    _ => {
+                        return Err(RuntimeError::TypeMismatch(format!(
+                            "cannot apply `{}` to `{}`",
+                            if matches!(op, Op::And(_)) { "&&" } else { "||" },
+                            self.pop().type_name()
+                        )))
+                    }
+                },
+                Op::Coalesce(target) => match self.stack.last() {
+                    Some(Value::Null) => {
+                        self.pop();
+                    }
+                    _ => self.frames.last_mut().unwrap().ip = target,
+                },
+                Op::Call(function, argc) => self.call(function, argc)?,
+                Op::Invoke(idx, argc) => {
+                    let method = &self.bytecode.names[idx];
+                    let function = match &self.stack[self.stack.len() - argc - 1] {
+                        Value::Struct(instance) => {
+                            format!("{}.{}", instance.borrow().name, method)
+                        }
+                        receiver => {
+                            return Err(RuntimeError::TypeMismatch(format!(
+                                "cannot call the method `{}` on `{}`",
+                                method,
+                                receiver.type_name()
+                            )))
+                        }
+                    };
+                    match self.bytecode.get_function(function.as_str()) {
+                        Some(function) => self.call(function, argc + 1)?, // the receiver is `self`
+                        None => return Err(RuntimeError::UnknownFunction(function)),
+                    }
+                }
+                Op::Intrinsic(idx, argc) | Op::IntrinsicMut(idx, argc) => {
+                    let args = self.stack.split_off(self.stack.len() - argc);
+                    let mut receiver = self.pop();
+                    let name = format!("{}.{}", receiver.type_name(), self.bytecode.names[idx]);
+                    let value = intrinsics::call(name.as_str(), &mut receiver, args)?;
+                    if matches!(op, Op::IntrinsicMut(..)) {
+                        self.stack.push(receiver);
+                    }
+                    self.stack.push(value);
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    if self.frames.is_empty() {
+                        return Ok(()); // returning from <main> ends the program
+                    }
+                    self.stack.truncate(frame.base);
+                    self.stack.push(value);
+                }
+                Op::Print => {
+                    let value = self.pop();
+                    self.output.push_str(format!("{}\n", value).as_str());
+                }
+            }
+        }
+    }
+
+    /// Start running `function`, its arguments are the top `argc` values on the stack
+    fn call(&mut self, function: usize, argc: usize) -> Result<(), RuntimeError> {
+        let callee = &self.bytecode.functions[function];
+        if !callee.defined {
+            return Err(RuntimeError::UnknownFunction(callee.name.clone()));
+        }
+        // the first frame is `<main>`, it isn't a call
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow);
+        }
+        let base = self.stack.len() - argc;
+        self.stack.resize(base + callee.locals, Value::Unit);
+        self.frames.push(Frame {
+            function,
+            ip: 0,
+            base,
+        });
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("popped an empty vm stack")
+    }
+
+    // the three ints a range is made of, in the order they were pushed
+    fn pop_range(&mut self) -> Result<(WhiteLangInt, WhiteLangInt, WhiteLangInt), RuntimeError> {
+        let mut ints = [0; 3];
+        for int in ints.iter_mut().rev() {
+            *int = match self.pop() {
+                Value::Int(value) => value,
+                value => {
+                    return Err(RuntimeError::TypeMismatch(format!(
+                        "cannot make a range out of `{}`",
+                        value.type_name()
+                    )))
+                }
+            };
+        }
+        Ok((ints[0], ints[1], ints[2]))
+    }
+
+    /// The functions we were in when the program stopped, innermost call first
+    pub fn stack_trace(&self) -> Vec<String> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| self.bytecode.functions[frame.function].name.clone())
+            .collect()
+    }
+
+    /// Take everything printed since the last call
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+}
+
+/// `lhs op rhs` for `+`, `-`, `*`, `/` and `%`, shared with compound assignments in the runtime
+pub(crate) fn arithmetic(op: Op, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => {
+            let (result, symbol) = match op {
+                Op::Add => (lhs.checked_add(rhs), "+"),
+                Op::Subtract => (lhs.checked_sub(rhs), "-"),
+                Op::Multiply => (lhs.checked_mul(rhs), "*"),
+                _ if rhs == 0 => return Err(RuntimeError::DivisionByZero),
+                Op::Divide => (lhs.checked_div(rhs), "/"),
+                _ => (lhs.checked_rem(rhs), "%"),
+            };
+            return result
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::Overflow(format!("{} {} {}", lhs, symbol, rhs)));
+        }
+        (Value::Float(lhs), Value::Float(rhs)) => (lhs, rhs),
+        (Value::Float(lhs), Value::Int(rhs)) => (lhs, rhs as WhiteLangFloat),
+        (Value::Int(lhs), Value::Float(rhs)) => (lhs as WhiteLangFloat, rhs),
+        (lhs, rhs) => {
+            return Err(RuntimeError::TypeMismatch(format!(
+                "cannot {} `{}` and `{}`",
+                match op {
+                    Op::Add => "add",
+                    Op::Subtract => "subtract",
+                    Op::Multiply => "multiply",
+                    Op::Divide => "divide",
+                    _ => "take the remainder of",
+                },
+                lhs.type_name(),
+                rhs.type_name()
+            )))
+        }
+    };
+    Ok(Value::Float(match op {
+        Op::Add => lhs + rhs,
+        Op::Subtract => lhs - rhs,
+        Op::Multiply => lhs * rhs,
+        Op::Divide => lhs / rhs,
+        _ => lhs % rhs,
+    }))
+}
+
+fn compare(op: Op, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => {
+            return Ok(Value::Bool(match op {
+                Op::Greater => lhs > rhs,
+                Op::GreaterEqual => lhs >= rhs,
+                Op::Less => lhs < rhs,
+                _ => lhs <= rhs,
+            }))
+        }
+        (Value::Float(lhs), Value::Float(rhs)) => (lhs, rhs),
+        (Value::Float(lhs), Value::Int(rhs)) => (lhs, rhs as WhiteLangFloat),
+        (Value::Int(lhs), Value::Float(rhs)) => (lhs as WhiteLangFloat, rhs),
+        (lhs, rhs) => {
+            return Err(RuntimeError::TypeMismatch(format!(
+                "cannot compare `{}` with `{}`",
+                lhs.type_name(),
+                rhs.type_name()
+            )))
+        }
+    };
+    Ok(Value::Bool(match op {
+        Op::Greater => lhs > rhs,
+        Op::GreaterEqual => lhs >= rhs,
+        Op::Less => lhs < rhs,
+        _ => lhs <= rhs,
+    }))
+}
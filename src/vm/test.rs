@@ -0,0 +1,108 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use crate::bytecode::{Bytecode, Op};
+    use crate::runtime::RuntimeError;
+    use crate::value::Value;
+    use crate::vm::Vm;
+    use crate::*;
+
+    fn test_execute_vm(src: &str, expected: &str) {
+        let mut core: CoreObjects = CoreObjects::new(src);
+        core.get_program_mut()
+            .execute_vm()
+            .unwrap_or_else(|_| panic!("{}", core.get_program().stderr));
+        assert_eq!(core.get_program().stdout.as_str(), expected);
+    }
+
+    #[test]
+    fn test_vm_runs_bytecode() {
+        let mut bytecode = Bytecode::new();
+        bytecode.constant(Value::Int(1));
+        bytecode.constant(Value::Float(0.5));
+        bytecode.emit(Op::Add);
+        bytecode.emit(Op::Print);
+        let mut vm = Vm::new(bytecode);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!(vm.take_output(), "1.5\n");
+    }
+
+    #[test]
+    fn test_functions_use_local_slots() {
+        let mut bytecode = Bytecode::new();
+        bytecode.begin_function("add", &[String::from("a"), String::from("b")]);
+        bytecode.declare("c");
+        assert_eq!(bytecode.load("a"), 0);
+        assert_eq!(bytecode.functions[1].code[0], Op::GetLocal(0));
+        bytecode.load("b");
+        bytecode.emit(Op::Add);
+        bytecode.store("c");
+        assert_eq!(bytecode.functions[1].code[3], Op::SetLocal(2));
+        bytecode.load("x"); // not a local, has to be a global
+        assert_eq!(bytecode.functions[1].code[4], Op::GetGlobal(0));
+        bytecode.end_function();
+        assert_eq!(bytecode.functions[1].arity, 2);
+        assert_eq!(bytecode.functions[1].locals, 3);
+    }
+
+    #[test]
+    fn test_vm_fibonacci() {
+        let src = "
+        fn fib(n : int) : int {
+            if (n < 2) {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+        print(fib(25));";
+        test_execute_vm(src, "75025\n");
+    }
+
+    #[test]
+    fn test_vm_calls_before_definitions() {
+        let src = "
+        fn is_even(n : int) : bool {
+            if (n == 0) { return true; }
+            return is_odd(n - 1);
+        }
+        fn is_odd(n : int) : bool {
+            if (n == 0) { return false; }
+            return is_even(n - 1);
+        }
+        print(is_even(10));
+        print(is_odd(7));";
+        test_execute_vm(src, "true\ntrue\n");
+    }
+
+    #[test]
+    fn test_vm_nested_loops_break_out_of_the_innermost() {
+        let src = "
+        for (x in [1, 2, 3]) {
+            let y = 0;
+            while (true) {
+                y = y + 1;
+                if (y == x) { break; }
+            }
+            print(y);
+        }";
+        test_execute_vm(src, "1\n2\n3\n");
+    }
+
+    #[test]
+    fn test_vm_errors_have_a_stack_trace() {
+        let src = "
+        fn divide(a : int, b : int) : int { return a / b; }
+        print(1);
+        print(divide(1, 0));";
+        let mut core: CoreObjects = CoreObjects::new(src);
+        assert_eq!(
+            core.get_program_mut().execute_vm(),
+            Err(RuntimeError::DivisionByZero)
+        );
+        assert_eq!(core.get_program().stdout.as_str(), "1\n");
+        assert_eq!(
+            core.get_program().stderr.as_str(),
+            "[RUNTIME ERROR] attempt to divide by zero\nstack trace:\n    at divide\n    at <main>\n"
+        );
+    }
+}